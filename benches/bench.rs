@@ -0,0 +1,346 @@
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use serde::{Deserialize as _, Serialize as _};
+use serde_derive::{Deserialize, Serialize};
+use serde_yaml::{Deserializer, Mapping, SerializerBuilder, Value};
+use std::fmt::Write as _;
+use std::io;
+use std::time::{Duration, Instant};
+
+const DEPLOYMENT: &str = include_str!("fixtures/deployment.yaml");
+
+// The large inputs are generated from the checked in fixture rather than
+// checked in themselves. Everything is deterministic so that numbers are
+// comparable between runs.
+
+// A `kind: List` manifest holding `n` copies of the deployment.
+fn manifest(n: usize) -> String {
+    let mut yaml = String::from("apiVersion: v1\nkind: List\nitems:\n");
+    for i in 0..n {
+        let mut prefix = "  - ";
+        for line in DEPLOYMENT.lines() {
+            if line == "  name: web" {
+                writeln!(yaml, "{}  name: web-{}", prefix, i).unwrap();
+            } else {
+                writeln!(yaml, "{}{}", prefix, line).unwrap();
+            }
+            prefix = "    ";
+        }
+    }
+    yaml
+}
+
+// A stream of `n` documents, each a copy of the deployment.
+fn stream(n: usize) -> String {
+    let mut yaml = String::new();
+    for _ in 0..n {
+        yaml.push_str("---\n");
+        yaml.push_str(DEPLOYMENT);
+    }
+    yaml
+}
+
+#[derive(Serialize, Deserialize)]
+struct Point {
+    id: u64,
+    x: f64,
+    y: f64,
+    z: f64,
+    weight: i32,
+    visible: bool,
+}
+
+fn points(n: usize) -> String {
+    let points: Vec<Point> = (0..n as u64)
+        .map(|id| Point {
+            id,
+            x: id as f64 * 0.25,
+            y: -(id as f64) / 3.0,
+            z: 1e6 + id as f64,
+            weight: (id % 200) as i32 - 100,
+            visible: id % 3 == 0,
+        })
+        .collect();
+    serde_yaml::to_string(&points).unwrap()
+}
+
+// Many fields per element, to measure the per-key cost of deserializing
+// structs.
+#[derive(Deserialize)]
+#[allow(dead_code)]
+struct Wide {
+    field_00: u32,
+    field_01: u32,
+    field_02: u32,
+    field_03: u32,
+    field_04: u32,
+    field_05: u32,
+    field_06: u32,
+    field_07: u32,
+    field_08: u32,
+    field_09: u32,
+    field_10: u32,
+    field_11: u32,
+    field_12: u32,
+    field_13: u32,
+    field_14: u32,
+    field_15: u32,
+    field_16: u32,
+    field_17: u32,
+    field_18: u32,
+    field_19: u32,
+    field_20: u32,
+    field_21: u32,
+    field_22: u32,
+    field_23: u32,
+    field_24: u32,
+    field_25: u32,
+    field_26: u32,
+    field_27: u32,
+    field_28: u32,
+    field_29: u32,
+}
+
+fn wide(n: usize) -> String {
+    let mut yaml = String::new();
+    for i in 0..n {
+        for field in 0..30 {
+            let prefix = if field == 0 { "- " } else { "  " };
+            writeln!(yaml, "{}field_{:02}: {}", prefix, field, i + field).unwrap();
+        }
+    }
+    yaml
+}
+
+// A mapping nested `depth` levels deep with `width` entries on each level,
+// half of them scalars and half nested mappings.
+fn deep(depth: usize, width: usize) -> Value {
+    let mut mapping = Mapping::new();
+    for i in 0..width {
+        let key = format!("key{}", i);
+        let value = if depth > 0 && i % 2 == 0 {
+            deep(depth - 1, width)
+        } else {
+            Value::from(format!("value {} at depth {}", i, depth))
+        };
+        mapping.insert(Value::from(key), value);
+    }
+    Value::Mapping(mapping)
+}
+
+// One anchored mapping followed by `n` aliases to it.
+fn aliases(n: usize) -> String {
+    let mut yaml = String::from("base: &base\n  image: registry.example.com/web:1.24.3\n  replicas: 3\n  labels: {tier: frontend, team: storefront}\nitems:\n");
+    for _ in 0..n {
+        yaml.push_str("  - *base\n");
+    }
+    yaml
+}
+
+// A scalar of `len` bytes, anchored and then aliased `n` times, which must fail
+// about as fast as the scalar alone parses rather than copying it `n` times.
+fn long_aliases(len: usize, n: usize) -> String {
+    let mut yaml = format!("text: &text {}\naliases: [", "x".repeat(len));
+    for _ in 0..n {
+        yaml.push_str("*text, ");
+    }
+    yaml.push_str("]\n");
+    yaml
+}
+
+// The shortest of a few runs of `f`, for comparing inputs against each other
+// before benchmarking them.
+fn fastest<T>(f: impl Fn() -> T) -> Duration {
+    (0..5)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn bench_parse_manifest(c: &mut Criterion) {
+    let yaml = manifest(200);
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(yaml.len() as u64));
+    group.bench_function("manifest", |b| {
+        b.iter(|| serde_yaml::from_str::<Value>(black_box(&yaml)).unwrap());
+    });
+    group.finish();
+}
+
+fn bench_parse_typed(c: &mut Criterion) {
+    let yaml = points(10_000);
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(yaml.len() as u64));
+    group.bench_function("typed", |b| {
+        b.iter(|| serde_yaml::from_str::<Vec<Point>>(black_box(&yaml)).unwrap());
+    });
+    group.finish();
+}
+
+fn bench_parse_wide(c: &mut Criterion) {
+    let yaml = wide(100_000);
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(yaml.len() as u64));
+    group.bench_function("wide", |b| {
+        b.iter(|| serde_yaml::from_str::<Vec<Wide>>(black_box(&yaml)).unwrap());
+    });
+    group.finish();
+}
+
+fn bench_parse_stream(c: &mut Criterion) {
+    let yaml = stream(200);
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(yaml.len() as u64));
+    group.bench_function("stream", |b| {
+        b.iter(|| {
+            for document in Deserializer::from_str(black_box(&yaml)) {
+                black_box(Value::deserialize(document).unwrap());
+            }
+        });
+    });
+    group.finish();
+}
+
+fn bench_parse_aliases(c: &mut Criterion) {
+    let yaml = aliases(10_000);
+    let mut group = c.benchmark_group("parse");
+    group.throughput(Throughput::Bytes(yaml.len() as u64));
+    group.bench_function("aliases", |b| {
+        b.iter(|| serde_yaml::from_str::<Value>(black_box(&yaml)).unwrap());
+    });
+    group.finish();
+}
+
+fn bench_parse_long_aliases(c: &mut Criterion) {
+    let yaml = long_aliases(1 << 20, 5000);
+    // The same document with each alias replaced by a quoted scalar of the
+    // same length, which parses successfully.
+    let well_formed = yaml.replace("*text", "\"txt\"");
+    assert_eq!(yaml.len(), well_formed.len());
+    serde_yaml::from_str::<Value>(&well_formed).unwrap();
+    let pathological = fastest(|| serde_yaml::from_str::<Value>(&yaml).unwrap_err());
+    let baseline = fastest(|| serde_yaml::from_str::<Value>(&well_formed).unwrap());
+    assert!(
+        pathological < baseline * 4,
+        "long aliases took {:?} to fail, well-formed input of the same size parses in {:?}",
+        pathological,
+        baseline,
+    );
+
+    let mut group = c.benchmark_group("parse");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(yaml.len() as u64));
+    group.bench_function("long_aliases", |b| {
+        b.iter(|| serde_yaml::from_str::<Value>(black_box(&yaml)).unwrap_err());
+    });
+    group.finish();
+}
+
+// Checking that every document of a stream is well-formed, against
+// deserializing each into a `Value` that is thrown away.
+fn bench_validate_stream(c: &mut Criterion) {
+    let yaml = stream(200);
+    let mut group = c.benchmark_group("validate");
+    group.throughput(Throughput::Bytes(yaml.len() as u64));
+    group.bench_function("value", |b| {
+        b.iter(|| {
+            for document in Deserializer::from_str(black_box(&yaml)) {
+                black_box(Value::deserialize(document).unwrap());
+            }
+        });
+    });
+    group.bench_function("validate", |b| {
+        b.iter(|| serde_yaml::validate(black_box(&yaml)).unwrap());
+    });
+    group.finish();
+}
+
+fn bench_serialize_deep(c: &mut Criterion) {
+    let value = deep(6, 6);
+    let len = serde_yaml::to_string(&value).unwrap().len();
+    let mut group = c.benchmark_group("serialize");
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("deep", |b| {
+        b.iter(|| serde_yaml::to_string(black_box(&value)).unwrap());
+    });
+    group.finish();
+}
+
+fn bench_serialize_floats(c: &mut Criterion) {
+    let floats: Vec<f64> = (0..1_000_000).map(|i| i as f64 / 7.0 - 1e5).collect();
+    let len = serde_yaml::to_string(&floats).unwrap().len();
+    let mut group = c.benchmark_group("serialize");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("floats", |b| {
+        b.iter(|| serde_yaml::to_string(black_box(&floats)).unwrap());
+    });
+    group.finish();
+}
+
+// Discards its input, counting the calls to `write`.
+struct CountingWriter {
+    writes: usize,
+}
+
+impl io::Write for CountingWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writes += 1;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+fn write_counted(value: &Value, builder: SerializerBuilder) -> usize {
+    let mut serializer = builder.build(CountingWriter { writes: 0 }).unwrap();
+    value.serialize(&mut serializer).unwrap();
+    serializer.into_inner().unwrap().writes
+}
+
+// A document of about 10 MB written straight to the writer, which gets one
+// call per 16 KiB that libyaml flushes, against collecting 1 MiB at a time.
+fn bench_serialize_buffered(c: &mut Criterion) {
+    let value: Value = serde_yaml::from_str(&manifest(3000)).unwrap();
+    let len = serde_yaml::to_string(&value).unwrap().len();
+    let buffered = || SerializerBuilder::new().buffer_capacity(1 << 20);
+    let unbuffered_writes = write_counted(&value, SerializerBuilder::new());
+    let buffered_writes = write_counted(&value, buffered());
+    println!(
+        "serialize/unbuffered: {} writes for {} bytes, serialize/buffered: {} writes",
+        unbuffered_writes, len, buffered_writes,
+    );
+    assert!(buffered_writes * 16 < unbuffered_writes);
+
+    let mut group = c.benchmark_group("serialize");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(len as u64));
+    group.bench_function("unbuffered", |b| {
+        b.iter(|| write_counted(black_box(&value), SerializerBuilder::new()));
+    });
+    group.bench_function("buffered", |b| {
+        b.iter(|| write_counted(black_box(&value), buffered()));
+    });
+    group.finish();
+}
+
+criterion_group!(
+    benches,
+    bench_parse_manifest,
+    bench_parse_typed,
+    bench_parse_wide,
+    bench_parse_stream,
+    bench_parse_aliases,
+    bench_parse_long_aliases,
+    bench_validate_stream,
+    bench_serialize_deep,
+    bench_serialize_floats,
+    bench_serialize_buffered,
+);
+criterion_main!(benches);
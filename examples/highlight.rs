@@ -0,0 +1,107 @@
+//! Prints a YAML file with syntax highlighting for a terminal.
+//!
+//!     cargo run --example highlight -- path/to/file.yaml
+//!
+//! Without an argument, highlights a sample Kubernetes manifest.
+
+use serde_yaml::raw::{ScalarStyle, Scanner, Token};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::process;
+
+const SAMPLE: &str = include_str!("../benches/fixtures/deployment.yaml");
+
+const RESET: &str = "\x1b[0m";
+const KEY: &str = "\x1b[34m";
+const STRING: &str = "\x1b[32m";
+const NUMBER: &str = "\x1b[33m";
+const PUNCTUATION: &str = "\x1b[90m";
+const META: &str = "\x1b[35m";
+const COMMENT: &str = "\x1b[2m";
+
+fn main() {
+    let input = match env::args_os().nth(1) {
+        Some(path) => match fs::read_to_string(&path) {
+            Ok(input) => input,
+            Err(err) => {
+                eprintln!("{}: {}", path.to_string_lossy(), err);
+                process::exit(1);
+            }
+        },
+        None => SAMPLE.to_owned(),
+    };
+    let stdout = io::stdout();
+    if let Err(err) = highlight(&input, &mut stdout.lock()) {
+        eprintln!("{}", err);
+        process::exit(1);
+    }
+}
+
+fn highlight(input: &str, out: &mut dyn Write) -> Result<(), Box<dyn std::error::Error>> {
+    let mut pos = 0;
+    let mut in_key = false;
+    for token in Scanner::new(input.as_bytes())? {
+        let (token, start, end) = token?;
+        let (start, end) = (start.index(), end.index());
+        if start == end {
+            // Tokens like BlockMappingStart and implicit keys take up no space.
+            in_key |= token == Token::Key;
+            continue;
+        }
+        write_gap(&input[pos..start], out)?;
+        let text = &input[start..end];
+        let color = match token {
+            Token::Key => {
+                in_key = true;
+                PUNCTUATION
+            }
+            Token::Scalar { .. } if in_key => KEY,
+            Token::Scalar { style, .. } => {
+                if style == ScalarStyle::Plain && is_number(text) {
+                    NUMBER
+                } else {
+                    STRING
+                }
+            }
+            Token::Alias(_)
+            | Token::Anchor(_)
+            | Token::Tag { .. }
+            | Token::VersionDirective { .. }
+            | Token::TagDirective { .. } => META,
+            _ => PUNCTUATION,
+        };
+        if let Token::Scalar { .. } | Token::Value = token {
+            in_key = false;
+        }
+        write!(out, "{}{}{}", color, text, RESET)?;
+        pos = end;
+    }
+    write_gap(&input[pos..], out)?;
+    Ok(())
+}
+
+// Writes the whitespace and comments between two tokens.
+fn write_gap(gap: &str, out: &mut dyn Write) -> io::Result<()> {
+    for (i, line) in gap.split('\n').enumerate() {
+        if i > 0 {
+            out.write_all(b"\n")?;
+        }
+        match line.find('#') {
+            Some(hash) => write!(
+                out,
+                "{}{}{}{}",
+                &line[..hash],
+                COMMENT,
+                &line[hash..],
+                RESET
+            )?,
+            None => out.write_all(line.as_bytes())?,
+        }
+    }
+    Ok(())
+}
+
+fn is_number(text: &str) -> bool {
+    serde_yaml::from_str::<serde_yaml::Value>(text).is_ok_and(|value| value.is_number())
+}
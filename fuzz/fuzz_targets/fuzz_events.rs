@@ -0,0 +1,39 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_yaml::raw::{CollectionStyle, Emitter, Event, Parser, ScalarStyle};
+
+// The emitter is free to choose a different style for a scalar or collection,
+// and to add or omit the markers around a document, so those are not compared.
+fn normalize(mut event: Event) -> Event {
+    match &mut event {
+        Event::DocumentStart(document_start) => document_start.implicit = false,
+        Event::DocumentEnd(document_end) => document_end.implicit = false,
+        Event::Scalar(scalar) => scalar.style = ScalarStyle::Any,
+        Event::SequenceStart(sequence) => sequence.style = CollectionStyle::Any,
+        Event::MappingStart(mapping) => mapping.style = CollectionStyle::Any,
+        _ => {}
+    }
+    event
+}
+
+fn parse(input: &[u8]) -> Option<Vec<Event<'_>>> {
+    Parser::new(input)
+        .ok()?
+        .map(|result| result.ok().map(|(event, _location)| normalize(event)))
+        .collect()
+}
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() <= 10240 {
+        if let Some(events) = parse(data) {
+            let mut emitter = Emitter::new(Vec::new()).unwrap();
+            for event in events.clone() {
+                emitter.emit(event).unwrap();
+            }
+            let output = emitter.into_inner().unwrap();
+            let again = parse(&output).unwrap();
+            assert_eq!(events, again, "{}", String::from_utf8_lossy(&output));
+        }
+    }
+});
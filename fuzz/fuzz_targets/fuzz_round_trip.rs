@@ -0,0 +1,14 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use serde_yaml::Value;
+
+fuzz_target!(|data: &[u8]| {
+    if data.len() <= 10240 {
+        if let Ok(value) = serde_yaml::from_slice::<Value>(data) {
+            let yaml = serde_yaml::to_string(&value).unwrap();
+            let again: Value = serde_yaml::from_str(&yaml).unwrap();
+            assert_eq!(value, again, "{}", yaml);
+        }
+    }
+});
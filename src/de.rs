@@ -1,16 +1,28 @@
-use crate::error::{self, Error, ErrorImpl};
+use crate::diagnostic::{Code, Diagnostic, Options};
+use crate::document::NodeKind;
+use crate::document_value::DocumentValues;
+use crate::error::{self, Error, ErrorImpl, Location};
+use crate::expected::Expected;
 use crate::libyaml::error::Mark;
-use crate::libyaml::parser::{MappingStart, Scalar, ScalarStyle, SequenceStart, Tag};
-use crate::loader::{Document, Loader};
+use crate::libyaml::event::{
+    CollectionStyle, DocumentEnd, DocumentStart, MappingStart, Scalar, ScalarStyle, SequenceStart,
+    Tag,
+};
+use crate::loader::{self, Document, Loader};
 use crate::path::Path;
+use crate::peek::{self, PeekAccess};
+use crate::scalar_info::{self, ScalarInfoAccess};
+use crate::stream::ReaderDocuments;
+use crate::value::styled;
 use serde::de::value::StrDeserializer;
-use serde::de::{
-    self, Deserialize, DeserializeOwned, DeserializeSeed, Expected, IgnoredAny, Unexpected, Visitor,
-};
+use serde::de::{self, Deserialize, DeserializeOwned, DeserializeSeed, Unexpected, Visitor};
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt;
 use std::io;
 use std::mem;
 use std::num::ParseIntError;
+use std::ptr;
 use std::str;
 use std::sync::Arc;
 
@@ -54,30 +66,146 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 ///     Ok(())
 /// }
 /// ```
+///
+/// Converting to another format with [`serde_transcode`], without building a
+/// [`Value`](crate::Value) first. Only one document's worth of parser events
+/// is held at a time. A node the other format cannot represent, like a tagged
+/// value or a sequence as a mapping key in JSON, fails with an error naming
+/// it:
+///
+/// ```
+/// let input = "a: [1, two]\n? [x, y]\n: 3\n";
+///
+/// let de = serde_yaml::Deserializer::from_str(input);
+/// let mut json = Vec::new();
+/// let mut ser = serde_json::Serializer::new(&mut json);
+/// let err = serde_transcode::transcode(de, &mut ser).unwrap_err();
+/// assert_eq!(
+///     err.to_string(),
+///     "mapping key at line 2 column 3 is a sequence and was rejected: key must be a string",
+/// );
+/// ```
+///
+/// [`serde_transcode`]: https://docs.rs/serde-transcode
 pub struct Deserializer<'de> {
     progress: Progress<'de>,
+    recover: bool,
+    duplicate_anchors: DuplicateAnchors,
+    stringify_scalars: bool,
+    legacy_resolution: bool,
+    schema: Schema,
+    resolvers: Vec<Resolver>,
+    tag_handlers: Vec<(String, TagHandler)>,
+    max_documents: Option<usize>,
+    max_input_bytes: Option<usize>,
+    report_progress: Option<(u64, fn(u64))>,
+}
+
+/// What the deserializer does with an anchor that is defined more than once
+/// in the same document.
+///
+/// YAML allows an anchor to be defined again, after which aliases refer to
+/// the newer definition. Since this can make a document mean something other
+/// than what it appears to at a glance, it can be rejected instead.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum DuplicateAnchors {
+    /// Later definitions shadow earlier ones. This is the default.
+    #[default]
+    Allow,
+    /// A second definition of an anchor is an error.
+    Error,
 }
 
+/// Which plain scalars are resolved to something other than a string, as
+/// set by [`Deserializer::schema`].
+///
+/// Quoted scalars are always strings, and a scalar with a tag like `!!int`
+/// is whatever its tag says under every schema.
+#[derive(Copy, Clone, PartialEq, Eq, Default, Debug)]
+pub enum Schema {
+    /// The YAML 1.2 core schema, in which `~` and `Null` are null, `True`
+    /// is a boolean and `0x10`, `0o17` and `.inf` are numbers. This is the
+    /// default.
+    #[default]
+    Core,
+    /// The YAML 1.2 JSON schema, which only resolves `null`, `true`, `false`
+    /// and numbers written as JSON writes them. Anything else, like `~`,
+    /// `yes`, `0x10`, `+1` or an empty value, is a string.
+    Json,
+    /// The failsafe schema, in which every plain scalar is a string.
+    Failsafe,
+}
+
+// Gives the tag for a plain scalar, if it should have one.
+pub(crate) type Resolver = fn(&str) -> Option<&'static str>;
+
+// Gives the value to deserialize in place of a scalar with some tag.
+pub(crate) type TagHandler = Arc<dyn Fn(&str) -> Result<crate::Value> + Send + Sync>;
+
 pub(crate) enum Progress<'de> {
     Str(&'de str),
     Slice(&'de [u8]),
-    Read(Box<dyn io::Read + 'de>),
+    Owned(Vec<u8>),
     Iterable(Loader<'de>),
     Document(Document<'de>),
     Fail(Arc<ErrorImpl>),
 }
 
 impl<'de> Deserializer<'de> {
+    pub(crate) fn new(progress: Progress<'de>) -> Self {
+        Deserializer {
+            progress,
+            recover: false,
+            duplicate_anchors: DuplicateAnchors::Allow,
+            stringify_scalars: true,
+            legacy_resolution: false,
+            schema: Schema::Core,
+            resolvers: Vec::new(),
+            tag_handlers: Vec::new(),
+            max_documents: None,
+            max_input_bytes: None,
+            report_progress: None,
+        }
+    }
+
     /// Creates a YAML deserializer from a `&str`.
     pub fn from_str(s: &'de str) -> Self {
-        let progress = Progress::Str(s);
-        Deserializer { progress }
+        Deserializer::new(Progress::Str(s))
     }
 
     /// Creates a YAML deserializer from a `&[u8]`.
     pub fn from_slice(v: &'de [u8]) -> Self {
-        let progress = Progress::Slice(v);
-        Deserializer { progress }
+        Deserializer::new(Progress::Slice(v))
+    }
+
+    /// Creates a YAML deserializer from bytes that are either borrowed or
+    /// owned.
+    ///
+    /// Borrowed input deserializes like [`from_slice`](Self::from_slice), so
+    /// `&'de str` fields and `Cow<'de, str>` fields marked `#[serde(borrow)]`
+    /// can point into it. Owned input is dropped along with the deserializer,
+    /// so nothing can borrow from it: `Cow` fields come out owned and `&str`
+    /// fields fail to deserialize. To borrow from an owned buffer for as long
+    /// as it is kept around, pass `&buffer` instead.
+    pub fn from_cow(input: impl Into<Cow<'de, [u8]>>) -> Self {
+        let progress = match input.into() {
+            Cow::Borrowed(bytes) => Progress::Slice(bytes),
+            Cow::Owned(bytes) => Progress::Owned(bytes),
+        };
+        Deserializer::new(progress)
+    }
+
+    /// Creates a YAML deserializer from a string that is either borrowed or
+    /// owned, such as a `Cow<str>`, `String` or `&str`.
+    ///
+    /// What can borrow from it is the same as for
+    /// [`from_cow`](Self::from_cow).
+    pub fn from_cow_str(input: impl Into<Cow<'de, str>>) -> Self {
+        let progress = match input.into() {
+            Cow::Borrowed(s) => Progress::Str(s),
+            Cow::Owned(s) => Progress::Owned(s.into_bytes()),
+        };
+        Deserializer::new(progress)
     }
 
     /// Creates a YAML deserializer from an `io::Read`.
@@ -85,56 +213,604 @@ impl<'de> Deserializer<'de> {
     /// Reader-based deserializers do not support deserializing borrowed types
     /// like `&str`, since the `std::io::Read` trait has no non-copying methods
     /// -- everything it does involves copying bytes out of the data source.
-    pub fn from_reader<R>(rdr: R) -> Self
+    ///
+    /// The reader is read to the end when the deserializer is created. An I/O
+    /// error is reported once deserialization begins. To stop reading input
+    /// that is too long, use [`from_reader_with_limit`](Self::from_reader_with_limit).
+    ///
+    /// The reader must be blocking. A read that fails with
+    /// [`Interrupted`](io::ErrorKind::Interrupted) is retried, as
+    /// [`Read::read_to_end`](io::Read::read_to_end) does, but any other
+    /// error, including [`WouldBlock`](io::ErrorKind::WouldBlock), ends the
+    /// input, and every document of the stream fails with that error. Input
+    /// from a non-blocking source has to be collected until it is complete
+    /// and then deserialized with [`from_slice`](Self::from_slice).
+    pub fn from_reader<R>(mut rdr: R) -> Self
+    where
+        R: io::Read + 'de,
+    {
+        let mut buffer = Vec::new();
+        let progress = match rdr.read_to_end(&mut buffer) {
+            Ok(_) => Progress::Owned(buffer),
+            Err(io_error) => Progress::Fail(Arc::new(ErrorImpl::Io(io_error))),
+        };
+        Deserializer::new(progress)
+    }
+
+    /// Creates a YAML deserializer from an `io::Read`, reading no more than
+    /// one byte past `max_input_bytes`.
+    ///
+    /// Input longer than that fails once deserialization begins, as with
+    /// [`max_input_bytes`](Self::max_input_bytes), without the rest of it
+    /// being read into memory.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use std::io::{self, Read};
+    ///
+    /// // An endless stream of documents.
+    /// let input = io::repeat(b'-').take(u64::MAX);
+    ///
+    /// let de = serde_yaml::Deserializer::from_reader_with_limit(input, 1 << 20);
+    /// let err = serde_yaml::Value::deserialize(de).unwrap_err();
+    /// assert_eq!(err.to_string(), "input exceeds the limit of 1048576 bytes");
+    /// ```
+    pub fn from_reader_with_limit<R>(rdr: R, max_input_bytes: usize) -> Self
     where
         R: io::Read + 'de,
     {
-        let progress = Progress::Read(Box::new(rdr));
-        Deserializer { progress }
+        let limit = (max_input_bytes as u64).saturating_add(1);
+        let mut buffer = Vec::new();
+        let progress = match io::Read::read_to_end(&mut rdr.take(limit), &mut buffer) {
+            Ok(len) if len > max_input_bytes => Progress::Fail(Arc::new(
+                ErrorImpl::InputLimitExceeded(max_input_bytes, None),
+            )),
+            Ok(_) => Progress::Owned(buffer),
+            Err(io_error) => Progress::Fail(Arc::new(ErrorImpl::Io(io_error))),
+        };
+        Deserializer::new(progress).max_input_bytes(max_input_bytes)
+    }
+
+    /// Keeps iterating over a multi-document stream after a document that is
+    /// not well-formed YAML.
+    ///
+    /// By default, once a document fails to parse, every later document
+    /// yields the same error. With recovery enabled, the broken document
+    /// yields its error and iteration resumes at the next line that starts
+    /// with `---`. Documents between the error and that line are skipped.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// let input = "---\na: 1\n---\nb: [2\n---\nc: 3\n";
+    /// let de = serde_yaml::Deserializer::from_str(input).recover_documents(true);
+    ///
+    /// let results: Vec<_> = de.map(serde_yaml::Value::deserialize).collect();
+    /// assert_eq!(results.len(), 3);
+    /// assert!(results[0].is_ok());
+    /// assert!(results[1].is_err());
+    /// assert!(results[2].is_ok());
+    /// ```
+    pub fn recover_documents(mut self, recover: bool) -> Self {
+        self.recover = recover;
+        self
+    }
+
+    /// Sets what to do with an anchor that is defined more than once in the
+    /// same document. Anchors are scoped to a document, so defining the same
+    /// anchor again in a later document of a stream is always allowed.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, DuplicateAnchors, Value};
+    ///
+    /// let input = "a: &x 1\nb: &x 2\nc: *x\n";
+    ///
+    /// let de = Deserializer::from_str(input);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["c"], 2);
+    ///
+    /// let de = Deserializer::from_str(input).duplicate_anchors(DuplicateAnchors::Error);
+    /// let err = Value::deserialize(de).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "duplicate anchor \"x\" at line 2 column 4, first defined at line 1 column 4",
+    /// );
+    /// ```
+    pub fn duplicate_anchors(mut self, duplicate_anchors: DuplicateAnchors) -> Self {
+        self.duplicate_anchors = duplicate_anchors;
+        self
+    }
+
+    /// Sets whether a `String` or `&str` accepts a scalar that YAML resolves
+    /// to something other than a string, like `3`, `3.10` or `true`.
+    ///
+    /// This is on by default, and the string is the scalar's text exactly as
+    /// written, so `3.10` stays `"3.10"` and `0x1F` stays `"0x1F"`. That
+    /// includes null: `null` and `~` are read as those words, and a missing
+    /// value as an empty string. Turned off, such scalars are an invalid type
+    /// for a string, as they are when deserializing from a [`Value`]. Either
+    /// way, sequences and mappings are never strings, and a [`Value`] still
+    /// holds a number for `3`.
+    ///
+    /// [`Value`]: crate::Value
+    ///
+    /// ```
+    /// # use serde_derive::Deserialize;
+    /// use serde::Deserialize;
+    /// use serde_yaml::Deserializer;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Package {
+    ///     version: String,
+    /// }
+    ///
+    /// let input = "version: 3.10\n";
+    ///
+    /// let package = Package::deserialize(Deserializer::from_str(input)).unwrap();
+    /// assert_eq!(package.version, "3.10");
+    ///
+    /// let de = Deserializer::from_str(input).stringify_scalars(false);
+    /// let err = Package::deserialize(de).err().unwrap();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "version: invalid type: floating point `3.1`, expected a string at line 1 column 10",
+    /// );
+    /// ```
+    pub fn stringify_scalars(mut self, stringify_scalars: bool) -> Self {
+        self.stringify_scalars = stringify_scalars;
+        self
+    }
+
+    /// Resolves plain scalars as YAML 1.1 does, for documents written for a
+    /// parser that follows it.
+    ///
+    /// By default, plain scalars are resolved by the YAML 1.2 core schema, in
+    /// which `yes`, `on`, `0755`, `1:30`, `1_000` and `=` are all strings. In
+    /// YAML 1.1 the first two are booleans and the next three are the
+    /// integers 493, 90 and 1000, while `1e3` and `0o17` are strings rather
+    /// than numbers. YAML 1.1 reads `=` as the "default value", which has no
+    /// meaning in Rust, so it is an error here as it is in most YAML 1.1
+    /// parsers. Quoted and tagged scalars are unaffected.
+    ///
+    /// Since each scalar is resolved as it is parsed,
+    /// [`ScalarInfo::text`](crate::ScalarInfo::text) reports one that YAML 1.1
+    /// reads differently as YAML 1.2 would write it, like `true` for `yes`.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, Value};
+    ///
+    /// let input = "enabled: yes\nmode: 0755\n";
+    ///
+    /// let value = Value::deserialize(Deserializer::from_str(input)).unwrap();
+    /// assert_eq!(value["enabled"], "yes");
+    /// assert_eq!(value["mode"], "0755");
+    ///
+    /// let de = Deserializer::from_str(input).legacy_resolution(true);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["enabled"], true);
+    /// assert_eq!(value["mode"], 0o755);
+    /// ```
+    pub fn legacy_resolution(mut self, legacy_resolution: bool) -> Self {
+        self.legacy_resolution = legacy_resolution;
+        self
+    }
+
+    /// Resolves plain scalars by the given schema instead of the YAML 1.2
+    /// core schema.
+    ///
+    /// A plain scalar that the schema does not resolve is a string, and is
+    /// deserialized as if it had been quoted, whether into a [`Value`] or a
+    /// typed field. So under [`Schema::Failsafe`], `version: 3.10` can only
+    /// be the string `"3.10"`, and a `u16` field needs `!!int 8080` rather
+    /// than `8080`. [`legacy_resolution`](Deserializer::legacy_resolution)
+    /// only applies with the core schema.
+    ///
+    /// [`Value`]: crate::Value
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, Schema, Value};
+    ///
+    /// let input = "version: 3.10\nenabled: true\nnothing: ~\n";
+    ///
+    /// let de = Deserializer::from_str(input).schema(Schema::Json);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["version"], 3.1);
+    /// assert_eq!(value["enabled"], true);
+    /// assert_eq!(value["nothing"], "~");
+    ///
+    /// let de = Deserializer::from_str(input).schema(Schema::Failsafe);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["version"], "3.10");
+    /// assert_eq!(value["enabled"], "true");
+    /// assert_eq!(value["nothing"], "~");
+    /// ```
+    pub fn schema(mut self, schema: Schema) -> Self {
+        self.schema = schema;
+        self
+    }
+
+    /// Adds a function that tags untagged plain scalars, so that they can be
+    /// recognized by what they look like.
+    ///
+    /// `resolve` is given the text of each plain scalar without a tag, and
+    /// returns the tag to give it, written in full like `!duration`, or
+    /// `None` to leave it alone. Resolvers are tried in the order they were
+    /// added, and the first one to return a tag wins. Quoted scalars and
+    /// scalars with an explicit tag are never passed to a resolver.
+    ///
+    /// The tagged scalar deserializes as if the tag had been written in the
+    /// document, for example as a [`Value::Tagged`](crate::Value::Tagged), or
+    /// as the variant of an enum named by the tag, with errors pointing at
+    /// the scalar.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, Value};
+    ///
+    /// fn duration(scalar: &str) -> Option<&'static str> {
+    ///     let digits = scalar.trim_end_matches(['m', 's', 'h']);
+    ///     let valid = !digits.is_empty()
+    ///         && digits.bytes().all(|b| b.is_ascii_digit())
+    ///         && ["ms", "s", "m", "h"].contains(&&scalar[digits.len()..]);
+    ///     valid.then_some("!duration")
+    /// }
+    ///
+    /// let de = Deserializer::from_str("timeout: 30s\nretries: 3\n").resolver(duration);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["timeout"].to_string(), "!duration 30s");
+    /// assert_eq!(value["retries"], 3);
+    /// ```
+    pub fn resolver(mut self, resolve: fn(&str) -> Option<&'static str>) -> Self {
+        self.resolvers.push(resolve);
+        self
+    }
+
+    /// Deserializes the value that `handler` returns for each scalar with the
+    /// given tag in place of the scalar, as if the document had it there.
+    ///
+    /// This is how a convention like `!include other.yaml` is supported: the
+    /// handler is called with the scalar, here `other.yaml`, and reads and
+    /// parses the file it names. The tag is written in full, like `!include`.
+    /// A sequence or mapping with the tag is not passed to the handler, and
+    /// deserializes as it would without one. Registering a tag again replaces
+    /// its handler.
+    ///
+    /// Scalars with the tag in what a handler returns are handled in turn, up
+    /// to the same depth that collections may be nested. Like aliases, the
+    /// handlers may be called at most 100 times for each node of the document
+    /// itself, and what they return is counted toward the bytes of scalars
+    /// that aliases may repeat. An error from a handler, or past those limits,
+    /// fails the document at the tagged scalar.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, Value};
+    ///
+    /// fn include(path: &str) -> serde_yaml::Result<Value> {
+    ///     match path {
+    ///         "db.yaml" => serde_yaml::from_str("host: localhost\nport: 5432\n"),
+    ///         _ => Err(serde::de::Error::custom(format!("{} not found", path))),
+    ///     }
+    /// }
+    ///
+    /// let input = "name: web\ndatabase: !include db.yaml\n";
+    /// let de = Deserializer::from_str(input).tag_handler("!include", include);
+    /// let value = Value::deserialize(de).unwrap();
+    /// assert_eq!(value["database"]["port"], 5432);
+    ///
+    /// let input = "name: web\ndatabase: !include other.yaml\n";
+    /// let de = Deserializer::from_str(input).tag_handler("!include", include);
+    /// let err = Value::deserialize(de).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "!include other.yaml at line 2 column 11: other.yaml not found",
+    /// );
+    /// ```
+    pub fn tag_handler<F>(mut self, tag: &str, handler: F) -> Self
+    where
+        F: Fn(&str) -> Result<crate::Value> + Send + Sync + 'static,
+    {
+        self.tag_handlers.retain(|(handled, _)| handled != tag);
+        self.tag_handlers.push((tag.to_owned(), Arc::new(handler)));
+        self
+    }
+
+    /// Fails when a stream has more than `max_documents` documents.
+    ///
+    /// The first document past the limit yields an error, and is the last one
+    /// yielded, without the rest of the stream being parsed. Each document is
+    /// held to the usual limits on nesting and aliases, so this bounds the
+    /// work that input of many small documents can cause.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, Value};
+    ///
+    /// let input = "--- 1\n--- 2\n--- 3\n";
+    /// let mut documents = Deserializer::from_str(input).max_documents(2);
+    ///
+    /// assert_eq!(Value::deserialize(documents.next().unwrap()).unwrap(), 1);
+    /// assert_eq!(Value::deserialize(documents.next().unwrap()).unwrap(), 2);
+    /// let err = Value::deserialize(documents.next().unwrap()).unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "document 3 exceeds the limit of 2 documents at line 3 column 1",
+    /// );
+    /// assert!(documents.next().is_none());
+    /// ```
+    pub fn max_documents(mut self, max_documents: usize) -> Self {
+        self.max_documents = Some(max_documents);
+        self
+    }
+
+    /// Fails when the input is longer than `max_input_bytes`, before any of
+    /// it is parsed.
+    ///
+    /// A deserializer [from a reader](Self::from_reader) has read all of its
+    /// input by the time this is called; use
+    /// [`from_reader_with_limit`](Self::from_reader_with_limit) to stop
+    /// reading at the limit instead.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    ///
+    /// let de = serde_yaml::Deserializer::from_str("a: 1\nb: 2\n").max_input_bytes(8);
+    /// let err = serde_yaml::Value::deserialize(de).unwrap_err();
+    /// assert_eq!(err.to_string(), "input of 10 bytes exceeds the limit of 8 bytes");
+    /// ```
+    pub fn max_input_bytes(mut self, max_input_bytes: usize) -> Self {
+        self.max_input_bytes = Some(max_input_bytes);
+        self
+    }
+
+    /// Calls `report` with the number of bytes parsed so far each time at
+    /// least another `every_n_bytes` have been parsed, and once more at the
+    /// end of the input, to show progress through a large input.
+    ///
+    /// The count is where the parser's latest event began, so it may be
+    /// behind by the length of one scalar. The parser runs a document at a
+    /// time, before the document is deserialized, so the calls are
+    /// interleaved with deserializing when iterating over the documents of a
+    /// stream. A [reader](Self::from_reader) has been read to the end before
+    /// parsing begins, so the count is of the bytes read from it.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use std::sync::atomic::{AtomicU64, Ordering};
+    ///
+    /// static PARSED: AtomicU64 = AtomicU64::new(0);
+    ///
+    /// fn report(bytes: u64) {
+    ///     PARSED.store(bytes, Ordering::Relaxed);
+    /// }
+    ///
+    /// let input = "a: 1\nb: 2\n";
+    /// let de = serde_yaml::Deserializer::from_str(input).progress(4, report);
+    /// serde_yaml::Value::deserialize(de).unwrap();
+    /// assert_eq!(PARSED.load(Ordering::Relaxed), input.len() as u64);
+    /// ```
+    pub fn progress(mut self, every_n_bytes: u64, report: fn(u64)) -> Self {
+        self.report_progress = Some((every_n_bytes, report));
+        self
+    }
+
+    /// The number of bytes of input parsed so far, which is where the
+    /// parser's latest event began.
+    ///
+    /// While iterating over the documents of a stream, this is how far the
+    /// parser is through the input. A document yielded by the iterator
+    /// reports how far into the input its own last event is, and a
+    /// deserializer that has not begun parsing reports 0.
+    ///
+    /// ```
+    /// let mut documents = serde_yaml::Deserializer::from_str("--- 1\n--- 22\n");
+    /// assert_eq!(documents.bytes_consumed(), 0);
+    /// documents.next().unwrap();
+    /// assert_eq!(documents.bytes_consumed(), 6);
+    /// documents.next().unwrap();
+    /// assert_eq!(documents.bytes_consumed(), 13);
+    /// assert!(documents.next().is_none());
+    /// assert_eq!(documents.bytes_consumed(), 13);
+    /// ```
+    pub fn bytes_consumed(&self) -> u64 {
+        match &self.progress {
+            Progress::Iterable(loader) => loader.bytes_consumed(),
+            Progress::Document(document) => document
+                .events
+                .last()
+                .map_or(0, |(_event, mark)| mark.index() as u64),
+            _ => 0,
+        }
+    }
+
+    // Begins parsing the input, if it is within the limits.
+    fn loader(&self, progress: Progress<'de>) -> Result<Loader<'de>> {
+        let len = match &progress {
+            Progress::Str(s) => Some(s.len()),
+            Progress::Slice(bytes) => Some(bytes.len()),
+            Progress::Owned(bytes) => Some(bytes.len()),
+            _ => None,
+        };
+        if let (Some(len), Some(max)) = (len, self.max_input_bytes) {
+            if len > max {
+                return Err(error::new(ErrorImpl::InputLimitExceeded(max, Some(len))));
+            }
+        }
+        let mut loader = Loader::new(progress)?;
+        loader.recover_documents(self.recover);
+        loader.duplicate_anchors(self.duplicate_anchors);
+        loader.max_documents(self.max_documents);
+        loader.legacy_resolution(self.legacy_resolution);
+        loader.schema(self.schema);
+        loader.resolvers(self.resolvers.clone());
+        loader.tag_handlers(self.tag_handlers.clone());
+        if let Some((every_n_bytes, report)) = self.report_progress {
+            loader.report_progress(every_n_bytes, report);
+        }
+        Ok(loader)
+    }
+
+    // One of the documents that iterating over this deserializer yields.
+    fn yielded(&self, progress: Progress<'de>) -> Self {
+        Deserializer {
+            stringify_scalars: self.stringify_scalars,
+            ..Deserializer::new(progress)
+        }
+    }
+
+    /// Iterates over the documents of a multi-document stream like the
+    /// deserializer itself does, but deserializes each one into a
+    /// [`DocumentValue`](crate::DocumentValue) that also records its `---` and `...` markers and
+    /// its directives.
+    ///
+    /// Writing the documents back out with
+    /// [`to_writer_documents`](crate::to_writer_documents) keeps that
+    /// framing, which writing a `Vec<Value>` loses.
+    ///
+    /// ```
+    /// # fn main() -> serde_yaml::Result<()> {
+    /// use serde_yaml::{Deserializer, DocumentValue};
+    ///
+    /// let input = "%YAML 1.2\n---\na: 1\n...\n---\nb: 2\n";
+    ///
+    /// let documents = Deserializer::from_str(input)
+    ///     .document_values()
+    ///     .collect::<serde_yaml::Result<Vec<DocumentValue>>>()?;
+    /// assert_eq!(documents[0].version, Some((1, 2)));
+    /// assert!(documents[0].explicit_end);
+    /// assert!(!documents[1].explicit_end);
+    ///
+    /// let mut output = Vec::new();
+    /// serde_yaml::to_writer_documents(&mut output, &documents)?;
+    /// assert_eq!(String::from_utf8(output).unwrap(), input);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn document_values(self) -> DocumentValues<'de> {
+        DocumentValues { de: self }
+    }
+
+    /// Checks that the input is well-formed YAML that deserializing would
+    /// accept, without deserializing it into anything.
+    ///
+    /// Every document is parsed, and checked against the options of this
+    /// deserializer and the limits that deserializing applies: how deeply
+    /// collections nest, including within what aliases repeat, and how much
+    /// aliases repeat. Nothing is built from the documents and no scalar is
+    /// resolved, which saves about a third of the time that deserializing
+    /// into a [`Value`](crate::Value) takes, and most of the memory. Whether
+    /// the documents would deserialize into any particular type is not
+    /// checked.
+    ///
+    /// The first error is returned, at the same location as deserializing
+    /// would report it. A deserializer that was yielded by iterating over a
+    /// stream checks just its own document.
+    ///
+    /// ```
+    /// use serde_yaml::Deserializer;
+    ///
+    /// let input = "--- {a: 1}\n--- [b, *c]\n";
+    /// let err = Deserializer::from_str(input).validate().unwrap_err();
+    /// assert_eq!(err.to_string(), "unknown anchor at line 2 column 9");
+    ///
+    /// let err = Deserializer::from_str(input)
+    ///     .max_documents(1)
+    ///     .validate()
+    ///     .unwrap_err();
+    /// assert_eq!(
+    ///     err.to_string(),
+    ///     "document 2 exceeds the limit of 1 documents at line 2 column 1",
+    /// );
+    /// ```
+    pub fn validate(mut self) -> Result<()> {
+        let dummy = Progress::Str("");
+        let mut loader = match mem::replace(&mut self.progress, dummy) {
+            Progress::Document(document) => {
+                return validate_document(&document)
+                    .map_err(|err| error::in_document(err, document.span));
+            }
+            Progress::Fail(err) => return Err(error::shared(err)),
+            Progress::Iterable(loader) => loader,
+            input => self.loader(input)?,
+        };
+        while let Some(document) = loader.next_document() {
+            validate_document(&document).map_err(|err| error::in_document(err, document.span))?;
+        }
+        Ok(())
+    }
+
+    /// The `%TAG` directives of the document that this deserializer was
+    /// yielded for by iterating over a stream, as pairs of handle and prefix
+    /// in the order they were written.
+    ///
+    /// Tags are resolved with these before they reach the `Deserialize` impl,
+    /// so this is what [`Tag::shorthand`](crate::value::Tag::shorthand) needs
+    /// to write a tag the way the document did. A deserializer that has not
+    /// been yielded by iterating has none.
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::{Deserializer, Value};
+    ///
+    /// let input = "%TAG !x! !local-\n--- !x!thing 1\n--- !other 2\n";
+    /// let shorthands: Vec<String> = Deserializer::from_str(input)
+    ///     .map(|document| {
+    ///         let directives = document.tag_directives().to_vec();
+    ///         let value = Value::deserialize(document).unwrap();
+    ///         value.tag().unwrap().shorthand(&directives).unwrap()
+    ///     })
+    ///     .collect();
+    /// assert_eq!(shorthands, ["!x!thing", "!other"]);
+    /// ```
+    pub fn tag_directives(&self) -> &[(String, String)] {
+        match &self.progress {
+            Progress::Document(document) => &document.start.tag_directives,
+            _ => &[],
+        }
+    }
+
+    // The start and end of the document this deserializer was yielded for by
+    // the iterator, or the implicit ones of a document on its own.
+    pub(crate) fn framing(&self) -> (DocumentStart, DocumentEnd) {
+        match &self.progress {
+            Progress::Document(document) => (document.start.clone(), document.end.clone()),
+            _ => (loader::implicit_start(), DocumentEnd { implicit: true }),
+        }
     }
 
     fn de<T>(
         self,
         f: impl for<'document> FnOnce(&mut DeserializerFromEvents<'de, 'document>) -> Result<T>,
     ) -> Result<T> {
-        let mut pos = 0;
-        let mut jumpcount = 0;
+        self.de_with_ignored(None, f)
+    }
 
+    fn de_with_ignored<T>(
+        mut self,
+        ignored: Option<&mut IgnoredCallback>,
+        f: impl for<'document> FnOnce(&mut DeserializerFromEvents<'de, 'document>) -> Result<T>,
+    ) -> Result<T> {
         match self.progress {
             Progress::Iterable(_) => return Err(error::new(ErrorImpl::MoreThanOneDocument)),
             Progress::Document(document) => {
-                let t = f(&mut DeserializerFromEvents {
-                    document: &document,
-                    pos: &mut pos,
-                    jumpcount: &mut jumpcount,
-                    path: Path::Root,
-                    remaining_depth: 128,
-                    current_enum: None,
-                })?;
-                if let Some(parse_error) = document.error {
-                    return Err(error::shared(parse_error));
-                }
-                return Ok(t);
+                return de_document(&document, ignored, self.stringify_scalars, f)
+                    .map_err(|err| error::in_document(err, document.span));
             }
             _ => {}
         }
 
-        let mut loader = Loader::new(self.progress)?;
+        let dummy = Progress::Str("");
+        let input = mem::replace(&mut self.progress, dummy);
+        let mut loader = self.loader(input)?;
+        loader.recover_documents(false);
         let document = match loader.next_document() {
             Some(document) => document,
             None => return Err(error::new(ErrorImpl::EndOfStream)),
         };
-        let t = f(&mut DeserializerFromEvents {
-            document: &document,
-            pos: &mut pos,
-            jumpcount: &mut jumpcount,
-            path: Path::Root,
-            remaining_depth: 128,
-            current_enum: None,
-        })?;
-        if let Some(parse_error) = document.error {
-            return Err(error::shared(parse_error));
-        }
+        let t = de_document(&document, ignored, self.stringify_scalars, f)?;
         if loader.next_document().is_none() {
             Ok(t)
         } else {
@@ -143,6 +819,152 @@ impl<'de> Deserializer<'de> {
     }
 }
 
+// Fails where deserializing the document into a `Value` would, other than
+// because of what its scalars are: if it failed to load, or if collections
+// nest too deeply or aliases repeat too much.
+fn validate_document(document: &Document) -> Result<()> {
+    struct Open {
+        // Where the collection starts.
+        pos: usize,
+        // How deeply collections nest within it, counting what aliases
+        // repeat.
+        depth: usize,
+        // What aliases had repeated before it started.
+        jumps: usize,
+        bytes: usize,
+    }
+
+    if let Some(err) = &document.error {
+        return Err(error::shared(Arc::clone(err)));
+    }
+    let events = &document.events;
+    let len = match (events.first(), events.last()) {
+        (Some((_, first)), Some((_, last))) => last.index() - first.index(),
+        _ => 0,
+    };
+    // For each collection by where it starts, how deeply collections nest
+    // within it and how much its aliases repeat, all counting what aliases
+    // repeat.
+    let mut collections = HashMap::new();
+    let mut open: Vec<Open> = Vec::new();
+    let (mut jumps, mut bytes) = (0usize, 0usize);
+    for (pos, (event, mark)) in events.iter().enumerate() {
+        match event {
+            Event::SequenceStart(_) | Event::MappingStart(_) => {
+                if open.len() >= 128 {
+                    return Err(error::new(ErrorImpl::RecursionLimitExceeded(*mark)));
+                }
+                open.push(Open {
+                    pos,
+                    depth: 0,
+                    jumps,
+                    bytes,
+                });
+            }
+            Event::SequenceEnd | Event::MappingEnd => {
+                let collection = open.pop().unwrap();
+                let depth = collection.depth + 1;
+                if let Some(parent) = open.last_mut() {
+                    parent.depth = parent.depth.max(depth);
+                }
+                let repeated = (jumps - collection.jumps, bytes - collection.bytes);
+                collections.insert(collection.pos, (depth, repeated));
+            }
+            Event::Alias(id) => {
+                let target = document.aliases[id];
+                if open.iter().any(|collection| collection.pos == target) {
+                    // Within the node it refers to, which nests in itself
+                    // until the limit.
+                    let mark = events[target].1;
+                    return Err(error::new(ErrorImpl::RecursionLimitExceeded(mark)));
+                }
+                let (depth, (inner_jumps, inner_bytes)) =
+                    collections.get(&target).copied().unwrap_or((0, (0, 0)));
+                if open.len() + depth > 128 {
+                    let mark = too_deep(document, &collections, target, open.len());
+                    return Err(error::new(ErrorImpl::RecursionLimitExceeded(mark)));
+                }
+                if let Some(parent) = open.last_mut() {
+                    parent.depth = parent.depth.max(depth);
+                }
+                let anchored = document.anchored_bytes.get(id).copied().unwrap_or(0);
+                jumps = jumps.saturating_add(1).saturating_add(inner_jumps);
+                bytes = bytes.saturating_add(anchored).saturating_add(inner_bytes);
+                if !loader::repetition_allowed(jumps, bytes, events.len(), len) {
+                    return Err(error::new(ErrorImpl::RepetitionLimitExceeded));
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+// Where deserializing first nests collections too deeply within the node at
+// `pos`, which starts `level` collections deep and is known to nest too
+// deeply, following aliases into the collections they repeat.
+fn too_deep(
+    document: &Document,
+    collections: &HashMap<usize, (usize, (usize, usize))>,
+    pos: usize,
+    mut level: usize,
+) -> Mark {
+    for (event, mark) in &document.events[pos..] {
+        match event {
+            Event::SequenceStart(_) | Event::MappingStart(_) => {
+                level += 1;
+                if level > 128 {
+                    return *mark;
+                }
+            }
+            Event::SequenceEnd | Event::MappingEnd => level -= 1,
+            Event::Alias(id) => {
+                let target = document.aliases[id];
+                if let Some((depth, _repeated)) = collections.get(&target) {
+                    if level + depth > 128 {
+                        return too_deep(document, collections, target, level);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    unreachable!()
+}
+
+fn de_document<'de, T>(
+    document: &Document<'de>,
+    mut ignored: Option<&mut IgnoredCallback>,
+    stringify_scalars: bool,
+    f: impl for<'document> FnOnce(&mut DeserializerFromEvents<'de, 'document>) -> Result<T>,
+) -> Result<T> {
+    let mut pos = 0;
+    let mut repetition = Repetition::default();
+    let mut raised = None;
+    let mut expected = None;
+    let t = f(&mut DeserializerFromEvents {
+        document,
+        pos: &mut pos,
+        repetition: &mut repetition,
+        raised: &mut raised,
+        expected: &mut expected,
+        path: Path::Root,
+        remaining_depth: 128,
+        current_enum: None,
+        ignored: reborrow(&mut ignored),
+        stringify_scalars,
+        key_mark: None,
+        is_key: false,
+    });
+    #[cfg(feature = "expected")]
+    let t = t.map_err(|err| error::with_expected(err, expected));
+    let t = t?;
+    if let Some(parse_error) = &document.error {
+        return Err(error::shared(Arc::clone(parse_error)));
+    }
+    Ok(t)
+}
+
 impl<'de> Iterator for Deserializer<'de> {
     type Item = Self;
 
@@ -150,22 +972,19 @@ impl<'de> Iterator for Deserializer<'de> {
         match &mut self.progress {
             Progress::Iterable(loader) => {
                 let document = loader.next_document()?;
-                return Some(Deserializer {
-                    progress: Progress::Document(document),
-                });
+                return Some(self.yielded(Progress::Document(document)));
             }
             Progress::Document(_) => return None,
             Progress::Fail(err) => {
-                return Some(Deserializer {
-                    progress: Progress::Fail(Arc::clone(err)),
-                });
+                let err = Arc::clone(err);
+                return Some(self.yielded(Progress::Fail(err)));
             }
             _ => {}
         }
 
         let dummy = Progress::Str("");
         let input = mem::replace(&mut self.progress, dummy);
-        match Loader::new(input) {
+        match self.loader(input) {
             Ok(loader) => {
                 self.progress = Progress::Iterable(loader);
                 self.next()
@@ -173,9 +992,7 @@ impl<'de> Iterator for Deserializer<'de> {
             Err(err) => {
                 let fail = err.shared();
                 self.progress = Progress::Fail(Arc::clone(&fail));
-                Some(Deserializer {
-                    progress: Progress::Fail(fail),
-                })
+                Some(self.yielded(Progress::Fail(fail)))
             }
         }
     }
@@ -431,10 +1248,42 @@ pub(crate) enum Event<'de> {
 struct DeserializerFromEvents<'de, 'document> {
     document: &'document Document<'de>,
     pos: &'document mut usize,
-    jumpcount: &'document mut usize,
+    repetition: &'document mut Repetition,
+    // The last error returned to a visitor, in case the visitor returns it
+    // again as just a message.
+    raised: &'document mut Option<Arc<ErrorImpl>>,
+    expected: &'document mut Option<Expected>,
     path: Path<'document>,
     remaining_depth: u8,
     current_enum: Option<CurrentEnum<'document>>,
+    ignored: Option<&'document mut IgnoredCallback<'document>>,
+    stringify_scalars: bool,
+    // Where the key begins, if this is the value of a mapping entry.
+    key_mark: Option<Mark>,
+    // Whether the node is a mapping key, which is read by the mapping's own
+    // deserializer.
+    is_key: bool,
+}
+
+// How much aliases have repeated so far in the document being deserialized.
+#[derive(Default)]
+struct Repetition {
+    jumps: usize,
+    bytes: usize,
+}
+
+type IgnoredCallback<'a> = dyn FnMut(&str, Mark) + 'a;
+
+// Lends the callback to a nested deserializer. `Option::as_deref_mut` would
+// keep the longer lifetime in the trait object, which `&mut` does not let
+// shrink afterwards.
+fn reborrow<'a>(
+    ignored: &'a mut Option<&mut IgnoredCallback>,
+) -> Option<&'a mut IgnoredCallback<'a>> {
+    match ignored {
+        Some(callback) => Some(&mut **callback),
+        None => None,
+    }
 }
 
 #[derive(Copy, Clone)]
@@ -449,7 +1298,7 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
     }
 
     fn peek_event_mark(&self) -> Result<(&'document Event<'de>, Mark)> {
-        match self.document.events.get(*self.pos) {
+        match self.document.event(*self.pos) {
             Some((event, mark)) => Ok((event, *mark)),
             None => Err(match &self.document.error {
                 Some(parse_error) => error::shared(Arc::clone(parse_error)),
@@ -462,6 +1311,19 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         self.next_event_mark().map(|(event, _mark)| event)
     }
 
+    // Like `next_event`, but leaves the value of a scalar that was left in the
+    // input there, for events that are only skipped.
+    fn skip_event(&mut self) -> Result<&'document Event<'de>> {
+        match self.document.events.get(*self.pos) {
+            Some((event, _mark)) => {
+                *self.pos += 1;
+                self.current_enum = None;
+                Ok(event)
+            }
+            None => self.next_event(),
+        }
+    }
+
     fn next_event_mark(&mut self) -> Result<(&'document Event<'de>, Mark)> {
         self.peek_event_mark().map(|(event, mark)| {
             *self.pos += 1;
@@ -470,12 +1332,207 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         })
     }
 
+    // The kind and tag of the next node, following aliases, for `peek`.
+    fn peek_node(&self) -> Result<(NodeKind, Option<String>)> {
+        let mut event = self.peek_event()?;
+        if let Event::Alias(pos) = event {
+            match self.document.aliases.get(pos) {
+                Some(found) => event = &self.document.events[*found].0,
+                None => panic!("unresolved alias: {}", *pos),
+            }
+        }
+        let (kind, tag) = match event {
+            Event::Scalar(scalar) => (NodeKind::Scalar, &scalar.tag),
+            Event::SequenceStart(sequence) => (NodeKind::Sequence, &sequence.tag),
+            Event::MappingStart(mapping) => (NodeKind::Mapping, &mapping.tag),
+            Event::Void => return Ok((NodeKind::Scalar, None)),
+            Event::Alias(_) | Event::SequenceEnd | Event::MappingEnd => {
+                return Err(error::new(ErrorImpl::EndOfStream));
+            }
+        };
+        // A tag that an enum has already taken as its variant is not seen by
+        // the variant's contents.
+        let tag = match (tag, self.current_enum) {
+            (Some(tag), None) => Some(String::from_utf8_lossy(tag.as_ref()).into_owned()),
+            _ => None,
+        };
+        Ok((kind, tag))
+    }
+
+    // Hands a `ScalarInfo` the style and text of the next scalar, following
+    // aliases, then the scalar itself.
+    fn deserialize_scalar_info<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let (mut next, mark) = self.peek_event_mark()?;
+        if let Event::Alias(pos) = next {
+            match self.document.aliases.get(pos) {
+                Some(found) => next = &self.document.event(*found).unwrap().0,
+                None => panic!("unresolved alias: {}", *pos),
+            }
+        }
+        let (style, text) = match next {
+            Event::Scalar(scalar) => (
+                scalar.style,
+                String::from_utf8_lossy(&scalar.value).into_owned(),
+            ),
+            Event::Void => (ScalarStyle::Plain, String::new()),
+            other => {
+                let err = invalid_type(other, &visitor);
+                return Err(error::fix_mark(err, mark, self.path));
+            }
+        };
+        visitor.visit_map(ScalarInfoAccess::new(style, text, self))
+    }
+
+    // Deserializes an integer between `min` and `max`, which are the range of
+    // the type `ty`, failing with the scalar as written if it is out of range.
+    fn deserialize_int<V>(
+        &mut self,
+        visitor: V,
+        ty: &'static str,
+        min: i128,
+        max: u128,
+    ) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let tagged_already = self.current_enum.is_some();
+        let (next, mark) = self.next_event_mark()?;
+        let result = loop {
+            match next {
+                Event::Alias(mut pos) => {
+                    break self.jump(&mut pos)?.deserialize_int(visitor, ty, min, max);
+                }
+                Event::Scalar(scalar)
+                    if is_plain_or_tagged_scalar(Tag::INT, scalar, tagged_already) =>
+                {
+                    if let Ok(value) = str::from_utf8(scalar.value.as_ref()) {
+                        // Whether it is negative, and its magnitude.
+                        let int = match parse_signed_int(value, i128::from_str_radix) {
+                            Some(int) => Some((int < 0, int.unsigned_abs())),
+                            None => parse_unsigned_int(value, u128::from_str_radix)
+                                .map(|int| (false, int)),
+                        };
+                        let wide = max > u128::from(u64::MAX);
+                        match int {
+                            Some((true, magnitude)) if magnitude <= min.unsigned_abs() => {
+                                let int = 0i128.wrapping_sub(magnitude as i128);
+                                break if wide {
+                                    visitor.visit_i128(int)
+                                } else {
+                                    visitor.visit_i64(int as i64)
+                                };
+                            }
+                            Some((false, int)) if int <= max => {
+                                break match (min < 0, wide) {
+                                    (true, true) => visitor.visit_i128(int as i128),
+                                    (true, false) => visitor.visit_i64(int as i64),
+                                    (false, true) => visitor.visit_u128(int),
+                                    (false, false) => visitor.visit_u64(int as u64),
+                                };
+                            }
+                            _ => {}
+                        }
+                        if is_int_literal(value) {
+                            let range = OutOfRange {
+                                text: value,
+                                ty,
+                                min,
+                                max,
+                            };
+                            break Err(de::Error::custom(range));
+                        }
+                    }
+                }
+                _ => {}
+            }
+            break Err(invalid_type(next, &visitor));
+        }
+        .map_err(|err| error::fix_mark(err, mark, self.path));
+        result
+    }
+
+    // Hands a `StyledValue` the next node: a scalar the same way as to a
+    // `ScalarInfo`, and anything else as it is.
+    fn deserialize_styled<V>(&mut self, visitor: V) -> Result<V::Value>
+    where
+        V: Visitor<'de>,
+    {
+        let mut next = self.peek_event()?;
+        if let Event::Alias(pos) = next {
+            match self.document.aliases.get(pos) {
+                Some(found) => next = &self.document.events[*found].0,
+                None => panic!("unresolved alias: {}", *pos),
+            }
+        }
+        match next {
+            Event::Scalar(_) | Event::Void => self.deserialize_scalar_info(visitor),
+            _ => de::Deserializer::deserialize_any(self, visitor),
+        }
+    }
+
+    // A mapping where the type expects a sequence, or the other way around, is
+    // worth pointing out when it is the whole document rather than some value
+    // within it, since then the document most likely has the wrong shape.
+    fn root_mismatch(&self, expected: &'static str) -> Option<Error> {
+        if !matches!(self.path, Path::Root) {
+            return None;
+        }
+        let (event, mark) = self.peek_event_mark().ok()?;
+        let (found, hint) = match event {
+            Event::SequenceStart(sequence) if expected != "a sequence" => {
+                let first_is_mapping = matches!(
+                    self.document.events.get(*self.pos + 1),
+                    Some((Event::MappingStart(_), _)),
+                );
+                let hint = if sequence.style == CollectionStyle::Block && first_is_mapping {
+                    Some("did you mean to remove the `- ` before the first key?")
+                } else {
+                    None
+                };
+                ("a sequence", hint)
+            }
+            Event::MappingStart(_) if expected != "a mapping" => (
+                "a mapping",
+                Some("did you mean to start each entry with `- `?"),
+            ),
+            Event::Scalar(scalar)
+                if scalar.style != ScalarStyle::Plain || !scalar.value.is_empty() =>
+            {
+                let value = scalar.value.as_ref();
+                let missing_space = scalar.style == ScalarStyle::Plain
+                    && value
+                        .windows(2)
+                        .any(|pair| pair[0] == b':' && !pair[1].is_ascii_whitespace());
+                let hint = if expected == "a mapping" && missing_space {
+                    Some("did you mean to put a space after `:`?")
+                } else {
+                    None
+                };
+                ("a scalar", hint)
+            }
+            _ => return None,
+        };
+        Some(error::new(ErrorImpl::RootMismatch(
+            expected, found, hint, mark,
+        )))
+    }
+
     fn jump<'anchor>(
         &'anchor mut self,
         pos: &'anchor mut usize,
     ) -> Result<DeserializerFromEvents<'de, 'anchor>> {
-        *self.jumpcount += 1;
-        if *self.jumpcount > self.document.events.len() * 100 {
+        self.repetition.jumps += 1;
+        self.repetition.bytes += self.document.anchored_bytes.get(pos).copied().unwrap_or(0);
+        let events = &self.document.events;
+        let len = match (events.first(), events.last()) {
+            (Some((_, first)), Some((_, last))) => last.index() - first.index(),
+            _ => 0,
+        };
+        let (jumps, bytes) = (self.repetition.jumps, self.repetition.bytes);
+        if !loader::repetition_allowed(jumps, bytes, events.len(), len) {
             return Err(error::new(ErrorImpl::RepetitionLimitExceeded));
         }
         match self.document.aliases.get(pos) {
@@ -484,10 +1541,16 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
                 Ok(DeserializerFromEvents {
                     document: self.document,
                     pos,
-                    jumpcount: self.jumpcount,
+                    repetition: self.repetition,
+                    raised: self.raised,
+                    expected: self.expected,
                     path: Path::Alias { parent: &self.path },
                     remaining_depth: self.remaining_depth,
                     current_enum: None,
+                    ignored: reborrow(&mut self.ignored),
+                    stringify_scalars: self.stringify_scalars,
+                    key_mark: self.key_mark,
+                    is_key: self.is_key,
                 })
             }
             None => panic!("unresolved alias: {}", *pos),
@@ -503,7 +1566,7 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
         let mut stack = Vec::new();
 
         loop {
-            match self.next_event()? {
+            match self.skip_event()? {
                 Event::Alias(_) | Event::Scalar(_) | Event::Void => {}
                 Event::SequenceStart(_) => {
                     stack.push(Nest::Sequence);
@@ -534,7 +1597,8 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
     where
         V: Visitor<'de>,
     {
-        let (value, len) = self.recursion_check(mark, |de| {
+        let is_key = self.is_key;
+        self.recursion_check(mark, |de| {
             let mut seq = SeqAccess {
                 empty: false,
                 de,
@@ -542,89 +1606,85 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
             };
             let value = visitor.visit_seq(&mut seq)?;
             Ok((value, seq.len))
-        })?;
-        self.end_sequence(len)?;
-        Ok(value)
+        })
+        .and_then(|(value, len)| {
+            self.end_sequence(len)?;
+            Ok(value)
+        })
+        .map_err(|err| {
+            let err = self.key_error(err, is_key, "a sequence".to_owned(), mark);
+            error::in_node(err, "sequence", mark, self.path)
+        })
     }
 
     fn visit_mapping<V>(&mut self, visitor: V, mark: Mark) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let (value, len) = self.recursion_check(mark, |de| {
+        let is_key = self.is_key;
+        self.recursion_check(mark, |de| {
             let mut map = MapAccess {
                 empty: false,
                 de,
                 len: 0,
                 key: None,
+                key_mark: None,
             };
             let value = visitor.visit_map(&mut map)?;
             Ok((value, map.len))
-        })?;
-        self.end_mapping(len)?;
-        Ok(value)
+        })
+        .and_then(|(value, len)| {
+            self.end_mapping(len)?;
+            Ok(value)
+        })
+        .map_err(|err| {
+            let err = self.key_error(err, is_key, "a mapping".to_owned(), mark);
+            error::in_node(err, "mapping", mark, self.path)
+        })
     }
 
-    fn end_sequence(&mut self, len: usize) -> Result<()> {
-        let total = {
-            let mut seq = SeqAccess {
-                empty: false,
-                de: self,
-                len,
-            };
-            while de::SeqAccess::next_element::<IgnoredAny>(&mut seq)?.is_some() {}
-            seq.len
-        };
-        match self.next_event()? {
-            Event::SequenceEnd | Event::Void => {}
-            _ => panic!("expected a SequenceEnd event"),
-        }
-        if total == len {
-            Ok(())
+    // For an error from the visitor given a mapping key, says what the key is
+    // if the visitor refused it outright, as a serializer for a format that
+    // only has string keys does.
+    fn key_error(&mut self, err: Error, is_key: bool, kind: String, mark: Mark) -> Error {
+        let err = error::restore(err, self.raised);
+        if is_key {
+            error::rejected(err, "mapping key".to_owned(), Some(kind), mark, self.path)
         } else {
-            struct ExpectedSeq(usize);
-            impl Expected for ExpectedSeq {
-                fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                    if self.0 == 1 {
-                        write!(formatter, "sequence of 1 element")
-                    } else {
-                        write!(formatter, "sequence of {} elements", self.0)
-                    }
+            err
+        }
+    }
+
+    // Notes what the type expected here if deserializing the node failed, for
+    // `Error::expected`. A struct or enum only does if nothing inside it failed
+    // first, which would say more.
+    fn expect<T>(&mut self, result: Result<T>, expected: Expected) -> Result<T> {
+        match &result {
+            Ok(_) => *self.expected = None,
+            Err(_) => {
+                if self.expected.is_none() || matches!(expected, Expected::Primitive(_)) {
+                    *self.expected = Some(expected);
                 }
             }
-            Err(de::Error::invalid_length(total, &ExpectedSeq(len)))
         }
+        result
     }
 
-    fn end_mapping(&mut self, len: usize) -> Result<()> {
-        let total = {
-            let mut map = MapAccess {
-                empty: false,
-                de: self,
-                len,
-                key: None,
-            };
-            while de::MapAccess::next_entry::<IgnoredAny, IgnoredAny>(&mut map)?.is_some() {}
-            map.len
-        };
-        match self.next_event()? {
-            Event::MappingEnd | Event::Void => {}
-            _ => panic!("expected a MappingEnd event"),
+    // Fails if the visitor stopped before the end of the sequence, pointing at
+    // the first element it left.
+    fn end_sequence(&mut self, len: usize) -> Result<()> {
+        match self.next_event_mark()? {
+            (Event::SequenceEnd | Event::Void, _mark) => Ok(()),
+            (_, mark) => Err(error::fix_mark(trailing_elements(len), mark, self.path)),
         }
-        if total == len {
-            Ok(())
-        } else {
-            struct ExpectedMap(usize);
-            impl Expected for ExpectedMap {
-                fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                    if self.0 == 1 {
-                        write!(formatter, "map containing 1 entry")
-                    } else {
-                        write!(formatter, "map containing {} entries", self.0)
-                    }
-                }
-            }
-            Err(de::Error::invalid_length(total, &ExpectedMap(len)))
+    }
+
+    // Fails if the visitor stopped before the end of the mapping, pointing at
+    // the key of the first entry it left.
+    fn end_mapping(&mut self, len: usize) -> Result<()> {
+        match self.next_event_mark()? {
+            (Event::MappingEnd | Event::Void, _mark) => Ok(()),
+            (_, mark) => Err(error::fix_mark(trailing_entries(len), mark, self.path)),
         }
     }
 
@@ -644,6 +1704,130 @@ impl<'de, 'document> DeserializerFromEvents<'de, 'document> {
     }
 }
 
+pub(crate) fn trailing_elements(len: usize) -> Error {
+    if len == 1 {
+        de::Error::custom("unexpected trailing elements, expected sequence of 1 element")
+    } else {
+        de::Error::custom(format_args!(
+            "unexpected trailing elements, expected sequence of {} elements",
+            len,
+        ))
+    }
+}
+
+pub(crate) fn trailing_entries(len: usize) -> Error {
+    if len == 1 {
+        de::Error::custom("unexpected trailing entries, expected map containing 1 entry")
+    } else {
+        de::Error::custom(format_args!(
+            "unexpected trailing entries, expected map containing {} entries",
+            len,
+        ))
+    }
+}
+
+// A unit struct named `name` deserializes from null, from an empty mapping,
+// from either of those tagged with its name like `!Marker`, or from a mapping
+// of its name to either like `Marker: null`, the way an enum's unit variant
+// can be written.
+pub(crate) struct UnitStructVisitor<V> {
+    pub name: &'static str,
+    pub visitor: V,
+}
+
+impl<'de, V> Visitor<'de> for UnitStructVisitor<V>
+where
+    V: Visitor<'de>,
+{
+    type Value = V::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        self.visitor.expecting(formatter)
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_none<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        self.visitor.visit_unit()
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let key = match map.next_key::<String>()? {
+            Some(key) => key,
+            None => return self.visitor.visit_unit(),
+        };
+        if key != self.name {
+            return Err(de::Error::invalid_value(Unexpected::Str(&key), &self));
+        }
+        map.next_value::<NullOrEmpty>()?;
+        if map.next_key::<de::IgnoredAny>()?.is_some() {
+            return Err(de::Error::invalid_length(2, &self));
+        }
+        self.visitor.visit_unit()
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::EnumAccess<'de>,
+    {
+        let (tag, content) = data.variant::<String>()?;
+        if tag != self.name {
+            let tag = format!("tag `!{}`", tag);
+            return Err(de::Error::invalid_value(Unexpected::Other(&tag), &self));
+        }
+        de::VariantAccess::newtype_variant::<NullOrEmpty>(content)?;
+        self.visitor.visit_unit()
+    }
+}
+
+// The content of a unit struct's tag or entry.
+struct NullOrEmpty;
+
+impl<'de> Deserialize<'de> for NullOrEmpty {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: de::Deserializer<'de>,
+    {
+        deserializer.deserialize_any(NullOrEmpty)
+    }
+}
+
+impl<'de> Visitor<'de> for NullOrEmpty {
+    type Value = NullOrEmpty;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("null or an empty mapping")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E>
+    where
+        E: de::Error,
+    {
+        Ok(NullOrEmpty)
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        match map.next_key::<de::IgnoredAny>()? {
+            Some(de::IgnoredAny) => Err(de::Error::invalid_length(1, &self)),
+            None => Ok(NullOrEmpty),
+        }
+    }
+}
+
 struct SeqAccess<'de, 'document, 'seq> {
     empty: bool,
     de: &'seq mut DeserializerFromEvents<'de, 'document>,
@@ -666,13 +1850,19 @@ impl<'de, 'document, 'seq> de::SeqAccess<'de> for SeqAccess<'de, 'document, 'seq
                 let mut element_de = DeserializerFromEvents {
                     document: self.de.document,
                     pos: self.de.pos,
-                    jumpcount: self.de.jumpcount,
+                    repetition: self.de.repetition,
+                    raised: self.de.raised,
+                    expected: self.de.expected,
                     path: Path::Seq {
                         parent: &self.de.path,
                         index: self.len,
                     },
                     remaining_depth: self.de.remaining_depth,
                     current_enum: None,
+                    ignored: reborrow(&mut self.de.ignored),
+                    stringify_scalars: self.de.stringify_scalars,
+                    key_mark: None,
+                    is_key: false,
                 };
                 self.len += 1;
                 seed.deserialize(&mut element_de).map(Some)
@@ -686,6 +1876,7 @@ struct MapAccess<'de, 'document, 'map> {
     de: &'map mut DeserializerFromEvents<'de, 'document>,
     len: usize,
     key: Option<&'document [u8]>,
+    key_mark: Option<Mark>,
 }
 
 impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map> {
@@ -698,17 +1889,25 @@ impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map
         if self.empty {
             return Ok(None);
         }
-        match self.de.peek_event()? {
+        let (event, mark) = self.de.peek_event_mark()?;
+        self.key_mark = Some(mark);
+        match event {
             Event::MappingEnd | Event::Void => Ok(None),
             Event::Scalar(scalar) => {
                 self.len += 1;
                 self.key = Some(scalar.value.as_ref());
-                seed.deserialize(&mut *self.de).map(Some)
+                self.de.is_key = true;
+                let key = seed.deserialize(&mut *self.de);
+                self.de.is_key = false;
+                key.map(Some)
             }
             _ => {
                 self.len += 1;
                 self.key = None;
-                seed.deserialize(&mut *self.de).map(Some)
+                self.de.is_key = true;
+                let key = seed.deserialize(&mut *self.de);
+                self.de.is_key = false;
+                key.map(Some)
             }
         }
     }
@@ -720,7 +1919,9 @@ impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map
         let mut value_de = DeserializerFromEvents {
             document: self.de.document,
             pos: self.de.pos,
-            jumpcount: self.de.jumpcount,
+            repetition: self.de.repetition,
+            raised: self.de.raised,
+            expected: self.de.expected,
             path: if let Some(key) = self.key.and_then(|key| str::from_utf8(key).ok()) {
                 Path::Map {
                     parent: &self.de.path,
@@ -733,6 +1934,10 @@ impl<'de, 'document, 'map> de::MapAccess<'de> for MapAccess<'de, 'document, 'map
             },
             remaining_depth: self.de.remaining_depth,
             current_enum: None,
+            ignored: reborrow(&mut self.de.ignored),
+            stringify_scalars: self.de.stringify_scalars,
+            key_mark: self.key_mark,
+            is_key: false,
         };
         seed.deserialize(&mut value_de)
     }
@@ -757,13 +1962,19 @@ impl<'de, 'document, 'variant> de::EnumAccess<'de> for EnumAccess<'de, 'document
         let visitor = DeserializerFromEvents {
             document: self.de.document,
             pos: self.de.pos,
-            jumpcount: self.de.jumpcount,
+            repetition: self.de.repetition,
+            raised: self.de.raised,
+            expected: self.de.expected,
             path: self.de.path,
             remaining_depth: self.de.remaining_depth,
             current_enum: Some(CurrentEnum {
                 name: self.name,
                 tag: self.tag,
             }),
+            ignored: reborrow(&mut self.de.ignored),
+            stringify_scalars: self.de.stringify_scalars,
+            key_mark: None,
+            is_key: false,
         };
         Ok((variant, visitor))
     }
@@ -876,6 +2087,7 @@ where
         } else if tag.as_ref() == Tag::INT {
             return match visit_int(visitor, v) {
                 Ok(result) => result,
+                Err(_) if is_int_literal(v) => Err(out_of_any_range(v)),
                 Err(_) => Err(de::Error::invalid_value(Unexpected::Str(v), &"an integer")),
             };
         } else if tag.as_ref() == Tag::FLOAT {
@@ -884,41 +2096,32 @@ where
                 None => Err(de::Error::invalid_value(Unexpected::Str(v), &"a float")),
             };
         } else if tag.as_ref() == Tag::NULL {
-            return match parse_null(v.as_bytes()) {
+            return match parse_tagged_null(v.as_bytes()) {
                 Some(()) => visitor.visit_unit(),
                 None => Err(de::Error::invalid_value(Unexpected::Str(v), &"null")),
             };
         } else if tag.as_ref().starts_with(b"!") && scalar.style == ScalarStyle::Plain {
-            return visit_untagged_scalar(visitor, v, scalar.repr, scalar.style);
+            return visit_untagged_scalar(visitor, v, borrowed_str(v, &scalar.value));
         }
     } else if scalar.style == ScalarStyle::Plain {
-        return visit_untagged_scalar(visitor, v, scalar.repr, scalar.style);
+        return visit_untagged_scalar(visitor, v, borrowed_str(v, &scalar.value));
     }
-    if let Some(borrowed) = parse_borrowed_str(v, scalar.repr, scalar.style) {
+    if let Some(borrowed) = borrowed_str(v, &scalar.value) {
         visitor.visit_borrowed_str(borrowed)
     } else {
         visitor.visit_str(v)
     }
 }
 
-fn parse_borrowed_str<'de>(
-    utf8_value: &str,
-    repr: Option<&'de [u8]>,
-    style: ScalarStyle,
-) -> Option<&'de str> {
-    let borrowed_repr = repr?;
-    let expected_offset = match style {
-        ScalarStyle::Plain => 0,
-        ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted => 1,
-        ScalarStyle::Literal | ScalarStyle::Folded => return None,
-    };
-    let expected_end = borrowed_repr.len().checked_sub(expected_offset)?;
-    let expected_start = expected_end.checked_sub(utf8_value.len())?;
-    let borrowed_bytes = borrowed_repr.get(expected_start..expected_end)?;
-    if borrowed_bytes == utf8_value.as_bytes() {
-        return Some(unsafe { str::from_utf8_unchecked(borrowed_bytes) });
+// Returns `utf8_value` with the lifetime of the input, if it was parsed from
+// bytes borrowed from the input.
+fn borrowed_str<'de>(utf8_value: &str, value: &Cow<'de, [u8]>) -> Option<&'de str> {
+    match value {
+        Cow::Borrowed(bytes) if ptr::eq(*bytes, utf8_value.as_bytes()) => {
+            Some(unsafe { str::from_utf8_unchecked(bytes) })
+        }
+        _ => None,
     }
-    None
 }
 
 fn parse_null(scalar: &[u8]) -> Option<()> {
@@ -928,6 +2131,16 @@ fn parse_null(scalar: &[u8]) -> Option<()> {
     }
 }
 
+// The core schema's null also matches the empty string, which a `!!null` tag
+// makes null even when it is quoted.
+fn parse_tagged_null(scalar: &[u8]) -> Option<()> {
+    if scalar.is_empty() {
+        Some(())
+    } else {
+        parse_null(scalar)
+    }
+}
+
 fn parse_bool(scalar: &str) -> Option<bool> {
     match scalar {
         "true" | "True" | "TRUE" => Some(true),
@@ -1095,6 +2308,72 @@ pub(crate) fn digits_but_not_number(scalar: &str) -> bool {
     scalar.len() > 1 && scalar.starts_with('0') && scalar[1..].bytes().all(|b| b.is_ascii_digit())
 }
 
+// Whether a plain scalar is written as an integer, even one too large or too
+// small for any integer type.
+pub(crate) fn is_int_literal(scalar: &str) -> bool {
+    let unsigned = scalar.strip_prefix(['-', '+']).unwrap_or(scalar);
+    let (digits, radix) = if let Some(digits) = unsigned.strip_prefix("0x") {
+        (digits, 16)
+    } else if let Some(digits) = unsigned.strip_prefix("0o") {
+        (digits, 8)
+    } else if let Some(digits) = unsigned.strip_prefix("0b") {
+        (digits, 2)
+    } else {
+        (unsigned, 10)
+    };
+    !digits.is_empty()
+        && digits.chars().all(|ch| ch.is_digit(radix))
+        && !digits_but_not_number(scalar)
+}
+
+// An integer that does not fit in the type it is deserialized into, quoted as
+// it was written.
+pub(crate) struct OutOfRange<'a> {
+    pub text: &'a str,
+    pub ty: &'static str,
+    pub min: i128,
+    pub max: u128,
+}
+
+impl<'a> fmt::Display for OutOfRange<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "integer `{}` is out of range for {} ({} to {})",
+            self.text, self.ty, self.min, self.max,
+        )
+    }
+}
+
+// Integers that do not fit in `i128` or `u128`, which are the widest that
+// this crate reads, whatever they are deserialized into.
+fn out_of_any_range(scalar: &str) -> Error {
+    de::Error::custom(OutOfRange {
+        text: scalar,
+        ty: "i128 or u128",
+        min: i128::MIN,
+        max: u128::MAX,
+    })
+}
+
+// Whether a scalar resolves to an integer that does not fit in `i128` or
+// `u128`.
+fn is_out_of_any_range(scalar: &Scalar) -> bool {
+    let resolved = match &scalar.tag {
+        None => true,
+        Some(tag) => tag.as_ref() == Tag::INT || tag.as_ref().starts_with(b"!"),
+    };
+    let v = match str::from_utf8(&scalar.value) {
+        Ok(v) => v,
+        Err(_) => return false,
+    };
+    resolved
+        && scalar.style == ScalarStyle::Plain
+        && is_int_literal(v)
+        && parse_signed_int(v, i128::from_str_radix).is_none()
+        && parse_unsigned_int(v, u128::from_str_radix).is_none()
+}
+
 pub(crate) fn visit_int<'de, V>(visitor: V, v: &str) -> Result<Result<V::Value>, V>
 where
     V: Visitor<'de>,
@@ -1105,11 +2384,15 @@ where
     if let Some(int) = parse_negative_int(v, i64::from_str_radix) {
         return Ok(visitor.visit_i64(int));
     }
+    // A visitor that cannot hold one this wide, like that of `Value`, is
+    // only given it in decimal.
     if let Some(int) = parse_unsigned_int(v, u128::from_str_radix) {
-        return Ok(visitor.visit_u128(int));
+        let result = visitor.visit_u128(int);
+        return Ok(result.map_err(|err| error::int_as_written(err, &int.to_string(), v)));
     }
     if let Some(int) = parse_negative_int(v, i128::from_str_radix) {
-        return Ok(visitor.visit_i128(int));
+        let result = visitor.visit_i128(int);
+        return Ok(result.map_err(|err| error::int_as_written(err, &int.to_string(), v)));
     }
     Err(visitor)
 }
@@ -1117,8 +2400,7 @@ where
 pub(crate) fn visit_untagged_scalar<'de, V>(
     visitor: V,
     v: &str,
-    repr: Option<&'de [u8]>,
-    style: ScalarStyle,
+    borrowed: Option<&'de str>,
 ) -> Result<V::Value>
 where
     V: Visitor<'de>,
@@ -1133,35 +2415,128 @@ where
         Ok(result) => return result,
         Err(visitor) => visitor,
     };
+    if is_int_literal(v) {
+        return Err(out_of_any_range(v));
+    }
     if !digits_but_not_number(v) {
         if let Some(float) = parse_f64(v) {
             return visitor.visit_f64(float);
         }
     }
-    if let Some(borrowed) = parse_borrowed_str(v, repr, style) {
+    if let Some(borrowed) = borrowed {
         visitor.visit_borrowed_str(borrowed)
     } else {
         visitor.visit_str(v)
     }
 }
 
-fn is_plain_or_tagged_literal_scalar(
-    expected: &[u8],
-    scalar: &Scalar,
-    tagged_already: bool,
-) -> bool {
-    match (scalar.style, &scalar.tag, tagged_already) {
-        (ScalarStyle::Plain, _, _) => true,
-        (ScalarStyle::Literal, Some(tag), false) => tag.as_ref() == expected,
-        _ => false,
+// Gives an untagged plain scalar the tag from the first resolver that has one
+// for it.
+pub(crate) fn apply_resolvers(resolvers: &[Resolver], scalar: &mut Scalar) {
+    if resolvers.is_empty() || scalar.style != ScalarStyle::Plain || scalar.tag.is_some() {
+        return;
+    }
+    let value = match str::from_utf8(&scalar.value) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    scalar.tag = resolvers
+        .iter()
+        .find_map(|resolve| resolve(value))
+        .map(Tag::new);
+}
+
+// Tags a plain scalar that the core schema resolves to something other than
+// a string as a string, if the given schema does not resolve it.
+pub(crate) fn restrict_to_schema(schema: Schema, scalar: &mut Scalar) {
+    if schema == Schema::Core || scalar.style != ScalarStyle::Plain || scalar.tag.is_some() {
+        return;
+    }
+    let value = match str::from_utf8(&scalar.value) {
+        Ok(value) => value,
+        Err(_) => return,
+    };
+    if schema == Schema::Json && is_json_scalar(value) {
+        return;
+    }
+
+    struct IsString;
+
+    impl<'de> Visitor<'de> for IsString {
+        type Value = ();
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string")
+        }
+
+        fn visit_str<E>(self, _v: &str) -> Result<Self::Value, E> {
+            Ok(())
+        }
+    }
+
+    if visit_untagged_scalar(IsString, value, None).is_err() {
+        scalar.tag = Some(Tag::new(Tag::STR));
+    }
+}
+
+// `null`, `true`, `false`, or a number matching
+// `-?(0|[1-9][0-9]*)(\.[0-9]*)?([eE][-+]?[0-9]+)?`.
+fn is_json_scalar(scalar: &str) -> bool {
+    if let "null" | "true" | "false" = scalar {
+        return true;
+    }
+    let mut rest = scalar.strip_prefix('-').unwrap_or(scalar).as_bytes();
+    let digits = |rest: &mut &[u8]| {
+        let n = rest.iter().take_while(|b| b.is_ascii_digit()).count();
+        *rest = &rest[n..];
+        n
+    };
+    let int = rest;
+    match digits(&mut rest) {
+        0 => return false,
+        1 => {}
+        _ if int[0] == b'0' => return false,
+        _ => {}
+    }
+    if let Some(fraction) = rest.strip_prefix(b".") {
+        rest = fraction;
+        digits(&mut rest);
+    }
+    if let Some(exponent) = rest.strip_prefix(b"e").or_else(|| rest.strip_prefix(b"E")) {
+        rest = exponent;
+        if let Some(unsigned) = rest.strip_prefix(b"-").or_else(|| rest.strip_prefix(b"+")) {
+            rest = unsigned;
+        }
+        if digits(&mut rest) == 0 {
+            return false;
+        }
+    }
+    rest.is_empty()
+}
+
+// Whether a scalar can be read as the core schema type `expected`. A scalar
+// with one of the core tags is read as that type whatever its style, so
+// `!!int "42"` is an integer and `!!str 42` is not. Otherwise only plain
+// scalars are candidates.
+fn is_plain_or_tagged_scalar(expected: &[u8], scalar: &Scalar, tagged_already: bool) -> bool {
+    match (&scalar.tag, tagged_already) {
+        (Some(tag), false) if is_core_scalar_tag(tag) => {
+            let tag = tag.as_ref();
+            tag == expected || (tag == Tag::INT && expected == Tag::FLOAT)
+        }
+        _ => scalar.style == ScalarStyle::Plain,
     }
 }
 
-fn invalid_type(event: &Event, exp: &dyn Expected) -> Error {
+fn is_core_scalar_tag(tag: &Tag) -> bool {
+    [Tag::NULL, Tag::BOOL, Tag::INT, Tag::FLOAT, Tag::STR].contains(&tag.as_ref())
+}
+
+fn invalid_type(event: &Event, exp: &dyn de::Expected) -> Error {
     enum Void {}
 
     struct InvalidType<'a> {
-        exp: &'a dyn Expected,
+        exp: &'a dyn de::Expected,
     }
 
     impl<'de, 'a> Visitor<'de> for InvalidType<'a> {
@@ -1174,6 +2549,12 @@ fn invalid_type(event: &Event, exp: &dyn Expected) -> Error {
 
     match event {
         Event::Alias(_) => unreachable!(),
+        Event::Scalar(scalar) if is_out_of_any_range(scalar) => {
+            // Rather than that it is out of range, which is beside the point
+            // if no integer was expected.
+            let v = String::from_utf8_lossy(&scalar.value);
+            de::Error::invalid_type(Unexpected::Other(&format!("integer `{}`", v)), exp)
+        }
         Event::Scalar(scalar) => {
             let get_type = InvalidType { exp };
             match visit_scalar(get_type, scalar, false) {
@@ -1209,6 +2590,8 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let tagged_already = self.current_enum.is_some();
+        let is_key = self.is_key;
+        let start = *self.pos;
         let (next, mark) = self.next_event_mark()?;
         fn enum_tag(tag: &Option<Tag>, tagged_already: bool) -> Option<&str> {
             if tagged_already {
@@ -1257,9 +2640,42 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                 Event::Void => break visitor.visit_none(),
             }
         }
-        // The de::Error impl creates errors with unknown line and column. Fill
-        // in the position here by looking at the current index in the input.
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| {
+            let err = error::restore(err, self.raised);
+            // A visitor that does not take enums refuses a tagged node
+            // without looking inside it. Any other error about the node is
+            // reported as it is.
+            let tag = match next {
+                Event::Scalar(scalar) => scalar.tag.as_ref(),
+                Event::SequenceStart(sequence) => sequence.tag.as_ref(),
+                Event::MappingStart(mapping) => mapping.tag.as_ref(),
+                _ => None,
+            };
+            let err = match (tag, next) {
+                (Some(tag), _) if *self.pos == start && error::refuses_enum(&err) => {
+                    let tag = String::from_utf8_lossy(tag.as_ref());
+                    let node = format!("tagged value `{}`", tag);
+                    error::rejected(err, node, None, mark, self.path)
+                }
+                (_, Event::Scalar(scalar)) => {
+                    let kind = if scalar.value.is_empty() {
+                        "empty".to_owned()
+                    } else {
+                        let text = String::from_utf8_lossy(&scalar.value);
+                        error::Backticked(&text).to_string()
+                    };
+                    self.key_error(err, is_key, kind, mark)
+                }
+                _ => err,
+            };
+            // The de::Error impl creates errors with unknown line and column.
+            // Fill in the position here by looking at the current index in
+            // the input.
+            let err = error::fix_mark(err, mark, self.path);
+            // Kept in case the visitor passes it through another format's
+            // error type on the way out of an enclosing node.
+            error::raise(err, self.raised)
+        })
     }
 
     fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value>
@@ -1268,11 +2684,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     {
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
-        loop {
+        let result = loop {
             match next {
                 Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_bool(visitor),
                 Event::Scalar(scalar)
-                    if is_plain_or_tagged_literal_scalar(Tag::BOOL, scalar, tagged_already) =>
+                    if is_plain_or_tagged_scalar(Tag::BOOL, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(scalar.value.as_ref()) {
                         if let Some(boolean) = parse_bool(value) {
@@ -1284,156 +2700,96 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
             }
             break Err(invalid_type(next, &visitor));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, self.path));
+        self.expect(result, Expected::Primitive("bool"))
     }
 
     fn deserialize_i8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_i64(visitor)
+        let result = self.deserialize_int(visitor, "i8", i8::MIN.into(), i8::MAX as u128);
+        self.expect(result, Expected::Primitive("i8"))
     }
 
     fn deserialize_i16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_i64(visitor)
+        let result = self.deserialize_int(visitor, "i16", i16::MIN.into(), i16::MAX as u128);
+        self.expect(result, Expected::Primitive("i16"))
     }
 
     fn deserialize_i32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_i64(visitor)
+        let result = self.deserialize_int(visitor, "i32", i32::MIN.into(), i32::MAX as u128);
+        self.expect(result, Expected::Primitive("i32"))
     }
 
     fn deserialize_i64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let tagged_already = self.current_enum.is_some();
-        let (next, mark) = self.next_event_mark()?;
-        loop {
-            match next {
-                Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_i64(visitor),
-                Event::Scalar(scalar)
-                    if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
-                {
-                    if let Ok(value) = str::from_utf8(scalar.value.as_ref()) {
-                        if let Some(int) = parse_signed_int(value, i64::from_str_radix) {
-                            break visitor.visit_i64(int);
-                        }
-                    }
-                }
-                _ => {}
-            }
-            break Err(invalid_type(next, &visitor));
-        }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        let result = self.deserialize_int(visitor, "i64", i64::MIN.into(), i64::MAX as u128);
+        self.expect(result, Expected::Primitive("i64"))
     }
 
     fn deserialize_i128<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let tagged_already = self.current_enum.is_some();
-        let (next, mark) = self.next_event_mark()?;
-        loop {
-            match next {
-                Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_i128(visitor),
-                Event::Scalar(scalar)
-                    if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
-                {
-                    if let Ok(value) = str::from_utf8(scalar.value.as_ref()) {
-                        if let Some(int) = parse_signed_int(value, i128::from_str_radix) {
-                            break visitor.visit_i128(int);
-                        }
-                    }
-                }
-                _ => {}
-            }
-            break Err(invalid_type(next, &visitor));
-        }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        let result = self.deserialize_int(visitor, "i128", i128::MIN, i128::MAX as u128);
+        self.expect(result, Expected::Primitive("i128"))
     }
 
     fn deserialize_u8<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_u64(visitor)
+        let result = self.deserialize_int(visitor, "u8", 0, u8::MAX.into());
+        self.expect(result, Expected::Primitive("u8"))
     }
 
     fn deserialize_u16<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_u64(visitor)
+        let result = self.deserialize_int(visitor, "u16", 0, u16::MAX.into());
+        self.expect(result, Expected::Primitive("u16"))
     }
 
     fn deserialize_u32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_u64(visitor)
+        let result = self.deserialize_int(visitor, "u32", 0, u32::MAX.into());
+        self.expect(result, Expected::Primitive("u32"))
     }
 
     fn deserialize_u64<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let tagged_already = self.current_enum.is_some();
-        let (next, mark) = self.next_event_mark()?;
-        loop {
-            match next {
-                Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_u64(visitor),
-                Event::Scalar(scalar)
-                    if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
-                {
-                    if let Ok(value) = str::from_utf8(scalar.value.as_ref()) {
-                        if let Some(int) = parse_unsigned_int(value, u64::from_str_radix) {
-                            break visitor.visit_u64(int);
-                        }
-                    }
-                }
-                _ => {}
-            }
-            break Err(invalid_type(next, &visitor));
-        }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        let result = self.deserialize_int(visitor, "u64", 0, u64::MAX.into());
+        self.expect(result, Expected::Primitive("u64"))
     }
 
     fn deserialize_u128<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        let tagged_already = self.current_enum.is_some();
-        let (next, mark) = self.next_event_mark()?;
-        loop {
-            match next {
-                Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_u128(visitor),
-                Event::Scalar(scalar)
-                    if is_plain_or_tagged_literal_scalar(Tag::INT, scalar, tagged_already) =>
-                {
-                    if let Ok(value) = str::from_utf8(scalar.value.as_ref()) {
-                        if let Some(int) = parse_unsigned_int(value, u128::from_str_radix) {
-                            break visitor.visit_u128(int);
-                        }
-                    }
-                }
-                _ => {}
-            }
-            break Err(invalid_type(next, &visitor));
-        }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        let result = self.deserialize_int(visitor, "u128", 0, u128::MAX);
+        self.expect(result, Expected::Primitive("u128"))
     }
 
     fn deserialize_f32<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_f64(visitor)
+        let result = self.deserialize_f64(visitor);
+        self.expect(result, Expected::Primitive("f32"))
     }
 
     fn deserialize_f64<V>(self, visitor: V) -> Result<V::Value>
@@ -1442,11 +2798,11 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     {
         let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
-        loop {
+        let result = loop {
             match next {
                 Event::Alias(mut pos) => break self.jump(&mut pos)?.deserialize_f64(visitor),
                 Event::Scalar(scalar)
-                    if is_plain_or_tagged_literal_scalar(Tag::FLOAT, scalar, tagged_already) =>
+                    if is_plain_or_tagged_scalar(Tag::FLOAT, scalar, tagged_already) =>
                 {
                     if let Ok(value) = str::from_utf8(scalar.value.as_ref()) {
                         if let Some(float) = parse_f64(value) {
@@ -1458,25 +2814,31 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
             }
             break Err(invalid_type(next, &visitor));
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, self.path));
+        self.expect(result, Expected::Primitive("f64"))
     }
 
     fn deserialize_char<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        let result = self.deserialize_str(visitor);
+        self.expect(result, Expected::Primitive("char"))
     }
 
     fn deserialize_str<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        let tagged_already = self.current_enum.is_some();
         let (next, mark) = self.next_event_mark()?;
-        match next {
+        let result = match next {
+            Event::Scalar(scalar) if !self.stringify_scalars => {
+                visit_scalar(visitor, scalar, tagged_already)
+            }
             Event::Scalar(scalar) => {
                 if let Ok(v) = str::from_utf8(scalar.value.as_ref()) {
-                    if let Some(borrowed) = parse_borrowed_str(v, scalar.repr, scalar.style) {
+                    if let Some(borrowed) = borrowed_str(v, &scalar.value) {
                         visitor.visit_borrowed_str(borrowed)
                     } else {
                         visitor.visit_str(v)
@@ -1488,14 +2850,16 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_str(visitor),
             other => Err(invalid_type(other, &visitor)),
         }
-        .map_err(|err: Error| error::fix_mark(err, mark, self.path))
+        .map_err(|err: Error| error::fix_mark(err, mark, self.path));
+        self.expect(result, Expected::Primitive("str"))
     }
 
     fn deserialize_string<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        let result = self.deserialize_str(visitor);
+        self.expect(result, Expected::Primitive("string"))
     }
 
     fn deserialize_bytes<V>(self, _visitor: V) -> Result<V::Value>
@@ -1517,32 +2881,33 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
-        let is_some = match self.peek_event()? {
+        let (next, mark) = self.peek_event_mark()?;
+        let is_some = match next {
             Event::Alias(mut pos) => {
                 *self.pos += 1;
                 return self.jump(&mut pos)?.deserialize_option(visitor);
             }
             Event::Scalar(scalar) => {
                 let tagged_already = self.current_enum.is_some();
-                if scalar.style != ScalarStyle::Plain {
-                    true
-                } else if let (Some(tag), false) = (&scalar.tag, tagged_already) {
+                if let (Some(tag), false) = (&scalar.tag, tagged_already) {
                     if tag.as_ref() == Tag::NULL {
-                        if let Some(()) = parse_null(scalar.value.as_ref()) {
+                        if let Some(()) = parse_tagged_null(scalar.value.as_ref()) {
                             false
-                        } else if let Ok(v) = str::from_utf8(scalar.value.as_ref()) {
-                            return Err(de::Error::invalid_value(Unexpected::Str(v), &"null"));
                         } else {
-                            return Err(de::Error::invalid_value(
-                                Unexpected::Bytes(scalar.value.as_ref()),
-                                &"null",
-                            ));
+                            let unexpected = match str::from_utf8(scalar.value.as_ref()) {
+                                Ok(v) => Unexpected::Str(v),
+                                Err(_) => Unexpected::Bytes(scalar.value.as_ref()),
+                            };
+                            let err = de::Error::invalid_value(unexpected, &"null");
+                            return Err(error::fix_mark(err, mark, self.path));
                         }
                     } else {
                         true
                     }
                 } else {
-                    !scalar.value.as_ref().is_empty() && parse_null(scalar.value.as_ref()).is_none()
+                    scalar.style != ScalarStyle::Plain
+                        || !scalar.value.as_ref().is_empty()
+                            && parse_null(scalar.value.as_ref()).is_none()
                 }
             }
             Event::SequenceStart(_) | Event::MappingStart(_) => true,
@@ -1567,12 +2932,12 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Scalar(scalar) => {
-                let is_null = if scalar.style != ScalarStyle::Plain {
-                    false
-                } else if let (Some(tag), false) = (&scalar.tag, tagged_already) {
-                    tag.as_ref() == Tag::NULL && parse_null(scalar.value.as_ref()).is_some()
+                let is_null = if let (Some(tag), false) = (&scalar.tag, tagged_already) {
+                    tag.as_ref() == Tag::NULL && parse_tagged_null(scalar.value.as_ref()).is_some()
                 } else {
-                    scalar.value.as_ref().is_empty() || parse_null(scalar.value.as_ref()).is_some()
+                    scalar.style == ScalarStyle::Plain
+                        && (scalar.value.as_ref().is_empty()
+                            || parse_null(scalar.value.as_ref()).is_some())
                 };
                 if is_null {
                     visitor.visit_unit()
@@ -1592,18 +2957,29 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         .map_err(|err| error::fix_mark(err, mark, self.path))
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_unit(visitor)
+        let (_event, mark) = self.peek_event_mark()?;
+        let result = self
+            .deserialize_any(UnitStructVisitor { name, visitor })
+            .map_err(|err| error::fix_mark(err, mark, self.path));
+        self.expect(result, Expected::Struct { name, fields: &[] })
     }
 
-    /// Parses a newtype struct as the underlying value.
-    fn deserialize_newtype_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value>
+    /// Parses a newtype struct as the underlying value, tag and all.
+    fn deserialize_newtype_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if name == peek::NAME {
+            let (kind, tag) = self.peek_node()?;
+            return visitor.visit_seq(PeekAccess::new(kind, tag, self));
+        }
+        if name == styled::NAME {
+            return self.deserialize_styled(visitor);
+        }
         let (_event, mark) = self.peek_event_mark()?;
         self.recursion_check(mark, |de| visitor.visit_newtype_struct(de))
     }
@@ -1612,6 +2988,9 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some(err) = self.root_mismatch("a sequence") {
+            return Err(err);
+        }
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_seq(visitor),
@@ -1660,6 +3039,9 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
     where
         V: Visitor<'de>,
     {
+        if let Some(err) = self.root_mismatch("a mapping") {
+            return Err(err);
+        }
         let (next, mark) = self.next_event_mark()?;
         match next {
             Event::Alias(mut pos) => self.jump(&mut pos)?.deserialize_map(visitor),
@@ -1677,6 +3059,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                         de: self,
                         len: 0,
                         key: None,
+                        key_mark: None,
                     })
                 } else {
                     Err(invalid_type(other, &visitor))
@@ -1688,14 +3071,18 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
-        _fields: &'static [&'static str],
+        name: &'static str,
+        fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_map(visitor)
+        if name == scalar_info::NAME {
+            return self.deserialize_scalar_info(visitor);
+        }
+        let result = self.deserialize_map(visitor);
+        self.expect(result, Expected::Struct { name, fields })
     }
 
     /// Parses an enum as a single key:value pair where the key identifies the
@@ -1711,7 +3098,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
         V: Visitor<'de>,
     {
         let (next, mark) = self.peek_event_mark()?;
-        loop {
+        let result = loop {
             if let Some(current_enum) = self.current_enum {
                 if let Event::Scalar(scalar) = next {
                     if !scalar.value.as_ref().is_empty() {
@@ -1739,7 +3126,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                 }
                 Event::Scalar(scalar) => {
                     if let Some(tag) = parse_tag(&scalar.tag) {
-                        return visitor.visit_enum(EnumAccess {
+                        break visitor.visit_enum(EnumAccess {
                             de: self,
                             name: Some(name),
                             tag,
@@ -1749,7 +3136,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                 }
                 Event::MappingStart(mapping) => {
                     if let Some(tag) = parse_tag(&mapping.tag) {
-                        return visitor.visit_enum(EnumAccess {
+                        break visitor.visit_enum(EnumAccess {
                             de: self,
                             name: Some(name),
                             tag,
@@ -1761,7 +3148,7 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                 }
                 Event::SequenceStart(sequence) => {
                     if let Some(tag) = parse_tag(&sequence.tag) {
-                        return visitor.visit_enum(EnumAccess {
+                        break visitor.visit_enum(EnumAccess {
                             de: self,
                             name: Some(name),
                             tag,
@@ -1776,20 +3163,28 @@ impl<'de, 'document> de::Deserializer<'de> for &mut DeserializerFromEvents<'de,
                 Event::Void => Err(error::new(ErrorImpl::EndOfStream)),
             };
         }
-        .map_err(|err| error::fix_mark(err, mark, self.path))
+        .map_err(|err| error::fix_mark(err, mark, self.path));
+        self.expect(result, Expected::Enum { name, variants })
     }
 
     fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_str(visitor)
+        let result = self.deserialize_str(visitor);
+        // A field or variant name that does not fit is for the struct or enum
+        // around it to describe.
+        *self.expected = None;
+        result
     }
 
     fn deserialize_ignored_any<V>(self, visitor: V) -> Result<V::Value>
     where
         V: Visitor<'de>,
     {
+        if let (Some(callback), Some(key_mark)) = (&mut self.ignored, self.key_mark) {
+            callback(&self.path.to_string(), key_mark);
+        }
         self.ignore_any()?;
         visitor.visit_unit()
     }
@@ -1811,6 +3206,186 @@ where
     T::deserialize(Deserializer::from_str(s))
 }
 
+/// Deserialize an instance of type `T` from a string of YAML text, calling
+/// `callback` with the path and location of each mapping key that `T` has no
+/// use for, such as an unrecognized field of a struct.
+///
+/// The location is where the key begins. Keys are only reported when `T`
+/// skips over their value, so entries that are buffered first, as by
+/// `#[serde(flatten)]` or `#[serde(untagged)]`, are not reported.
+///
+/// ```
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     transport: Transport,
+/// }
+///
+/// #[derive(Deserialize)]
+/// struct Transport {
+///     retries: u32,
+/// }
+///
+/// let input = "transport:\n  retries: 3\n  retrys: 5\n";
+/// let mut unused = Vec::new();
+/// let config: Config = serde_yaml::from_str_with_ignored_callback(input, |path, location| {
+///     unused.push(format!("{} at line {}", path, location.line()));
+/// })
+/// .unwrap();
+/// assert_eq!(config.transport.retries, 3);
+/// assert_eq!(unused, ["transport.retrys at line 3"]);
+/// ```
+pub fn from_str_with_ignored_callback<'de, T, F>(s: &'de str, mut callback: F) -> Result<T>
+where
+    T: Deserialize<'de>,
+    F: FnMut(&str, Location),
+{
+    let mut callback = |path: &str, mark| callback(path, Location::from_mark(mark));
+    Deserializer::from_str(s).de_with_ignored(Some(&mut callback), |state| T::deserialize(state))
+}
+
+/// Deserialize an instance of type `T` from a string of YAML text, along with
+/// findings about the input that do not stop it from being deserialized.
+///
+/// The findings are things like anchors that are never used, plain scalars
+/// that YAML 1.1 would read differently, and fields that `T` has no use for,
+/// sorted by where they are in the input. [`Options`] chooses which kinds of
+/// finding fail deserialization instead. Other ways to deserialize do not look
+/// for any of these, so they cost nothing there.
+///
+/// ```
+/// use serde_derive::Deserialize;
+/// use serde_yaml::diagnostic::{Code, Options};
+///
+/// #[derive(Deserialize)]
+/// struct Config {
+///     verbose: String,
+/// }
+///
+/// let input = "verbose: yes\nretrys: 5\n";
+/// let (config, diagnostics) =
+///     serde_yaml::from_str_with_diagnostics::<Config>(input, &Options::new()).unwrap();
+/// assert_eq!(config.verbose, "yes");
+///
+/// let codes: Vec<Code> = diagnostics.iter().map(|d| d.code()).collect();
+/// assert_eq!(codes, [Code::Yaml11Scalar, Code::UnknownField]);
+/// assert_eq!(diagnostics[1].to_string(), "unknown field retrys at line 2 column 1");
+///
+/// let options = Options::new().deny(Code::UnknownField);
+/// let result = serde_yaml::from_str_with_diagnostics::<Config>(input, &options);
+/// let err = result.err().unwrap();
+/// assert_eq!(err.to_string(), "unknown field retrys at line 2 column 1");
+/// ```
+///
+/// [`Options`]: crate::diagnostic::Options
+pub fn from_str_with_diagnostics<'de, T>(
+    s: &'de str,
+    options: &Options,
+) -> Result<(T, Vec<Diagnostic>)>
+where
+    T: Deserialize<'de>,
+{
+    let mut loader = Loader::new(Progress::Str(s))?;
+    loader.collect_diagnostics();
+    if options.denies(Code::DuplicateAnchor) {
+        loader.duplicate_anchors(DuplicateAnchors::Error);
+    }
+    let document = match loader.next_document() {
+        Some(document) => document,
+        None => return Err(error::new(ErrorImpl::EndOfStream)),
+    };
+    let mut diagnostics = loader.take_diagnostics();
+    if document.error.is_none() {
+        options.check(&diagnostics)?;
+    }
+    let mut callback = |path: &str, mark| {
+        let message = format!("unknown field {}", path);
+        diagnostics.push(Diagnostic::new(Code::UnknownField, message, mark));
+    };
+    let t = de_document(&document, Some(&mut callback), true, |state| {
+        T::deserialize(state)
+    })?;
+    if loader.next_document().is_some() {
+        return Err(error::new(ErrorImpl::MoreThanOneDocument));
+    }
+    options.check(&diagnostics)?;
+    diagnostics.sort_by_key(|diagnostic| diagnostic.mark().index());
+    Ok((t, diagnostics))
+}
+
+/// Deserialize just the part of a document that `T` asks for, keeping the
+/// document around to deserialize again as something else later.
+///
+/// This suits documents whose type is given by a field inside them. Mapping
+/// entries that `T` has no use for are skipped without being converted, and
+/// the [`UnparsedRest`] holds the parsed document so that deserializing it
+/// again does not go back to the YAML text.
+///
+/// Like [`from_str`], this fails if the input contains more than one
+/// document.
+///
+/// ```
+/// use serde_derive::Deserialize;
+///
+/// #[derive(Deserialize)]
+/// struct Head {
+///     kind: String,
+/// }
+///
+/// #[derive(Deserialize, PartialEq, Debug)]
+/// struct Ping {
+///     kind: String,
+///     seq: u64,
+/// }
+///
+/// let input = "kind: ping\nseq: 7\n";
+/// let (head, rest) = serde_yaml::from_str_partial::<Head>(input).unwrap();
+/// assert_eq!(head.kind, "ping");
+///
+/// let ping: Ping = rest.deserialize().unwrap();
+/// assert_eq!(ping.seq, 7);
+/// ```
+pub fn from_str_partial<'de, T>(s: &'de str) -> Result<(T, UnparsedRest<'de>)>
+where
+    T: Deserialize<'de>,
+{
+    let mut loader = Loader::new(Progress::Str(s))?;
+    let document = match loader.next_document() {
+        Some(document) => document,
+        None => return Err(error::new(ErrorImpl::EndOfStream)),
+    };
+    let t = de_document(&document, None, true, |state| T::deserialize(state))?;
+    if loader.next_document().is_some() {
+        return Err(error::new(ErrorImpl::MoreThanOneDocument));
+    }
+    Ok((t, UnparsedRest { document }))
+}
+
+/// A parsed document returned by [`from_str_partial`], which can be
+/// deserialized any number of times.
+pub struct UnparsedRest<'de> {
+    document: Document<'de>,
+}
+
+impl<'de> UnparsedRest<'de> {
+    /// Deserializes the whole document as a `T`.
+    pub fn deserialize<T>(&self) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        de_document(&self.document, None, true, |state| T::deserialize(state))
+    }
+}
+
+impl<'de> fmt::Debug for UnparsedRest<'de> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("UnparsedRest")
+            .finish_non_exhaustive()
+    }
+}
+
 /// Deserialize an instance of type `T` from an IO stream of YAML.
 ///
 /// This conversion can fail if the structure of the Value does not match the
@@ -1828,6 +3403,112 @@ where
     T::deserialize(Deserializer::from_reader(rdr))
 }
 
+/// Deserializes each document of a stream of YAML from an IO stream, reading
+/// and parsing one document at a time.
+///
+/// Where [`from_reader`] and [`Deserializer::from_reader`] read all of their
+/// input before parsing any of it, this holds no more than one document of the
+/// stream in memory, so it can filter a stream of any length. The stream is
+/// cut before each line that begins with a `---` marker and after each line
+/// that begins with a `...` marker, since neither can be inside a document.
+/// Only where a `%YAML` or `%TAG` line comes after a document's content
+/// without a `...` marker before it are the document and the one after it
+/// held in memory together. Errors are reported where they are in the whole
+/// stream.
+///
+/// Input that is empty or holds nothing but comments yields no documents,
+/// whereas iterating over [`Deserializer::from_str`] yields a single null
+/// document for it.
+///
+/// A document that fails to deserialize, or is not well-formed YAML, yields
+/// its error, and the documents after it are read as usual. An error reading
+/// from the stream is yielded last.
+///
+/// Together with [`to_writer_multi`](crate::to_writer_multi) this makes a
+/// filter that copies a stream of documents, changing each along the way:
+///
+/// ```
+/// use serde_yaml::Value;
+///
+/// let input = "name: a\n---\nname: b\n";
+///
+/// let mut output = Vec::new();
+/// let documents = serde_yaml::from_reader_multi(input.as_bytes()).map(|document| {
+///     let mut document: Value = document.unwrap();
+///     document["seen"] = Value::Bool(true);
+///     document
+/// });
+/// serde_yaml::to_writer_multi(&mut output, documents).unwrap();
+///
+/// let expected = "name: a\nseen: true\n---\nname: b\nseen: true\n";
+/// assert_eq!(String::from_utf8(output).unwrap(), expected);
+/// ```
+pub fn from_reader_multi<R, T>(rdr: R) -> impl Iterator<Item = Result<T>>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    ReaderDocuments::new(rdr)
+}
+
+/// Deserialize an instance of type `T` from bytes of YAML text that are
+/// either borrowed or owned.
+///
+/// See [`Deserializer::from_cow`] for what `T` may borrow in each case.
+///
+/// ```
+/// use serde_derive::Deserialize;
+/// use std::borrow::Cow;
+///
+/// #[derive(Deserialize)]
+/// struct Entry<'a> {
+///     #[serde(borrow)]
+///     name: Cow<'a, str>,
+/// }
+///
+/// let input = b"name: cached\n";
+/// let entry: Entry = serde_yaml::from_cow(Cow::Borrowed(&input[..])).unwrap();
+/// assert!(matches!(entry.name, Cow::Borrowed("cached")));
+///
+/// let entry: Entry = serde_yaml::from_cow(input.to_vec()).unwrap();
+/// assert!(matches!(entry.name, Cow::Owned(_)));
+/// ```
+pub fn from_cow<'de, T>(input: impl Into<Cow<'de, [u8]>>) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(Deserializer::from_cow(input))
+}
+
+/// Deserialize an instance of type `T` from YAML text that is either borrowed
+/// or owned.
+///
+/// See [`Deserializer::from_cow`] for what `T` may borrow in each case.
+///
+/// ```
+/// use serde_derive::Deserialize;
+/// use std::borrow::Cow;
+///
+/// #[derive(Deserialize)]
+/// struct Entry<'a> {
+///     #[serde(borrow)]
+///     name: Cow<'a, str>,
+/// }
+///
+/// let input = "name: cached\n";
+/// let entry: Entry = serde_yaml::from_cow_str(Cow::Borrowed(input)).unwrap();
+/// assert!(matches!(entry.name, Cow::Borrowed("cached")));
+///
+/// let entry: Entry = serde_yaml::from_cow_str(input.to_owned()).unwrap();
+/// assert!(matches!(entry.name, Cow::Owned(_)));
+/// ```
+pub fn from_cow_str<'de, T>(input: impl Into<Cow<'de, str>>) -> Result<T>
+where
+    T: Deserialize<'de>,
+{
+    T::deserialize(Deserializer::from_cow_str(input))
+}
+
 /// Deserialize an instance of type `T` from bytes of YAML text.
 ///
 /// This conversion can fail if the structure of the Value does not match the
@@ -1843,3 +3524,29 @@ where
 {
     T::deserialize(Deserializer::from_slice(v))
 }
+
+/// Checks that a string is well-formed YAML, without deserializing it.
+///
+/// This is [`Deserializer::validate`] with the default options, which
+/// describes what is checked. Every document of a multi-document stream is
+/// checked.
+///
+/// ```
+/// assert!(serde_yaml::validate("a: [1, 2]\n---\nb: 3\n").is_ok());
+///
+/// let err = serde_yaml::validate("a: [1, 2\nb: 3\n").unwrap_err();
+/// assert_eq!(err.location().unwrap().line(), 2);
+/// ```
+pub fn validate(s: &str) -> Result<()> {
+    Deserializer::from_str(s).validate()
+}
+
+/// Checks that an IO stream is well-formed YAML, without deserializing it.
+///
+/// The stream is read to the end first. See [`validate`] for what is checked.
+pub fn validate_reader<R>(rdr: R) -> Result<()>
+where
+    R: io::Read,
+{
+    Deserializer::from_reader(rdr).validate()
+}
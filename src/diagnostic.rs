@@ -0,0 +1,164 @@
+//! Findings about a document that do not stop it from being deserialized,
+//! reported by [`from_str_with_diagnostics`](crate::from_str_with_diagnostics).
+
+use crate::de::DuplicateAnchors;
+use crate::error::{self, ErrorImpl, Location};
+use crate::libyaml::error::Mark;
+use std::fmt::{self, Display};
+
+/// A finding about a document, such as an anchor that nothing refers to.
+///
+/// Each one has a [`Code`] saying what kind of finding it is, which
+/// [`Options::deny`] can turn into an error.
+#[derive(Clone, Debug)]
+pub struct Diagnostic {
+    code: Code,
+    message: String,
+    mark: Mark,
+}
+
+impl Diagnostic {
+    pub(crate) fn new(code: Code, message: String, mark: Mark) -> Self {
+        Diagnostic {
+            code,
+            message,
+            mark,
+        }
+    }
+
+    /// What kind of finding this is.
+    pub fn code(&self) -> Code {
+        self.code
+    }
+
+    /// How much the finding is likely to matter.
+    pub fn severity(&self) -> Severity {
+        self.code.severity()
+    }
+
+    /// A description of the finding, without its location.
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    /// Where in the input the finding is.
+    pub fn location(&self) -> Location {
+        Location::from_mark(self.mark)
+    }
+
+    pub(crate) fn mark(&self) -> Mark {
+        self.mark
+    }
+}
+
+impl Display for Diagnostic {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "{} at {}", self.message, self.mark)
+    }
+}
+
+/// The kinds of [`Diagnostic`].
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+#[non_exhaustive]
+pub enum Code {
+    /// An anchor is defined again in the same document, so that aliases after
+    /// the second definition refer to a different node than those before it.
+    DuplicateAnchor,
+    /// An anchor is never referred to by an alias.
+    UnusedAnchor,
+    /// A plain scalar that YAML 1.1 reads differently than YAML 1.2, like
+    /// `yes`, `0755`, `1:30` or `=`, which are strings in YAML 1.2, or `1e3`,
+    /// which is a string in YAML 1.1.
+    Yaml11Scalar,
+    /// A mapping entry that the type being deserialized has no use for.
+    UnknownField,
+}
+
+impl Code {
+    /// A stable, kebab-case name for the code, like `"unused-anchor"`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Code::DuplicateAnchor => "duplicate-anchor",
+            Code::UnusedAnchor => "unused-anchor",
+            Code::Yaml11Scalar => "yaml-1.1-scalar",
+            Code::UnknownField => "unknown-field",
+        }
+    }
+
+    fn severity(self) -> Severity {
+        match self {
+            Code::DuplicateAnchor | Code::Yaml11Scalar | Code::UnknownField => Severity::Warning,
+            Code::UnusedAnchor => Severity::Note,
+        }
+    }
+}
+
+impl Display for Code {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(self.as_str())
+    }
+}
+
+/// How much a [`Diagnostic`] is likely to matter.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
+#[non_exhaustive]
+pub enum Severity {
+    /// Harmless, but possibly a mistake.
+    Note,
+    /// The document may not mean what its author intended.
+    Warning,
+}
+
+/// Which diagnostics [`from_str_with_diagnostics`](crate::from_str_with_diagnostics)
+/// turns into errors.
+///
+/// By default, none are.
+#[derive(Clone, Default, Debug)]
+pub struct Options {
+    denied: Vec<Code>,
+}
+
+impl Options {
+    /// Options under which every diagnostic is reported but none is an error.
+    pub fn new() -> Self {
+        Options::default()
+    }
+
+    /// Makes diagnostics with the given code fail deserialization, with the
+    /// first of them as the error.
+    pub fn deny(mut self, code: Code) -> Self {
+        if !self.denied.contains(&code) {
+            self.denied.push(code);
+        }
+        self
+    }
+
+    /// The same setting as [`Deserializer::duplicate_anchors`]: with
+    /// [`DuplicateAnchors::Error`], this denies [`Code::DuplicateAnchor`].
+    ///
+    /// [`Deserializer::duplicate_anchors`]: crate::Deserializer::duplicate_anchors
+    pub fn duplicate_anchors(mut self, duplicate_anchors: DuplicateAnchors) -> Self {
+        match duplicate_anchors {
+            DuplicateAnchors::Allow => self.denied.retain(|&code| code != Code::DuplicateAnchor),
+            DuplicateAnchors::Error => self = self.deny(Code::DuplicateAnchor),
+        }
+        self
+    }
+
+    pub(crate) fn denies(&self, code: Code) -> bool {
+        self.denied.contains(&code)
+    }
+
+    // The first of `diagnostics` that is denied, as an error.
+    pub(crate) fn check(&self, diagnostics: &[Diagnostic]) -> crate::Result<()> {
+        match diagnostics
+            .iter()
+            .find(|diagnostic| self.denies(diagnostic.code))
+        {
+            Some(diagnostic) => Err(error::new(ErrorImpl::Diagnostic(Box::new(
+                diagnostic.clone(),
+            )))),
+            None => Ok(()),
+        }
+    }
+}
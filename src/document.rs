@@ -0,0 +1,592 @@
+//! An editable YAML document that keeps its comments and formatting.
+//!
+//! A [`Document`] holds the YAML text it was parsed from, together with where
+//! each node is in that text and how it is written. Edits replace the text of
+//! one node and leave every other byte as it was, so that comments, blank
+//! lines, quoting styles, anchors and key order all survive.
+//!
+//! ```
+//! # fn main() -> serde_yaml::Result<()> {
+//! use serde_yaml::document::Document;
+//!
+//! let input = "\
+//! spec:
+//!   replicas: 1   # scaled by the rollout job
+//!   image: 'nginx:1.25'
+//! ";
+//! let mut doc: Document = input.parse()?;
+//! doc.set("/spec/replicas", &3)?;
+//! doc.set("/spec/image", "nginx:1.27")?;
+//!
+//! let expected = "\
+//! spec:
+//!   replicas: 3   # scaled by the rollout job
+//!   image: 'nginx:1.27'
+//! ";
+//! assert_eq!(doc.as_str(), expected);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Only scalars and aliases can be replaced for now. Replacing a sequence or
+//! mapping, or writing one in place of a scalar, is an error.
+
+use crate::error::{self, Error, ErrorImpl, Location, Result};
+use crate::libyaml::error::Mark;
+use crate::libyaml::event::{CollectionStyle, Event, ScalarStyle, Tag};
+use crate::libyaml::parser::{Parser, Scanner};
+use crate::libyaml::token::Token;
+use crate::value::pointer;
+use crate::value::{to_value, Value};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt::{self, Debug, Display, Write as _};
+use std::iter;
+use std::result;
+use std::str::FromStr;
+
+/// A parsed YAML document that can be edited in place and written back out
+/// with everything that was not edited unchanged.
+///
+/// See the [module documentation](self) for an example.
+pub struct Document {
+    source: String,
+    root: NodeData,
+}
+
+/// A node of a [`Document`], as written in its text.
+#[derive(Copy, Clone)]
+pub struct Node<'a> {
+    source: &'a str,
+    data: &'a NodeData,
+}
+
+/// The kinds of [`Node`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum NodeKind {
+    /// A scalar, in any style.
+    Scalar,
+    /// A sequence, in block or flow style.
+    Sequence,
+    /// A mapping, in block or flow style.
+    Mapping,
+    /// An alias to an anchored node.
+    Alias,
+}
+
+struct NodeData {
+    // Where the node begins, including any anchor and tag.
+    start: Mark,
+    // Where the scalar or alias itself begins, after any anchor and tag.
+    content_start: usize,
+    // Where a scalar or alias ends.
+    end: usize,
+    anchor: Option<String>,
+    tag: Option<Tag>,
+    // Whether the node is inside a flow collection, where plain scalars may
+    // not contain flow indicators.
+    in_flow: bool,
+    content: Content,
+}
+
+enum Content {
+    Scalar {
+        value: String,
+        style: ScalarStyle,
+    },
+    Alias(String),
+    Sequence {
+        style: CollectionStyle,
+        elements: Vec<NodeData>,
+    },
+    Mapping {
+        style: CollectionStyle,
+        entries: Vec<(NodeData, NodeData)>,
+    },
+}
+
+impl Document {
+    /// Parses a single YAML document.
+    ///
+    /// Like [`from_str`](crate::from_str), this fails if the input contains
+    /// more than one document.
+    pub fn parse(input: &str) -> Result<Self> {
+        let mut events = Vec::new();
+        let mut parser = Parser::new(Cow::Borrowed(input.as_bytes()))?;
+        let mut documents = 0;
+        let stream_end = loop {
+            let (event, start, end) = parser.next_span()?;
+            match event {
+                Event::StreamEnd => break start,
+                Event::DocumentStart(_) => documents += 1,
+                Event::StreamStart | Event::DocumentEnd(_) => continue,
+                _ => {}
+            }
+            if documents > 1 {
+                return Err(error::new(ErrorImpl::MoreThanOneDocument));
+            }
+            if !matches!(event, Event::DocumentStart(_)) {
+                events.push((event, start, end));
+            }
+        };
+
+        // Where each scalar begins after its anchor and tag, by where it
+        // ends. Scalar events begin at their anchor or tag.
+        let mut scalar_starts = HashMap::new();
+        let mut scanner = Scanner::new(Cow::Borrowed(input.as_bytes()))?;
+        loop {
+            match scanner.next()? {
+                (Token::StreamEnd, _start, _end) => break,
+                (Token::Scalar { .. }, start, end) => {
+                    scalar_starts.insert(end.index(), start.index());
+                }
+                _ => {}
+            }
+        }
+
+        let mut events = events.into_iter();
+        let root = match events.next() {
+            Some(first) => build(first, &mut events, &scalar_starts, false)?,
+            // An empty document, which is null.
+            None => NodeData {
+                start: stream_end,
+                content_start: input.len(),
+                end: input.len(),
+                anchor: None,
+                tag: None,
+                in_flow: false,
+                content: Content::Scalar {
+                    value: String::new(),
+                    style: ScalarStyle::Plain,
+                },
+            },
+        };
+        Ok(Document {
+            source: input.to_owned(),
+            root,
+        })
+    }
+
+    /// The text of the document, with any edits made so far.
+    pub fn as_str(&self) -> &str {
+        &self.source
+    }
+
+    /// Consumes the document, returning its text.
+    pub fn into_string(self) -> String {
+        self.source
+    }
+
+    /// The node that the whole document consists of.
+    pub fn root(&self) -> Node<'_> {
+        Node {
+            source: &self.source,
+            data: &self.root,
+        }
+    }
+
+    /// Looks up a node by a JSON Pointer-style path, like `/spec/replicas`.
+    ///
+    /// The syntax is the same as for [`Value::pointer`]. Mapping keys are
+    /// matched against the text of scalar keys, and aliases are not followed.
+    pub fn get(&self, pointer: &str) -> Option<Node<'_>> {
+        let data = find(&self.root, pointer).ok()?;
+        Some(Node {
+            source: &self.source,
+            data,
+        })
+    }
+
+    /// Serializes `value` and writes it in place of the scalar or alias at
+    /// `pointer`. See [`Value::pointer`] for the syntax.
+    ///
+    /// Only the text of that node changes. A string keeps the quoting style of
+    /// the scalar it replaces where it can be written that way, and one of
+    /// several lines in place of a literal block scalar is written as a
+    /// literal block at the same indentation. An anchor or tag on the scalar
+    /// is kept. The edit is checked by parsing the
+    /// result, and fails without changing anything if the document would not
+    /// read back with `value` at `pointer`, such as when a tag on the scalar
+    /// would change its meaning.
+    pub fn set<T>(&mut self, pointer: &str, value: &T) -> Result<()>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = to_value(value)?;
+        let node = find(&self.root, pointer).map_err(error::new)?;
+        let unsupported =
+            |reason: String| error::new(ErrorImpl::UnsupportedEdit(pointer.to_owned(), reason));
+        let kind = match node.content {
+            Content::Scalar { .. } | Content::Alias(_) => None,
+            Content::Sequence { .. } => Some("sequence"),
+            Content::Mapping { .. } => Some("mapping"),
+        };
+        if let Some(kind) = kind {
+            return Err(unsupported(format!(
+                "replacing a {} is not supported, only a scalar",
+                kind,
+            )));
+        }
+        let old = &self.source[node.content_start..node.end];
+        let text = match render(&value, node, old) {
+            Some(text) => text,
+            None => {
+                return Err(unsupported(format!(
+                    "writing {} in place of a scalar is not supported",
+                    describe(&value),
+                )));
+            }
+        };
+
+        let mut source = String::with_capacity(self.source.len() + text.len());
+        source.push_str(&self.source[..node.content_start]);
+        if old.is_empty() && source.ends_with(':') {
+            source.push(' ');
+        }
+        source.push_str(&text);
+        if let Content::Scalar {
+            style: ScalarStyle::Literal | ScalarStyle::Folded,
+            ..
+        } = node.content
+        {
+            // A block scalar ends after the line breaks that follow it.
+            source.push_str(&old[old.trim_end_matches(['\r', '\n']).len()..]);
+        }
+        source.push_str(&self.source[node.end..]);
+
+        let reads_back = match crate::from_str::<Value>(&source) {
+            Ok(parsed) => parsed.pointer(pointer) == Some(&value),
+            Err(_) => false,
+        };
+        if !reads_back {
+            return Err(unsupported(format!(
+                "writing {} here would not read back the same",
+                text,
+            )));
+        }
+        *self = Document::parse(&source)?;
+        Ok(())
+    }
+
+    /// Deserializes the document as it is now, with any edits, as a `T`.
+    pub fn deserialize<'de, T>(&'de self) -> Result<T>
+    where
+        T: Deserialize<'de>,
+    {
+        crate::from_str(&self.source)
+    }
+}
+
+impl FromStr for Document {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Document::parse(s)
+    }
+}
+
+impl Display for Document {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(&self.source)
+    }
+}
+
+impl Debug for Document {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Document")
+            .field("source", &self.source)
+            .finish_non_exhaustive()
+    }
+}
+
+impl<'a> Node<'a> {
+    /// What kind of node this is.
+    pub fn kind(&self) -> NodeKind {
+        match self.data.content {
+            Content::Scalar { .. } => NodeKind::Scalar,
+            Content::Alias(_) => NodeKind::Alias,
+            Content::Sequence { .. } => NodeKind::Sequence,
+            Content::Mapping { .. } => NodeKind::Mapping,
+        }
+    }
+
+    /// The value of a scalar, after escapes and folding are processed, or the
+    /// name of the anchor that an alias refers to.
+    pub fn as_str(&self) -> Option<&'a str> {
+        match &self.data.content {
+            Content::Scalar { value, .. } | Content::Alias(value) => Some(value),
+            Content::Sequence { .. } | Content::Mapping { .. } => None,
+        }
+    }
+
+    /// How a scalar is written.
+    pub fn scalar_style(&self) -> Option<ScalarStyle> {
+        match self.data.content {
+            Content::Scalar { style, .. } => Some(style),
+            _ => None,
+        }
+    }
+
+    /// How a sequence or mapping is written.
+    pub fn collection_style(&self) -> Option<CollectionStyle> {
+        match self.data.content {
+            Content::Sequence { style, .. } | Content::Mapping { style, .. } => Some(style),
+            _ => None,
+        }
+    }
+
+    /// The anchor defined on this node, without the `&`.
+    pub fn anchor(&self) -> Option<&'a str> {
+        self.data.anchor.as_deref()
+    }
+
+    /// The tag written on this node, with any tag handle expanded.
+    pub fn tag(&self) -> Option<&'a Tag> {
+        self.data.tag.as_ref()
+    }
+
+    /// Where the node begins, including any anchor and tag.
+    pub fn location(&self) -> Location {
+        Location::from_mark(self.data.start)
+    }
+
+    /// The text of a scalar or alias as written, without its anchor and tag.
+    pub fn source(&self) -> Option<&'a str> {
+        match self.data.content {
+            Content::Scalar { .. } | Content::Alias(_) => {
+                Some(&self.source[self.data.content_start..self.data.end])
+            }
+            Content::Sequence { .. } | Content::Mapping { .. } => None,
+        }
+    }
+
+    /// The comment after a scalar or alias on the same line, without the `#`
+    /// and surrounding whitespace.
+    pub fn comment(&self) -> Option<&'a str> {
+        self.source()?;
+        let before = &self.source[..self.data.end];
+        if before.is_empty() || before.ends_with(['\n', '\r']) {
+            return None;
+        }
+        let rest = self.source[self.data.end..].trim_start_matches([' ', '\t']);
+        let comment = rest.strip_prefix('#')?;
+        let line = match comment.find(['\n', '\r']) {
+            Some(end) => &comment[..end],
+            None => comment,
+        };
+        Some(line.trim())
+    }
+}
+
+impl<'a> Debug for Node<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("Node")
+            .field("kind", &self.kind())
+            .field("location", &self.location())
+            .finish_non_exhaustive()
+    }
+}
+
+type Spanned<'input> = (Event<'input>, Mark, Mark);
+
+fn build<'input>(
+    (event, start, end): Spanned<'input>,
+    events: &mut impl Iterator<Item = Spanned<'input>>,
+    scalar_starts: &HashMap<usize, usize>,
+    in_flow: bool,
+) -> Result<NodeData> {
+    let name = |bytes: &[u8]| String::from_utf8_lossy(bytes).into_owned();
+    let mut node = NodeData {
+        start,
+        content_start: start.index(),
+        end: end.index(),
+        anchor: None,
+        tag: None,
+        in_flow,
+        content: Content::Alias(String::new()),
+    };
+    match event {
+        Event::Alias(anchor) => node.content = Content::Alias(name(anchor.as_bytes())),
+        Event::Scalar(scalar) => {
+            node.anchor = scalar.anchor.map(|anchor| name(anchor.as_bytes()));
+            node.tag = scalar.tag;
+            node.content_start = scalar_starts.get(&node.end).copied().unwrap_or(node.end);
+            node.content = Content::Scalar {
+                value: name(&scalar.value),
+                style: scalar.style,
+            };
+        }
+        Event::SequenceStart(sequence) => {
+            node.anchor = sequence.anchor.map(|anchor| name(anchor.as_bytes()));
+            node.tag = sequence.tag;
+            let nested = in_flow || sequence.style == CollectionStyle::Flow;
+            let mut elements = Vec::new();
+            while let Some(event) = events.next() {
+                if let Event::SequenceEnd = event.0 {
+                    break;
+                }
+                elements.push(build(event, events, scalar_starts, nested)?);
+            }
+            node.content = Content::Sequence {
+                style: sequence.style,
+                elements,
+            };
+        }
+        Event::MappingStart(mapping) => {
+            node.anchor = mapping.anchor.map(|anchor| name(anchor.as_bytes()));
+            node.tag = mapping.tag;
+            let nested = in_flow || mapping.style == CollectionStyle::Flow;
+            let mut entries = Vec::new();
+            while let Some(event) = events.next() {
+                if let Event::MappingEnd = event.0 {
+                    break;
+                }
+                let key = build(event, events, scalar_starts, nested)?;
+                let value = match events.next() {
+                    Some(event) => build(event, events, scalar_starts, nested)?,
+                    None => break,
+                };
+                entries.push((key, value));
+            }
+            node.content = Content::Mapping {
+                style: mapping.style,
+                entries,
+            };
+        }
+        Event::StreamStart
+        | Event::StreamEnd
+        | Event::DocumentStart(_)
+        | Event::DocumentEnd(_)
+        | Event::SequenceEnd
+        | Event::MappingEnd => unreachable!(),
+    }
+    Ok(node)
+}
+
+fn find<'a>(root: &'a NodeData, pointer: &str) -> result::Result<&'a NodeData, ErrorImpl> {
+    let mut node = root;
+    for (n, token) in pointer::tokens(pointer)?.enumerate() {
+        let found = match &node.content {
+            Content::Mapping { entries, .. } => entries
+                .iter()
+                .find(|(key, _value)| match &key.content {
+                    Content::Scalar { value, .. } => *value == token,
+                    _ => false,
+                })
+                .map(|(_key, value)| value),
+            Content::Sequence { elements, .. } => {
+                pointer::sequence_index(&token).and_then(|index| elements.get(index))
+            }
+            Content::Scalar { .. } | Content::Alias(_) => None,
+        };
+        node = found.ok_or_else(|| ErrorImpl::PointerNotFound(pointer::prefix(pointer, n)))?;
+    }
+    Ok(node)
+}
+
+// The text to write `value` as in place of `node`, which is a scalar or alias.
+fn render(value: &Value, node: &NodeData, old: &str) -> Option<String> {
+    let string = match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) => return Some(value.to_string()),
+        Value::String(string) => string,
+        Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => return None,
+    };
+    let printable = |ch: char| !ch.is_control() && !matches!(ch, '\u{2028}' | '\u{2029}');
+    let style = match node.content {
+        Content::Scalar { style, .. } => style,
+        _ => ScalarStyle::Plain,
+    };
+    Some(match style {
+        ScalarStyle::DoubleQuoted => double_quoted(string),
+        ScalarStyle::SingleQuoted if string.chars().all(printable) => {
+            format!("'{}'", string.replace('\'', "''"))
+        }
+        _ => {
+            let yaml = value.to_string();
+            let quoted = yaml.starts_with(['\'', '"']);
+            if yaml.contains('\n') {
+                let literal = match style {
+                    ScalarStyle::Literal => literal(string, old),
+                    _ => None,
+                };
+                literal.unwrap_or_else(|| double_quoted(string))
+            } else if node.in_flow && !quoted && yaml.contains([',', '[', ']', '{', '}']) {
+                double_quoted(string)
+            } else {
+                yaml
+            }
+        }
+    })
+}
+
+// A literal block scalar with the lines of `string`, indented like the lines
+// of the block scalar `old` that it replaces, or None if `string` cannot be
+// written that way. The line breaks after `old` are kept after it, so it does
+// not end with one.
+fn literal(string: &str, old: &str) -> Option<String> {
+    let body = old.split_once('\n')?.1;
+    let indent = body
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start_matches(' ').len())
+        .min()?;
+    let (chomp, content) = match string.strip_suffix('\n') {
+        Some(content) => ("", content),
+        None => ("-", string),
+    };
+    // More than one line break at the end would need `|+`, which would also
+    // keep the breaks after it, and a first line that begins with a space
+    // would need an indentation indicator.
+    let first = content.split('\n').find(|line| !line.is_empty());
+    if content.ends_with('\n')
+        || first.is_none_or(|line| line.starts_with(' '))
+        || content
+            .chars()
+            .any(|ch| ch != '\n' && ch != '\t' && (ch.is_control() || ch == '\u{feff}'))
+    {
+        return None;
+    }
+    let mut block = format!("|{}", chomp);
+    for line in content.split('\n') {
+        block.push('\n');
+        if !line.is_empty() {
+            block.extend(iter::repeat_n(' ', indent));
+            block.push_str(line);
+        }
+    }
+    Some(block)
+}
+
+fn double_quoted(string: &str) -> String {
+    let mut quoted = String::with_capacity(string.len() + 2);
+    quoted.push('"');
+    for ch in string.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            '\r' => quoted.push_str("\\r"),
+            '\u{2028}' => quoted.push_str("\\L"),
+            '\u{2029}' => quoted.push_str("\\P"),
+            ch if ch.is_control() => {
+                let _ = write!(quoted, "\\u{:04X}", ch as u32);
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+fn describe(value: &Value) -> &'static str {
+    match value {
+        Value::Sequence(_) => "a sequence",
+        Value::Mapping(_) => "a mapping",
+        _ => "a tagged value",
+    }
+}
@@ -0,0 +1,92 @@
+use crate::de::Deserializer;
+use crate::error::Result;
+use crate::libyaml::event::{DocumentEnd, DocumentStart};
+use crate::value::Value;
+use serde::Deserialize;
+
+/// One document of a YAML stream together with how it was framed: its
+/// `---` and `...` markers and its `%YAML` and `%TAG` directives.
+///
+/// Returned by [`Deserializer::document_values`], and written back out by
+/// [`to_writer_documents`](crate::to_writer_documents) with the same framing.
+#[derive(Clone, PartialEq, Debug)]
+pub struct DocumentValue {
+    /// The content of the document.
+    pub value: Value,
+    /// Whether the document starts with a `---` marker.
+    pub explicit_start: bool,
+    /// Whether the document ends with a `...` marker.
+    pub explicit_end: bool,
+    /// The major and minor version from a `%YAML` directive.
+    pub version: Option<(u8, u8)>,
+    /// The handle and prefix of each `%TAG` directive, in order.
+    pub tag_directives: Vec<(String, String)>,
+}
+
+impl DocumentValue {
+    /// A document with no markers or directives.
+    pub fn new(value: Value) -> Self {
+        DocumentValue {
+            value,
+            explicit_start: false,
+            explicit_end: false,
+            version: None,
+            tag_directives: Vec::new(),
+        }
+    }
+
+    pub(crate) fn from_events(value: Value, start: &DocumentStart, end: &DocumentEnd) -> Self {
+        let version = start.version.map(|(major, minor)| {
+            let clamp = |n: i32| u8::try_from(n).unwrap_or(u8::MAX);
+            (clamp(major), clamp(minor))
+        });
+        DocumentValue {
+            value,
+            explicit_start: !start.implicit,
+            explicit_end: !end.implicit,
+            version,
+            tag_directives: start.tag_directives.clone(),
+        }
+    }
+
+    pub(crate) fn start_event(&self) -> DocumentStart {
+        DocumentStart {
+            implicit: !self.explicit_start,
+            version: self
+                .version
+                .map(|(major, minor)| (i32::from(major), i32::from(minor))),
+            tag_directives: self.tag_directives.clone(),
+        }
+    }
+
+    pub(crate) fn end_event(&self) -> DocumentEnd {
+        DocumentEnd {
+            implicit: !self.explicit_end,
+        }
+    }
+}
+
+impl From<Value> for DocumentValue {
+    fn from(value: Value) -> Self {
+        DocumentValue::new(value)
+    }
+}
+
+/// Iterator over the documents of a YAML stream as [`DocumentValue`]s,
+/// returned by [`Deserializer::document_values`].
+pub struct DocumentValues<'de> {
+    pub(crate) de: Deserializer<'de>,
+}
+
+impl<'de> Iterator for DocumentValues<'de> {
+    type Item = Result<DocumentValue>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let document = self.de.next()?;
+        let (start, end) = document.framing();
+        Some(
+            Value::deserialize(document)
+                .map(|value| DocumentValue::from_events(value, &start, &end)),
+        )
+    }
+}
@@ -1,22 +1,43 @@
+//! When serializing or deserializing YAML goes wrong.
+
+use crate::diagnostic::Diagnostic;
 use crate::libyaml::{emitter, error as libyaml};
-use crate::path::Path;
-use serde::{de, ser};
+use crate::loader::DocumentSpan;
+use crate::path::{OwnedPath, Path, Segment};
+use serde::ser::{self, SerializeStruct, Serializer};
+use serde::{de, Serialize};
 use std::error::Error as StdError;
 use std::fmt::{self, Debug, Display};
 use std::io;
+use std::mem;
+use std::ops::Range;
 use std::result;
 use std::string;
 use std::sync::Arc;
 
+#[cfg(feature = "expected")]
+pub use crate::expected::Expected;
+pub use crate::snippet::DisplayWithSource;
+
 /// An error that happened serializing or deserializing YAML data.
+///
+/// The details are boxed, so an `Error` is the size of a pointer and a
+/// `Result` costs little more than its `Ok` value on the success path.
 pub struct Error(Box<ErrorImpl>);
 
+const _: () = assert!(mem::size_of::<Error>() == mem::size_of::<usize>());
+
 /// Alias for a `Result` with the error type `serde_yaml::Error`.
 pub type Result<T> = result::Result<T, Error>;
 
 #[derive(Debug)]
 pub(crate) enum ErrorImpl {
     Message(String, Option<Pos>),
+    MessageAtPath(String, OwnedPath),
+    // Missing field and invalid length errors are about a mapping or sequence
+    // as a whole, so they are reported together with the kind of node and
+    // where it starts, rather than at a position within it.
+    MessageInNode(String, Option<(&'static str, Pos)>),
 
     Libyaml(libyaml::Error),
     Io(io::Error),
@@ -26,8 +47,29 @@ pub(crate) enum ErrorImpl {
     MoreThanOneDocument,
     RecursionLimitExceeded(libyaml::Mark),
     RepetitionLimitExceeded,
+    // The limit, and the number of the document past it.
+    DocumentLimitExceeded(usize, libyaml::Mark),
+    // The limit, and the length of the input if it was read to the end.
+    InputLimitExceeded(usize, Option<usize>),
     BytesUnsupported,
+    ScalarTooLong(usize),
+    RoundTripMismatch(String),
     UnknownAnchor(libyaml::Mark),
+    DefaultValue(libyaml::Mark),
+    DuplicateAnchor(String, libyaml::Mark, libyaml::Mark),
+    // What the type expected at the document root, what the document is, and
+    // a guess at what went wrong.
+    RootMismatch(
+        &'static str,
+        &'static str,
+        Option<&'static str>,
+        libyaml::Mark,
+    ),
+    // A node that a visitor would not accept, usually because the format it
+    // is transcoding into cannot represent it: the node, what it is if that
+    // is the problem, and the visitor's reason.
+    Rejected(String, Option<String>, String, Pos),
+    Diagnostic(Box<Diagnostic>),
     SerializeNestedEnum,
     ScalarInMerge,
     TaggedInMerge,
@@ -35,6 +77,26 @@ pub(crate) enum ErrorImpl {
     SequenceInMergeElement,
     EmptyTag,
     FailedToParseNumber,
+    IntegerOutOfRange(String),
+    InvalidPointer(String),
+    PointerNotFound(String),
+    PointerNotTraversable(String, String),
+    PointerIndexOutOfBounds(String, usize),
+    UnsupportedEdit(String, String),
+    AtPointer(String, Box<ErrorImpl>),
+    // An error in one of the documents of a stream, which it is and where.
+    InDocument(Box<ErrorImpl>, DocumentSpan),
+    // An error from the handler for a tag: the tagged scalar, like
+    // `!include other.yaml`, the handler's error, and where the scalar is.
+    InTag(String, Box<ErrorImpl>, libyaml::Mark),
+    #[cfg(feature = "expected")]
+    WithExpected(Box<ErrorImpl>, Expected),
+    #[cfg(feature = "json")]
+    NonStringKeyInJson(String),
+    #[cfg(feature = "json")]
+    TaggedInJson(String),
+    #[cfg(feature = "json")]
+    NonFiniteFloatInJson(String),
 
     Shared(Arc<ErrorImpl>),
 }
@@ -46,7 +108,10 @@ pub(crate) struct Pos {
 }
 
 /// The input location that an error occured.
-#[derive(Debug)]
+///
+/// Locations compare and sort by their byte index. They serialize as a struct
+/// with `index`, `line` and `column` fields.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct Location {
     index: usize,
     line: usize,
@@ -69,21 +134,132 @@ impl Location {
         self.column
     }
 
+    // The start of the input.
+    pub(crate) const START: Location = Location {
+        index: 0,
+        line: 1,
+        column: 1,
+    };
+
+    // The beginning of the line that begins at byte `index`, after `line`
+    // other lines.
+    pub(crate) fn line_start(index: usize, line: usize) -> Self {
+        Location {
+            index,
+            line: line + 1,
+            column: 1,
+        }
+    }
+
     // This is to keep decoupled with the yaml crate
     #[doc(hidden)]
-    fn from_mark(mark: libyaml::Mark) -> Self {
+    pub(crate) fn from_mark(mark: libyaml::Mark) -> Self {
         Location {
-            index: mark.index() as usize,
+            index: mark.index(),
             // `line` and `column` returned from libyaml are 0-indexed but all error messages add +1 to this value
-            line: mark.line() as usize + 1,
-            column: mark.column() as usize + 1,
+            line: mark.line() + 1,
+            column: mark.column() + 1,
         }
     }
 }
 
+impl Serialize for Location {
+    fn serialize<S>(&self, serializer: S) -> result::Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut location = serializer.serialize_struct("Location", 3)?;
+        location.serialize_field("index", &self.index)?;
+        location.serialize_field("line", &self.line)?;
+        location.serialize_field("column", &self.column)?;
+        location.end()
+    }
+}
+
+/// Broad categories of [`Error`], as returned by [`Error::classify`].
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Category {
+    /// The input is not well-formed YAML, for example because of a stray
+    /// character, bad indentation, or an alias to an undefined anchor.
+    Syntax,
+
+    /// Reading the input or writing the output failed, or libyaml ran out of
+    /// memory or its emitter failed. The underlying `io::Error`, if there is
+    /// one, is available from [`Error::io_error`].
+    Io,
+
+    /// The YAML is well formed but does not fit the type being deserialized or
+    /// serialized, for example an invalid type, a missing field, an unknown
+    /// variant, or an error raised by a `Serialize` or `Deserialize` impl.
+    Data,
+
+    /// Deserialization hit one of the limits that protect against malicious
+    /// input, such as the recursion limit or the alias expansion budget.
+    Limit,
+
+    /// The input ended before a complete document was read, so appending more
+    /// input may fix the error.
+    Eof,
+}
+
 impl Error {
+    /// Categorizes the cause of this error.
+    ///
+    /// ```
+    /// use serde_yaml::error::Category;
+    ///
+    /// let err = serde_yaml::from_str::<Vec<u32>>("[1, 2").unwrap_err();
+    /// assert_eq!(err.classify(), Category::Eof);
+    ///
+    /// let err = serde_yaml::from_str::<Vec<u32>>("[1, }").unwrap_err();
+    /// assert_eq!(err.classify(), Category::Syntax);
+    ///
+    /// let err = serde_yaml::from_str::<Vec<u32>>("[1, x]").unwrap_err();
+    /// assert_eq!(err.classify(), Category::Data);
+    /// ```
+    pub fn classify(&self) -> Category {
+        self.0.classify()
+    }
+
+    /// Returns true if this error was caused by malformed YAML.
+    pub fn is_syntax(&self) -> bool {
+        self.classify() == Category::Syntax
+    }
+
+    /// Returns true if this error was caused by failing to read or write bytes
+    /// on an I/O stream, or by libyaml running out of memory or its emitter
+    /// failing.
+    pub fn is_io(&self) -> bool {
+        self.classify() == Category::Io
+    }
+
+    /// Returns true if this error was caused by input or output that does not
+    /// fit the type being deserialized or serialized.
+    pub fn is_data(&self) -> bool {
+        self.classify() == Category::Data
+    }
+
+    /// Returns true if this error was caused by exceeding a limit on recursion
+    /// depth or alias expansion.
+    pub fn is_limit(&self) -> bool {
+        self.classify() == Category::Limit
+    }
+
+    /// Returns true if this error was caused by input that ended prematurely.
+    pub fn is_eof(&self) -> bool {
+        self.classify() == Category::Eof
+    }
+
     /// Returns the Location from the error if one exists.
     ///
+    /// Syntax errors are located where the parser gave up. Errors about the
+    /// shape of the data, such as an invalid type, a missing field, or an
+    /// unknown variant, are located at the start of the node that was being
+    /// deserialized. Missing field and invalid length errors describe that
+    /// node in their message, as in ``missing field `name` in mapping starting
+    /// at line 4 column 3``.
+    ///
     /// Not all types of errors have a location so this can return `None`.
     ///
     /// # Examples
@@ -102,6 +278,192 @@ impl Error {
     pub fn location(&self) -> Option<Location> {
         self.0.location()
     }
+
+    /// Returns which document of a stream the error is in, counting from 0.
+    ///
+    /// This is known to errors from the documents that iterating over a
+    /// [`Deserializer`](crate::Deserializer) yields, and from
+    /// [`Deserializer::validate`](crate::Deserializer::validate), along with
+    /// [`document_range`](Error::document_range) and
+    /// [`document_location`](Error::document_location).
+    ///
+    /// ```
+    /// use serde::Deserialize;
+    /// use serde_yaml::Deserializer;
+    ///
+    /// let input = "--- [1]\n---\n[2, x]\n--- [3]\n";
+    /// let mut documents = Deserializer::from_str(input);
+    /// Vec::<u16>::deserialize(documents.next().unwrap()).unwrap();
+    /// let err = Vec::<u16>::deserialize(documents.next().unwrap()).unwrap_err();
+    ///
+    /// assert_eq!(err.document_index(), Some(1));
+    /// assert_eq!(err.document_range(), Some(8..19));
+    /// let location = err.location().unwrap();
+    /// assert_eq!((location.line(), location.column()), (3, 5));
+    /// let location = err.document_location().unwrap();
+    /// assert_eq!((location.line(), location.column()), (2, 5));
+    /// ```
+    pub fn document_index(&self) -> Option<usize> {
+        self.0.document().map(|span| span.index)
+    }
+
+    /// Returns the bytes of the input that the document with the error spans,
+    /// from its `---` marker or directives to the end of its `...` marker or
+    /// the start of the next document.
+    ///
+    /// A document without a `---` marker begins at its first node, or where
+    /// the one before it ended, and one that fails to parse ends where the
+    /// parser gave up.
+    pub fn document_range(&self) -> Option<Range<u64>> {
+        self.0
+            .document()
+            .map(|span| span.start.index as u64..span.end as u64)
+    }
+
+    /// Returns the [location](Error::location) of the error relative to the
+    /// start of its document, as if the document were the whole input.
+    ///
+    /// Columns are only relative on the document's first line.
+    pub fn document_location(&self) -> Option<Location> {
+        let start = self.0.document()?.start;
+        let location = self.location()?;
+        Some(Location {
+            index: location.index.saturating_sub(start.index),
+            line: location.line.saturating_sub(start.line) + 1,
+            column: if location.line == start.line {
+                location.column.saturating_sub(start.column) + 1
+            } else {
+                location.column
+            },
+        })
+    }
+
+    /// Returns what the parser was in the middle of when it hit a syntax
+    /// error, such as `"while parsing a flow sequence"`.
+    ///
+    /// The same text is included in the `Display` representation of the
+    /// error, except where the input ended inside of a quoted scalar or flow
+    /// collection. That displays as the construct never being closed, like
+    /// "flow sequence started at line 12 column 8 was never closed".
+    pub fn context(&self) -> Option<&str> {
+        self.0.context()
+    }
+
+    /// Returns the location where the construct described by
+    /// [`context`](Error::context) begins.
+    ///
+    /// This points at the opening of the unterminated collection or scalar,
+    /// while [`location`](Error::location) points at where the parser gave up.
+    ///
+    /// ```
+    /// let err = serde_yaml::from_str::<serde_yaml::Value>("a: [1, 2\nb: 3\n").unwrap_err();
+    /// assert_eq!(err.context(), Some("while parsing a flow sequence"));
+    ///
+    /// let context = err.context_location().unwrap();
+    /// assert_eq!((context.line(), context.column()), (1, 4));
+    ///
+    /// let problem = err.location().unwrap();
+    /// assert_eq!((problem.line(), problem.column()), (2, 2));
+    /// ```
+    pub fn context_location(&self) -> Option<Location> {
+        self.0.context_mark().map(Location::from_mark)
+    }
+
+    /// Displays the error followed by the line of `source` that it points at,
+    /// with a caret under the offending column.
+    ///
+    /// `source` must be the input that was being deserialized. Tabs are
+    /// expanded to four spaces and wide characters are accounted for when
+    /// placing the caret. Long lines are cut down to a window around the
+    /// caret. Errors without a [location](Error::location) are displayed as
+    /// usual.
+    ///
+    /// ```
+    /// # use indoc::indoc;
+    /// let source = "name: demo\nreplicas: [1, }\n";
+    /// let err = serde_yaml::from_str::<serde_yaml::Value>(source).unwrap_err();
+    /// let expected = indoc! {"
+    ///     did not find expected node content at line 2 column 15, while parsing a flow node
+    ///       |
+    ///     2 | replicas: [1, }
+    ///       |               ^"};
+    /// assert_eq!(err.display_with_source(source).to_string(), expected);
+    /// ```
+    pub fn display_with_source<'a>(&'a self, source: &'a str) -> DisplayWithSource<'a> {
+        DisplayWithSource {
+            error: self,
+            source,
+        }
+    }
+
+    /// Returns the I/O error that caused this error, if it was caused by the
+    /// underlying reader or writer failing.
+    ///
+    /// The same `io::Error` is also returned by [`source()`][StdError::source].
+    ///
+    /// ```
+    /// # use std::io::{self, Write};
+    /// #
+    /// struct Full;
+    ///
+    /// impl Write for Full {
+    ///     fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+    ///         Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+    ///     }
+    ///
+    ///     fn flush(&mut self) -> io::Result<()> {
+    ///         Ok(())
+    ///     }
+    /// }
+    ///
+    /// let err = serde_yaml::to_writer(Full, &"x").unwrap_err();
+    /// assert_eq!(err.io_error().unwrap().kind(), io::ErrorKind::StorageFull);
+    /// ```
+    pub fn io_error(&self) -> Option<&io::Error> {
+        self.0.io_error()
+    }
+
+    /// Returns what the type being deserialized asked for where
+    /// deserialization failed: the struct and its fields, the enum and its
+    /// variants, or the primitive type.
+    ///
+    /// This is only known to errors from deserializing YAML text with this
+    /// crate's [`Deserializer`](crate::Deserializer) or functions like
+    /// [`from_str`](crate::from_str), and not to errors from deserializing a
+    /// [`Value`](crate::Value).
+    ///
+    /// ```
+    /// use serde_derive::Deserialize;
+    /// use serde_yaml::error::Expected;
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// #[serde(rename_all = "lowercase")]
+    /// enum Format {
+    ///     Plain,
+    ///     Json,
+    ///     Logfmt,
+    /// }
+    ///
+    /// #[derive(Deserialize, Debug)]
+    /// struct Logging {
+    ///     format: Format,
+    /// }
+    ///
+    /// let err = serde_yaml::from_str::<Logging>("format: xml").unwrap_err();
+    /// let expected = err.expected().unwrap();
+    /// assert_eq!(
+    ///     expected,
+    ///     Expected::Enum {
+    ///         name: "Format",
+    ///         variants: &["plain", "json", "logfmt"],
+    ///     },
+    /// );
+    /// assert_eq!(expected.to_string(), "one of `plain`, `json`, `logfmt`");
+    /// ```
+    #[cfg(feature = "expected")]
+    pub fn expected(&self) -> Option<Expected> {
+        self.0.expected()
+    }
 }
 
 pub(crate) fn new(inner: ErrorImpl) -> Error {
@@ -112,12 +474,147 @@ pub(crate) fn shared(shared: Arc<ErrorImpl>) -> Error {
     Error(Box::new(ErrorImpl::Shared(shared)))
 }
 
+// Notes what the type expected where deserialization failed, for
+// `Error::expected`, unless the input was at fault rather than its shape.
+#[cfg(feature = "expected")]
+pub(crate) fn with_expected(error: Error, expected: Option<Expected>) -> Error {
+    match expected {
+        Some(expected) if error.classify() == Category::Data => {
+            new(ErrorImpl::WithExpected(error.0, expected))
+        }
+        _ => error,
+    }
+}
+
+pub(crate) fn at_pointer(error: Error, pointer: &str) -> Error {
+    new(ErrorImpl::AtPointer(pointer.to_owned(), error.0))
+}
+
+pub(crate) fn in_document(error: Error, span: DocumentSpan) -> Error {
+    new(ErrorImpl::InDocument(error.0, span))
+}
+
+pub(crate) fn in_tag(error: Error, node: String, mark: libyaml::Mark) -> ErrorImpl {
+    ErrorImpl::InTag(node, error.0, mark)
+}
+
 pub(crate) fn fix_mark(mut error: Error, mark: libyaml::Mark, path: Path) -> Error {
-    if let ErrorImpl::Message(_, none @ None) = error.0.as_mut() {
-        *none = Some(Pos {
+    match error.0.as_mut() {
+        ErrorImpl::Message(_, none @ None) => {
+            *none = Some(Pos {
+                mark,
+                path: path.to_string(),
+            });
+        }
+        ErrorImpl::MessageInNode(msg, None) => {
+            let msg = mem::take(msg);
+            *error.0 = ErrorImpl::Message(
+                msg,
+                Some(Pos {
+                    mark,
+                    path: path.to_string(),
+                }),
+            );
+        }
+        _ => {}
+    }
+    error
+}
+
+// Quotes an integer as it was written in a visitor's complaint that it is out
+// of range, where the visitor could only quote it in decimal.
+pub(crate) fn int_as_written(mut error: Error, decimal: &str, written: &str) -> Error {
+    if let ErrorImpl::Message(msg, None) = error.0.as_mut() {
+        let prefix = format!("integer `{}` is out of range", decimal);
+        if let Some(rest) = msg.strip_prefix(&prefix) {
+            *msg = format!("integer `{}` is out of range{}", written, rest);
+        }
+    }
+    error
+}
+
+// Like fix_mark, but for an error from a visitor given the contents of the
+// mapping or sequence that starts at `mark`.
+pub(crate) fn in_node(
+    mut error: Error,
+    kind: &'static str,
+    mark: libyaml::Mark,
+    path: Path,
+) -> Error {
+    if let ErrorImpl::MessageInNode(_, none @ None) = error.0.as_mut() {
+        *none = Some((
+            kind,
+            Pos {
+                mark,
+                path: path.to_string(),
+            },
+        ));
+    }
+    fix_mark(error, mark, path)
+}
+
+// For an error from a visitor that refused the node at `mark` outright,
+// names the node, and what it is if that is why it was refused.
+pub(crate) fn rejected(
+    mut error: Error,
+    node: String,
+    kind: Option<String>,
+    mark: libyaml::Mark,
+    path: Path,
+) -> Error {
+    if let ErrorImpl::Message(reason, None) = error.0.as_mut() {
+        let reason = mem::take(reason);
+        let pos = Pos {
             mark,
             path: path.to_string(),
-        });
+        };
+        *error.0 = ErrorImpl::Rejected(node, kind, reason, pos);
+    }
+    error
+}
+
+// Whether a visitor refused an enum without looking inside it, which is how
+// one that does not take enums, like that of a serializer it is transcoding
+// into, refuses a tagged node.
+pub(crate) fn refuses_enum(error: &Error) -> bool {
+    match error.0.as_ref() {
+        ErrorImpl::Message(msg, None) => msg.starts_with("invalid type: enum,"),
+        _ => false,
+    }
+}
+
+// Keeps a copy of an error on its way back to a visitor, for `restore`.
+pub(crate) fn raise(error: Error, raised: &mut Option<Arc<ErrorImpl>>) -> Error {
+    let error = error.shared();
+    *raised = Some(Arc::clone(&error));
+    shared(error)
+}
+
+// A visitor that passes errors through another format's error type, as
+// serde_transcode does with the serializer it writes to, hands them back as
+// just their message. Recognizes the error last raised, so that it is
+// reported once as it was rather than with the path and location of each
+// enclosing node added again.
+pub(crate) fn restore(error: Error, raised: &Option<Arc<ErrorImpl>>) -> Error {
+    if let (ErrorImpl::Message(msg, None), Some(raised)) = (error.0.as_ref(), raised) {
+        let raised = shared(Arc::clone(raised));
+        if *msg == raised.to_string() {
+            return raised;
+        }
+    }
+    error
+}
+
+// Records that `error` happened inside `segment` of the value being
+// deserialized. Called once per level as the error propagates outwards.
+pub(crate) fn in_segment(mut error: Error, segment: Segment) -> Error {
+    match error.0.as_mut() {
+        ErrorImpl::Message(msg, None) | ErrorImpl::MessageInNode(msg, None) => {
+            let msg = mem::take(msg);
+            *error.0 = ErrorImpl::MessageAtPath(msg, OwnedPath::new(segment));
+        }
+        ErrorImpl::MessageAtPath(_, path) => path.push_parent(segment),
+        _ => {}
     }
     error
 }
@@ -143,6 +640,8 @@ impl From<emitter::EmitterError> for Error {
         match err {
             emitter::EmitterError::Libyaml(err) => Self::from(err),
             emitter::EmitterError::Io(err) => new(ErrorImpl::Io(err)),
+            emitter::EmitterError::ScalarTooLong(len) => new(ErrorImpl::ScalarTooLong(len)),
+            emitter::EmitterError::InvalidInput(msg) => new(ErrorImpl::Message(msg, None)),
         }
     }
 }
@@ -177,6 +676,127 @@ impl de::Error for Error {
     fn custom<T: Display>(msg: T) -> Self {
         Error(Box::new(ErrorImpl::Message(msg.to_string(), None)))
     }
+
+    fn invalid_length(len: usize, exp: &dyn de::Expected) -> Self {
+        let msg = format!("invalid length {}, expected {}", len, exp);
+        Error(Box::new(ErrorImpl::MessageInNode(msg, None)))
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        let msg = format!("missing field `{}`", field);
+        Error(Box::new(ErrorImpl::MessageInNode(msg, None)))
+    }
+
+    // The rest are serde's messages, with any text from the input cut short so
+    // that a scalar of a megabyte does not make an error of a megabyte.
+
+    fn invalid_type(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        let msg = format!("invalid type: {}, expected {}", Unexpected(unexp), exp);
+        Error(Box::new(ErrorImpl::Message(msg, None)))
+    }
+
+    fn invalid_value(unexp: de::Unexpected, exp: &dyn de::Expected) -> Self {
+        let msg = format!("invalid value: {}, expected {}", Unexpected(unexp), exp);
+        Error(Box::new(ErrorImpl::Message(msg, None)))
+    }
+
+    fn unknown_variant(variant: &str, expected: &'static [&'static str]) -> Self {
+        let msg = if expected.is_empty() {
+            format!(
+                "unknown variant {}, there are no variants",
+                Backticked(variant),
+            )
+        } else {
+            format!(
+                "unknown variant {}, expected {}",
+                Backticked(variant),
+                OneOf(expected),
+            )
+        };
+        Error(Box::new(ErrorImpl::Message(msg, None)))
+    }
+
+    fn unknown_field(field: &str, expected: &'static [&'static str]) -> Self {
+        let msg = if expected.is_empty() {
+            format!("unknown field {}, there are no fields", Backticked(field))
+        } else {
+            format!(
+                "unknown field {}, expected {}",
+                Backticked(field),
+                OneOf(expected),
+            )
+        };
+        Error(Box::new(ErrorImpl::Message(msg, None)))
+    }
+}
+
+// Text from the input longer than this many bytes is cut short in messages.
+const MAX_QUOTED: usize = 100;
+
+// Displays text from the input in backticks, or its start followed by how long
+// it is.
+pub(crate) struct Backticked<'a>(pub &'a str);
+
+impl<'a> Display for Backticked<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match prefix(self.0) {
+            Some(prefix) => write!(formatter, "`{}...` ({} bytes)", prefix, self.0.len()),
+            None => write!(formatter, "`{}`", self.0),
+        }
+    }
+}
+
+fn prefix(string: &str) -> Option<&str> {
+    if string.len() <= MAX_QUOTED {
+        return None;
+    }
+    let mut end = MAX_QUOTED;
+    while !string.is_char_boundary(end) {
+        end -= 1;
+    }
+    Some(&string[..end])
+}
+
+// Like `Backticked`, but in double quotes with escapes.
+pub(crate) struct Quoted<'a>(pub &'a str);
+
+impl<'a> Display for Quoted<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match prefix(self.0) {
+            Some(prefix) => write!(formatter, "{:?}... ({} bytes)", prefix, self.0.len()),
+            None => write!(formatter, "{:?}", self.0),
+        }
+    }
+}
+
+struct Unexpected<'a>(de::Unexpected<'a>);
+
+impl<'a> Display for Unexpected<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            de::Unexpected::Str(string) => write!(formatter, "string {}", Quoted(string)),
+            unexp => Display::fmt(&unexp, formatter),
+        }
+    }
+}
+
+pub(crate) struct OneOf(pub &'static [&'static str]);
+
+impl Display for OneOf {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self.0 {
+            [] => Ok(()),
+            [name] => write!(formatter, "`{}`", name),
+            [first, second] => write!(formatter, "`{}` or `{}`", first, second),
+            [first, rest @ ..] => {
+                write!(formatter, "one of `{}`", first)?;
+                for name in rest {
+                    write!(formatter, ", `{}`", name)?;
+                }
+                Ok(())
+            }
+        }
+    }
 }
 
 impl ErrorImpl {
@@ -184,11 +804,105 @@ impl ErrorImpl {
         self.mark().map(Location::from_mark)
     }
 
+    fn document(&self) -> Option<&DocumentSpan> {
+        match self {
+            ErrorImpl::InDocument(_, span) => Some(span),
+            ErrorImpl::Shared(err) => err.document(),
+            ErrorImpl::AtPointer(_, err) => err.document(),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.document(),
+            _ => None,
+        }
+    }
+
+    fn classify(&self) -> Category {
+        match self {
+            ErrorImpl::Libyaml(err) => {
+                if err.is_end_of_input() {
+                    Category::Eof
+                } else if err.is_syntax() {
+                    Category::Syntax
+                } else if err.is_io() {
+                    Category::Io
+                } else {
+                    Category::Data
+                }
+            }
+            ErrorImpl::Io(_) => Category::Io,
+            ErrorImpl::EndOfStream => Category::Eof,
+            ErrorImpl::UnknownAnchor(_) | ErrorImpl::DuplicateAnchor(..) => Category::Syntax,
+            ErrorImpl::RecursionLimitExceeded(_)
+            | ErrorImpl::RepetitionLimitExceeded
+            | ErrorImpl::DocumentLimitExceeded(..)
+            | ErrorImpl::InputLimitExceeded(..) => Category::Limit,
+            ErrorImpl::Message(..)
+            | ErrorImpl::MessageAtPath(..)
+            | ErrorImpl::MessageInNode(..)
+            | ErrorImpl::FromUtf8(_)
+            | ErrorImpl::MoreThanOneDocument
+            | ErrorImpl::BytesUnsupported
+            | ErrorImpl::ScalarTooLong(_)
+            | ErrorImpl::RoundTripMismatch(_)
+            | ErrorImpl::SerializeNestedEnum
+            | ErrorImpl::ScalarInMerge
+            | ErrorImpl::TaggedInMerge
+            | ErrorImpl::ScalarInMergeElement
+            | ErrorImpl::SequenceInMergeElement
+            | ErrorImpl::EmptyTag
+            | ErrorImpl::FailedToParseNumber
+            | ErrorImpl::IntegerOutOfRange(_)
+            | ErrorImpl::InvalidPointer(_)
+            | ErrorImpl::PointerNotFound(_)
+            | ErrorImpl::PointerNotTraversable(..)
+            | ErrorImpl::PointerIndexOutOfBounds(..)
+            | ErrorImpl::UnsupportedEdit(..)
+            | ErrorImpl::RootMismatch(..)
+            | ErrorImpl::DefaultValue(_)
+            | ErrorImpl::Rejected(..)
+            | ErrorImpl::Diagnostic(_) => Category::Data,
+            #[cfg(feature = "json")]
+            ErrorImpl::NonStringKeyInJson(_)
+            | ErrorImpl::TaggedInJson(_)
+            | ErrorImpl::NonFiniteFloatInJson(_) => Category::Data,
+            ErrorImpl::AtPointer(_, err) | ErrorImpl::InDocument(err, _) => err.classify(),
+            ErrorImpl::InTag(_, err, _) => err.classify(),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.classify(),
+            ErrorImpl::Shared(err) => err.classify(),
+        }
+    }
+
+    #[cfg(feature = "expected")]
+    fn expected(&self) -> Option<Expected> {
+        match self {
+            ErrorImpl::WithExpected(_, expected) => Some(*expected),
+            ErrorImpl::Shared(err) => err.expected(),
+            ErrorImpl::AtPointer(_, err) | ErrorImpl::InDocument(err, _) => err.expected(),
+            _ => None,
+        }
+    }
+
+    fn io_error(&self) -> Option<&io::Error> {
+        match self {
+            ErrorImpl::Io(err) => Some(err),
+            ErrorImpl::Shared(err) => err.io_error(),
+            ErrorImpl::AtPointer(_, err) | ErrorImpl::InDocument(err, _) => err.io_error(),
+            ErrorImpl::InTag(_, err, _) => err.io_error(),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.io_error(),
+            _ => None,
+        }
+    }
+
     fn source(&self) -> Option<&(dyn StdError + 'static)> {
         match self {
-            ErrorImpl::Io(err) => err.source(),
+            ErrorImpl::Io(err) => Some(err),
             ErrorImpl::FromUtf8(err) => err.source(),
             ErrorImpl::Shared(err) => err.source(),
+            ErrorImpl::AtPointer(_, err) | ErrorImpl::InDocument(err, _) => err.source(),
+            ErrorImpl::InTag(_, err, _) => err.source(),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.source(),
             _ => None,
         }
     }
@@ -196,21 +910,59 @@ impl ErrorImpl {
     fn mark(&self) -> Option<libyaml::Mark> {
         match self {
             ErrorImpl::Message(_, Some(Pos { mark, path: _ }))
+            | ErrorImpl::MessageInNode(_, Some((_, Pos { mark, path: _ })))
+            | ErrorImpl::Rejected(_, _, _, Pos { mark, path: _ })
             | ErrorImpl::RecursionLimitExceeded(mark)
-            | ErrorImpl::UnknownAnchor(mark) => Some(*mark),
-            ErrorImpl::Libyaml(err) => Some(err.mark()),
+            | ErrorImpl::DocumentLimitExceeded(_, mark)
+            | ErrorImpl::UnknownAnchor(mark)
+            | ErrorImpl::DefaultValue(mark)
+            | ErrorImpl::InTag(_, _, mark)
+            | ErrorImpl::DuplicateAnchor(_, _, mark)
+            | ErrorImpl::RootMismatch(_, _, _, mark) => Some(*mark),
+            ErrorImpl::Diagnostic(diagnostic) => Some(diagnostic.mark()),
+            ErrorImpl::Libyaml(err) => err.mark(),
             ErrorImpl::Shared(err) => err.mark(),
+            ErrorImpl::AtPointer(_, err) | ErrorImpl::InDocument(err, _) => err.mark(),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.mark(),
+            _ => None,
+        }
+    }
+
+    fn context(&self) -> Option<&str> {
+        match self {
+            ErrorImpl::Libyaml(err) => err.context(),
+            ErrorImpl::DuplicateAnchor(..) => Some("first defined"),
+            ErrorImpl::Shared(err) => err.context(),
+            ErrorImpl::AtPointer(_, err) | ErrorImpl::InDocument(err, _) => err.context(),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.context(),
+            _ => None,
+        }
+    }
+
+    fn context_mark(&self) -> Option<libyaml::Mark> {
+        match self {
+            ErrorImpl::Libyaml(err) => err.context_mark(),
+            ErrorImpl::DuplicateAnchor(_, first, _mark) => Some(*first),
+            ErrorImpl::Shared(err) => err.context_mark(),
+            ErrorImpl::AtPointer(_, err) | ErrorImpl::InDocument(err, _) => err.context_mark(),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.context_mark(),
             _ => None,
         }
     }
 
     fn message_no_mark(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
-            ErrorImpl::Message(msg, None) => f.write_str(msg),
-            ErrorImpl::Message(msg, Some(Pos { mark: _, path })) => {
-                if path != "." {
-                    write!(f, "{}: ", path)?;
-                }
+            ErrorImpl::Message(msg, None) | ErrorImpl::MessageInNode(msg, None) => f.write_str(msg),
+            ErrorImpl::Message(msg, Some(Pos { mark: _, path }))
+            | ErrorImpl::MessageInNode(msg, Some((_, Pos { mark: _, path }))) => {
+                write_path(f, path)?;
+                f.write_str(msg)
+            }
+            ErrorImpl::MessageAtPath(msg, path) => {
+                write!(f, "{}: ", path)?;
                 f.write_str(msg)
             }
             ErrorImpl::Libyaml(_) => unreachable!(),
@@ -222,10 +974,63 @@ impl ErrorImpl {
             ),
             ErrorImpl::RecursionLimitExceeded(_mark) => f.write_str("recursion limit exceeded"),
             ErrorImpl::RepetitionLimitExceeded => f.write_str("repetition limit exceeded"),
+            ErrorImpl::DocumentLimitExceeded(max, _mark) => write!(
+                f,
+                "document {} exceeds the limit of {} documents",
+                max + 1,
+                max,
+            ),
+            ErrorImpl::InputLimitExceeded(max, Some(len)) => write!(
+                f,
+                "input of {} bytes exceeds the limit of {} bytes",
+                len, max,
+            ),
+            ErrorImpl::InputLimitExceeded(max, None) => {
+                write!(f, "input exceeds the limit of {} bytes", max)
+            }
             ErrorImpl::BytesUnsupported => {
                 f.write_str("serialization and deserialization of bytes in YAML is not implemented")
             }
+            ErrorImpl::ScalarTooLong(len) => write!(
+                f,
+                "scalar of {} bytes is longer than the {} that libyaml can emit",
+                len,
+                i32::MAX,
+            ),
+            ErrorImpl::RoundTripMismatch(detail) => {
+                write!(
+                    f,
+                    "serialized YAML does not parse back the same: {}",
+                    detail
+                )
+            }
             ErrorImpl::UnknownAnchor(_mark) => f.write_str("unknown anchor"),
+            ErrorImpl::DefaultValue(_mark) => {
+                f.write_str("YAML 1.1 default value `=` cannot be deserialized")
+            }
+            ErrorImpl::DuplicateAnchor(name, _first, _mark) => {
+                write!(f, "duplicate anchor {:?}", name)
+            }
+            ErrorImpl::Diagnostic(diagnostic) => f.write_str(diagnostic.message()),
+            ErrorImpl::RootMismatch(expected, found, hint, _mark) => {
+                write!(
+                    f,
+                    "expected {} at the document root, but the document is {}",
+                    expected, found,
+                )?;
+                if let Some(hint) = hint {
+                    write!(f, " ({})", hint)?;
+                }
+                Ok(())
+            }
+            ErrorImpl::Rejected(node, kind, reason, Pos { mark: _, path }) => {
+                write_path(f, path)?;
+                f.write_str(node)?;
+                if let Some(kind) = kind {
+                    write!(f, " is {} and", kind)?;
+                }
+                write!(f, " was rejected: {}", reason)
+            }
             ErrorImpl::SerializeNestedEnum => {
                 f.write_str("serializing nested enums in YAML is not supported yet")
             }
@@ -241,6 +1046,59 @@ impl ErrorImpl {
             }
             ErrorImpl::EmptyTag => f.write_str("empty YAML tag is not allowed"),
             ErrorImpl::FailedToParseNumber => f.write_str("failed to parse YAML number"),
+            ErrorImpl::IntegerOutOfRange(int) => {
+                write!(f, "integer {} does not fit in serde_yaml::Value", int)
+            }
+            ErrorImpl::InvalidPointer(pointer) => write!(
+                f,
+                "invalid pointer {:?}, expected it to be empty or to start with '/'",
+                pointer,
+            ),
+            ErrorImpl::PointerNotFound(pointer) => write!(f, "no value found at {}", pointer),
+            ErrorImpl::PointerNotTraversable(pointer, found) => write!(
+                f,
+                "{}: parent is a {}, not a mapping or sequence",
+                pointer, found,
+            ),
+            ErrorImpl::PointerIndexOutOfBounds(pointer, len) => write!(
+                f,
+                "{}: index is past the end of a sequence of length {}",
+                pointer, len,
+            ),
+            ErrorImpl::UnsupportedEdit(pointer, reason) => {
+                if !pointer.is_empty() {
+                    write!(f, "{}: ", pointer)?;
+                }
+                f.write_str(reason)
+            }
+            ErrorImpl::AtPointer(pointer, err) => {
+                if !pointer.is_empty() {
+                    write!(f, "{}: ", pointer)?;
+                }
+                err.message_no_mark(f)
+            }
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.message_no_mark(f),
+            ErrorImpl::InDocument(err, _) => err.message_no_mark(f),
+            ErrorImpl::InTag(node, err, _mark) => {
+                write!(f, "{}: ", node)?;
+                err.display(f)
+            }
+            #[cfg(feature = "json")]
+            ErrorImpl::NonStringKeyInJson(path) => {
+                write_path(f, path)?;
+                f.write_str("mapping key is not a string and cannot be represented in JSON")
+            }
+            #[cfg(feature = "json")]
+            ErrorImpl::TaggedInJson(path) => {
+                write_path(f, path)?;
+                f.write_str("tagged value cannot be represented in JSON")
+            }
+            #[cfg(feature = "json")]
+            ErrorImpl::NonFiniteFloatInJson(path) => {
+                write_path(f, path)?;
+                f.write_str("infinite or NaN float cannot be represented in JSON")
+            }
             ErrorImpl::Shared(_) => unreachable!(),
         }
     }
@@ -249,6 +1107,55 @@ impl ErrorImpl {
         match self {
             ErrorImpl::Libyaml(err) => Display::fmt(err, f),
             ErrorImpl::Shared(err) => err.display(f),
+            ErrorImpl::InDocument(err, _) => err.display(f),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.display(f),
+            ErrorImpl::MessageInNode(_, Some((kind, Pos { mark, path: _ }))) => {
+                self.message_no_mark(f)?;
+                write!(
+                    f,
+                    " in {} starting at line {} column {}",
+                    kind,
+                    mark.line() + 1,
+                    mark.column() + 1,
+                )
+            }
+            ErrorImpl::DuplicateAnchor(_, first, mark) => {
+                self.message_no_mark(f)?;
+                write!(f, " at {}, first defined at {}", mark, first)
+            }
+            ErrorImpl::InTag(node, err, mark) => {
+                write!(f, "{} at {}: ", node, mark)?;
+                err.display(f)
+            }
+            ErrorImpl::RootMismatch(expected, found, hint, mark) => {
+                write!(
+                    f,
+                    "expected {} at the document root, but the document is {} starting at line {} column {}",
+                    expected,
+                    found,
+                    mark.line() + 1,
+                    mark.column() + 1,
+                )?;
+                if let Some(hint) = hint {
+                    write!(f, " ({})", hint)?;
+                }
+                Ok(())
+            }
+            ErrorImpl::Rejected(node, kind, reason, Pos { mark, path }) => {
+                write_path(f, path)?;
+                write!(
+                    f,
+                    "{} at line {} column {}",
+                    node,
+                    mark.line() + 1,
+                    mark.column() + 1,
+                )?;
+                if let Some(kind) = kind {
+                    write!(f, " is {} and", kind)?;
+                }
+                write!(f, " was rejected: {}", reason)
+            }
             _ => {
                 self.message_no_mark(f)?;
                 if let Some(mark) = self.mark() {
@@ -265,6 +1172,9 @@ impl ErrorImpl {
         match self {
             ErrorImpl::Libyaml(err) => Debug::fmt(err, f),
             ErrorImpl::Shared(err) => err.debug(f),
+            ErrorImpl::InDocument(err, _) => err.debug(f),
+            #[cfg(feature = "expected")]
+            ErrorImpl::WithExpected(err, _) => err.debug(f),
             _ => {
                 f.write_str("Error(")?;
                 struct MessageNoMark<'a>(&'a ErrorImpl);
@@ -288,3 +1198,10 @@ impl ErrorImpl {
         }
     }
 }
+
+fn write_path(f: &mut fmt::Formatter, path: &str) -> fmt::Result {
+    if path != "." {
+        write!(f, "{}: ", path)?;
+    }
+    Ok(())
+}
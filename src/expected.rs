@@ -0,0 +1,61 @@
+// Only reachable through `Error::expected` with the "expected" feature, but
+// recorded regardless so that deserializing looks the same either way.
+#![cfg_attr(not(feature = "expected"), allow(dead_code))]
+
+use crate::error::OneOf;
+use std::fmt::{self, Display};
+
+/// What the type being deserialized asked for where deserialization failed,
+/// as returned by [`Error::expected`](crate::Error::expected).
+///
+/// This is the innermost struct, enum or primitive whose deserialization
+/// failed. An unknown or missing field is the struct's, and an unknown variant
+/// is the enum's, so a tool can list what would have been accepted next to the
+/// location of the error.
+///
+/// It displays as a short description, like ``one of `plain`, `json`,
+/// `logfmt` `` for an enum.
+#[derive(Copy, Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum Expected {
+    /// A struct, deserialized from a mapping with these keys.
+    Struct {
+        /// The name of the struct.
+        name: &'static str,
+        /// The names of its fields.
+        fields: &'static [&'static str],
+    },
+    /// An enum, written as one of these variant names, by itself or as the
+    /// only key of a mapping or as a tag.
+    Enum {
+        /// The name of the enum.
+        name: &'static str,
+        /// The names of its variants.
+        variants: &'static [&'static str],
+    },
+    /// A primitive type, by its Rust name, like `u32`, `bool` or `string`.
+    Primitive(&'static str),
+}
+
+impl Display for Expected {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Expected::Struct { name, fields: [] } => write!(formatter, "struct {}", name),
+            Expected::Struct { name, fields } => {
+                write!(formatter, "struct {} with fields ", name)?;
+                for (i, field) in fields.iter().enumerate() {
+                    if i > 0 {
+                        formatter.write_str(", ")?;
+                    }
+                    write!(formatter, "`{}`", field)?;
+                }
+                Ok(())
+            }
+            Expected::Enum { name, variants: [] } => {
+                write!(formatter, "enum {} with no variants", name)
+            }
+            Expected::Enum { name: _, variants } => Display::fmt(&OneOf(variants), formatter),
+            Expected::Primitive(name) => formatter.write_str(name),
+        }
+    }
+}
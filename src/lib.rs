@@ -114,6 +114,39 @@
 //!     Ok(())
 //! }
 //! ```
+//!
+//! A unit struct serializes as `null`, and deserializes from `null` or an
+//! empty mapping, either of them tagged with the struct's name, or a mapping
+//! of its name to either. A newtype struct is its field, tag and all, so a
+//! newtype around an enum reads and writes the enum's `!tag`.
+//!
+//! ```
+//! # use serde_derive::{Serialize, Deserialize};
+//! use serde::{Serialize, Deserialize};
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct Marker;
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! enum Shape {
+//!     Circle(f64),
+//! }
+//!
+//! #[derive(Serialize, Deserialize, PartialEq, Debug)]
+//! struct Wrapper(Shape);
+//!
+//! fn main() -> Result<(), serde_yaml::Error> {
+//!     for yaml in ["null", "{}", "!Marker", "Marker: null"] {
+//!         assert_eq!(serde_yaml::from_str::<Marker>(yaml)?, Marker);
+//!     }
+//!     assert!(serde_yaml::from_str::<Marker>("!Other").is_err());
+//!
+//!     let yaml = serde_yaml::to_string(&Wrapper(Shape::Circle(1.0)))?;
+//!     assert_eq!(yaml, "!Circle 1.0\n");
+//!     assert_eq!(serde_yaml::from_str::<Wrapper>(&yaml)?, Wrapper(Shape::Circle(1.0)));
+//!     Ok(())
+//! }
+//! ```
 
 #![doc(html_root_url = "https://docs.rs/serde_yaml/0.9.34+deprecated")]
 #![deny(missing_docs, unsafe_op_in_unsafe_fn)]
@@ -161,29 +194,64 @@
     clippy::needless_lifetimes,
 )]
 
-pub use crate::de::{from_reader, from_slice, from_str, Deserializer};
+pub use crate::de::{
+    from_cow, from_cow_str, from_reader, from_reader_multi, from_slice, from_str, from_str_partial,
+    from_str_with_diagnostics, from_str_with_ignored_callback, validate, validate_reader,
+    Deserializer, DuplicateAnchors, Schema, UnparsedRest,
+};
+pub use crate::document_value::{DocumentValue, DocumentValues};
 pub use crate::error::{Error, Location, Result};
-pub use crate::ser::{to_string, to_writer, Serializer};
+pub use crate::peek::{peek, Peeked};
+pub use crate::scalar_info::ScalarInfo;
+pub use crate::ser::{
+    to_string, to_vec, to_writer, to_writer_counted, to_writer_documents, to_writer_multi,
+    Serializer, SerializerBuilder,
+};
+pub use crate::transcode::{transcode, EmitOptions};
 #[doc(inline)]
-pub use crate::value::{from_value, to_value, Index, Number, Sequence, Value};
+pub use crate::value::{from_value, to_value, Index, Number, Sequence, StyledValue, Value};
 
 #[doc(inline)]
 pub use crate::mapping::Mapping;
 
 mod de;
-mod error;
+pub mod diagnostic;
+pub mod document;
+mod document_value;
+pub mod error;
+mod expected;
 mod libyaml;
 mod loader;
 pub mod mapping;
 mod number;
 mod path;
+mod peek;
+pub mod raw;
+mod scalar_info;
+mod sequence;
 mod ser;
+mod snippet;
+mod stream;
+#[cfg(feature = "test-util")]
+pub mod test_util;
+mod transcode;
 pub mod value;
+mod verify;
 pub mod with;
+mod yaml11;
 
 // Prevent downstream code from implementing the Index trait.
 mod private {
     pub trait Sealed {}
+    impl Sealed for i8 {}
+    impl Sealed for i16 {}
+    impl Sealed for i32 {}
+    impl Sealed for i64 {}
+    impl Sealed for isize {}
+    impl Sealed for u8 {}
+    impl Sealed for u16 {}
+    impl Sealed for u32 {}
+    impl Sealed for u64 {}
     impl Sealed for usize {}
     impl Sealed for str {}
     impl Sealed for String {}
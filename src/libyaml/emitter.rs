@@ -1,23 +1,36 @@
 use crate::libyaml::error::Error;
+use crate::libyaml::event::{Anchor, CollectionStyle, Event, ScalarStyle, Tag};
+use std::pin::Pin;
 use unsafe_libyaml as sys;
 
 #[repr(transparent)]
 struct PinnedHandle(sys::yaml_emitter_t, std::marker::PhantomPinned);
 
 impl PinnedHandle {
-    fn init(&mut self, handler: sys::yaml_write_handler_t, data: *mut std::ffi::c_void) {
+    fn init(
+        &mut self,
+        handler: sys::yaml_write_handler_t,
+        data: *mut std::ffi::c_void,
+    ) -> Result<(), Error> {
         unsafe {
             let this = &raw mut self.0;
             if sys::yaml_emitter_initialize(this).fail {
-                panic!("malloc error: {}", Error::get_emitter_error(this));
+                return Err(Error::get_emitter_error(this));
             }
             sys::yaml_emitter_set_unicode(this, true);
             sys::yaml_emitter_set_width(this, -1);
             sys::yaml_emitter_set_output(this, handler, data);
         }
+        Ok(())
     }
 }
 
+// The raw pointers in libyaml's state point to its own allocations and to the
+// boxed struct holding the handle, which moves between threads along with it.
+// The writer in that struct is only Send if `W` is, which keeps `Emitter<W>`
+// from being Send otherwise. libyaml keeps no thread-local state.
+unsafe impl Send for PinnedHandle {}
+
 impl Drop for PinnedHandle {
     fn drop(&mut self) {
         unsafe { sys::yaml_emitter_delete(&mut self.0) }
@@ -28,75 +41,135 @@ impl Drop for PinnedHandle {
 pub enum EmitterError {
     Libyaml(Error),
     Io(std::io::Error),
+    // libyaml takes the length of a scalar as an i32.
+    ScalarTooLong(usize),
+    // An anchor, tag or tag directive that cannot be written as given.
+    InvalidInput(String),
 }
 
-#[derive(Debug)]
-pub enum Event<'a> {
-    StreamStart,
-    StreamEnd,
-    DocumentStart,
-    DocumentEnd,
-    Scalar(Scalar<'a>),
-    SequenceStart(Sequence),
-    SequenceEnd,
-    MappingStart(Mapping),
-    MappingEnd,
-}
-
-#[derive(Debug)]
-pub struct Scalar<'a> {
-    pub tag: Option<String>,
-    pub value: &'a str,
-    pub style: ScalarStyle,
-}
-
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
-pub enum ScalarStyle {
-    Any,
-    Plain,
-    SingleQuoted,
-    Literal,
-}
-
-#[derive(Debug)]
-pub struct Sequence {
-    pub tag: Option<String>,
+impl std::fmt::Display for EmitterError {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EmitterError::Libyaml(err) => std::fmt::Display::fmt(err, formatter),
+            EmitterError::Io(err) => std::fmt::Display::fmt(err, formatter),
+            EmitterError::ScalarTooLong(len) => write!(
+                formatter,
+                "scalar of {} bytes is longer than the {} that libyaml can emit",
+                len,
+                i32::MAX,
+            ),
+            EmitterError::InvalidInput(msg) => formatter.write_str(msg),
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct Mapping {
-    pub tag: Option<String>,
+impl std::error::Error for EmitterError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            EmitterError::Libyaml(err) => Some(err),
+            EmitterError::Io(err) => Some(err),
+            EmitterError::ScalarTooLong(_) | EmitterError::InvalidInput(_) => None,
+        }
+    }
 }
 
+// Holds the libyaml emitter together with the state its write handler uses.
+// libyaml is given a pointer to this struct as the handler's data, so it is
+// always kept in a `Pin<Box<..>>` and its fields are only reached through
+// `project`, which never moves any of them out.
 struct EmitterPinned<W> {
     handle: PinnedHandle,
     writer: Option<W>,
     error: Option<std::io::Error>,
+    // Output collected between writes to `writer`. Unused if the capacity is
+    // zero, in which case every chunk from libyaml is written immediately.
+    buffer: Vec<u8>,
+    // A copy of all output since it was last taken, if requested.
+    copy: Option<Vec<u8>>,
+    // Bytes of output for the writer so far, whether written or buffered.
+    written: u64,
+    // Spaces written at the start of every line that is not empty, and
+    // whether the output so far ends at the start of a line.
+    prefix: usize,
+    line_start: bool,
 }
 
-pub struct Emitter<W> {
-    pinned: Box<EmitterPinned<W>>,
+struct EmitterProjection<'a, W> {
+    handle: *mut sys::yaml_emitter_t,
+    writer: &'a mut Option<W>,
+    error: &'a mut Option<std::io::Error>,
+    buffer: &'a mut Vec<u8>,
+    copy: &'a mut Option<Vec<u8>>,
+    written: &'a mut u64,
+    prefix: &'a mut usize,
+    line_start: &'a mut bool,
 }
 
-impl<W> Emitter<W> {
-    pub fn new(write: W) -> Emitter<W>
+impl<W> EmitterPinned<W> {
+    fn new(writer: W, buffer_capacity: usize) -> Result<Pin<Box<Self>>, Error>
     where
         W: std::io::Write,
     {
-        let mut pinned = Box::<EmitterPinned<W>>::new(EmitterPinned {
+        let mut pinned = Box::pin(EmitterPinned {
             handle: unsafe { std::mem::zeroed() },
-            writer: Some(write),
+            writer: Some(writer),
             error: None,
+            buffer: Vec::with_capacity(buffer_capacity),
+            copy: None,
+            written: 0,
+            prefix: 0,
+            line_start: true,
         });
-        let handler = handler::<W>;
-        let data = (pinned.as_mut() as *mut EmitterPinned<W>).cast();
-        pinned.handle.init(handler, data);
-        Emitter { pinned }
+        let this = unsafe { pinned.as_mut().get_unchecked_mut() };
+        let data = (&raw mut *this).cast();
+        this.handle.init(handler::<W>, data)?;
+        Ok(pinned)
+    }
+
+    // The references returned must not be held across a call into libyaml,
+    // which may run the write handler and modify the same fields.
+    fn project(self: Pin<&mut Self>) -> EmitterProjection<'_, W> {
+        let this = unsafe { self.get_unchecked_mut() };
+        EmitterProjection {
+            handle: &raw mut this.handle.0,
+            writer: &mut this.writer,
+            error: &mut this.error,
+            buffer: &mut this.buffer,
+            copy: &mut this.copy,
+            written: &mut this.written,
+            prefix: &mut this.prefix,
+            line_start: &mut this.line_start,
+        }
+    }
+}
+
+pub struct Emitter<W> {
+    pinned: Pin<Box<EmitterPinned<W>>>,
+    // Hold the NUL-terminated anchor and tag of the event being emitted.
+    // libyaml copies them while initializing the event, so one buffer of each
+    // serves every event.
+    anchor: Vec<u8>,
+    tag: Vec<u8>,
+}
+
+impl<W> Emitter<W> {
+    pub fn new(write: W, buffer_capacity: usize) -> Result<Emitter<W>, Error>
+    where
+        W: std::io::Write,
+    {
+        let pinned = EmitterPinned::new(write, buffer_capacity)?;
+        Ok(Emitter {
+            pinned,
+            anchor: Vec::new(),
+            tag: Vec::new(),
+        })
     }
 
     pub fn emit(&mut self, event: Event) -> Result<(), EmitterError> {
-        let emitter = &raw mut self.pinned.handle.0;
-        let error = &mut self.pinned.error;
+        validate(&event)?;
+        let emitter = self.pinned.as_mut().project().handle;
+        let anchor_buffer = &mut self.anchor;
+        let tag_buffer = &mut self.tag;
         unsafe {
             let mut sys_event = std::mem::zeroed::<sys::yaml_event_t>();
             let initialize_status = match event {
@@ -104,38 +177,67 @@ impl<W> Emitter<W> {
                     sys::yaml_stream_start_event_initialize(&mut sys_event, sys::YAML_UTF8_ENCODING)
                 }
                 Event::StreamEnd => sys::yaml_stream_end_event_initialize(&mut sys_event),
-                Event::DocumentStart => {
-                    let version_directive = std::ptr::null_mut();
-                    let tag_directives_start = std::ptr::null_mut();
-                    let tag_directives_end = std::ptr::null_mut();
-                    let implicit = true;
+                Event::DocumentStart(document_start) => {
+                    let mut version = std::mem::zeroed::<sys::yaml_version_directive_t>();
+                    let version_directive = match document_start.version {
+                        Some((major, minor)) => {
+                            version.major = major;
+                            version.minor = minor;
+                            &raw mut version
+                        }
+                        None => std::ptr::null_mut(),
+                    };
+                    // The handles and prefixes are NUL-terminated one after
+                    // another in `strings`, which is not modified again once
+                    // pointers into it are taken.
+                    let mut strings = Vec::new();
+                    for (handle, prefix) in &document_start.tag_directives {
+                        strings.extend_from_slice(handle.as_bytes());
+                        strings.push(b'\0');
+                        strings.extend_from_slice(prefix.as_bytes());
+                        strings.push(b'\0');
+                    }
+                    let mut offset = 0;
+                    let mut tag_directives = Vec::new();
+                    for (handle, prefix) in &document_start.tag_directives {
+                        let mut directive = std::mem::zeroed::<sys::yaml_tag_directive_t>();
+                        directive.handle = strings.as_mut_ptr().add(offset);
+                        offset += handle.len() + 1;
+                        directive.prefix = strings.as_mut_ptr().add(offset);
+                        offset += prefix.len() + 1;
+                        tag_directives.push(directive);
+                    }
+                    let tag_directives_start = tag_directives.as_mut_ptr();
+                    let tag_directives_end = tag_directives_start.add(tag_directives.len());
                     sys::yaml_document_start_event_initialize(
                         &mut sys_event,
                         version_directive,
                         tag_directives_start,
                         tag_directives_end,
-                        implicit,
+                        document_start.implicit,
                     )
                 }
-                Event::DocumentEnd => {
-                    let implicit = true;
-                    sys::yaml_document_end_event_initialize(&mut sys_event, implicit)
+                Event::DocumentEnd(document_end) => {
+                    sys::yaml_document_end_event_initialize(&mut sys_event, document_end.implicit)
+                }
+                Event::Alias(anchor) => {
+                    let anchor = c_str(anchor_buffer, Some(anchor.as_bytes()));
+                    sys::yaml_alias_event_initialize(&mut sys_event, anchor)
                 }
-                Event::Scalar(mut scalar) => {
-                    let anchor = std::ptr::null();
-                    let tag = scalar.tag.as_mut().map_or_else(std::ptr::null, |tag| {
-                        tag.push('\0');
-                        tag.as_ptr()
-                    });
+                Event::Scalar(scalar) => {
+                    let anchor = c_str(anchor_buffer, scalar.anchor.as_ref().map(|a| a.as_bytes()));
+                    let tag = c_str(tag_buffer, scalar.tag.as_ref().map(|t| t.as_bytes()));
                     let value = scalar.value.as_ptr();
-                    let length = scalar.value.len() as i32;
+                    let length = scalar_length(scalar.value.len())?;
                     let plain_implicit = tag.is_null();
                     let quoted_implicit = tag.is_null();
                     let style = match scalar.style {
                         ScalarStyle::Any => sys::YAML_ANY_SCALAR_STYLE,
                         ScalarStyle::Plain => sys::YAML_PLAIN_SCALAR_STYLE,
                         ScalarStyle::SingleQuoted => sys::YAML_SINGLE_QUOTED_SCALAR_STYLE,
+                        ScalarStyle::DoubleQuoted => sys::YAML_DOUBLE_QUOTED_SCALAR_STYLE,
                         ScalarStyle::Literal => sys::YAML_LITERAL_SCALAR_STYLE,
+                        ScalarStyle::Folded => sys::YAML_FOLDED_SCALAR_STYLE,
                     };
                     sys::yaml_scalar_event_initialize(
                         &mut sys_event,
@@ -148,14 +250,18 @@ impl<W> Emitter<W> {
                         style,
                     )
                 }
-                Event::SequenceStart(mut sequence) => {
-                    let anchor = std::ptr::null();
-                    let tag = sequence.tag.as_mut().map_or_else(std::ptr::null, |tag| {
-                        tag.push('\0');
-                        tag.as_ptr()
-                    });
+                Event::SequenceStart(sequence) => {
+                    let anchor = c_str(
+                        anchor_buffer,
+                        sequence.anchor.as_ref().map(|a| a.as_bytes()),
+                    );
+                    let tag = c_str(tag_buffer, sequence.tag.as_ref().map(|t| t.as_bytes()));
                     let implicit = tag.is_null();
-                    let style = sys::YAML_ANY_SEQUENCE_STYLE;
+                    let style = match sequence.style {
+                        CollectionStyle::Any => sys::YAML_ANY_SEQUENCE_STYLE,
+                        CollectionStyle::Block => sys::YAML_BLOCK_SEQUENCE_STYLE,
+                        CollectionStyle::Flow => sys::YAML_FLOW_SEQUENCE_STYLE,
+                    };
                     sys::yaml_sequence_start_event_initialize(
                         &mut sys_event,
                         anchor,
@@ -165,14 +271,16 @@ impl<W> Emitter<W> {
                     )
                 }
                 Event::SequenceEnd => sys::yaml_sequence_end_event_initialize(&mut sys_event),
-                Event::MappingStart(mut mapping) => {
-                    let anchor = std::ptr::null();
-                    let tag = mapping.tag.as_mut().map_or_else(std::ptr::null, |tag| {
-                        tag.push('\0');
-                        tag.as_ptr()
-                    });
+                Event::MappingStart(mapping) => {
+                    let anchor =
+                        c_str(anchor_buffer, mapping.anchor.as_ref().map(|a| a.as_bytes()));
+                    let tag = c_str(tag_buffer, mapping.tag.as_ref().map(|t| t.as_bytes()));
                     let implicit = tag.is_null();
-                    let style = sys::YAML_ANY_MAPPING_STYLE;
+                    let style = match mapping.style {
+                        CollectionStyle::Any => sys::YAML_ANY_MAPPING_STYLE,
+                        CollectionStyle::Block => sys::YAML_BLOCK_MAPPING_STYLE,
+                        CollectionStyle::Flow => sys::YAML_FLOW_MAPPING_STYLE,
+                    };
                     sys::yaml_mapping_start_event_initialize(
                         &mut sys_event,
                         anchor,
@@ -187,7 +295,7 @@ impl<W> Emitter<W> {
                 return Err(EmitterError::Libyaml(Error::get_emitter_error(emitter)));
             }
             if sys::yaml_emitter_emit(emitter, &mut sys_event).fail {
-                if let Some(error) = error.take() {
+                if let Some(error) = self.pinned.as_mut().project().error.take() {
                     return Err(EmitterError::Io(error));
                 } else {
                     return Err(EmitterError::Libyaml(Error::get_emitter_error(emitter)));
@@ -197,23 +305,164 @@ impl<W> Emitter<W> {
         Ok(())
     }
 
-    pub fn flush(&mut self) -> Result<(), EmitterError> {
-        let emitter = &raw mut self.pinned.handle.0;
-        let error = &mut self.pinned.error;
+    pub fn flush(&mut self) -> Result<(), EmitterError>
+    where
+        W: std::io::Write,
+    {
+        let emitter = self.pinned.as_mut().project().handle;
         unsafe {
             if sys::yaml_emitter_flush(emitter).fail {
-                if let Some(error) = error.take() {
+                if let Some(error) = self.pinned.as_mut().project().error.take() {
                     return Err(EmitterError::Io(error));
                 } else {
                     return Err(EmitterError::Libyaml(Error::get_emitter_error(emitter)));
                 }
             }
         }
+        let pinned = self.pinned.as_mut().project();
+        if let Some(writer) = pinned.writer {
+            if !pinned.buffer.is_empty() {
+                let result = writer.write_all(pinned.buffer);
+                pinned.buffer.clear();
+                result.map_err(EmitterError::Io)?;
+            }
+            writer.flush().map_err(EmitterError::Io)?;
+        }
         Ok(())
     }
 
-    pub fn into_inner(mut self) -> W {
-        self.pinned.writer.take().expect("writer is already taken")
+    // Indents all output by `prefix` spaces, leaving empty lines empty.
+    pub fn prefix_lines(&mut self, prefix: usize) {
+        *self.pinned.as_mut().project().prefix = prefix;
+    }
+
+    // Counts output from libyaml as it is produced, so bytes still in the
+    // buffer are included. libyaml flushes its own buffer at the end of every
+    // document, which makes this exact between documents.
+    pub fn bytes_written(&self) -> u64 {
+        self.pinned.written
+    }
+
+    // Starts keeping a copy of the output, as it is produced by libyaml and
+    // regardless of when it reaches the writer.
+    pub fn copy_output(&mut self) {
+        let pinned = self.pinned.as_mut().project();
+        pinned.copy.get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_output_copy(&mut self) -> Vec<u8> {
+        let pinned = self.pinned.as_mut().project();
+        pinned.copy.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    pub fn into_inner(self) -> Result<W, EmitterError>
+    where
+        W: std::io::Write,
+    {
+        let (writer, result) = self.into_inner_parts();
+        result.map(|()| writer)
+    }
+
+    // Flushes, then gives back the writer whether or not that succeeded.
+    pub fn into_inner_parts(mut self) -> (W, Result<(), EmitterError>)
+    where
+        W: std::io::Write,
+    {
+        let result = self.flush();
+        let pinned = self.pinned.as_mut().project();
+        let writer = pinned.writer.take().expect("writer is already taken");
+        (writer, result)
+    }
+}
+
+// Checks what libyaml would otherwise receive as NUL-terminated strings, so
+// that nothing is silently truncated and no anchor produces invalid YAML.
+fn validate(event: &Event) -> Result<(), EmitterError> {
+    let (anchor, tag) = match event {
+        Event::DocumentStart(document_start) => {
+            for (handle, prefix) in &document_start.tag_directives {
+                check_nul("tag handle", handle.as_bytes())?;
+                check_nul("tag prefix", prefix.as_bytes())?;
+            }
+            return Ok(());
+        }
+        Event::Alias(anchor) => (Some(anchor), None),
+        Event::Scalar(scalar) => (scalar.anchor.as_ref(), scalar.tag.as_ref()),
+        Event::SequenceStart(sequence) => (sequence.anchor.as_ref(), sequence.tag.as_ref()),
+        Event::MappingStart(mapping) => (mapping.anchor.as_ref(), mapping.tag.as_ref()),
+        _ => return Ok(()),
+    };
+    if let Some(anchor) = anchor {
+        check_anchor(anchor)?;
+    }
+    if let Some(tag) = tag {
+        check_tag(tag)?;
+    }
+    Ok(())
+}
+
+fn check_anchor(anchor: &Anchor) -> Result<(), EmitterError> {
+    let bytes = anchor.as_bytes();
+    let invalid = |reason: &str| {
+        EmitterError::InvalidInput(format!(
+            "invalid anchor {:?}: {}",
+            String::from_utf8_lossy(bytes),
+            reason,
+        ))
+    };
+    let Ok(name) = std::str::from_utf8(bytes) else {
+        return Err(invalid("anchors must be valid UTF-8"));
+    };
+    if name.is_empty() {
+        return Err(invalid("anchors must not be empty"));
+    }
+    match name.chars().find(|&ch| !is_anchor_char(ch)) {
+        Some(ch) => Err(invalid(&format!("{:?} is not allowed in an anchor", ch))),
+        None => Ok(()),
+    }
+}
+
+// The ns-anchor-char production of the YAML spec: any printable character
+// other than whitespace, the byte order mark, and the flow indicators.
+fn is_anchor_char(ch: char) -> bool {
+    match ch {
+        ',' | '[' | ']' | '{' | '}' | '\u{FEFF}' => false,
+        '!'..='~' | '\u{85}' | '\u{A0}'..='\u{D7FF}' | '\u{E000}'..='\u{FFFD}' => true,
+        _ => ch >= '\u{10000}',
+    }
+}
+
+fn check_tag(tag: &Tag) -> Result<(), EmitterError> {
+    check_nul("tag", tag.as_bytes())
+}
+
+fn check_nul(what: &str, bytes: &[u8]) -> Result<(), EmitterError> {
+    if bytes.contains(&b'\0') {
+        Err(EmitterError::InvalidInput(format!(
+            "invalid {} {:?}: '\\0' is not allowed in a {}",
+            what,
+            String::from_utf8_lossy(bytes),
+            what,
+        )))
+    } else {
+        Ok(())
+    }
+}
+
+fn scalar_length(len: usize) -> Result<i32, EmitterError> {
+    i32::try_from(len).map_err(|_| EmitterError::ScalarTooLong(len))
+}
+
+// Copies `bytes` into `buffer` followed by a NUL, for passing to libyaml.
+fn c_str(buffer: &mut Vec<u8>, bytes: Option<&[u8]>) -> *const u8 {
+    match bytes {
+        Some(bytes) => {
+            buffer.clear();
+            buffer.extend_from_slice(bytes);
+            buffer.push(b'\0');
+            buffer.as_ptr()
+        }
+        None => std::ptr::null(),
     }
 }
 
@@ -222,16 +471,65 @@ unsafe fn handler<W: std::io::Write>(
     buffer: *mut u8,
     size: u64,
 ) -> i32 {
-    let pinned = unsafe { &mut (*data.cast::<EmitterPinned<W>>()) };
-    let buf = unsafe { std::slice::from_raw_parts(buffer, size as _) };
-    if let Some(x) = &mut pinned.writer {
-        if let Err(err) = x.write_all(buf) {
-            pinned.error = Some(err);
-            0
+    // Only the fields other than the handle are touched here, since libyaml
+    // is using the handle while it calls this.
+    let pinned = unsafe { Pin::new_unchecked(&mut *data.cast::<EmitterPinned<W>>()) }.project();
+    // libyaml hands over at most its own output buffer at a time, which is
+    // far smaller than usize::MAX on any target.
+    let size = usize::try_from(size);
+    debug_assert!(size.is_ok());
+    let buf = unsafe { std::slice::from_raw_parts(buffer, size.unwrap_or(0)) };
+    if let Some(copy) = pinned.copy {
+        copy.extend_from_slice(buf);
+    }
+    let x = match pinned.writer {
+        Some(x) => x,
+        None => return 1,
+    };
+    let prefixed;
+    let buf = if *pinned.prefix == 0 {
+        buf
+    } else {
+        prefixed = prefix_lines(buf, *pinned.prefix, pinned.line_start);
+        &prefixed
+    };
+    *pinned.written += buf.len() as u64;
+    let capacity = pinned.buffer.capacity();
+    if pinned.buffer.len() + buf.len() <= capacity {
+        pinned.buffer.extend_from_slice(buf);
+        return 1;
+    }
+    // Short writes are retried by write_all, and only their error is kept for
+    // libyaml, so what the writer accepts per call never reaches the emitter.
+    let mut result = Ok(());
+    if !pinned.buffer.is_empty() {
+        result = x.write_all(pinned.buffer);
+        pinned.buffer.clear();
+    }
+    result = result.and_then(|()| {
+        if buf.len() < capacity {
+            pinned.buffer.extend_from_slice(buf);
+            Ok(())
         } else {
-            1
+            x.write_all(buf)
         }
+    });
+    if let Err(err) = result {
+        *pinned.error = Some(err);
+        0
     } else {
         1
     }
 }
+
+fn prefix_lines(buf: &[u8], prefix: usize, line_start: &mut bool) -> Vec<u8> {
+    let mut prefixed = Vec::with_capacity(buf.len() + prefix);
+    for &byte in buf {
+        if *line_start && byte != b'\n' {
+            prefixed.resize(prefixed.len() + prefix, b' ');
+        }
+        *line_start = byte == b'\n';
+        prefixed.push(byte);
+    }
+    prefixed
+}
@@ -1,37 +1,43 @@
-use std::ffi::CStr;
+use std::ffi::{c_char, CStr};
 use unsafe_libyaml as sys;
 
 pub struct Error {
     kind: sys::yaml_error_type_t,
-    problem: Option<&'static CStr>,
+    problem: Option<Box<str>>,
     problem_offset: u64,
-    problem_mark: Mark,
-    context: Option<&'static CStr>,
-    context_mark: Mark,
+    problem_mark: Option<Mark>,
+    context: Option<Box<str>>,
+    context_mark: Option<Mark>,
+    end_of_input: bool,
+    // What the input ended in the middle of, which began at the context mark.
+    unclosed: Option<Unclosed>,
+}
+
+#[derive(Copy, Clone)]
+enum Unclosed {
+    SingleQuoted,
+    DoubleQuoted,
+    FlowSequence,
+    FlowMapping,
 }
 
 impl Error {
     pub unsafe fn get_parser_error(parser: *const sys::yaml_parser_t) -> Self {
         let parser = unsafe { &(*parser) };
+        let context = unsafe { owned(parser.context) };
         Error {
             kind: parser.error,
-            problem: if !parser.problem.is_null() {
-                Some(unsafe { CStr::from_ptr(parser.problem) })
-            } else {
-                None
-            },
+            problem: unsafe { owned(parser.problem) },
             problem_offset: parser.problem_offset,
-            problem_mark: Mark {
+            problem_mark: Some(Mark {
                 sys: parser.problem_mark,
-            },
-            context: if !parser.context.is_null() {
-                Some(unsafe { CStr::from_ptr(parser.context) })
-            } else {
-                None
-            },
-            context_mark: Mark {
+            }),
+            context_mark: context.as_ref().map(|_| Mark {
                 sys: parser.context_mark,
-            },
+            }),
+            context,
+            end_of_input: false,
+            unclosed: None,
         }
     }
 
@@ -39,42 +45,260 @@ impl Error {
         let emitter = unsafe { &(*emitter) };
         Error {
             kind: emitter.error,
-            problem: if !emitter.problem.is_null() {
-                Some(unsafe { CStr::from_ptr(emitter.problem) })
-            } else {
-                None
-            },
+            problem: unsafe { owned(emitter.problem) },
             problem_offset: 0,
-            problem_mark: Default::default(),
+            problem_mark: None,
             context: None,
-            context_mark: Default::default(),
+            context_mark: None,
+            end_of_input: false,
+            unclosed: None,
         }
     }
 
-    pub fn mark(&self) -> Mark {
+    /// An event or token from libyaml of a type that this crate does not
+    /// handle, which would indicate a bug rather than a problem with the
+    /// input.
+    pub fn unexpected(what: &str, type_: u32, mark: Mark) -> Self {
+        Error {
+            kind: sys::YAML_NO_ERROR,
+            problem: Some(Box::from(format!(
+                "unexpected libyaml {} type {}",
+                what, type_
+            ))),
+            problem_offset: 0,
+            problem_mark: Some(mark),
+            context: None,
+            context_mark: None,
+            end_of_input: false,
+            unclosed: None,
+        }
+    }
+
+    /// Position of the problem in the input. Errors from the emitter have no
+    /// position.
+    pub fn mark(&self) -> Option<Mark> {
         self.problem_mark
     }
+
+    /// Shifts the marks of an error from a parser that began `index` bytes
+    /// and `line` lines into its input.
+    pub fn offset(&mut self, index: u64, line: u64) {
+        self.problem_offset += index;
+        for mark in [&mut self.problem_mark, &mut self.context_mark]
+            .into_iter()
+            .flatten()
+        {
+            mark.offset(index, line);
+        }
+    }
+
+    /// Replaces libyaml's message for a `\u` or `\U` escape that does not
+    /// name a character with one that shows the escape and why, and moves the
+    /// mark from its hex digits back to the backslash. `input` is what the
+    /// parser was given, which the mark indexes into.
+    ///
+    /// YAML escapes name code points, so a surrogate is rejected even when it
+    /// is half of a pair as JSON would write it; the message then suggests
+    /// the `\U` escape for the character the pair encodes.
+    pub fn describe_escape(&mut self, input: &[u8]) {
+        if self.problem.as_deref() != Some("found invalid Unicode character escape code") {
+            return;
+        }
+        let Some(mark) = &mut self.problem_mark else {
+            return;
+        };
+        let digits = mark.index();
+        let width = match input.get(digits.wrapping_sub(2)..digits) {
+            Some(b"\\u") => 4,
+            Some(b"\\U") => 8,
+            _ => return,
+        };
+        let Some(code) = hex(input, digits, width) else {
+            return;
+        };
+        let escape = String::from_utf8_lossy(&input[digits - 2..digits + width]);
+        let reason = match code {
+            0xD800..=0xDBFF => {
+                let rest = &input[digits + width..];
+                let low = match rest {
+                    [b'\\', b'u', ..] => {
+                        hex(rest, 2, 4).filter(|low| (0xDC00..=0xDFFF).contains(low))
+                    }
+                    _ => None,
+                };
+                match low {
+                    Some(low) => {
+                        let pair = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                        format!(
+                            "half of a UTF-16 surrogate pair; write the character as \\U{:08X}",
+                            pair,
+                        )
+                    }
+                    None => "a UTF-16 surrogate, not a character".to_owned(),
+                }
+            }
+            0xDC00..=0xDFFF => "a UTF-16 surrogate, not a character".to_owned(),
+            _ => "beyond the last code point, U+10FFFF".to_owned(),
+        };
+        self.problem = Some(Box::from(format!(
+            "invalid escape \"{}\" (U+{:04X} is {})",
+            escape, code, reason,
+        )));
+        // The backslash and letter are ASCII, so they are on the same line as
+        // the digits and one column each.
+        mark.sys.index -= 2;
+        mark.sys.column -= 2;
+    }
+
+    /// Describes an error at the end of the input inside of a quoted scalar
+    /// or flow collection as that construct never being closed, and where it
+    /// began, rather than as what the parser did not find at the end. `input`
+    /// is what the parser was given, which the context mark indexes into.
+    /// Must come after `set_input_len`.
+    pub fn describe_unclosed(&mut self, input: &[u8]) {
+        let Some(mark) = self.context_mark else {
+            return;
+        };
+        if !self.end_of_input {
+            return;
+        }
+        self.unclosed = match self.context.as_deref() {
+            Some("while scanning a quoted scalar") => match input.get(mark.index()) {
+                Some(b'\'') => Some(Unclosed::SingleQuoted),
+                Some(b'"') => Some(Unclosed::DoubleQuoted),
+                _ => None,
+            },
+            Some("while parsing a flow sequence") => Some(Unclosed::FlowSequence),
+            Some("while parsing a flow mapping") => Some(Unclosed::FlowMapping),
+            _ => None,
+        };
+    }
+
+    /// Like `describe_unclosed`, for a parser that knows the innermost flow
+    /// collection it was in and where it began. After a `,` or `:` libyaml is
+    /// in the middle of a flow node instead, and has lost track of the
+    /// collection. A quoted scalar inside of the collection is what was left
+    /// open, if there is one.
+    pub fn describe_unclosed_collection(&mut self, mapping: bool, start: Mark) {
+        if !self.end_of_input
+            || matches!(
+                self.unclosed,
+                Some(Unclosed::SingleQuoted | Unclosed::DoubleQuoted),
+            )
+        {
+            return;
+        }
+        let (unclosed, context) = if mapping {
+            (Unclosed::FlowMapping, "while parsing a flow mapping")
+        } else {
+            (Unclosed::FlowSequence, "while parsing a flow sequence")
+        };
+        self.unclosed = Some(unclosed);
+        self.context = Some(Box::from(context));
+        self.context_mark = Some(start);
+    }
+
+    pub fn context(&self) -> Option<&str> {
+        self.context.as_deref()
+    }
+
+    pub fn context_mark(&self) -> Option<Mark> {
+        self.context_mark
+    }
+
+    /// Records whether the parser ran out of input, given the length of the
+    /// input it was parsing. Such errors may be fixed by appending to the
+    /// input, rather than by changing what is already there.
+    pub fn set_input_len(&mut self, len: usize) {
+        self.end_of_input = self.problem_mark.is_some_and(|mark| mark.index() >= len);
+    }
+
+    pub fn is_end_of_input(&self) -> bool {
+        self.end_of_input
+    }
+
+    pub fn is_syntax(&self) -> bool {
+        matches!(
+            self.kind,
+            sys::YAML_READER_ERROR
+                | sys::YAML_SCANNER_ERROR
+                | sys::YAML_PARSER_ERROR
+                | sys::YAML_COMPOSER_ERROR
+        )
+    }
+
+    /// Failing to write, running out of memory, or the emitter refusing an
+    /// event, none of which says anything about the data.
+    pub fn is_io(&self) -> bool {
+        matches!(
+            self.kind,
+            sys::YAML_WRITER_ERROR | sys::YAML_MEMORY_ERROR | sys::YAML_EMITTER_ERROR
+        )
+    }
+}
+
+// Copies a message out of the parser or emitter, so that the error does not
+// borrow from a handle that is about to be dropped.
+unsafe fn owned(message: *const c_char) -> Option<Box<str>> {
+    if message.is_null() {
+        None
+    } else {
+        let message = unsafe { CStr::from_ptr(message) };
+        Some(Box::from(message.to_string_lossy()))
+    }
+}
+
+// The number written as `width` hex digits at `start`.
+fn hex(input: &[u8], start: usize, width: usize) -> Option<u32> {
+    let digits = input.get(start..start + width)?;
+    u32::from_str_radix(std::str::from_utf8(digits).ok()?, 16).ok()
 }
 
 impl std::fmt::Display for Error {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if let Some(problem) = self.problem {
-            write!(formatter, "{}", problem.to_string_lossy())?;
+        if let (Some(unclosed), Some(mark)) = (self.unclosed, self.context_mark) {
+            let unclosed = match unclosed {
+                Unclosed::SingleQuoted => "single-quoted scalar",
+                Unclosed::DoubleQuoted => "double-quoted scalar",
+                Unclosed::FlowSequence => "flow sequence",
+                Unclosed::FlowMapping => "flow mapping",
+            };
+            return write!(
+                formatter,
+                "{} started at line {} column {} was never closed",
+                unclosed,
+                mark.line() + 1,
+                mark.column() + 1,
+            );
+        }
+        if let Some(problem) = &self.problem {
+            formatter.write_str(problem)?;
         } else {
             write!(formatter, "libyaml parser failed but there is no error")?;
         }
-        if self.problem_mark.sys.line != 0 || self.problem_mark.sys.column != 0 {
-            write!(formatter, " at {}", self.problem_mark)?;
-        } else if self.problem_offset != 0 {
-            write!(formatter, " at position {}", self.problem_offset)?;
+        match self.problem_mark {
+            Some(mark) if mark.has_line_column() => write!(formatter, " at {}", mark)?,
+            _ if self.problem_offset != 0 => {
+                write!(formatter, " at position {}", self.problem_offset)?;
+            }
+            _ => {}
         }
         if let Some(context) = &self.context {
-            write!(formatter, ", {}", context.to_string_lossy())?;
-            if (self.context_mark.sys.line != 0 || self.context_mark.sys.column != 0)
-                && (self.context_mark.sys.line != self.problem_mark.sys.line
-                    || self.context_mark.sys.column != self.problem_mark.sys.column)
-            {
-                write!(formatter, " at {}", self.context_mark)?;
+            write!(formatter, ", {}", context)?;
+            if let Some(mark) = self.context_mark {
+                let same = self.problem_mark.is_some_and(|problem| {
+                    (problem.line(), problem.column()) == (mark.line(), mark.column())
+                });
+                // Unlike a problem, a context always has a line and column,
+                // even at the very start of the input.
+                if !same {
+                    write!(
+                        formatter,
+                        " at line {} column {}",
+                        mark.line() + 1,
+                        mark.column() + 1,
+                    )?;
+                }
             }
         }
         Ok(())
@@ -97,51 +321,63 @@ impl std::fmt::Debug for Error {
             formatter.field("kind", &format_args!("{}", kind));
         }
         formatter.field("problem", &self.problem);
-        if self.problem_mark.sys.line != 0 || self.problem_mark.sys.column != 0 {
-            formatter.field("problem_mark", &self.problem_mark);
-        } else if self.problem_offset != 0 {
-            formatter.field("problem_offset", &self.problem_offset);
+        match self.problem_mark {
+            Some(mark) if mark.has_line_column() => {
+                formatter.field("problem_mark", &mark);
+            }
+            _ if self.problem_offset != 0 => {
+                formatter.field("problem_offset", &self.problem_offset);
+            }
+            _ => {}
         }
         if let Some(context) = &self.context {
             formatter.field("context", context);
-            if self.context_mark.sys.line != 0 || self.context_mark.sys.column != 0 {
-                formatter.field("context_mark", &self.context_mark);
+            if let Some(mark) = self.context_mark.filter(Mark::has_line_column) {
+                formatter.field("context_mark", &mark);
             }
         }
         formatter.finish()
     }
 }
 
+impl std::error::Error for Error {}
+
 #[derive(Copy, Clone)]
 pub struct Mark {
     pub(super) sys: sys::yaml_mark_t,
 }
 
 impl Mark {
-    pub fn index(&self) -> u64 {
-        self.sys.index
+    /// Byte offset into the input.
+    pub fn index(&self) -> usize {
+        self.sys.index as usize
     }
 
-    pub fn line(&self) -> u64 {
-        self.sys.line
+    /// Zero-based line number.
+    pub fn line(&self) -> usize {
+        self.sys.line as usize
     }
 
-    pub fn column(&self) -> u64 {
-        self.sys.column
+    /// Zero-based column, counted in characters.
+    pub fn column(&self) -> usize {
+        self.sys.column as usize
     }
-}
 
-impl Default for Mark {
-    fn default() -> Self {
-        Self {
-            sys: unsafe { std::mem::zeroed() },
-        }
+    // Marks at line 0 column 0 are displayed by their index instead. Errors
+    // from libyaml's reader have only an index.
+    fn has_line_column(&self) -> bool {
+        self.sys.line != 0 || self.sys.column != 0
+    }
+
+    pub(super) fn offset(&mut self, index: u64, line: u64) {
+        self.sys.index += index;
+        self.sys.line += line;
     }
 }
 
 impl std::fmt::Display for Mark {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
-        if self.sys.line != 0 || self.sys.column != 0 {
+        if self.has_line_column() {
             write!(
                 formatter,
                 "line {} column {}",
@@ -157,7 +393,7 @@ impl std::fmt::Display for Mark {
 impl std::fmt::Debug for Mark {
     fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
         let mut formatter = formatter.debug_struct("Mark");
-        if self.sys.line != 0 || self.sys.column != 0 {
+        if self.has_line_column() {
             formatter.field("line", &(self.sys.line + 1));
             formatter.field("column", &(self.sys.column + 1));
         } else {
@@ -0,0 +1,317 @@
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Write as _};
+use std::hash::{Hash, Hasher};
+use std::sync::Arc;
+
+/// One step of a YAML event stream.
+///
+/// A [`Parser`](crate::raw::Parser) produces these from YAML text and an
+/// [`Emitter`](crate::raw::Emitter) turns them back into YAML text, so a
+/// stream of events read from one can be written to the other unchanged.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Event<'a> {
+    /// The first event of every stream.
+    StreamStart,
+    /// The last event of every stream.
+    StreamEnd,
+    /// Begins a document, which holds exactly one node.
+    DocumentStart(DocumentStart),
+    /// Ends a document.
+    DocumentEnd(DocumentEnd),
+    /// Refers back to an earlier node by the name of its anchor.
+    Alias(Anchor),
+    /// A scalar node.
+    Scalar(Scalar<'a>),
+    /// Begins a sequence node, whose elements are the nodes up to the
+    /// matching `SequenceEnd`.
+    SequenceStart(SequenceStart),
+    /// Ends a sequence node.
+    SequenceEnd,
+    /// Begins a mapping node, whose keys and values alternate up to the
+    /// matching `MappingEnd`.
+    MappingStart(MappingStart),
+    /// Ends a mapping node.
+    MappingEnd,
+}
+
+/// The start of a document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocumentStart {
+    /// Whether the document has no `---` marker. The emitter may write one
+    /// anyway where it is needed, such as before any document but the first
+    /// or one with directives.
+    pub implicit: bool,
+    /// The major and minor version from a `%YAML` directive.
+    pub version: Option<(i32, i32)>,
+    /// The handle and prefix of each `%TAG` directive, in order.
+    pub tag_directives: Vec<(String, String)>,
+}
+
+/// The end of a document.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct DocumentEnd {
+    /// Whether the document has no `...` marker.
+    pub implicit: bool,
+}
+
+/// A scalar node.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Scalar<'a> {
+    /// Anchor by which later aliases can refer to this node.
+    pub anchor: Option<Anchor>,
+    /// Explicit tag of the node. Scalars without one are resolved by their
+    /// value and style.
+    pub tag: Option<Tag>,
+    /// The value after any escapes and folding have been processed. When
+    /// parsing borrowed input, this is borrowed from the input if the scalar
+    /// appears there verbatim, which is the case for most plain and quoted
+    /// scalars.
+    pub value: Cow<'a, [u8]>,
+    /// How the scalar is written.
+    pub style: ScalarStyle,
+}
+
+/// The start of a sequence node.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SequenceStart {
+    /// Anchor by which later aliases can refer to this node.
+    pub anchor: Option<Anchor>,
+    /// Explicit tag of the node.
+    pub tag: Option<Tag>,
+    /// Whether the collection is written in block or flow style.
+    pub style: CollectionStyle,
+}
+
+/// The start of a mapping node.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct MappingStart {
+    /// Anchor by which later aliases can refer to this node.
+    pub anchor: Option<Anchor>,
+    /// Explicit tag of the node.
+    pub tag: Option<Tag>,
+    /// Whether the collection is written in block or flow style.
+    pub style: CollectionStyle,
+}
+
+/// How a scalar is written.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum ScalarStyle {
+    /// Lets the emitter pick a style that can represent the value. Parsed
+    /// scalars always have one of the other styles.
+    #[default]
+    Any,
+    /// Unquoted, like `value`.
+    Plain,
+    /// In single quotes, like `'value'`.
+    SingleQuoted,
+    /// In double quotes with escapes, like `"value\n"`.
+    DoubleQuoted,
+    /// A block scalar keeping line breaks, introduced by `|`.
+    Literal,
+    /// A block scalar folding line breaks into spaces, introduced by `>`.
+    Folded,
+}
+
+/// How a sequence or mapping is written.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash)]
+pub enum CollectionStyle {
+    /// Lets the emitter pick a style. Parsed collections always have one of
+    /// the other styles.
+    #[default]
+    Any,
+    /// One entry per line, nested by indentation.
+    Block,
+    /// Enclosed in `[]` or `{}`, with entries separated by commas.
+    Flow,
+}
+
+/// The name of an anchor, without the leading `&` or `*`.
+///
+/// Anchor names are interned per parser, so that aliases to the same anchor
+/// share one allocation.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Anchor(pub(super) Arc<[u8]>);
+
+impl Anchor {
+    /// An anchor with the given name, which the emitter checks is made up of
+    /// characters allowed in anchor names.
+    pub fn new(name: impl AsRef<[u8]>) -> Self {
+        Anchor(Arc::from(name.as_ref()))
+    }
+
+    /// The name of the anchor.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl Debug for Anchor {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(formatter, "Anchor({:?})", String::from_utf8_lossy(&self.0))
+    }
+}
+
+/// The tag of a node, with any tag handle expanded.
+///
+/// Tags written as `!!str` in the input are parsed as
+/// `tag:yaml.org,2002:str`, and are written back in the short form.
+#[derive(Clone)]
+pub struct Tag(Repr);
+
+#[derive(Clone)]
+enum Repr {
+    // One of the tags in `Tag::CORE`, which are common enough that they are
+    // not worth allocating.
+    Core(&'static [u8]),
+    Custom(Box<[u8]>),
+}
+
+impl Tag {
+    pub(crate) const NULL: &'static [u8] = b"tag:yaml.org,2002:null";
+    pub(crate) const BOOL: &'static [u8] = b"tag:yaml.org,2002:bool";
+    pub(crate) const INT: &'static [u8] = b"tag:yaml.org,2002:int";
+    pub(crate) const FLOAT: &'static [u8] = b"tag:yaml.org,2002:float";
+    pub(crate) const STR: &'static [u8] = b"tag:yaml.org,2002:str";
+
+    const CORE: [&'static [u8]; 8] = [
+        Tag::NULL,
+        Tag::BOOL,
+        Tag::INT,
+        Tag::FLOAT,
+        Tag::STR,
+        b"tag:yaml.org,2002:seq",
+        b"tag:yaml.org,2002:map",
+        b"tag:yaml.org,2002:binary",
+    ];
+
+    /// A tag with the given full name, such as `tag:yaml.org,2002:str` or
+    /// `!Point`.
+    pub fn new(tag: impl AsRef<[u8]>) -> Self {
+        let bytes = tag.as_ref();
+        match Tag::core(bytes) {
+            Some(tag) => tag,
+            None => Tag(Repr::Custom(Box::from(bytes))),
+        }
+    }
+
+    fn core(bytes: &[u8]) -> Option<Self> {
+        let core = Tag::CORE.iter().find(|core| **core == bytes)?;
+        Some(Tag(Repr::Core(core)))
+    }
+
+    /// The full name of the tag.
+    pub fn as_bytes(&self) -> &[u8] {
+        match &self.0 {
+            Repr::Core(bytes) => bytes,
+            Repr::Custom(bytes) => bytes,
+        }
+    }
+
+    /// The tag as it would be written with one of the handles of a
+    /// document's `%TAG` directives, which is how the input most likely
+    /// wrote it.
+    ///
+    /// The handles `!` and `!!` stand for `!` and `tag:yaml.org,2002:` unless
+    /// a directive says otherwise. Of the handles whose prefix the tag starts
+    /// with, the one with the longest prefix is used, and characters that
+    /// cannot be part of a shorthand are escaped as `%XX`. Returns `None` if
+    /// no handle fits, in which case the tag can only be written verbatim, as
+    /// in `!<tag:example.com,2024:widget>`.
+    ///
+    /// ```
+    /// use serde_yaml::raw::Tag;
+    ///
+    /// let directives = [("!e!".to_owned(), "tag:example.com,2024:".to_owned())];
+    /// let tag = Tag::new("tag:example.com,2024:widget");
+    /// assert_eq!(tag.shorthand(&directives).as_deref(), Some("!e!widget"));
+    /// assert_eq!(tag.shorthand(&[]), None);
+    ///
+    /// let tag = Tag::new("tag:yaml.org,2002:str");
+    /// assert_eq!(tag.shorthand(&directives).as_deref(), Some("!!str"));
+    /// ```
+    pub fn shorthand(&self, tag_directives: &[(String, String)]) -> Option<String> {
+        let tag = self.as_bytes();
+        let defaults = [("!", "!"), ("!!", "tag:yaml.org,2002:")]
+            .into_iter()
+            .filter(|(handle, _prefix)| tag_directives.iter().all(|(h, _)| h != handle));
+        let handles = tag_directives
+            .iter()
+            .map(|(handle, prefix)| (handle.as_str(), prefix.as_str()))
+            .chain(defaults);
+        let mut best: Option<(&str, &str)> = None;
+        for (handle, prefix) in handles {
+            let fits = tag.len() > prefix.len() && tag.starts_with(prefix.as_bytes());
+            if fits && best.is_none_or(|(_, best)| prefix.len() > best.len()) {
+                best = Some((handle, prefix));
+            }
+        }
+        let (handle, prefix) = best?;
+        let mut shorthand = handle.to_owned();
+        for &byte in &tag[prefix.len()..] {
+            if byte.is_ascii_alphanumeric() || b"-#;/?:@&=+$_.~*'()".contains(&byte) {
+                shorthand.push(char::from(byte));
+            } else {
+                let _ = write!(shorthand, "%{:02X}", byte);
+            }
+        }
+        Some(shorthand)
+    }
+}
+
+impl From<String> for Tag {
+    fn from(tag: String) -> Self {
+        match Tag::core(tag.as_bytes()) {
+            Some(tag) => tag,
+            None => Tag(Repr::Custom(tag.into_bytes().into_boxed_slice())),
+        }
+    }
+}
+
+impl AsRef<[u8]> for Tag {
+    fn as_ref(&self) -> &[u8] {
+        self.as_bytes()
+    }
+}
+
+impl PartialEq for Tag {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_bytes() == other.as_bytes()
+    }
+}
+
+impl Eq for Tag {}
+
+impl PartialEq<[u8]> for Tag {
+    fn eq(&self, other: &[u8]) -> bool {
+        self.as_bytes() == other
+    }
+}
+
+impl PartialOrd for Tag {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Tag {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.as_bytes().cmp(other.as_bytes())
+    }
+}
+
+impl Hash for Tag {
+    fn hash<H: Hasher>(&self, hasher: &mut H) {
+        self.as_bytes().hash(hasher);
+    }
+}
+
+impl Debug for Tag {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            formatter,
+            "Tag({:?})",
+            String::from_utf8_lossy(self.as_bytes()),
+        )
+    }
+}
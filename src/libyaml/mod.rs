@@ -1,3 +1,5 @@
 pub mod emitter;
 pub mod error;
+pub mod event;
 pub mod parser;
+pub mod token;
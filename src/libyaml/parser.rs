@@ -1,151 +1,369 @@
 use crate::libyaml::error::{Error, Mark};
+use crate::libyaml::event::{
+    Anchor, CollectionStyle, DocumentEnd, DocumentStart, Event, MappingStart, Scalar, ScalarStyle,
+    SequenceStart, Tag,
+};
+use crate::libyaml::token::Token;
 use std::borrow::Cow;
+use std::collections::BTreeSet;
+use std::ops::{Deref, Range};
+use std::pin::Pin;
+use std::sync::Arc;
 use unsafe_libyaml as sys;
 
 #[repr(transparent)]
 struct PinnedHandle(sys::yaml_parser_t, std::marker::PhantomPinned);
 
 impl PinnedHandle {
-    fn init(&mut self, input: *const [u8]) {
+    fn init(&mut self, input: *const [u8]) -> Result<(), Error> {
         unsafe {
             let this = &raw mut self.0;
             if sys::yaml_parser_initialize(this).fail {
-                panic!("malloc error: {}", Error::get_parser_error(&self.0));
+                return Err(Error::get_parser_error(this));
             }
             sys::yaml_parser_set_encoding(this, sys::YAML_UTF8_ENCODING);
             sys::yaml_parser_set_input_string(this, input as _, input.len() as u64);
         }
+        Ok(())
     }
 }
 
+// The raw pointers in libyaml's state point to its own allocations and into
+// the input held next to the handle, which is either owned or a shared slice.
+// None of it is tied to a thread; libyaml keeps no thread-local state.
+unsafe impl Send for PinnedHandle {}
+
 impl Drop for PinnedHandle {
     fn drop(&mut self) {
         unsafe { sys::yaml_parser_delete(&mut self.0) }
     }
 }
 
-#[derive(Debug)]
-pub enum Event<'input> {
-    StreamStart,
-    StreamEnd,
-    DocumentStart,
-    DocumentEnd,
-    Alias(Anchor),
-    Scalar(Scalar<'input>),
-    SequenceStart(SequenceStart),
-    SequenceEnd,
-    MappingStart(MappingStart),
-    MappingEnd,
+// What a parser reads from: borrowed or owned input, or owned input that is
+// shared with the documents loaded from it so that they can leave long scalars
+// in it rather than copy them out.
+pub(crate) enum Input<'input> {
+    Cow(Cow<'input, [u8]>),
+    Shared(Arc<Vec<u8>>),
 }
 
-#[derive(Debug)]
-pub struct Scalar<'input> {
-    pub anchor: Option<Anchor>,
-    pub tag: Option<Tag>,
-    pub value: ScalarValue,
-    pub style: ScalarStyle,
-    pub repr: Option<&'input [u8]>,
+impl<'input> Default for Input<'input> {
+    fn default() -> Self {
+        Input::Cow(Cow::Borrowed(&[]))
+    }
 }
 
-#[derive(Debug)]
-pub struct SequenceStart {
-    pub anchor: Option<Anchor>,
-    pub tag: Option<Tag>,
+impl<'input> Deref for Input<'input> {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        match self {
+            Input::Cow(input) => input,
+            Input::Shared(input) => input,
+        }
+    }
 }
 
-#[derive(Debug)]
-pub struct MappingStart {
-    pub anchor: Option<Anchor>,
-    pub tag: Option<Tag>,
+// Holds the libyaml parser together with the input it reads from. It is always
+// kept in a `Pin<Box<..>>` and its fields are only reached through `project`,
+// so neither is ever moved out while libyaml may hold pointers to them.
+struct ParserPinned<'input> {
+    handle: PinnedHandle,
+    input: Input<'input>,
 }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Anchor(Box<[u8]>);
+impl<'input> ParserPinned<'input> {
+    fn new(input: Input<'input>, start: usize) -> Result<Pin<Box<Self>>, Error> {
+        let mut pinned = Box::pin(ParserPinned {
+            handle: unsafe { std::mem::zeroed() },
+            input,
+        });
+        let this = unsafe { pinned.as_mut().get_unchecked_mut() };
+        this.handle.init(&this.input[start..])?;
+        Ok(pinned)
+    }
 
-#[derive(Debug, Copy, Clone, PartialEq, Eq)]
-pub enum ScalarStyle {
-    Plain,
-    SingleQuoted,
-    DoubleQuoted,
-    Literal,
-    Folded,
-}
+    fn project(self: Pin<&mut Self>) -> (*mut sys::yaml_parser_t, &Input<'input>) {
+        let this = unsafe { self.get_unchecked_mut() };
+        (&raw mut this.handle.0, &this.input)
+    }
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct Tag(Box<[u8]>);
+    fn into_input(mut self: Pin<Box<Self>>) -> Input<'input> {
+        // libyaml points into the allocation of owned input or into the
+        // borrowed slice, neither of which moves along with the `Input`. The
+        // handle is deleted right after without reading the input again.
+        let this = unsafe { self.as_mut().get_unchecked_mut() };
+        std::mem::take(&mut this.input)
+    }
+}
 
-impl Tag {
-    pub const NULL: &'static [u8] = b"tag:yaml.org,2002:null";
-    pub const BOOL: &'static [u8] = b"tag:yaml.org,2002:bool";
-    pub const INT: &'static [u8] = b"tag:yaml.org,2002:int";
-    pub const FLOAT: &'static [u8] = b"tag:yaml.org,2002:float";
+pub struct Parser<'input> {
+    pinned: Pin<Box<ParserPinned<'input>>>,
+    // Where in the input parsing began, for parsers that resume partway
+    // through it. Marks reported by libyaml are relative to this point.
+    start: usize,
+    line: u64,
+    // Where the input itself begins in a longer stream that it was cut from,
+    // in bytes and lines, which marks are also shifted by.
+    origin: (u64, u64),
+    // The collections that have begun and not yet ended, innermost last:
+    // whether each is in flow style and a mapping, and where it began
+    // relative to the start.
+    open: Vec<(bool, bool, Mark)>,
+    anchors: BTreeSet<Arc<[u8]>>,
 }
 
-impl AsRef<[u8]> for Tag {
-    fn as_ref(&self) -> &[u8] {
-        self.0.as_ref()
+impl<'input> Parser<'input> {
+    pub fn new(input: Cow<'input, [u8]>) -> Result<Parser<'input>, Error> {
+        Parser::new_at(Input::Cow(input), (0, 0))
     }
-}
 
-#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
-pub struct ScalarValue(Box<[u8]>);
+    /// Parses owned input that is shared with whatever holds on to the
+    /// ranges that `next_span_locating` gives for scalars.
+    pub fn shared(input: Arc<Vec<u8>>) -> Result<Parser<'input>, Error> {
+        Parser::new_at(Input::Shared(input), (0, 0))
+    }
 
-impl AsRef<[u8]> for ScalarValue {
-    fn as_ref(&self) -> &[u8] {
-        self.0.as_ref()
+    /// Parses `input` beginning at byte offset `start`, which must be at the
+    /// beginning of line `line`. Marks are reported relative to the whole
+    /// input.
+    pub fn new_at(
+        input: Input<'input>,
+        (start, line): (usize, u64),
+    ) -> Result<Parser<'input>, Error> {
+        let pinned = ParserPinned::new(input, start)?;
+        Ok(Parser {
+            pinned,
+            start,
+            line,
+            origin: (0, 0),
+            open: Vec::new(),
+            anchors: BTreeSet::new(),
+        })
     }
-}
 
-struct ParserPinned<'input> {
-    handle: PinnedHandle,
-    input: Cow<'input, [u8]>,
-}
+    pub fn start(&self) -> usize {
+        self.start
+    }
 
-pub struct Parser<'input> {
-    pinned: Box<ParserPinned<'input>>,
-}
+    /// The line that `start` is at the beginning of.
+    pub fn line(&self) -> u64 {
+        self.line
+    }
 
-impl<'input> Parser<'input> {
-    pub fn new(input: Cow<'input, [u8]>) -> Parser<'input> {
-        let mut pinned = Box::<ParserPinned<'input>>::new(ParserPinned {
-            handle: unsafe { std::mem::zeroed() },
-            input,
-        });
-        pinned.handle.init(pinned.input.as_ref());
-        Parser { pinned }
+    pub fn origin(&self) -> (u64, u64) {
+        self.origin
+    }
+
+    /// Reports marks as if the input began `index` bytes and `line` lines
+    /// into a longer stream. The input must have been cut at the beginning
+    /// of a line.
+    pub fn set_origin(&mut self, (index, line): (u64, u64)) {
+        self.origin = (index, line);
+    }
+
+    pub fn into_input(self) -> Input<'input> {
+        self.pinned.into_input()
+    }
+
+    pub fn shared_input(&self) -> Option<&Arc<Vec<u8>>> {
+        match &self.pinned.input {
+            Input::Shared(input) => Some(input),
+            Input::Cow(_) => None,
+        }
     }
 
     pub fn next(&mut self) -> Result<(Event<'input>, Mark), super::error::Error> {
-        let parser = &raw mut self.pinned.handle.0;
-        let input = &self.pinned.input;
+        let (event, start, _end) = self.next_span()?;
+        Ok((event, start))
+    }
+
+    /// Like `next`, but also returns where the event ends.
+    pub fn next_span(&mut self) -> Result<(Event<'input>, Mark, Mark), super::error::Error> {
+        self.next_span_locating(usize::MAX, &mut None)
+    }
+
+    /// Like `next_span`, but for shared input, a scalar of at least `min_len`
+    /// bytes that appears in the input verbatim is not copied. Its value is
+    /// left empty, and where it is in the input is put in `located` instead.
+    pub fn next_span_locating(
+        &mut self,
+        min_len: usize,
+        located: &mut Option<Range<usize>>,
+    ) -> Result<(Event<'input>, Mark, Mark), super::error::Error> {
+        let (parser, input) = self.pinned.as_mut().project();
+        let (index, line) = (self.start as u64 + self.origin.0, self.line + self.origin.1);
         unsafe {
             let mut sys_event = std::mem::zeroed::<sys::yaml_event_t>();
-            if (*parser).error != sys::YAML_NO_ERROR {
-                return Err(Error::get_parser_error(parser));
+            if (&*parser).error != sys::YAML_NO_ERROR
+                || sys::yaml_parser_parse(parser, &mut sys_event).fail
+            {
+                let mut error = Error::get_parser_error(parser);
+                error.set_input_len(input.len() - self.start);
+                error.describe_escape(&input[self.start..]);
+                error.describe_unclosed(&input[self.start..]);
+                if let Some(&(true, mapping, start)) = self.open.last() {
+                    error.describe_unclosed_collection(mapping, start);
+                }
+                error.offset(index, line);
+                return Err(error);
             }
-            if sys::yaml_parser_parse(parser, &mut sys_event).fail {
-                return Err(Error::get_parser_error(parser));
+            let values = match input {
+                Input::Cow(Cow::Borrowed(input)) => Values::Borrow(&input[self.start..]),
+                Input::Cow(Cow::Owned(_)) => Values::Copy,
+                Input::Shared(input) => Values::Locate(&input[self.start..], min_len),
+            };
+            *located = None;
+            let event = convert_event(&sys_event, values, located, &mut self.anchors);
+            if let Some(range) = located {
+                *range = self.start + range.start..self.start + range.end;
             }
-            let event = convert_event(&sys_event, input);
-            let mark = Mark {
+            let mut mark = Mark {
                 sys: sys_event.start_mark,
             };
+            match &event {
+                Ok(Event::SequenceStart(sequence)) => {
+                    let flow = sequence.style == CollectionStyle::Flow;
+                    self.open.push((flow, false, mark));
+                }
+                Ok(Event::MappingStart(mapping)) => {
+                    let flow = mapping.style == CollectionStyle::Flow;
+                    self.open.push((flow, true, mark));
+                }
+                Ok(Event::SequenceEnd | Event::MappingEnd) => {
+                    self.open.pop();
+                }
+                _ => {}
+            }
+            mark.offset(index, line);
+            let mut end = Mark {
+                sys: sys_event.end_mark,
+            };
+            end.offset(index, line);
             sys::yaml_event_delete(&mut sys_event);
-            Ok((event, mark))
+            match event {
+                Ok(event) => Ok((event, mark, end)),
+                Err(mut error) => {
+                    error.offset(index, line);
+                    Err(error)
+                }
+            }
+        }
+    }
+}
+
+/// Reads the input as tokens, the level below events. Tokens and events come
+/// from the same libyaml handle, so a scanner cannot also produce events.
+pub struct Scanner<'input> {
+    pinned: Pin<Box<ParserPinned<'input>>>,
+}
+
+impl<'input> Scanner<'input> {
+    pub fn new(input: Cow<'input, [u8]>) -> Result<Scanner<'input>, Error> {
+        let pinned = ParserPinned::new(Input::Cow(input), 0)?;
+        Ok(Scanner { pinned })
+    }
+
+    pub fn next(&mut self) -> Result<(Token, Mark, Mark), Error> {
+        let (parser, input) = self.pinned.as_mut().project();
+        unsafe {
+            let mut sys_token = std::mem::zeroed::<sys::yaml_token_t>();
+            if (&*parser).error != sys::YAML_NO_ERROR
+                || sys::yaml_parser_scan(parser, &mut sys_token).fail
+            {
+                let mut error = Error::get_parser_error(parser);
+                error.set_input_len(input.len());
+                error.describe_escape(input);
+                error.describe_unclosed(input);
+                return Err(error);
+            }
+            // Everything the token points to is copied out before it is
+            // deleted.
+            let token = convert_token(&sys_token);
+            let start = Mark {
+                sys: sys_token.start_mark,
+            };
+            let end = Mark {
+                sys: sys_token.end_mark,
+            };
+            sys::yaml_token_delete(&mut sys_token);
+            Ok((token?, start, end))
+        }
+    }
+}
+
+// Number of line breaks in `input`, counted the way libyaml counts them.
+pub fn count_lines(input: &[u8]) -> u64 {
+    let mut lines = 0;
+    let mut i = 0;
+    while i < input.len() {
+        match input[i..] {
+            [b'\r', b'\n', ..] | [0xE2, 0x80, 0xA8 | 0xA9, ..] => {
+                lines += 1;
+                i += if input[i] == b'\r' { 2 } else { 3 };
+            }
+            [b'\r' | b'\n', ..] => {
+                lines += 1;
+                i += 1;
+            }
+            [0xC2, 0x85, ..] => {
+                lines += 1;
+                i += 2;
+            }
+            _ => i += 1,
         }
     }
+    lines
+}
+
+// The style of a scalar that libyaml parsed, which it always gives as one of
+// the five that can be written.
+fn scalar_style(
+    style: sys::yaml_scalar_style_t,
+    mark: sys::yaml_mark_t,
+) -> Result<ScalarStyle, Error> {
+    Ok(match style {
+        sys::YAML_PLAIN_SCALAR_STYLE => ScalarStyle::Plain,
+        sys::YAML_SINGLE_QUOTED_SCALAR_STYLE => ScalarStyle::SingleQuoted,
+        sys::YAML_DOUBLE_QUOTED_SCALAR_STYLE => ScalarStyle::DoubleQuoted,
+        sys::YAML_LITERAL_SCALAR_STYLE => ScalarStyle::Literal,
+        sys::YAML_FOLDED_SCALAR_STYLE => ScalarStyle::Folded,
+        sys::YAML_ANY_SCALAR_STYLE | _ => {
+            let mark = Mark { sys: mark };
+            return Err(Error::unexpected("scalar style", style as u32, mark));
+        }
+    })
+}
+
+// Where the values of scalar events come from: copied out of the event,
+// borrowed from the input where they appear in it verbatim, or, for those of
+// at least some length that appear in shared input verbatim, located in it
+// and not taken at all.
+#[derive(Copy, Clone)]
+enum Values<'a, 'input> {
+    Copy,
+    Borrow(&'input [u8]),
+    Locate(&'a [u8], usize),
 }
 
 unsafe fn convert_event<'input>(
     sys: &sys::yaml_event_t,
-    input: &Cow<'input, [u8]>,
-) -> Event<'input> {
-    unsafe fn parse_anchor(anchor: *const u8) -> Option<Anchor> {
+    values: Values<'_, 'input>,
+    located: &mut Option<Range<usize>>,
+    anchors: &mut BTreeSet<Arc<[u8]>>,
+) -> Result<Event<'input>, Error> {
+    unsafe fn parse_anchor(anchor: *const u8, anchors: &mut BTreeSet<Arc<[u8]>>) -> Option<Anchor> {
         if anchor.is_null() {
             return None;
         }
         let cstr = unsafe { std::ffi::CStr::from_ptr(anchor.cast()) };
-        Some(Anchor(Box::from(cstr.to_bytes())))
+        let bytes = cstr.to_bytes();
+        if let Some(interned) = anchors.get(bytes) {
+            return Some(Anchor(Arc::clone(interned)));
+        }
+        let interned = Arc::<[u8]>::from(bytes);
+        anchors.insert(Arc::clone(&interned));
+        Some(Anchor(interned))
     }
 
     unsafe fn parse_tag(tag: *const u8) -> Option<Tag> {
@@ -153,54 +371,194 @@ unsafe fn convert_event<'input>(
             return None;
         }
         let cstr = unsafe { std::ffi::CStr::from_ptr(tag.cast()) };
-        Some(Tag(Box::from(cstr.to_bytes())))
+        Some(Tag::new(cstr.to_bytes()))
     }
 
-    unsafe fn parse_value(value: *mut u8, length: u64) -> Option<ScalarValue> {
-        if value.is_null() {
-            return None;
+    unsafe fn parse_string(string: *const u8) -> String {
+        let cstr = unsafe { std::ffi::CStr::from_ptr(string.cast()) };
+        cstr.to_string_lossy().into_owned()
+    }
+
+    // Where the value of a scalar appears verbatim in the input, if it does.
+    // The event spans any anchor and tag, so the value is found by counting
+    // back from the end, past the closing quote if any.
+    fn verbatim(
+        sys: &sys::yaml_event_t,
+        style: ScalarStyle,
+        input: &[u8],
+        value: &[u8],
+    ) -> Option<Range<usize>> {
+        let quote = match style {
+            ScalarStyle::Plain => 0,
+            ScalarStyle::SingleQuoted | ScalarStyle::DoubleQuoted => 1,
+            ScalarStyle::Any | ScalarStyle::Literal | ScalarStyle::Folded => return None,
+        };
+        let start = sys.start_mark.index as usize;
+        let end = (sys.end_mark.index as usize).checked_sub(quote)?;
+        let range = end
+            .checked_sub(value.len())
+            .filter(|&begin| begin >= start)?..end;
+        (input.get(range.clone()) == Some(value)).then_some(range)
+    }
+
+    unsafe fn parse_value<'input>(
+        sys: &sys::yaml_event_t,
+        style: ScalarStyle,
+        values: Values<'_, 'input>,
+        located: &mut Option<Range<usize>>,
+    ) -> Cow<'input, [u8]> {
+        let value = unsafe { sys.data.scalar.value };
+        let length = unsafe { sys.data.scalar.length } as usize;
+        let slice = if value.is_null() {
+            &[][..]
+        } else {
+            unsafe { std::slice::from_raw_parts(value, length) }
+        };
+        match values {
+            Values::Copy => {}
+            Values::Borrow(input) => {
+                if let Some(range) = verbatim(sys, style, input, slice) {
+                    return Cow::Borrowed(&input[range]);
+                }
+            }
+            Values::Locate(input, min_len) => {
+                if length >= min_len {
+                    if let Some(range) = verbatim(sys, style, input, slice) {
+                        *located = Some(range);
+                        return Cow::Owned(Vec::new());
+                    }
+                }
+            }
         }
-        let slice = unsafe { std::slice::from_raw_parts(value, length as usize) };
-        Some(ScalarValue(Box::from(slice)))
+        Cow::Owned(slice.to_vec())
     }
 
-    match sys.type_ {
+    let event = match sys.type_ {
         sys::YAML_STREAM_START_EVENT => Event::StreamStart,
         sys::YAML_STREAM_END_EVENT => Event::StreamEnd,
-        sys::YAML_DOCUMENT_START_EVENT => Event::DocumentStart,
-        sys::YAML_DOCUMENT_END_EVENT => Event::DocumentEnd,
-        sys::YAML_ALIAS_EVENT => {
-            Event::Alias(unsafe { parse_anchor(sys.data.alias.anchor) }.unwrap())
-        }
-        sys::YAML_SCALAR_EVENT => Event::Scalar(Scalar {
-            anchor: unsafe { parse_anchor(sys.data.scalar.anchor) },
-            tag: unsafe { parse_tag(sys.data.scalar.tag) },
-            value: unsafe { parse_value(sys.data.scalar.value, sys.data.scalar.length).unwrap() },
-            style: match unsafe { sys.data.scalar.style } {
-                sys::YAML_PLAIN_SCALAR_STYLE => ScalarStyle::Plain,
-                sys::YAML_SINGLE_QUOTED_SCALAR_STYLE => ScalarStyle::SingleQuoted,
-                sys::YAML_DOUBLE_QUOTED_SCALAR_STYLE => ScalarStyle::DoubleQuoted,
-                sys::YAML_LITERAL_SCALAR_STYLE => ScalarStyle::Literal,
-                sys::YAML_FOLDED_SCALAR_STYLE => ScalarStyle::Folded,
-                sys::YAML_ANY_SCALAR_STYLE | _ => unreachable!(),
-            },
-            repr: if let Cow::Borrowed(input) = input {
-                Some(&input[sys.start_mark.index as usize..sys.end_mark.index as usize])
-            } else {
-                None
-            },
+        sys::YAML_DOCUMENT_START_EVENT => {
+            let document_start = unsafe { &sys.data.document_start };
+            let version = unsafe { document_start.version_directive.as_ref() }
+                .map(|version| (version.major, version.minor));
+            let mut tag_directives = Vec::new();
+            let mut directive = document_start.tag_directives.start;
+            while directive < document_start.tag_directives.end {
+                let sys::yaml_tag_directive_t { handle, prefix, .. } = unsafe { *directive };
+                tag_directives.push(unsafe { (parse_string(handle), parse_string(prefix)) });
+                directive = unsafe { directive.add(1) };
+            }
+            Event::DocumentStart(DocumentStart {
+                implicit: document_start.implicit,
+                version,
+                tag_directives,
+            })
+        }
+        sys::YAML_DOCUMENT_END_EVENT => Event::DocumentEnd(DocumentEnd {
+            implicit: unsafe { sys.data.document_end.implicit },
         }),
+        sys::YAML_ALIAS_EVENT => {
+            Event::Alias(unsafe { parse_anchor(sys.data.alias.anchor, anchors) }.unwrap())
+        }
+        sys::YAML_SCALAR_EVENT => {
+            let style = scalar_style(unsafe { sys.data.scalar.style }, sys.start_mark)?;
+            Event::Scalar(Scalar {
+                anchor: unsafe { parse_anchor(sys.data.scalar.anchor, anchors) },
+                tag: unsafe { parse_tag(sys.data.scalar.tag) },
+                value: unsafe { parse_value(sys, style, values, located) },
+                style,
+            })
+        }
         sys::YAML_SEQUENCE_START_EVENT => Event::SequenceStart(SequenceStart {
-            anchor: unsafe { parse_anchor(sys.data.sequence_start.anchor) },
+            anchor: unsafe { parse_anchor(sys.data.sequence_start.anchor, anchors) },
             tag: unsafe { parse_tag(sys.data.sequence_start.tag) },
+            style: match unsafe { sys.data.sequence_start.style } {
+                sys::YAML_FLOW_SEQUENCE_STYLE => CollectionStyle::Flow,
+                _ => CollectionStyle::Block,
+            },
         }),
         sys::YAML_SEQUENCE_END_EVENT => Event::SequenceEnd,
         sys::YAML_MAPPING_START_EVENT => Event::MappingStart(MappingStart {
-            anchor: unsafe { parse_anchor(sys.data.mapping_start.anchor) },
+            anchor: unsafe { parse_anchor(sys.data.mapping_start.anchor, anchors) },
             tag: unsafe { parse_tag(sys.data.mapping_start.tag) },
+            style: match unsafe { sys.data.mapping_start.style } {
+                sys::YAML_FLOW_MAPPING_STYLE => CollectionStyle::Flow,
+                _ => CollectionStyle::Block,
+            },
         }),
         sys::YAML_MAPPING_END_EVENT => Event::MappingEnd,
-        sys::YAML_NO_EVENT => unreachable!(),
-        _ => unimplemented!(),
+        // Includes YAML_NO_EVENT, which libyaml produces if asked for another
+        // event after the end of the stream.
+        _ => {
+            let mark = Mark {
+                sys: sys.start_mark,
+            };
+            return Err(Error::unexpected("event", sys.type_ as u32, mark));
+        }
+    };
+    Ok(event)
+}
+
+unsafe fn convert_token(sys: &sys::yaml_token_t) -> Result<Token, Error> {
+    unsafe fn owned(bytes: *const u8) -> Vec<u8> {
+        if bytes.is_null() {
+            return Vec::new();
+        }
+        let cstr = unsafe { std::ffi::CStr::from_ptr(bytes.cast()) };
+        cstr.to_bytes().to_vec()
     }
+
+    let token = match sys.type_ {
+        sys::YAML_STREAM_START_TOKEN => Token::StreamStart,
+        sys::YAML_STREAM_END_TOKEN => Token::StreamEnd,
+        sys::YAML_VERSION_DIRECTIVE_TOKEN => Token::VersionDirective {
+            major: unsafe { sys.data.version_directive.major },
+            minor: unsafe { sys.data.version_directive.minor },
+        },
+        sys::YAML_TAG_DIRECTIVE_TOKEN => Token::TagDirective {
+            handle: unsafe { owned(sys.data.tag_directive.handle) },
+            prefix: unsafe { owned(sys.data.tag_directive.prefix) },
+        },
+        sys::YAML_DOCUMENT_START_TOKEN => Token::DocumentStart,
+        sys::YAML_DOCUMENT_END_TOKEN => Token::DocumentEnd,
+        sys::YAML_BLOCK_SEQUENCE_START_TOKEN => Token::BlockSequenceStart,
+        sys::YAML_BLOCK_MAPPING_START_TOKEN => Token::BlockMappingStart,
+        sys::YAML_BLOCK_END_TOKEN => Token::BlockEnd,
+        sys::YAML_FLOW_SEQUENCE_START_TOKEN => Token::FlowSequenceStart,
+        sys::YAML_FLOW_SEQUENCE_END_TOKEN => Token::FlowSequenceEnd,
+        sys::YAML_FLOW_MAPPING_START_TOKEN => Token::FlowMappingStart,
+        sys::YAML_FLOW_MAPPING_END_TOKEN => Token::FlowMappingEnd,
+        sys::YAML_BLOCK_ENTRY_TOKEN => Token::BlockEntry,
+        sys::YAML_FLOW_ENTRY_TOKEN => Token::FlowEntry,
+        sys::YAML_KEY_TOKEN => Token::Key,
+        sys::YAML_VALUE_TOKEN => Token::Value,
+        sys::YAML_ALIAS_TOKEN => Token::Alias(Anchor::new(unsafe { owned(sys.data.alias.value) })),
+        sys::YAML_ANCHOR_TOKEN => {
+            Token::Anchor(Anchor::new(unsafe { owned(sys.data.anchor.value) }))
+        }
+        sys::YAML_TAG_TOKEN => Token::Tag {
+            handle: unsafe { owned(sys.data.tag.handle) },
+            suffix: unsafe { owned(sys.data.tag.suffix) },
+        },
+        sys::YAML_SCALAR_TOKEN => {
+            let value = unsafe { sys.data.scalar.value };
+            let length = unsafe { sys.data.scalar.length } as usize;
+            Token::Scalar {
+                value: if value.is_null() {
+                    Vec::new()
+                } else {
+                    unsafe { std::slice::from_raw_parts(value, length) }.to_vec()
+                },
+                style: scalar_style(unsafe { sys.data.scalar.style }, sys.start_mark)?,
+            }
+        }
+        // Scanning again after the end of the stream.
+        sys::YAML_NO_TOKEN => Token::StreamEnd,
+        _ => {
+            let mark = Mark {
+                sys: sys.start_mark,
+            };
+            return Err(Error::unexpected("token", sys.type_ as u32, mark));
+        }
+    };
+    Ok(token)
 }
@@ -0,0 +1,72 @@
+use crate::libyaml::event::{Anchor, ScalarStyle};
+
+/// One piece of YAML syntax, as found by a [`Scanner`](crate::raw::Scanner).
+///
+/// Tokens that mark where block collections begin and end take up no space in
+/// the input. Comments and whitespace between tokens are not tokens.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Token {
+    /// The first token of every stream.
+    StreamStart,
+    /// The last token of every stream.
+    StreamEnd,
+    /// A `%YAML` directive.
+    VersionDirective {
+        /// The major version number.
+        major: i32,
+        /// The minor version number.
+        minor: i32,
+    },
+    /// A `%TAG` directive.
+    TagDirective {
+        /// The tag handle, such as `!e!`.
+        handle: Vec<u8>,
+        /// The prefix that the handle stands for.
+        prefix: Vec<u8>,
+    },
+    /// A `---` marker.
+    DocumentStart,
+    /// A `...` marker.
+    DocumentEnd,
+    /// Where a block sequence begins.
+    BlockSequenceStart,
+    /// Where a block mapping begins.
+    BlockMappingStart,
+    /// Where a block sequence or block mapping ends.
+    BlockEnd,
+    /// A `[`.
+    FlowSequenceStart,
+    /// A `]`.
+    FlowSequenceEnd,
+    /// A `{`.
+    FlowMappingStart,
+    /// A `}`.
+    FlowMappingEnd,
+    /// A `-` in front of an element of a block sequence.
+    BlockEntry,
+    /// A `,` between entries of a flow collection.
+    FlowEntry,
+    /// Where a mapping key begins. Implicit keys take up no space in the
+    /// input; explicit keys begin with `?`.
+    Key,
+    /// A `:` between a mapping key and its value.
+    Value,
+    /// An alias, like `*name`.
+    Alias(Anchor),
+    /// An anchor, like `&name`.
+    Anchor(Anchor),
+    /// A tag, like `!!str` or `!point`.
+    Tag {
+        /// The tag handle, such as `!!`, or empty for a verbatim tag.
+        handle: Vec<u8>,
+        /// The rest of the tag after the handle.
+        suffix: Vec<u8>,
+    },
+    /// A scalar, including any quotes or block scalar header.
+    Scalar {
+        /// The value after any escapes and folding have been processed.
+        value: Vec<u8>,
+        /// How the scalar is written.
+        style: ScalarStyle,
+    },
+}
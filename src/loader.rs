@@ -1,46 +1,249 @@
-use crate::de::{Event, Progress};
-use crate::error::{self, Error, ErrorImpl, Result};
+use crate::de::{self, DuplicateAnchors, Event, Progress, Resolver, Schema, TagHandler};
+use crate::diagnostic::{Code, Diagnostic};
+use crate::error::{self, Error, ErrorImpl, Location, Result};
 use crate::libyaml::error::Mark;
-use crate::libyaml::parser::{Event as YamlEvent, Parser};
+use crate::libyaml::event::{DocumentEnd, DocumentStart, Event as YamlEvent, Scalar, ScalarStyle};
+use crate::libyaml::parser::{self, Parser};
+use crate::yaml11;
 use std::borrow::Cow;
+use std::cell::OnceCell;
 use std::collections::BTreeMap;
+use std::mem;
+use std::ops::Range;
 use std::sync::Arc;
 
+// Scalars of owned input at least this long are left in the input until they
+// are needed, so that one that is ignored is never copied. Shorter ones cost
+// less to copy right away than to look up later.
+const LOCATE_SCALARS_FROM: usize = 4096;
+
 pub(crate) struct Loader<'input> {
     parser: Option<Parser<'input>>,
     document_count: usize,
+    // How many documents of the stream came before the input.
+    documents_before: usize,
+    recover: bool,
+    duplicate_anchors: DuplicateAnchors,
+    max_documents: Option<usize>,
+    yaml11: bool,
+    schema: Schema,
+    resolvers: Vec<Resolver>,
+    tag_handlers: Vec<(String, TagHandler)>,
+    // Bytes of input parsed so far, as of the last event.
+    consumed: u64,
+    report: Option<Report>,
+    // An error that every later document repeats unless recovering, and the
+    // document it was found in.
+    failed: Option<(Arc<ErrorImpl>, DocumentSpan)>,
+    // Where the end of the last document left off, which is where the next
+    // one begins unless it has a `---` marker or directives.
+    next_start: Location,
+    // Only collected when asked for, so that loading pays nothing for them
+    // otherwise.
+    diagnostics: Option<Vec<Diagnostic>>,
+}
+
+// How often to report how much of the input has been parsed, to what, and
+// when next.
+struct Report {
+    every: u64,
+    report: fn(u64),
+    next: u64,
+}
+
+// Notes that the parser has read up to `mark`, and reports it if it is time
+// to.
+fn advance(consumed: &mut u64, report: &mut Option<Report>, mark: Mark, end: bool) {
+    *consumed = mark.index() as u64;
+    if let Some(report) = report {
+        if *consumed >= report.next || end {
+            (report.report)(*consumed);
+            report.next = consumed.saturating_add(report.every);
+        }
+    }
 }
 
 pub(crate) struct Document<'input> {
+    /// The value of a scalar that was left in the input is empty here, and
+    /// taken from `input` by `event`.
     pub events: Vec<(Event<'input>, Mark)>,
+    /// The owned input that scalars were left in, if any were.
+    pub input: Option<Arc<Vec<u8>>>,
+    /// Map from index in events of a scalar left in the input to where its
+    /// value is there, and the scalar with its value once it is needed.
+    pub located: BTreeMap<usize, (Range<usize>, Materialized<'input>)>,
     pub error: Option<Arc<ErrorImpl>>,
     /// Map from alias id to index in events.
     pub aliases: BTreeMap<usize, usize>,
+    /// Map from alias id to the number of bytes of scalars in the anchored
+    /// node, which is what following an alias to it repeats.
+    pub anchored_bytes: BTreeMap<usize, usize>,
+    /// The markers and directives around the document.
+    pub start: DocumentStart,
+    pub end: DocumentEnd,
+    pub span: DocumentSpan,
+}
+
+// A scalar that was left in the input, once its value is taken from there.
+pub(crate) type Materialized<'input> = OnceCell<(Event<'input>, Mark)>;
+
+impl<'input> Document<'input> {
+    /// The event at `pos`, with the value of a scalar that was left in the
+    /// input copied out of it the first time it is needed.
+    pub fn event(&self, pos: usize) -> Option<&(Event<'input>, Mark)> {
+        let event = self.events.get(pos)?;
+        if self.located.is_empty() {
+            return Some(event);
+        }
+        let (range, with_value) = match self.located.get(&pos) {
+            Some(located) => located,
+            None => return Some(event),
+        };
+        Some(with_value.get_or_init(|| match (event, &self.input) {
+            ((Event::Scalar(scalar), mark), Some(input)) => {
+                let value = Cow::Owned(input[range.clone()].to_vec());
+                (
+                    Event::Scalar(Scalar {
+                        value,
+                        ..scalar.clone()
+                    }),
+                    *mark,
+                )
+            }
+            _ => unreachable!(),
+        }))
+    }
+}
+
+// Which document of the stream a document is, and the bytes it spans: from
+// its `---` marker or directives, or else from its first node or the end of
+// the document before it, to the end of its `...` marker or the start of
+// what comes after it. A document that fails to load ends where loading
+// stopped.
+#[derive(Copy, Clone, Debug)]
+pub(crate) struct DocumentSpan {
+    pub index: usize,
+    pub start: Location,
+    pub end: usize,
 }
 
 impl<'input> Loader<'input> {
     pub fn new(progress: Progress<'input>) -> Result<Self> {
-        let input = match progress {
-            Progress::Str(s) => Cow::Borrowed(s.as_bytes()),
-            Progress::Slice(bytes) => Cow::Borrowed(bytes),
-            Progress::Read(mut rdr) => {
-                let mut buffer = Vec::new();
-                if let Err(io_error) = rdr.read_to_end(&mut buffer) {
-                    return Err(error::new(ErrorImpl::Io(io_error)));
-                }
-                Cow::Owned(buffer)
-            }
+        let parser = match progress {
+            Progress::Str(s) => Parser::new(Cow::Borrowed(s.as_bytes()))?,
+            Progress::Slice(bytes) => Parser::new(Cow::Borrowed(bytes))?,
+            Progress::Owned(bytes) => Parser::shared(Arc::new(bytes))?,
             Progress::Iterable(_) | Progress::Document(_) => unreachable!(),
             Progress::Fail(err) => return Err(error::shared(err)),
         };
 
         Ok(Loader {
-            parser: Some(Parser::new(input)),
+            parser: Some(parser),
             document_count: 0,
+            documents_before: 0,
+            recover: false,
+            duplicate_anchors: DuplicateAnchors::Allow,
+            max_documents: None,
+            yaml11: false,
+            schema: Schema::Core,
+            resolvers: Vec::new(),
+            tag_handlers: Vec::new(),
+            consumed: 0,
+            report: None,
+            failed: None,
+            next_start: Location::START,
+            diagnostics: None,
         })
     }
 
+    /// Loads the input as the part of a longer stream that begins `index`
+    /// bytes and `line` lines into it, after `documents` other documents, so
+    /// that marks and document spans are where they are in the stream.
+    pub fn origin(&mut self, index: usize, line: usize, documents: usize) {
+        if let Some(parser) = &mut self.parser {
+            parser.set_origin((index as u64, line as u64));
+        }
+        self.documents_before = documents;
+        self.consumed = index as u64;
+        self.next_start = Location::line_start(index, line);
+    }
+
+    /// After a document that fails to parse, resume parsing at the next
+    /// document start marker instead of repeating the error.
+    pub fn recover_documents(&mut self, recover: bool) {
+        self.recover = recover;
+    }
+
+    /// Whether an anchor may be defined again later in the same document.
+    pub fn duplicate_anchors(&mut self, duplicate_anchors: DuplicateAnchors) {
+        self.duplicate_anchors = duplicate_anchors;
+    }
+
+    /// Fails at the start of any document past this many, and stops parsing.
+    pub fn max_documents(&mut self, max_documents: Option<usize>) {
+        self.max_documents = max_documents;
+    }
+
+    /// Resolves plain scalars as YAML 1.1 does.
+    pub fn legacy_resolution(&mut self, yaml11: bool) {
+        self.yaml11 = yaml11;
+    }
+
+    /// Resolves plain scalars by this schema, instead of the core schema.
+    pub fn schema(&mut self, schema: Schema) {
+        self.schema = schema;
+    }
+
+    /// Tags untagged plain scalars by the first of these to give a tag.
+    pub fn resolvers(&mut self, resolvers: Vec<Resolver>) {
+        self.resolvers = resolvers;
+    }
+
+    /// Replaces scalars with one of these tags by what its handler returns.
+    pub fn tag_handlers(&mut self, tag_handlers: Vec<(String, TagHandler)>) {
+        self.tag_handlers = tag_handlers;
+    }
+
+    /// Calls `report` with the bytes parsed so far each time at least
+    /// `every` more have been parsed, and once at the end of the input.
+    pub fn report_progress(&mut self, every: u64, report: fn(u64)) {
+        let every = every.max(1);
+        self.report = Some(Report {
+            every,
+            report,
+            next: every,
+        });
+    }
+
+    pub fn bytes_consumed(&self) -> u64 {
+        self.consumed
+    }
+
+    /// Records diagnostics about each document from now on, for
+    /// take_diagnostics to return.
+    pub fn collect_diagnostics(&mut self) {
+        self.diagnostics.get_or_insert_with(Vec::new);
+    }
+
+    pub fn take_diagnostics(&mut self) -> Vec<Diagnostic> {
+        self.diagnostics.as_mut().map(mem::take).unwrap_or_default()
+    }
+
     pub fn next_document(&mut self) -> Option<Document<'input>> {
+        if let Some((err, span)) = &self.failed {
+            return Some(Document {
+                events: Vec::new(),
+                input: None,
+                located: BTreeMap::new(),
+                error: Some(Arc::clone(err)),
+                aliases: BTreeMap::new(),
+                anchored_bytes: BTreeMap::new(),
+                start: implicit_start(),
+                end: DocumentEnd { implicit: true },
+                span: *span,
+            });
+        }
+
         let parser = match &mut self.parser {
             Some(parser) => parser,
             None => return None,
@@ -48,22 +251,146 @@ impl<'input> Loader<'input> {
 
         let first = self.document_count == 0;
         self.document_count += 1;
+        let shared = parser.shared_input().cloned();
 
         let mut anchors = BTreeMap::new();
+        // Anchors not yet referred to by an alias, by id. Only kept when
+        // collecting diagnostics.
+        let mut unused = BTreeMap::new();
+        // Bytes of scalars so far, and the id of each open collection's
+        // anchor with the count when it began.
+        let mut scalar_bytes = 0;
+        let mut open = Vec::new();
+        let mut included = Included::default();
         let mut document = Document {
             events: Vec::new(),
+            input: None,
+            located: BTreeMap::new(),
             error: None,
             aliases: BTreeMap::new(),
+            anchored_bytes: BTreeMap::new(),
+            start: implicit_start(),
+            end: DocumentEnd { implicit: true },
+            span: DocumentSpan {
+                index: self.documents_before + self.document_count - 1,
+                start: self.next_start,
+                end: self.next_start.index(),
+            },
         };
 
         loop {
-            let (event, mark) = match parser.next() {
-                Ok((event, mark)) => (event, mark),
+            let mut located = None;
+            let next = parser.next_span_locating(LOCATE_SCALARS_FROM, &mut located);
+            let (mut event, mark, end_mark) = match next {
+                Ok((event, mark, end_mark)) => {
+                    let end = event == YamlEvent::StreamEnd;
+                    advance(&mut self.consumed, &mut self.report, mark, end);
+                    document.span.end = end_mark.index();
+                    (event, mark, end_mark)
+                }
                 Err(err) => {
-                    document.error = Some(Error::from(err).shared());
+                    if let Some(mark) = err.mark() {
+                        document.span.end = mark.index();
+                        self.next_start = Location::from_mark(mark);
+                    }
+                    let resync = err.mark().map_or(0, |mark| mark.index());
+                    let mut err = Error::from(err).shared();
+                    if self.recover {
+                        // Failing to go on ends the stream, with that error.
+                        if let Err(resync_err) = self.resync(resync) {
+                            err = resync_err.shared();
+                        }
+                    } else {
+                        self.failed = Some((Arc::clone(&err), document.span));
+                    }
+                    document.error = Some(err);
                     return Some(document);
                 }
             };
+            // The value of a scalar that was left in the input.
+            let located_value = located
+                .clone()
+                .zip(shared.as_deref())
+                .map(|(range, input)| &input[range]);
+            let anchor = match &mut event {
+                YamlEvent::Scalar(scalar) => scalar.anchor.take(),
+                YamlEvent::SequenceStart(sequence_start) => sequence_start.anchor.take(),
+                YamlEvent::MappingStart(mapping_start) => mapping_start.anchor.take(),
+                _ => None,
+            };
+            let mut anchored = None;
+            if let Some(anchor) = anchor {
+                let name = || String::from_utf8_lossy(anchor.as_bytes()).into_owned();
+                if let Some(&(_id, first)) = anchors.get(&anchor) {
+                    if self.duplicate_anchors == DuplicateAnchors::Error {
+                        self.fail(
+                            &mut document,
+                            ErrorImpl::DuplicateAnchor(name(), first, mark),
+                        );
+                        return Some(document);
+                    }
+                    if let Some(diagnostics) = &mut self.diagnostics {
+                        let message = format!("duplicate anchor {:?}", name());
+                        diagnostics.push(Diagnostic::new(Code::DuplicateAnchor, message, mark));
+                    }
+                }
+                // Ids are not reused when an anchor is shadowed, so that
+                // aliases resolve to the definition that precedes them.
+                let id = document.aliases.len();
+                if self.diagnostics.is_some() {
+                    unused.insert(id, (name(), mark));
+                }
+                anchors.insert(anchor, (id, mark));
+                document.aliases.insert(id, document.events.len());
+                anchored = Some(id);
+            }
+            match &event {
+                YamlEvent::Scalar(scalar) => {
+                    let len = located_value.unwrap_or(&scalar.value).len();
+                    scalar_bytes += len;
+                    if let Some(id) = anchored {
+                        document.anchored_bytes.insert(id, len);
+                    }
+                }
+                YamlEvent::SequenceStart(_) | YamlEvent::MappingStart(_) => {
+                    open.push((anchored, scalar_bytes));
+                }
+                YamlEvent::SequenceEnd | YamlEvent::MappingEnd => {
+                    if let Some((Some(id), start)) = open.pop() {
+                        document.anchored_bytes.insert(id, scalar_bytes - start);
+                    }
+                }
+                _ => {}
+            }
+            if let Some(diagnostics) = &mut self.diagnostics {
+                match &event {
+                    YamlEvent::Scalar(scalar)
+                        if scalar.style == ScalarStyle::Plain && scalar.tag.is_none() =>
+                    {
+                        let value = located_value.unwrap_or(&scalar.value);
+                        let value = String::from_utf8_lossy(value);
+                        if let Some(difference) = yaml11::difference(&value) {
+                            let message = format!(
+                                "plain scalar {:?} is {}, but {} in YAML 1.1",
+                                value, difference.yaml12, difference.yaml11,
+                            );
+                            diagnostics.push(Diagnostic::new(Code::Yaml11Scalar, message, mark));
+                        }
+                    }
+                    YamlEvent::Alias(alias) => {
+                        if let Some((id, _mark)) = anchors.get(alias) {
+                            unused.remove(id);
+                        }
+                    }
+                    YamlEvent::DocumentEnd(_) | YamlEvent::StreamEnd => {
+                        for (name, mark) in mem::take(&mut unused).into_values() {
+                            let message = format!("unused anchor {:?}", name);
+                            diagnostics.push(Diagnostic::new(Code::UnusedAnchor, message, mark));
+                        }
+                    }
+                    _ => {}
+                }
+            }
             let event = match event {
                 YamlEvent::StreamStart => continue,
                 YamlEvent::StreamEnd => {
@@ -77,43 +404,296 @@ impl<'input> Loader<'input> {
                         None
                     };
                 }
-                YamlEvent::DocumentStart => continue,
-                YamlEvent::DocumentEnd => return Some(document),
+                YamlEvent::DocumentStart(start) => {
+                    if let Some(max) = self.max_documents {
+                        if self.document_count > max {
+                            // The stream ends here, with nothing after it
+                            // parsed, even when recovering.
+                            let err = ErrorImpl::DocumentLimitExceeded(max, mark);
+                            document.span.start = Location::from_mark(mark);
+                            self.parser = None;
+                            document.error = Some(error::new(err).shared());
+                            return Some(document);
+                        }
+                    }
+                    document.start = start;
+                    document.span.start = Location::from_mark(mark);
+                    continue;
+                }
+                YamlEvent::DocumentEnd(end) => {
+                    document.end = end;
+                    self.next_start = Location::from_mark(end_mark);
+                    return Some(document);
+                }
                 YamlEvent::Alias(alias) => match anchors.get(&alias) {
-                    Some(id) => Event::Alias(*id),
+                    Some(&(id, _mark)) => Event::Alias(id),
                     None => {
-                        document.error = Some(error::new(ErrorImpl::UnknownAnchor(mark)).shared());
+                        self.fail(&mut document, ErrorImpl::UnknownAnchor(mark));
                         return Some(document);
                     }
                 },
                 YamlEvent::Scalar(mut scalar) => {
-                    if let Some(anchor) = scalar.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
+                    if let Some(handler) = handler_for(&self.tag_handlers, &scalar) {
+                        let value = located_value.unwrap_or(&scalar.value);
+                        match include(
+                            &self.tag_handlers,
+                            handler,
+                            value,
+                            mark,
+                            0,
+                            &mut document,
+                            &mut included,
+                        ) {
+                            Ok(bytes) => {
+                                scalar_bytes = scalar_bytes - value.len() + bytes;
+                                if let Some(id) = anchored {
+                                    document.anchored_bytes.insert(id, bytes);
+                                }
+                            }
+                            Err(err) => {
+                                self.fail(&mut document, err);
+                                return Some(document);
+                            }
+                        }
+                        continue;
                     }
-                    Event::Scalar(scalar)
-                }
-                YamlEvent::SequenceStart(mut sequence_start) => {
-                    if let Some(anchor) = sequence_start.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
+                    let (resolvers, schema, yaml11) = (&self.resolvers, self.schema, self.yaml11);
+                    let resolved = match located_value {
+                        Some(value) => {
+                            let mut view = Scalar {
+                                anchor: None,
+                                tag: scalar.tag.take(),
+                                value: Cow::Borrowed(value),
+                                style: scalar.style,
+                            };
+                            let resolved = resolve(resolvers, schema, yaml11, &mut view);
+                            scalar.tag = view.tag;
+                            if let Cow::Owned(value) = view.value {
+                                scalar.value = Cow::Owned(value);
+                                located = None;
+                            }
+                            resolved
+                        }
+                        None => resolve(resolvers, schema, yaml11, &mut scalar),
+                    };
+                    if !resolved {
+                        self.fail(&mut document, ErrorImpl::DefaultValue(mark));
+                        return Some(document);
                     }
-                    Event::SequenceStart(sequence_start)
-                }
-                YamlEvent::SequenceEnd => Event::SequenceEnd,
-                YamlEvent::MappingStart(mut mapping_start) => {
-                    if let Some(anchor) = mapping_start.anchor.take() {
-                        let id = anchors.len();
-                        anchors.insert(anchor, id);
-                        document.aliases.insert(id, document.events.len());
+                    if let (Some(range), Some(input)) = (located, &shared) {
+                        let index = document.events.len();
+                        document.located.insert(index, (range, OnceCell::new()));
+                        document.input.get_or_insert_with(|| Arc::clone(input));
                     }
-                    Event::MappingStart(mapping_start)
+                    Event::Scalar(scalar)
                 }
+                YamlEvent::SequenceStart(sequence_start) => Event::SequenceStart(sequence_start),
+                YamlEvent::SequenceEnd => Event::SequenceEnd,
+                YamlEvent::MappingStart(mapping_start) => Event::MappingStart(mapping_start),
                 YamlEvent::MappingEnd => Event::MappingEnd,
             };
             document.events.push((event, mark));
         }
     }
+
+    // Ends the document with an error that libyaml knows nothing about, so
+    // that the next document does not begin partway through this one.
+    fn fail(&mut self, document: &mut Document<'input>, err: ErrorImpl) {
+        let mut err = error::new(err).shared();
+        if self.recover {
+            match self.skip_document() {
+                Ok(Some(end)) => {
+                    document.span.end = end.index();
+                    self.next_start = Location::from_mark(end);
+                }
+                Ok(None) => {}
+                Err(resync_err) => err = resync_err.shared(),
+            }
+        } else {
+            self.failed = Some((Arc::clone(&err), document.span));
+        }
+        document.error = Some(err);
+    }
+
+    // Discards the rest of the current document, so that the next call to
+    // next_document begins with the one after it, and returns where the
+    // document ended if the parser got that far.
+    fn skip_document(&mut self) -> Result<Option<Mark>> {
+        while let Some(parser) = &mut self.parser {
+            match parser.next_span_locating(LOCATE_SCALARS_FROM, &mut None) {
+                Ok((event, mark, end_mark)) => {
+                    let end = event == YamlEvent::StreamEnd;
+                    advance(&mut self.consumed, &mut self.report, mark, end);
+                    match event {
+                        YamlEvent::DocumentEnd(_) => return Ok(Some(end_mark)),
+                        YamlEvent::StreamEnd => self.parser = None,
+                        _ => {}
+                    }
+                }
+                Err(err) => {
+                    self.resync(err.mark().map_or(0, |mark| mark.index()))?;
+                    return Ok(err.mark());
+                }
+            }
+        }
+        Ok(None)
+    }
+
+    // Replaces a parser that has failed with one that begins at the first
+    // "---" line at or after `index`. The failed parser cannot be reused
+    // because libyaml repeats the same error from then on. Fails if the new
+    // parser cannot be created, which leaves none.
+    fn resync(&mut self, index: usize) -> Result<()> {
+        let parser = match self.parser.take() {
+            Some(parser) => parser,
+            None => return Ok(()),
+        };
+        let origin = parser.origin();
+        let from = index
+            .saturating_sub(origin.0 as usize)
+            .max(parser.start() + 1);
+        // Lines are counted from where the failed parser began rather than
+        // from the beginning of the input, so that recovering from many
+        // broken documents takes time linear in the input.
+        let (resumed, line) = (parser.start(), parser.line());
+        let input = parser.into_input();
+        if let Some(start) = find_document_start(&input, from) {
+            let line = line + parser::count_lines(&input[resumed..start]);
+            let mut parser = Parser::new_at(input, (start, line))?;
+            parser.set_origin(origin);
+            self.parser = Some(parser);
+        }
+        Ok(())
+    }
+}
+
+// What tag handlers have spliced into a document so far: how many times they
+// were called, and how many events and bytes of scalars they gave.
+#[derive(Default)]
+struct Included {
+    calls: usize,
+    events: usize,
+    bytes: usize,
+}
+
+// Resolves the tag of a plain scalar the way the loader was asked to, and
+// returns false if it is the YAML 1.1 default value, which has none.
+fn resolve(resolvers: &[Resolver], schema: Schema, yaml11: bool, scalar: &mut Scalar) -> bool {
+    de::apply_resolvers(resolvers, scalar);
+    de::restrict_to_schema(schema, scalar);
+    !(yaml11 && schema == Schema::Core) || yaml11::resolve_as_yaml11(scalar)
+}
+
+fn handler_for<'a>(
+    handlers: &'a [(String, TagHandler)],
+    scalar: &Scalar,
+) -> Option<&'a (String, TagHandler)> {
+    let tag = scalar.tag.as_ref()?;
+    handlers
+        .iter()
+        .find(|(handled, _)| handled.as_bytes() == tag.as_bytes())
+}
+
+// Splices the events of what a tag handler returns for a scalar into the
+// document in place of the scalar, all at the scalar's mark, and returns the
+// bytes of scalars spliced in. Scalars in it with a handled tag are handled
+// the same way, up to the depth that collections may be nested. The handlers
+// are held to the same limits as aliases, counting the document's own events
+// and not those spliced into it, since those are what the handlers control.
+fn include<'input>(
+    handlers: &[(String, TagHandler)],
+    (tag, handler): &(String, TagHandler),
+    value: &[u8],
+    mark: Mark,
+    depth: usize,
+    document: &mut Document<'input>,
+    included: &mut Included,
+) -> std::result::Result<usize, ErrorImpl> {
+    if depth == 128 {
+        return Err(ErrorImpl::RecursionLimitExceeded(mark));
+    }
+    let value = String::from_utf8_lossy(value);
+    let node = format!("{} {}", tag, value);
+    let in_tag = |err| error::in_tag(err, node.clone(), mark);
+    let len = mark.index().saturating_sub(document.span.start.index()) + 1;
+    let allowed = |included: &Included, events: usize| {
+        repetition_allowed(
+            included.calls,
+            included.bytes,
+            events - included.events + 1,
+            len,
+        )
+    };
+    included.calls += 1;
+    if !allowed(included, document.events.len()) {
+        return Err(ErrorImpl::RepetitionLimitExceeded);
+    }
+    let value = handler(&value).map_err(in_tag)?;
+    let yaml = crate::to_string(&value).map_err(in_tag)?;
+    let mut parser =
+        Parser::new(Cow::Owned(yaml.into_bytes())).map_err(|err| in_tag(err.into()))?;
+    let mut bytes = 0;
+    loop {
+        let (event, _mark) = parser.next().map_err(|err| in_tag(err.into()))?;
+        let event = match event {
+            YamlEvent::StreamStart | YamlEvent::DocumentStart(_) | YamlEvent::DocumentEnd(_) => {
+                continue;
+            }
+            YamlEvent::StreamEnd => return Ok(bytes),
+            // A value is serialized without anchors.
+            YamlEvent::Alias(_) => unreachable!(),
+            YamlEvent::Scalar(scalar) => {
+                if let Some(nested) = handler_for(handlers, &scalar) {
+                    bytes += include(
+                        handlers,
+                        nested,
+                        &scalar.value,
+                        mark,
+                        depth + 1,
+                        document,
+                        included,
+                    )?;
+                    continue;
+                }
+                bytes += scalar.value.len();
+                included.bytes += scalar.value.len();
+                if !allowed(included, document.events.len()) {
+                    return Err(ErrorImpl::RepetitionLimitExceeded);
+                }
+                Event::Scalar(scalar)
+            }
+            YamlEvent::SequenceStart(sequence_start) => Event::SequenceStart(sequence_start),
+            YamlEvent::SequenceEnd => Event::SequenceEnd,
+            YamlEvent::MappingStart(mapping_start) => Event::MappingStart(mapping_start),
+            YamlEvent::MappingEnd => Event::MappingEnd,
+        };
+        document.events.push((event, mark));
+        included.events += 1;
+    }
+}
+
+// Whether aliases may repeat `jumps` nodes and `bytes` bytes of scalars in a
+// document of `events` events and `len` bytes. At most 100 times as many nodes
+// as the document has, and 10 times as many bytes or 16 MiB if that is more,
+// which is plenty for documents written by hand and stops expansion attacks
+// like the "billion laughs" early, before they copy the same few nodes or the
+// same long scalar over and over.
+pub(crate) fn repetition_allowed(jumps: usize, bytes: usize, events: usize, len: usize) -> bool {
+    jumps <= events * 100 && bytes <= (len * 10).max(1 << 24)
+}
+
+// The start of a document without a `---` marker or directives.
+pub(crate) fn implicit_start() -> DocumentStart {
+    DocumentStart {
+        implicit: true,
+        ..DocumentStart::default()
+    }
+}
+
+fn find_document_start(input: &[u8], from: usize) -> Option<usize> {
+    (from..input.len()).find(|&i| {
+        (i == 0 || matches!(input[i - 1], b'\n' | b'\r'))
+            && input[i..].starts_with(b"---")
+            && matches!(input.get(i + 3), None | Some(b' ' | b'\t' | b'\n' | b'\r'))
+    })
 }
@@ -7,9 +7,15 @@ use std::cmp::Ordering;
 use std::collections::hash_map::DefaultHasher;
 use std::fmt::{self, Display};
 use std::hash::{Hash, Hasher};
+use std::iter::FusedIterator;
 use std::mem;
 
 /// A YAML mapping in which the keys and values are both `serde_yaml::Value`.
+///
+/// Keys of any kind are written back out as the same kind of node they were
+/// read as, so `true: a` keeps a boolean key and `'true': a` a string key.
+/// This includes a `.nan` key: YAML has just one NaN, which compares equal to
+/// itself as a [`Number`](crate::Number).
 #[derive(Clone, Default, Eq, PartialEq)]
 pub struct Mapping {
     map: IndexMap<Value, Value>,
@@ -50,13 +56,124 @@ impl Mapping {
         self.map.shrink_to_fit();
     }
 
+    /// Collects key-value pairs of anything that converts into a [`Value`]
+    /// into a mapping, in order.
+    ///
+    /// If a key appears more than once, the last value wins, and the entry
+    /// stays where the key first appeared.
+    ///
+    /// ```
+    /// use serde_yaml::{Mapping, Value};
+    ///
+    /// let mapping = Mapping::from_pairs([("a", 1), ("b", 2), ("a", 3)]);
+    /// assert_eq!(mapping.len(), 2);
+    /// assert_eq!(mapping["a"], 3);
+    /// assert_eq!(mapping.keys().next(), Some(&Value::from("a")));
+    /// ```
+    pub fn from_pairs<I, K, V>(iter: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: Into<Value>,
+        V: Into<Value>,
+    {
+        iter.into_iter()
+            .map(|(k, v)| (k.into(), v.into()))
+            .collect()
+    }
+
     /// Inserts a key-value pair into the map. If the key already existed, the
     /// old value is returned.
+    ///
+    /// A new key is added at the end of the map. An existing key keeps its
+    /// position and only its value is replaced. The key itself is not
+    /// replaced, as with the standard library's maps, so if `k` is equal to
+    /// the existing key without being identical, like a float key of `0.0`
+    /// inserted over `-0.0`, the map keeps the original.
+    ///
+    /// ```
+    /// # use serde_yaml::Mapping;
+    /// let mut mapping = Mapping::new();
+    /// mapping.insert("a".into(), 1.into());
+    /// mapping.insert("b".into(), 2.into());
+    /// assert_eq!(mapping.insert("a".into(), 3.into()).unwrap(), 1);
+    /// assert_eq!(serde_yaml::to_string(&mapping).unwrap(), "a: 3\nb: 2\n");
+    /// ```
     #[inline]
     pub fn insert(&mut self, k: Value, v: Value) -> Option<Value> {
         self.map.insert(k, v)
     }
 
+    /// Like [`insert`][Self::insert], but takes anything that converts into a
+    /// [`Value`] for the key and value.
+    ///
+    /// This is a method of its own because `insert` cannot be made generic
+    /// without breaking existing code: a call like `m.insert("k".into(), v)`
+    /// compiles only because the parameter is `Value`, which tells `.into()`
+    /// what to convert to. The same goes for
+    /// [`shift_insert`][Self::shift_insert] and
+    /// [`insert_before`][Self::insert_before], which take a `Value` like
+    /// `insert` does.
+    ///
+    /// ```
+    /// # use serde_yaml::Mapping;
+    /// let mut mapping = Mapping::new();
+    /// mapping.put("a", 1);
+    /// mapping.put("b", true);
+    /// assert_eq!(mapping.put("a", 3).unwrap(), 1);
+    /// assert_eq!(serde_yaml::to_string(&mapping).unwrap(), "a: 3\nb: true\n");
+    /// ```
+    #[inline]
+    pub fn put<K, V>(&mut self, k: K, v: V) -> Option<Value>
+    where
+        K: Into<Value>,
+        V: Into<Value>,
+    {
+        self.map.insert(k.into(), v.into())
+    }
+
+    /// Inserts a key-value pair into the map if the key is not already
+    /// present, and returns a mutable reference to the inserted value.
+    ///
+    /// If the key already existed, the map is left unchanged and the key and
+    /// value that were passed in are returned in the error. Otherwise the new
+    /// entry is added at the end of the map.
+    ///
+    /// ```
+    /// # use serde_yaml::{Mapping, Value};
+    /// let mut mapping = Mapping::new();
+    /// *mapping.try_insert("a".into(), 1.into()).unwrap() = Value::from(2);
+    /// let (key, value) = mapping.try_insert("a".into(), 3.into()).unwrap_err();
+    /// assert_eq!((key, value), (Value::from("a"), Value::from(3)));
+    /// assert_eq!(mapping["a"], 2);
+    /// ```
+    #[allow(clippy::result_large_err)]
+    pub fn try_insert(&mut self, k: Value, v: Value) -> Result<&mut Value, (Value, Value)> {
+        if self.map.contains_key(&k) {
+            return Err((k, v));
+        }
+        Ok(self.map.entry(k).or_insert(v))
+    }
+
+    /// Moves all entries from `other` into `self`, leaving `other` empty.
+    ///
+    /// Entries are inserted in the order they appear in `other`, as if by
+    /// [`insert`][Self::insert]: keys not yet in `self` are added at the end,
+    /// and keys already in `self` keep their position but take the value from
+    /// `other`.
+    ///
+    /// ```
+    /// # use serde_yaml::Mapping;
+    /// let mut a: Mapping = serde_yaml::from_str("{x: 1, y: 2}").unwrap();
+    /// let mut b: Mapping = serde_yaml::from_str("{z: 3, x: 4}").unwrap();
+    /// a.append(&mut b);
+    /// assert!(b.is_empty());
+    /// assert_eq!(serde_yaml::to_string(&a).unwrap(), "x: 4\ny: 2\nz: 3\n");
+    /// ```
+    #[inline]
+    pub fn append(&mut self, other: &mut Mapping) {
+        self.map.append(&mut other.map);
+    }
+
     /// Checks if the map contains the given key.
     #[inline]
     pub fn contains_key<I: Index>(&self, index: I) -> bool {
@@ -69,6 +186,29 @@ impl Mapping {
         index.index_into(self)
     }
 
+    /// Returns the key as it is stored in the map, together with its value.
+    ///
+    /// The stored key can differ from the one looked up while still being
+    /// equal to it, like a float key of `-0.0` looked up as `0.0`. Keys of
+    /// different kinds are never equal, so neither the integer `1` nor the
+    /// string `"1"` finds a key of `1.0`.
+    ///
+    /// ```
+    /// # use serde_yaml::{Mapping, Value};
+    /// let mapping: Mapping = serde_yaml::from_str("-0.0: zero\n1.0: one\n").unwrap();
+    ///
+    /// let (stored, value) = mapping.get_key_value(Value::from(0.0)).unwrap();
+    /// assert!(stored.as_f64().unwrap().is_sign_negative());
+    /// assert_eq!(value, "zero");
+    ///
+    /// assert_eq!(mapping.get_key_value(1), None);
+    /// assert_eq!(mapping.get_key_value("1"), None);
+    /// ```
+    #[inline]
+    pub fn get_key_value<I: Index>(&self, index: I) -> Option<(&Value, &Value)> {
+        index.get_key_value_from(self)
+    }
+
     /// Returns the mutable reference corresponding to the key in the map.
     #[inline]
     pub fn get_mut<I: Index>(&mut self, index: I) -> Option<&mut Value> {
@@ -78,7 +218,7 @@ impl Mapping {
     /// Gets the given key's corresponding entry in the map for insertion and/or
     /// in-place manipulation.
     #[inline]
-    pub fn entry(&mut self, k: Value) -> Entry {
+    pub fn entry(&mut self, k: Value) -> Entry<'_> {
         match self.map.entry(k) {
             indexmap::map::Entry::Occupied(occupied) => Entry::Occupied(OccupiedEntry { occupied }),
             indexmap::map::Entry::Vacant(vacant) => Entry::Vacant(VacantEntry { vacant }),
@@ -87,24 +227,47 @@ impl Mapping {
 
     /// Removes and returns the value corresponding to the key from the map.
     ///
-    /// This is equivalent to [`.swap_remove(index)`][Self::swap_remove],
-    /// replacing this entry's position with the last element. If you need to
-    /// preserve the relative order of the keys in the map, use
-    /// [`.shift_remove(key)`][Self::shift_remove] instead.
+    /// This is equivalent to [`.shift_remove(key)`][Self::shift_remove]: the
+    /// remaining entries keep their relative order, so a mapping that is
+    /// serialized again after removing a key differs from the original only by
+    /// that entry. Use [`.swap_remove(key)`][Self::swap_remove] for a faster
+    /// removal that does not preserve the order.
+    ///
+    /// ```
+    /// # use serde_yaml::Mapping;
+    /// let mut mapping: Mapping = serde_yaml::from_str("{a: 1, b: 2, c: 3}").unwrap();
+    /// assert_eq!(mapping.remove("a").unwrap(), 1);
+    /// assert_eq!(serde_yaml::to_string(&mapping).unwrap(), "b: 2\nc: 3\n");
+    /// ```
     #[inline]
     pub fn remove<I: Index>(&mut self, index: I) -> Option<Value> {
-        self.swap_remove(index)
+        self.shift_remove(index)
     }
 
     /// Remove and return the key-value pair.
     ///
-    /// This is equivalent to [`.swap_remove_entry(index)`][Self::swap_remove_entry],
-    /// replacing this entry's position with the last element. If you need to
-    /// preserve the relative order of the keys in the map, use
-    /// [`.shift_remove_entry(key)`][Self::shift_remove_entry] instead.
+    /// This is equivalent to
+    /// [`.shift_remove_entry(key)`][Self::shift_remove_entry], preserving the
+    /// relative order of the remaining entries. Use
+    /// [`.swap_remove_entry(key)`][Self::swap_remove_entry] for a faster
+    /// removal that does not preserve the order.
     #[inline]
     pub fn remove_entry<I: Index>(&mut self, index: I) -> Option<(Value, Value)> {
-        self.swap_remove_entry(index)
+        self.shift_remove_entry(index)
+    }
+
+    /// Removes and returns the first entry of the map, or `None` if it is
+    /// empty. The remaining entries keep their relative order.
+    #[inline]
+    pub fn pop_first(&mut self) -> Option<(Value, Value)> {
+        self.map.shift_remove_index(0)
+    }
+
+    /// Removes and returns the last entry of the map, or `None` if it is
+    /// empty.
+    #[inline]
+    pub fn pop_last(&mut self) -> Option<(Value, Value)> {
+        self.map.pop()
     }
 
     /// Removes and returns the value corresponding to the key from the map.
@@ -147,6 +310,105 @@ impl Mapping {
         index.shift_remove_entry_from(self)
     }
 
+    /// Returns the key-value pair at the given position in the map, in order of
+    /// insertion.
+    #[inline]
+    pub fn get_index(&self, index: usize) -> Option<(&Value, &Value)> {
+        self.map.get_index(index)
+    }
+
+    /// Returns the key and a mutable reference to the value at the given
+    /// position in the map, in order of insertion.
+    #[inline]
+    pub fn get_index_mut(&mut self, index: usize) -> Option<(&Value, &mut Value)> {
+        self.map.get_index_mut(index)
+    }
+
+    /// Returns the position of the given key in the map, if present.
+    #[inline]
+    pub fn get_index_of<I: Index>(&self, index: I) -> Option<usize> {
+        index.get_index_of_from(self)
+    }
+
+    /// Inserts a key-value pair so that it ends up before the entry currently
+    /// at position `index`, or at the end if `index` equals the length.
+    ///
+    /// If the key already existed, it is moved to the new position and its old
+    /// value is returned. Returns the final position of the entry.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds. Valid indices are `0..=len()`.
+    #[inline]
+    pub fn insert_before(&mut self, index: usize, k: Value, v: Value) -> (usize, Option<Value>) {
+        self.map.insert_before(index, k, v)
+    }
+
+    /// Inserts a key-value pair at position `index`, shifting all following
+    /// entries.
+    ///
+    /// If the key already existed, it is moved to position `index` and its old
+    /// value is returned.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds. Valid indices are `0..len()` if the
+    /// key is already present, and `0..=len()` otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml::Mapping;
+    /// let mut mapping: Mapping = serde_yaml::from_str("apiVersion: v1\nspec: {}\n").unwrap();
+    /// let after = mapping.get_index_of("apiVersion").unwrap() + 1;
+    /// mapping.shift_insert(after, "kind".into(), "Pod".into());
+    /// assert_eq!(
+    ///     serde_yaml::to_string(&mapping).unwrap(),
+    ///     "apiVersion: v1\nkind: Pod\nspec: {}\n",
+    /// );
+    /// ```
+    #[inline]
+    pub fn shift_insert(&mut self, index: usize, k: Value, v: Value) -> Option<Value> {
+        self.map.shift_insert(index, k, v)
+    }
+
+    /// Moves the entry at position `from` to position `to`, shifting the
+    /// entries in between.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `from` or `to` are out of bounds.
+    #[inline]
+    pub fn move_index(&mut self, from: usize, to: usize) {
+        self.map.move_index(from, to);
+    }
+
+    /// Swaps the positions of the entries at positions `a` and `b`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `a` or `b` are out of bounds.
+    #[inline]
+    pub fn swap_indices(&mut self, a: usize, b: usize) {
+        self.map.swap_indices(a, b);
+    }
+
+    /// Removes and returns the key-value pair at position `index`.
+    ///
+    /// Like [`Vec::swap_remove`], the entry is removed by swapping it with the
+    /// last element of the map and popping it off.
+    #[inline]
+    pub fn swap_remove_index(&mut self, index: usize) -> Option<(Value, Value)> {
+        self.map.swap_remove_index(index)
+    }
+
+    /// Removes and returns the key-value pair at position `index`.
+    ///
+    /// Like [`Vec::remove`], the entry is removed by shifting all of the
+    /// elements that follow it, preserving their relative order.
+    #[inline]
+    pub fn shift_remove_index(&mut self, index: usize) -> Option<(Value, Value)> {
+        self.map.shift_remove_index(index)
+    }
+
     /// Scan through each key-value pair in the map and keep those where the
     /// closure `keep` returns true.
     #[inline]
@@ -157,6 +419,24 @@ impl Mapping {
         self.map.retain(keep);
     }
 
+    /// Sorts the entries by key, in the order of `Ord` for [`Value`]: null,
+    /// then booleans, numbers, strings, sequences, mappings and tagged values,
+    /// each compared by their contents.
+    ///
+    /// Only this mapping's entries are sorted, not mappings nested within
+    /// them. [`Value::canonicalize`] sorts every mapping in the same order.
+    ///
+    /// ```
+    /// # use serde_yaml::{Mapping, Value};
+    /// let mut mapping: Mapping = serde_yaml::from_str("b: 1\n2: 2\na: 3\n").unwrap();
+    /// mapping.sort_keys();
+    /// let keys: Vec<&Value> = mapping.keys().collect();
+    /// assert_eq!(keys, [&Value::from(2), &Value::from("a"), &Value::from("b")]);
+    /// ```
+    pub fn sort_keys(&mut self) {
+        self.map.sort_by(|a, _, b, _| total_cmp(a, b));
+    }
+
     /// Returns the maximum number of key-value pairs the map can hold without
     /// reallocating.
     #[inline]
@@ -185,7 +465,7 @@ impl Mapping {
     /// Returns a double-ended iterator visiting all key-value pairs in order of
     /// insertion. Iterator element type is `(&'a Value, &'a Value)`.
     #[inline]
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_> {
         Iter {
             iter: self.map.iter(),
         }
@@ -194,14 +474,14 @@ impl Mapping {
     /// Returns a double-ended iterator visiting all key-value pairs in order of
     /// insertion. Iterator element type is `(&'a Value, &'a mut ValuE)`.
     #[inline]
-    pub fn iter_mut(&mut self) -> IterMut {
+    pub fn iter_mut(&mut self) -> IterMut<'_> {
         IterMut {
             iter: self.map.iter_mut(),
         }
     }
 
     /// Return an iterator over the keys of the map.
-    pub fn keys(&self) -> Keys {
+    pub fn keys(&self) -> Keys<'_> {
         Keys {
             iter: self.map.keys(),
         }
@@ -215,14 +495,14 @@ impl Mapping {
     }
 
     /// Return an iterator over the values of the map.
-    pub fn values(&self) -> Values {
+    pub fn values(&self) -> Values<'_> {
         Values {
             iter: self.map.values(),
         }
     }
 
     /// Return an iterator over mutable references to the values of the map.
-    pub fn values_mut(&mut self) -> ValuesMut {
+    pub fn values_mut(&mut self) -> ValuesMut<'_> {
         ValuesMut {
             iter: self.map.values_mut(),
         }
@@ -237,7 +517,8 @@ impl Mapping {
 }
 
 /// A type that can be used to index into a `serde_yaml::Mapping`. See the
-/// methods `get`, `get_mut`, `contains_key`, and `remove` of `Value`.
+/// methods `get`, `get_mut`, `contains_key`, and `remove` of `Mapping`. Keys
+/// may be given as a `Value`, a string, or an integer, none of which allocate.
 ///
 /// This trait is sealed and cannot be implemented for types outside of
 /// `serde_yaml`.
@@ -262,6 +543,12 @@ pub trait Index: private::Sealed {
 
     #[doc(hidden)]
     fn shift_remove_entry_from(&self, v: &mut Mapping) -> Option<(Value, Value)>;
+
+    #[doc(hidden)]
+    fn get_index_of_from(&self, v: &Mapping) -> Option<usize>;
+
+    #[doc(hidden)]
+    fn get_key_value_from<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)>;
 }
 
 struct HashLikeValue<'a>(&'a str);
@@ -306,6 +593,12 @@ impl Index for Value {
     fn shift_remove_entry_from(&self, v: &mut Mapping) -> Option<(Value, Value)> {
         v.map.shift_remove_entry(self)
     }
+    fn get_index_of_from(&self, v: &Mapping) -> Option<usize> {
+        v.map.get_index_of(self)
+    }
+    fn get_key_value_from<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        v.map.get_key_value(self)
+    }
 }
 
 impl Index for str {
@@ -330,6 +623,12 @@ impl Index for str {
     fn shift_remove_entry_from(&self, v: &mut Mapping) -> Option<(Value, Value)> {
         v.map.shift_remove_entry(&HashLikeValue(self))
     }
+    fn get_index_of_from(&self, v: &Mapping) -> Option<usize> {
+        v.map.get_index_of(&HashLikeValue(self))
+    }
+    fn get_key_value_from<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        v.map.get_key_value(&HashLikeValue(self))
+    }
 }
 
 impl Index for String {
@@ -354,8 +653,53 @@ impl Index for String {
     fn shift_remove_entry_from(&self, v: &mut Mapping) -> Option<(Value, Value)> {
         self.as_str().shift_remove_entry_from(v)
     }
+    fn get_index_of_from(&self, v: &Mapping) -> Option<usize> {
+        self.as_str().get_index_of_from(v)
+    }
+    fn get_key_value_from<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        self.as_str().get_key_value_from(v)
+    }
 }
 
+// Integer keys are looked up as `Value::Number`, which does not allocate.
+macro_rules! index_by_number {
+    ($($ty:ty)*) => {
+        $(
+            impl Index for $ty {
+                fn is_key_into(&self, v: &Mapping) -> bool {
+                    v.map.contains_key(&Value::Number((*self).into()))
+                }
+                fn index_into<'a>(&self, v: &'a Mapping) -> Option<&'a Value> {
+                    v.map.get(&Value::Number((*self).into()))
+                }
+                fn index_into_mut<'a>(&self, v: &'a mut Mapping) -> Option<&'a mut Value> {
+                    v.map.get_mut(&Value::Number((*self).into()))
+                }
+                fn swap_remove_from(&self, v: &mut Mapping) -> Option<Value> {
+                    v.map.swap_remove(&Value::Number((*self).into()))
+                }
+                fn swap_remove_entry_from(&self, v: &mut Mapping) -> Option<(Value, Value)> {
+                    v.map.swap_remove_entry(&Value::Number((*self).into()))
+                }
+                fn shift_remove_from(&self, v: &mut Mapping) -> Option<Value> {
+                    v.map.shift_remove(&Value::Number((*self).into()))
+                }
+                fn shift_remove_entry_from(&self, v: &mut Mapping) -> Option<(Value, Value)> {
+                    v.map.shift_remove_entry(&Value::Number((*self).into()))
+                }
+                fn get_index_of_from(&self, v: &Mapping) -> Option<usize> {
+                    v.map.get_index_of(&Value::Number((*self).into()))
+                }
+                fn get_key_value_from<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+                    v.map.get_key_value(&Value::Number((*self).into()))
+                }
+            }
+        )*
+    };
+}
+
+index_by_number!(i8 i16 i32 i64 isize u8 u16 u32 u64 usize);
+
 impl<T> Index for &T
 where
     T: ?Sized + Index,
@@ -381,6 +725,12 @@ where
     fn shift_remove_entry_from(&self, v: &mut Mapping) -> Option<(Value, Value)> {
         (**self).shift_remove_entry_from(v)
     }
+    fn get_index_of_from(&self, v: &Mapping) -> Option<usize> {
+        (**self).get_index_of_from(v)
+    }
+    fn get_key_value_from<'a>(&self, v: &'a Mapping) -> Option<(&'a Value, &'a Value)> {
+        (**self).get_key_value_from(v)
+    }
 }
 
 #[allow(clippy::derived_hash_with_manual_eq)]
@@ -398,89 +748,94 @@ impl Hash for Mapping {
     }
 }
 
-impl PartialOrd for Mapping {
-    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+// Compares the entries in order of their keys, since two mappings with the
+// same entries in a different order are equal.
+impl Ord for Mapping {
+    fn cmp(&self, other: &Self) -> Ordering {
         let mut self_entries = Vec::from_iter(self);
         let mut other_entries = Vec::from_iter(other);
 
-        // Sort in an arbitrary order that is consistent with Value's PartialOrd
-        // impl.
-        fn total_cmp(a: &Value, b: &Value) -> Ordering {
-            match (a, b) {
-                (Value::Null, Value::Null) => Ordering::Equal,
-                (Value::Null, _) => Ordering::Less,
-                (_, Value::Null) => Ordering::Greater,
-
-                (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
-                (Value::Bool(_), _) => Ordering::Less,
-                (_, Value::Bool(_)) => Ordering::Greater,
-
-                (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
-                (Value::Number(_), _) => Ordering::Less,
-                (_, Value::Number(_)) => Ordering::Greater,
-
-                (Value::String(a), Value::String(b)) => a.cmp(b),
-                (Value::String(_), _) => Ordering::Less,
-                (_, Value::String(_)) => Ordering::Greater,
-
-                (Value::Sequence(a), Value::Sequence(b)) => iter_cmp_by(a, b, total_cmp),
-                (Value::Sequence(_), _) => Ordering::Less,
-                (_, Value::Sequence(_)) => Ordering::Greater,
-
-                (Value::Mapping(a), Value::Mapping(b)) => {
-                    iter_cmp_by(a, b, |(ak, av), (bk, bv)| {
-                        total_cmp(ak, bk).then_with(|| total_cmp(av, bv))
-                    })
-                }
-                (Value::Mapping(_), _) => Ordering::Less,
-                (_, Value::Mapping(_)) => Ordering::Greater,
+        // While sorting by map key, we get to assume that no two keys are
+        // equal, otherwise they wouldn't both be in the map. This is not a safe
+        // assumption outside of this situation.
+        let by_key = |&(a, _): &_, &(b, _): &_| total_cmp(a, b);
+        self_entries.sort_by(by_key);
+        other_entries.sort_by(by_key);
+        iter_cmp_by(self_entries, other_entries, |(ak, av), (bk, bv)| {
+            total_cmp(ak, bk).then_with(|| total_cmp(av, bv))
+        })
+    }
+}
 
-                (Value::Tagged(a), Value::Tagged(b)) => a
-                    .tag
-                    .cmp(&b.tag)
-                    .then_with(|| total_cmp(&a.value, &b.value)),
-            }
-        }
+impl PartialOrd for Mapping {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
 
-        fn iter_cmp_by<I, F>(this: I, other: I, mut cmp: F) -> Ordering
-        where
-            I: IntoIterator,
-            F: FnMut(I::Item, I::Item) -> Ordering,
-        {
-            let mut this = this.into_iter();
-            let mut other = other.into_iter();
-
-            loop {
-                let x = match this.next() {
-                    None => {
-                        if other.next().is_none() {
-                            return Ordering::Equal;
-                        } else {
-                            return Ordering::Less;
-                        }
-                    }
-                    Some(val) => val,
-                };
+// The order of Value's Ord impl, which sort_keys sorts by.
+pub(crate) fn total_cmp(a: &Value, b: &Value) -> Ordering {
+    match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Null, _) => Ordering::Less,
+        (_, Value::Null) => Ordering::Greater,
 
-                let y = match other.next() {
-                    None => return Ordering::Greater,
-                    Some(val) => val,
-                };
+        (Value::Bool(a), Value::Bool(b)) => a.cmp(b),
+        (Value::Bool(_), _) => Ordering::Less,
+        (_, Value::Bool(_)) => Ordering::Greater,
 
-                match cmp(x, y) {
-                    Ordering::Equal => {}
-                    non_eq => return non_eq,
+        (Value::Number(a), Value::Number(b)) => a.total_cmp(b),
+        (Value::Number(_), _) => Ordering::Less,
+        (_, Value::Number(_)) => Ordering::Greater,
+
+        (Value::String(a), Value::String(b)) => a.cmp(b),
+        (Value::String(_), _) => Ordering::Less,
+        (_, Value::String(_)) => Ordering::Greater,
+
+        (Value::Sequence(a), Value::Sequence(b)) => iter_cmp_by(a, b, total_cmp),
+        (Value::Sequence(_), _) => Ordering::Less,
+        (_, Value::Sequence(_)) => Ordering::Greater,
+
+        (Value::Mapping(a), Value::Mapping(b)) => a.cmp(b),
+        (Value::Mapping(_), _) => Ordering::Less,
+        (_, Value::Mapping(_)) => Ordering::Greater,
+
+        (Value::Tagged(a), Value::Tagged(b)) => a
+            .tag
+            .cmp(&b.tag)
+            .then_with(|| total_cmp(&a.value, &b.value)),
+    }
+}
+
+fn iter_cmp_by<I, F>(this: I, other: I, mut cmp: F) -> Ordering
+where
+    I: IntoIterator,
+    F: FnMut(I::Item, I::Item) -> Ordering,
+{
+    let mut this = this.into_iter();
+    let mut other = other.into_iter();
+
+    loop {
+        let x = match this.next() {
+            None => {
+                if other.next().is_none() {
+                    return Ordering::Equal;
+                } else {
+                    return Ordering::Less;
                 }
             }
-        }
+            Some(val) => val,
+        };
 
-        // While sorting by map key, we get to assume that no two keys are
-        // equal, otherwise they wouldn't both be in the map. This is not a safe
-        // assumption outside of this situation.
-        let total_cmp = |&(a, _): &_, &(b, _): &_| total_cmp(a, b);
-        self_entries.sort_by(total_cmp);
-        other_entries.sort_by(total_cmp);
-        self_entries.partial_cmp(&other_entries)
+        let y = match other.next() {
+            None => return Ordering::Greater,
+            Some(val) => val,
+        };
+
+        match cmp(x, y) {
+            Ordering::Equal => {}
+            non_eq => return non_eq,
+        }
     }
 }
 
@@ -515,6 +870,14 @@ impl Extend<(Value, Value)> for Mapping {
     }
 }
 
+impl<'a> Extend<(&'a Value, &'a Value)> for Mapping {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (&'a Value, &'a Value)>>(&mut self, iter: I) {
+        self.map
+            .extend(iter.into_iter().map(|(k, v)| (k.clone(), v.clone())));
+    }
+}
+
 impl FromIterator<(Value, Value)> for Mapping {
     #[inline]
     fn from_iter<I: IntoIterator<Item = (Value, Value)>>(iter: I) -> Self {
@@ -538,12 +901,21 @@ macro_rules! delegate_iterator {
             }
         }
 
+        impl $($generics)* DoubleEndedIterator for $name $($generics)* {
+            #[inline]
+            fn next_back(&mut self) -> Option<Self::Item> {
+                self.iter.next_back()
+            }
+        }
+
         impl $($generics)* ExactSizeIterator for $name $($generics)* {
             #[inline]
             fn len(&self) -> usize {
                 self.iter.len()
             }
         }
+
+        impl $($generics)* FusedIterator for $name $($generics)* {}
     }
 }
 
@@ -735,16 +1107,20 @@ impl<'a> OccupiedEntry<'a> {
         self.occupied.insert(value)
     }
 
-    /// Takes the value of the entry out of the map, and returns it.
+    /// Takes the value of the entry out of the map, and returns it. The
+    /// remaining entries keep their relative order, as with
+    /// [`Mapping::remove`].
     #[inline]
     pub fn remove(self) -> Value {
-        self.occupied.swap_remove()
+        self.occupied.shift_remove()
     }
 
     /// Remove and return the key, value pair stored in the map for this entry.
+    /// The remaining entries keep their relative order, as with
+    /// [`Mapping::remove_entry`].
     #[inline]
     pub fn remove_entry(self) -> (Value, Value) {
-        self.occupied.swap_remove_entry()
+        self.occupied.shift_remove_entry()
     }
 }
 
@@ -809,6 +1185,8 @@ impl<'de> Deserialize<'de> for Mapping {
             where
                 A: serde::de::MapAccess<'de>,
             {
+                // Grown one entry at a time rather than reserved from the size
+                // hint, which may not match how many entries there are.
                 let mut mapping = Mapping::new();
 
                 while let Some(key) = data.next_key()? {
@@ -8,6 +8,15 @@ use std::hash::{Hash, Hasher};
 use std::str::FromStr;
 
 /// Represents a YAML number, whether integer or floating point.
+///
+/// An integer is held as an `i64` or `u64`. Deserializing a larger integer
+/// into a [`Value`](crate::Value) fails, quoting the integer as it was written,
+/// rather than rounding it to a float. To read such an integer approximately,
+/// deserialize it into an `f64` instead.
+///
+/// Serializing writes a number as the kind it holds: an integer as its
+/// digits, and a float in a form that reads back as a float with the same
+/// bits, like `25.0` or `1e16`, or as `.nan` for any NaN.
 #[derive(Clone, PartialEq, PartialOrd)]
 pub struct Number {
     n: N,
@@ -314,8 +323,16 @@ impl FromStr for Number {
     type Err = Error;
 
     fn from_str(repr: &str) -> Result<Self, Self::Err> {
-        if let Ok(result) = de::visit_int(NumberVisitor, repr) {
-            return result;
+        if let Ok(Ok(number)) = de::visit_int(NumberVisitor, repr) {
+            return Ok(number);
+        }
+        if de::is_int_literal(repr) {
+            return Err(serde::de::Error::custom(de::OutOfRange {
+                text: repr,
+                ty: "serde_yaml::Number",
+                min: i64::MIN.into(),
+                max: u64::MAX.into(),
+            }));
         }
         if !de::digits_but_not_number(repr) {
             if let Some(float) = de::parse_f64(repr) {
@@ -552,7 +569,7 @@ impl Hash for Number {
     }
 }
 
-pub(crate) fn unexpected(number: &Number) -> Unexpected {
+pub(crate) fn unexpected(number: &Number) -> Unexpected<'_> {
     match number.n {
         N::PosInt(u) => Unexpected::Unsigned(u),
         N::NegInt(i) => Unexpected::Signed(i),
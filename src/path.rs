@@ -32,3 +32,56 @@ impl<'a> Display for Path<'a> {
         }
     }
 }
+
+/// Owned counterpart of `Path`, for errors that are built before the path to
+/// them is known. Segments are added from the innermost outwards as the error
+/// propagates up through the nodes of a `Value`.
+#[derive(Debug)]
+pub(crate) struct OwnedPath {
+    reversed: Vec<Segment>,
+}
+
+#[derive(Debug)]
+pub(crate) enum Segment {
+    Seq(usize),
+    Map(String),
+    Unknown,
+}
+
+impl OwnedPath {
+    pub(crate) fn new(segment: Segment) -> Self {
+        OwnedPath {
+            reversed: vec![segment],
+        }
+    }
+
+    pub(crate) fn push_parent(&mut self, segment: Segment) {
+        self.reversed.push(segment);
+    }
+}
+
+impl Display for OwnedPath {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> Result<(), fmt::Error> {
+        fn fmt(
+            reversed: &[Segment],
+            parent: &Path,
+            formatter: &mut fmt::Formatter,
+        ) -> Result<(), fmt::Error> {
+            let (segment, rest) = match reversed.split_last() {
+                Some(split) => split,
+                None => return Display::fmt(parent, formatter),
+            };
+            let path = match segment {
+                Segment::Seq(index) => Path::Seq {
+                    parent,
+                    index: *index,
+                },
+                Segment::Map(key) => Path::Map { parent, key },
+                Segment::Unknown => Path::Unknown { parent },
+            };
+            fmt(rest, &path, formatter)
+        }
+
+        fmt(&self.reversed, &Path::Root, formatter)
+    }
+}
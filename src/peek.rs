@@ -0,0 +1,213 @@
+use crate::document::NodeKind;
+use crate::error::Error;
+use crate::value::Tag;
+use serde::de::value::StrDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer, SeqAccess, Visitor};
+use std::fmt;
+use std::marker::PhantomData;
+
+// The newtype struct name by which `peek` asks one of this crate's
+// deserializers to describe the next node.
+pub(crate) const NAME: &str = "$serde_yaml::peek";
+
+const EXPECTING: &str = "serde_yaml's deserializer, which serde_yaml::peek requires";
+
+/// The kind and tag of a node that has not been deserialized yet, as seen by
+/// [`peek`].
+#[derive(Clone, Debug)]
+pub struct Peeked {
+    kind: NodeKind,
+    tag: Option<Tag>,
+}
+
+impl Peeked {
+    /// Whether the node is a scalar, a sequence or a mapping.
+    ///
+    /// Aliases are followed, so this is never [`NodeKind::Alias`]. A missing
+    /// value, as in `key:` with nothing after it, is a scalar.
+    pub fn node_kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// The node's tag, like `!Point` or `tag:yaml.org,2002:str`, if it has
+    /// one.
+    pub fn tag(&self) -> Option<&Tag> {
+        self.tag.as_ref()
+    }
+}
+
+/// Looks at the next node before deciding how to deserialize it.
+///
+/// `f` receives the kind and tag of the node and returns the seed that then
+/// deserializes it, so a `Deserialize` impl can choose between a string and a
+/// struct, or dispatch on a tag, without first buffering the node into a
+/// [`Value`](crate::Value). Nothing is consumed until the seed runs.
+///
+/// This only works when `deserializer` is one of this crate's: a
+/// [`Deserializer`](crate::Deserializer), or a [`Value`](crate::Value) or
+/// `&Value` being deserialized from. Any other deserializer fails with an
+/// error saying so.
+///
+/// ```
+/// use serde::de::{Deserialize, Deserializer};
+/// use serde_yaml::document::NodeKind;
+///
+/// #[derive(serde_derive::Deserialize, PartialEq, Debug)]
+/// struct Build {
+///     context: String,
+///     dockerfile: Option<String>,
+/// }
+///
+/// // Accepts either `build: ./dir` or `build: {context: ./dir, ...}`.
+/// fn string_or_struct<'de, D>(deserializer: D) -> Result<Build, D::Error>
+/// where
+///     D: Deserializer<'de>,
+/// {
+///     serde_yaml::peek(deserializer, |peeked| match peeked.node_kind() {
+///         NodeKind::Scalar => StringOrStruct::String,
+///         _ => StringOrStruct::Struct,
+///     })
+/// }
+///
+/// enum StringOrStruct {
+///     String,
+///     Struct,
+/// }
+///
+/// impl<'de> serde::de::DeserializeSeed<'de> for StringOrStruct {
+///     type Value = Build;
+///
+///     fn deserialize<D>(self, deserializer: D) -> Result<Build, D::Error>
+///     where
+///         D: Deserializer<'de>,
+///     {
+///         match self {
+///             StringOrStruct::String => Ok(Build {
+///                 context: String::deserialize(deserializer)?,
+///                 dockerfile: None,
+///             }),
+///             StringOrStruct::Struct => Build::deserialize(deserializer),
+///         }
+///     }
+/// }
+///
+/// #[derive(serde_derive::Deserialize)]
+/// struct Service {
+///     #[serde(deserialize_with = "string_or_struct")]
+///     build: Build,
+/// }
+///
+/// let service: Service = serde_yaml::from_str("build: ./web").unwrap();
+/// assert_eq!(service.build.context, "./web");
+///
+/// let yaml = "build: {context: ./web, dockerfile: Dockerfile.dev}";
+/// let service: Service = serde_yaml::from_str(yaml).unwrap();
+/// assert_eq!(service.build.dockerfile.as_deref(), Some("Dockerfile.dev"));
+/// ```
+pub fn peek<'de, D, F, S>(deserializer: D, f: F) -> Result<S::Value, D::Error>
+where
+    D: Deserializer<'de>,
+    F: FnOnce(&Peeked) -> S,
+    S: DeserializeSeed<'de>,
+{
+    deserializer.deserialize_newtype_struct(
+        NAME,
+        PeekVisitor {
+            f,
+            marker: PhantomData,
+        },
+    )
+}
+
+struct PeekVisitor<F, S> {
+    f: F,
+    marker: PhantomData<S>,
+}
+
+impl<'de, F, S> Visitor<'de> for PeekVisitor<F, S>
+where
+    F: FnOnce(&Peeked) -> S,
+    S: DeserializeSeed<'de>,
+{
+    type Value = S::Value;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str(EXPECTING)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<Self::Value, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let kind = match seq.next_element::<String>()?.as_deref() {
+            Some("scalar") => NodeKind::Scalar,
+            Some("sequence") => NodeKind::Sequence,
+            Some("mapping") => NodeKind::Mapping,
+            _ => return Err(de::Error::invalid_length(0, &EXPECTING)),
+        };
+        let tag = match seq.next_element::<String>()? {
+            Some(tag) if tag.is_empty() => None,
+            Some(tag) => Some(Tag::new(tag)),
+            None => return Err(de::Error::invalid_length(1, &EXPECTING)),
+        };
+        let seed = (self.f)(&Peeked { kind, tag });
+        match seq.next_element_seed(seed)? {
+            Some(value) => Ok(value),
+            None => Err(de::Error::invalid_length(2, &EXPECTING)),
+        }
+    }
+}
+
+// What one of this crate's deserializers hands to `PeekVisitor`: the node's
+// kind, its tag or an empty string, then the deserializer itself.
+pub(crate) struct PeekAccess<D> {
+    kind: NodeKind,
+    tag: Option<String>,
+    de: Option<D>,
+    step: usize,
+}
+
+impl<D> PeekAccess<D> {
+    pub(crate) fn new(kind: NodeKind, tag: Option<String>, de: D) -> Self {
+        PeekAccess {
+            kind,
+            tag,
+            de: Some(de),
+            step: 0,
+        }
+    }
+}
+
+impl<'de, D> SeqAccess<'de> for PeekAccess<D>
+where
+    D: Deserializer<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Error>
+    where
+        T: DeserializeSeed<'de>,
+    {
+        self.step += 1;
+        match self.step {
+            1 => {
+                let kind = match self.kind {
+                    NodeKind::Scalar | NodeKind::Alias => "scalar",
+                    NodeKind::Sequence => "sequence",
+                    NodeKind::Mapping => "mapping",
+                };
+                seed.deserialize(StrDeserializer::<Error>::new(kind))
+                    .map(Some)
+            }
+            2 => {
+                let tag = self.tag.as_deref().unwrap_or("");
+                seed.deserialize(StrDeserializer::<Error>::new(tag))
+                    .map(Some)
+            }
+            _ => match self.de.take() {
+                Some(de) => seed.deserialize(de).map(Some),
+                None => Ok(None),
+            },
+        }
+    }
+}
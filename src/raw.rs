@@ -0,0 +1,219 @@
+//! Direct access to the YAML event stream, underneath serde.
+//!
+//! A [`Parser`] turns YAML text into a sequence of [`Event`]s, and an
+//! [`Emitter`] turns a sequence of events back into YAML text. Events read
+//! from a parser can be passed to an emitter unchanged, which preserves the
+//! structure of the input including anchors, aliases, tags and the style of
+//! each node, though not comments or exact whitespace.
+//!
+//! ```
+//! # fn main() -> serde_yaml::Result<()> {
+//! use serde_yaml::raw::{Emitter, Event, Parser};
+//!
+//! let input = "base: &base {x: 1}\nderived: *base\nname: 'quoted'\n";
+//!
+//! let mut emitter = Emitter::new(Vec::new())?;
+//! for event in Parser::new(input.as_bytes())? {
+//!     let (event, _location) = event?;
+//!     let event = match event {
+//!         Event::Scalar(mut scalar) if scalar.value.as_ref() == b"x" => {
+//!             scalar.value = b"y".to_vec().into();
+//!             Event::Scalar(scalar)
+//!         }
+//!         event => event,
+//!     };
+//!     emitter.emit(event)?;
+//! }
+//! let output = emitter.into_inner()?;
+//!
+//! let expected = "base: &base {y: 1}\nderived: *base\nname: 'quoted'\n";
+//! assert_eq!(String::from_utf8(output).unwrap(), expected);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A [`Scanner`] is also available for tokenizing YAML text, for uses such as
+//! syntax highlighting that care about the syntax more than the structure.
+
+use crate::error::{Location, Result};
+use crate::libyaml::{emitter, parser};
+use std::borrow::Cow;
+use std::fmt::{self, Debug};
+use std::io;
+
+pub use crate::libyaml::event::{
+    Anchor, CollectionStyle, DocumentEnd, DocumentStart, Event, MappingStart, Scalar, ScalarStyle,
+    SequenceStart, Tag,
+};
+pub use crate::libyaml::token::Token;
+
+/// Reads YAML text as a sequence of events.
+///
+/// The parser is an iterator that yields each event along with the location
+/// where it begins in the input. It ends after [`Event::StreamEnd`], or after
+/// the first error.
+pub struct Parser<'input> {
+    parser: Option<parser::Parser<'input>>,
+}
+
+impl<'input> Parser<'input> {
+    /// Creates a parser of UTF-8 input, either borrowed or owned.
+    ///
+    /// Fails if libyaml is unable to allocate the parser.
+    pub fn new(input: impl Into<Cow<'input, [u8]>>) -> Result<Self> {
+        let parser = parser::Parser::new(input.into())?;
+        Ok(Parser {
+            parser: Some(parser),
+        })
+    }
+}
+
+impl<'input> Iterator for Parser<'input> {
+    type Item = Result<(Event<'input>, Location)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let parser = self.parser.as_mut()?;
+        Some(match parser.next() {
+            Ok((event, mark)) => {
+                if let Event::StreamEnd = event {
+                    self.parser = None;
+                }
+                Ok((event, Location::from_mark(mark)))
+            }
+            Err(err) => {
+                self.parser = None;
+                Err(err.into())
+            }
+        })
+    }
+}
+
+impl<'input> Debug for Parser<'input> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("Parser").finish_non_exhaustive()
+    }
+}
+
+/// Reads YAML text as a sequence of tokens, without checking how they fit
+/// together into documents.
+///
+/// The scanner is an iterator that yields each token along with the
+/// locations where it begins and ends in the input. It ends after
+/// [`Token::StreamEnd`], or after the first error. Anything between the end of
+/// one token and the start of the next is whitespace or comments.
+///
+/// ```
+/// # fn main() -> serde_yaml::Result<()> {
+/// use serde_yaml::raw::{Scanner, Token};
+///
+/// let input = "key: value # comment\n";
+/// let mut keys = Vec::new();
+/// let mut scanner = Scanner::new(input.as_bytes())?;
+/// while let Some(token) = scanner.next() {
+///     if let (Token::Key, _, _) = token? {
+///         if let Some((Token::Scalar { .. }, start, end)) = scanner.next().transpose()? {
+///             keys.push(&input[start.index()..end.index()]);
+///         }
+///     }
+/// }
+/// assert_eq!(keys, ["key"]);
+/// # Ok(())
+/// # }
+/// ```
+pub struct Scanner<'input> {
+    scanner: Option<parser::Scanner<'input>>,
+}
+
+impl<'input> Scanner<'input> {
+    /// Creates a scanner of UTF-8 input, either borrowed or owned.
+    ///
+    /// Fails if libyaml is unable to allocate the scanner.
+    pub fn new(input: impl Into<Cow<'input, [u8]>>) -> Result<Self> {
+        let scanner = parser::Scanner::new(input.into())?;
+        Ok(Scanner {
+            scanner: Some(scanner),
+        })
+    }
+}
+
+impl<'input> Iterator for Scanner<'input> {
+    type Item = Result<(Token, Location, Location)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let scanner = self.scanner.as_mut()?;
+        Some(match scanner.next() {
+            Ok((token, start, end)) => {
+                if let Token::StreamEnd = token {
+                    self.scanner = None;
+                }
+                Ok((token, Location::from_mark(start), Location::from_mark(end)))
+            }
+            Err(err) => {
+                self.scanner = None;
+                Err(err.into())
+            }
+        })
+    }
+}
+
+impl<'input> Debug for Scanner<'input> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("Scanner").finish_non_exhaustive()
+    }
+}
+
+/// Writes a sequence of events as YAML text.
+///
+/// The events must form a well-formed stream: a [`Event::StreamStart`],
+/// then documents each holding one node, then [`Event::StreamEnd`].
+/// Anything else is reported as an error by [`emit`](Emitter::emit).
+pub struct Emitter<W> {
+    emitter: emitter::Emitter<W>,
+}
+
+impl<W> Emitter<W>
+where
+    W: io::Write,
+{
+    /// Creates an emitter that writes to `writer`.
+    ///
+    /// Fails if libyaml is unable to allocate the emitter.
+    pub fn new(writer: W) -> Result<Self> {
+        let emitter = emitter::Emitter::new(writer, 0)?;
+        Ok(Emitter { emitter })
+    }
+
+    /// Writes one event. Output may be held back until later events show how
+    /// it should be formatted.
+    pub fn emit(&mut self, event: Event) -> Result<()> {
+        self.emitter.emit(event)?;
+        Ok(())
+    }
+
+    /// Writes out any output held back so far, then calls
+    /// [`.flush()`](io::Write::flush) on the underlying writer.
+    pub fn flush(&mut self) -> Result<()> {
+        self.emitter.flush()?;
+        Ok(())
+    }
+
+    /// Unwraps the underlying writer, after writing out any output held back
+    /// so far.
+    pub fn into_inner(self) -> Result<W> {
+        Ok(self.emitter.into_inner()?)
+    }
+
+    /// Unwraps the underlying writer after trying to write out any output
+    /// held back so far. Unlike [`into_inner`](Emitter::into_inner), the
+    /// writer is returned even if that fails, together with the error.
+    pub fn into_inner_parts(self) -> (W, Result<()>) {
+        let (writer, result) = self.emitter.into_inner_parts();
+        (writer, result.map_err(Into::into))
+    }
+}
+
+impl<W> Debug for Emitter<W> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.debug_struct("Emitter").finish_non_exhaustive()
+    }
+}
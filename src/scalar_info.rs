@@ -0,0 +1,241 @@
+use crate::error::Error;
+use crate::libyaml::event::ScalarStyle;
+use serde::de::value::StrDeserializer;
+use serde::de::{self, DeserializeSeed, Deserializer, MapAccess, Visitor};
+use serde::ser::{Serialize, Serializer};
+use std::fmt;
+use std::marker::PhantomData;
+
+// The struct name by which `ScalarInfo` asks the deserializer for a scalar's
+// style and text, and the fields they arrive in, in order.
+pub(crate) const NAME: &str = "$serde_yaml::ScalarInfo";
+pub(crate) const STYLE: &str = "$serde_yaml::ScalarInfo::style";
+pub(crate) const TEXT: &str = "$serde_yaml::ScalarInfo::text";
+pub(crate) const VALUE: &str = "$serde_yaml::ScalarInfo::value";
+const FIELDS: &[&str] = &[STYLE, TEXT, VALUE];
+
+/// A scalar deserialized into `T`, along with how it was written.
+///
+/// This tells `port: "8080"` apart from `port: 8080`, which deserialize to the
+/// same `String`. Deserializing anything other than a scalar into a
+/// `ScalarInfo` is an error. An alias reports the style and text of the scalar
+/// it refers to.
+///
+/// The style is only known to this crate's [`Deserializer`](crate::Deserializer),
+/// so deserializing a `ScalarInfo` from a [`Value`](crate::Value) or from
+/// another format fails. Serializing one serializes just the value.
+///
+/// ```
+/// # use serde_derive::Deserialize;
+/// use serde::Deserialize;
+/// use serde_yaml::raw::ScalarStyle;
+/// use serde_yaml::ScalarInfo;
+///
+/// #[derive(Deserialize)]
+/// struct Listen {
+///     port: ScalarInfo<String>,
+/// }
+///
+/// // A plain port is a number, and a quoted one names a service to look up.
+/// fn port(listen: &Listen) -> Result<u16, String> {
+///     match listen.port.style() {
+///         ScalarStyle::Plain => listen.port.parse().map_err(|err| format!("{}", err)),
+///         _ => match listen.port.as_str() {
+///             "http-alt" => Ok(8080),
+///             name => Err(format!("unknown service {:?}", name)),
+///         },
+///     }
+/// }
+///
+/// let listen: Listen = serde_yaml::from_str("port: 8080").unwrap();
+/// assert_eq!(port(&listen), Ok(8080));
+///
+/// let listen: Listen = serde_yaml::from_str("port: \"8080\"").unwrap();
+/// assert_eq!(listen.port.style(), ScalarStyle::DoubleQuoted);
+/// assert_eq!(port(&listen), Err("unknown service \"8080\"".to_owned()));
+/// ```
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
+pub struct ScalarInfo<T> {
+    value: T,
+    style: ScalarStyle,
+    text: String,
+}
+
+impl<T> ScalarInfo<T> {
+    /// How the scalar was written: plain, quoted, or as a block scalar.
+    pub fn style(&self) -> ScalarStyle {
+        self.style
+    }
+
+    /// The scalar's contents after quotes and escapes are processed, but
+    /// before being resolved to a type, like `0x1F` for `0x1F` or `it's` for
+    /// `'it''s'`.
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    /// The deserialized value.
+    pub fn get_ref(&self) -> &T {
+        &self.value
+    }
+
+    /// The deserialized value, mutably.
+    pub fn get_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+
+    /// Discards the style and text, keeping the value.
+    pub fn into_inner(self) -> T {
+        self.value
+    }
+}
+
+impl<T> std::ops::Deref for ScalarInfo<T> {
+    type Target = T;
+
+    fn deref(&self) -> &T {
+        &self.value
+    }
+}
+
+impl<T> std::ops::DerefMut for ScalarInfo<T> {
+    fn deref_mut(&mut self) -> &mut T {
+        &mut self.value
+    }
+}
+
+impl<T> Serialize for ScalarInfo<T>
+where
+    T: Serialize,
+{
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T> de::Deserialize<'de> for ScalarInfo<T>
+where
+    T: de::Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_struct(NAME, FIELDS, ScalarInfoVisitor(PhantomData))
+    }
+}
+
+struct ScalarInfoVisitor<T>(PhantomData<T>);
+
+impl<'de, T> Visitor<'de> for ScalarInfoVisitor<T>
+where
+    T: de::Deserialize<'de>,
+{
+    type Value = ScalarInfo<T>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a YAML scalar")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let style = match map.next_entry::<String, String>()? {
+            Some((key, style)) if key == STYLE => match parse_style(&style) {
+                Some(style) => style,
+                None => return Err(de::Error::invalid_value(de::Unexpected::Str(&style), &self)),
+            },
+            _ => return Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        };
+        let text = match map.next_entry::<String, String>()? {
+            Some((key, text)) if key == TEXT => text,
+            _ => return Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        };
+        match map.next_key::<String>()? {
+            Some(key) if key == VALUE => {}
+            _ => return Err(de::Error::invalid_type(de::Unexpected::Map, &self)),
+        }
+        let value = map.next_value()?;
+        Ok(ScalarInfo { value, style, text })
+    }
+}
+
+// The entries that one of this crate's deserializers hands to
+// `ScalarInfoVisitor`: the style, the text, then the scalar itself.
+pub(crate) struct ScalarInfoAccess<D> {
+    style: ScalarStyle,
+    text: String,
+    de: Option<D>,
+    step: usize,
+}
+
+impl<D> ScalarInfoAccess<D> {
+    pub(crate) fn new(style: ScalarStyle, text: String, de: D) -> Self {
+        ScalarInfoAccess {
+            style,
+            text,
+            de: Some(de),
+            step: 0,
+        }
+    }
+}
+
+impl<'de, D> MapAccess<'de> for ScalarInfoAccess<D>
+where
+    D: Deserializer<'de, Error = Error>,
+{
+    type Error = Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Error>
+    where
+        K: DeserializeSeed<'de>,
+    {
+        match FIELDS.get(self.step) {
+            Some(field) => seed
+                .deserialize(StrDeserializer::<Error>::new(field))
+                .map(Some),
+            None => Ok(None),
+        }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Error>
+    where
+        V: DeserializeSeed<'de>,
+    {
+        self.step += 1;
+        match self.step {
+            1 => seed.deserialize(StrDeserializer::<Error>::new(style_name(self.style))),
+            2 => seed.deserialize(StrDeserializer::<Error>::new(&self.text)),
+            _ => match self.de.take() {
+                Some(de) => seed.deserialize(de),
+                None => Err(de::Error::custom("value is missing")),
+            },
+        }
+    }
+}
+
+// How the style of a scalar is named in the entries of a `ScalarInfoAccess`.
+fn style_name(style: ScalarStyle) -> &'static str {
+    match style {
+        ScalarStyle::Any | ScalarStyle::Plain => "plain",
+        ScalarStyle::SingleQuoted => "single-quoted",
+        ScalarStyle::DoubleQuoted => "double-quoted",
+        ScalarStyle::Literal => "literal",
+        ScalarStyle::Folded => "folded",
+    }
+}
+
+pub(crate) fn parse_style(name: &str) -> Option<ScalarStyle> {
+    match name {
+        "plain" => Some(ScalarStyle::Plain),
+        "single-quoted" => Some(ScalarStyle::SingleQuoted),
+        "double-quoted" => Some(ScalarStyle::DoubleQuoted),
+        "literal" => Some(ScalarStyle::Literal),
+        "folded" => Some(ScalarStyle::Folded),
+        _ => None,
+    }
+}
@@ -0,0 +1,298 @@
+//! A YAML sequence.
+
+use crate::value::Index;
+use crate::Value;
+use serde::{Deserialize, Deserializer, Serialize};
+use std::ops::{Deref, DerefMut};
+use std::slice;
+use std::vec;
+
+/// A YAML sequence in which the elements are `serde_yaml::Value`.
+///
+/// Dereferences to `[Value]`, so all of the usual slice methods are available.
+/// Use [`Sequence::from`] and [`Vec::from`] to convert to and from
+/// `Vec<Value>`.
+#[derive(Clone, Default, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Sequence {
+    vec: Vec<Value>,
+}
+
+impl Sequence {
+    /// Creates an empty YAML sequence.
+    #[inline]
+    pub const fn new() -> Self {
+        Sequence { vec: Vec::new() }
+    }
+
+    /// Creates an empty YAML sequence with the given initial capacity.
+    #[inline]
+    pub fn with_capacity(capacity: usize) -> Self {
+        Sequence {
+            vec: Vec::with_capacity(capacity),
+        }
+    }
+
+    /// Returns the number of elements the sequence can hold without
+    /// reallocating.
+    #[inline]
+    pub fn capacity(&self) -> usize {
+        self.vec.capacity()
+    }
+
+    /// Reserves capacity for at least `additional` more elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the new capacity overflows `usize`.
+    #[inline]
+    pub fn reserve(&mut self, additional: usize) {
+        self.vec.reserve(additional);
+    }
+
+    /// Shrinks the capacity of the sequence as much as possible.
+    #[inline]
+    pub fn shrink_to_fit(&mut self) {
+        self.vec.shrink_to_fit();
+    }
+
+    /// Returns the element at `index`. A negative index counts from the end,
+    /// so `-1` is the last element.
+    ///
+    /// The slice method `get` is still there for a `usize` index or a range.
+    ///
+    /// ```
+    /// # use serde_yaml::{Sequence, Value};
+    /// let seq: Sequence = serde_yaml::from_str("[a, b, c]").unwrap();
+    /// assert_eq!(seq.get_signed(0).unwrap(), "a");
+    /// assert_eq!(seq.get_signed(-1).unwrap(), "c");
+    /// assert_eq!(seq.get_signed(-4), None);
+    /// assert_eq!(seq.get(1..).unwrap(), ["b", "c"]);
+    /// ```
+    #[inline]
+    pub fn get_signed(&self, index: isize) -> Option<&Value> {
+        self.vec.get(self.resolve(index)?)
+    }
+
+    /// Returns a mutable reference to the element at `index`. A negative index
+    /// counts from the end, so `-1` is the last element.
+    #[inline]
+    pub fn get_signed_mut(&mut self, index: isize) -> Option<&mut Value> {
+        let index = self.resolve(index)?;
+        self.vec.get_mut(index)
+    }
+
+    fn resolve(&self, index: isize) -> Option<usize> {
+        if index >= 0 {
+            Some(index as usize)
+        } else {
+            self.vec.len().checked_sub(index.unsigned_abs())
+        }
+    }
+
+    /// Appends an element to the end of the sequence.
+    #[inline]
+    pub fn push(&mut self, value: Value) {
+        self.vec.push(value);
+    }
+
+    /// Removes the last element and returns it, or `None` if the sequence is
+    /// empty.
+    #[inline]
+    pub fn pop(&mut self) -> Option<Value> {
+        self.vec.pop()
+    }
+
+    /// Inserts an element at position `index`, shifting all elements after it
+    /// to the right.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index > len`.
+    #[inline]
+    pub fn insert(&mut self, index: usize, value: Value) {
+        self.vec.insert(index, value);
+    }
+
+    /// Removes and returns the element at position `index`, shifting all
+    /// elements after it to the left.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `index` is out of bounds.
+    #[inline]
+    pub fn remove(&mut self, index: usize) -> Value {
+        self.vec.remove(index)
+    }
+
+    /// Shortens the sequence, keeping the first `len` elements.
+    #[inline]
+    pub fn truncate(&mut self, len: usize) {
+        self.vec.truncate(len);
+    }
+
+    /// Removes all elements.
+    #[inline]
+    pub fn clear(&mut self) {
+        self.vec.clear();
+    }
+
+    /// Retains only the elements for which `keep` returns true.
+    #[inline]
+    pub fn retain<F>(&mut self, keep: F)
+    where
+        F: FnMut(&Value) -> bool,
+    {
+        self.vec.retain(keep);
+    }
+
+    /// Extracts a slice containing the entire sequence.
+    #[inline]
+    pub fn as_slice(&self) -> &[Value] {
+        &self.vec
+    }
+
+    /// Extracts a mutable slice containing the entire sequence.
+    #[inline]
+    pub fn as_mut_slice(&mut self) -> &mut [Value] {
+        &mut self.vec
+    }
+
+    /// Yields `element[key]` for each element of the sequence, in order.
+    /// Elements that are not mappings, or do not contain the key, yield
+    /// `Value::Null`, the same as indexing a `Value` would.
+    ///
+    /// ```
+    /// # use serde_yaml::{Sequence, Value};
+    /// let seq: Sequence = serde_yaml::from_str("[{name: a}, {name: b}, 1]").unwrap();
+    /// let names: Vec<&Value> = seq.iter_mapping_values("name").collect();
+    /// assert_eq!(names, [&Value::from("a"), &Value::from("b"), &Value::Null]);
+    /// ```
+    pub fn iter_mapping_values<'a, I>(&'a self, key: I) -> impl Iterator<Item = &'a Value> + 'a
+    where
+        I: Index + 'a,
+    {
+        static NULL: Value = Value::Null;
+        self.vec
+            .iter()
+            .map(move |element| element.get(&key).unwrap_or(&NULL))
+    }
+}
+
+impl Deref for Sequence {
+    type Target = [Value];
+
+    #[inline]
+    fn deref(&self) -> &[Value] {
+        &self.vec
+    }
+}
+
+impl DerefMut for Sequence {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut [Value] {
+        &mut self.vec
+    }
+}
+
+impl AsRef<[Value]> for Sequence {
+    #[inline]
+    fn as_ref(&self) -> &[Value] {
+        &self.vec
+    }
+}
+
+impl From<Vec<Value>> for Sequence {
+    #[inline]
+    fn from(vec: Vec<Value>) -> Self {
+        Sequence { vec }
+    }
+}
+
+impl From<Sequence> for Vec<Value> {
+    #[inline]
+    fn from(sequence: Sequence) -> Self {
+        sequence.vec
+    }
+}
+
+impl FromIterator<Value> for Sequence {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Value>>(iter: I) -> Self {
+        Sequence {
+            vec: Vec::from_iter(iter),
+        }
+    }
+}
+
+impl Extend<Value> for Sequence {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Value>>(&mut self, iter: I) {
+        self.vec.extend(iter);
+    }
+}
+
+impl IntoIterator for Sequence {
+    type Item = Value;
+    type IntoIter = vec::IntoIter<Value>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.vec.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a Sequence {
+    type Item = &'a Value;
+    type IntoIter = slice::Iter<'a, Value>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.vec.iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a mut Sequence {
+    type Item = &'a mut Value;
+    type IntoIter = slice::IterMut<'a, Value>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.vec.iter_mut()
+    }
+}
+
+impl Serialize for Sequence {
+    #[inline]
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        self.vec.serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for Sequence {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        // Serde's `Vec` reserves at most a small amount from the size hint
+        // and grows from there, whatever the hint claims.
+        Vec::deserialize(deserializer).map(Sequence::from)
+    }
+}
+
+impl PartialEq<[Value]> for Sequence {
+    fn eq(&self, other: &[Value]) -> bool {
+        self.vec == other
+    }
+}
+
+impl PartialEq<Vec<Value>> for Sequence {
+    fn eq(&self, other: &Vec<Value>) -> bool {
+        self.vec == *other
+    }
+}
+
+impl PartialEq<Sequence> for Vec<Value> {
+    fn eq(&self, other: &Sequence) -> bool {
+        *self == other.vec
+    }
+}
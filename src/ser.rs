@@ -2,12 +2,20 @@
 //!
 //! This module provides YAML serialization with the type `Serializer`.
 
+use crate::document_value::DocumentValue;
 use crate::error::{self, Error, ErrorImpl};
-use crate::libyaml;
-use crate::libyaml::emitter::{Emitter, Event, Mapping, Scalar, ScalarStyle, Sequence};
+use crate::libyaml::emitter::Emitter;
+use crate::libyaml::event::{
+    CollectionStyle, DocumentEnd, DocumentStart, Event, MappingStart, Scalar, ScalarStyle,
+    SequenceStart, Tag,
+};
+use crate::value::styled;
 use crate::value::tagged::{self, MaybeTag};
+use crate::verify::Verifier;
+use crate::yaml11;
 use serde::de::Visitor;
 use serde::ser::{self, Serializer as _};
+use std::borrow::{Borrow, Cow};
 use std::fmt::{self, Display};
 use std::io;
 use std::mem;
@@ -18,6 +26,15 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 
 /// A structure for serializing Rust values into YAML.
 ///
+/// # Buffered output
+///
+/// A serializer built with [`SerializerBuilder::buffer_capacity`] holds on to
+/// output until its buffer fills. Call [`flush`](Serializer::flush) or
+/// [`into_inner`](Serializer::into_inner) when done: **dropping the
+/// serializer discards whatever is still buffered**, unlike dropping a
+/// [`BufWriter`](std::io::BufWriter). Without a buffer capacity each
+/// document reaches the writer as soon as it is complete.
+///
 /// # Example
 ///
 /// ```
@@ -27,7 +44,7 @@ type Result<T, E = Error> = std::result::Result<T, E>;
 ///
 /// fn main() -> Result<()> {
 ///     let mut buffer = Vec::new();
-///     let mut ser = serde_yaml::Serializer::new(&mut buffer);
+///     let mut ser = serde_yaml::Serializer::new(&mut buffer)?;
 ///
 ///     let mut object = BTreeMap::new();
 ///     object.insert("k", 107);
@@ -44,6 +61,164 @@ pub struct Serializer<W> {
     depth: usize,
     state: State,
     emitter: Emitter<W>,
+    verifier: Option<Verifier>,
+    // The markers and directives for the next document, if not the default
+    // of none.
+    framing: Option<(DocumentStart, DocumentEnd)>,
+    prefix_indent: usize,
+    // The style a `StyledValue` asked for the string being serialized.
+    string_style: Option<ScalarStyle>,
+}
+
+/// Configures a [`Serializer`] before it is created.
+///
+/// ```
+/// # fn main() -> serde_yaml::Result<()> {
+/// use serde::Serialize;
+///
+/// let mut ser = serde_yaml::SerializerBuilder::new()
+///     .buffer_capacity(64 * 1024)
+///     .build(Vec::new())?;
+/// for i in 0..3 {
+///     i.serialize(&mut ser)?;
+/// }
+/// let buffer = ser.into_inner()?;
+/// assert_eq!(buffer, b"0\n--- 1\n--- 2\n");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct SerializerBuilder {
+    buffer_capacity: usize,
+    verify_roundtrip: bool,
+    prefix_indent: usize,
+}
+
+impl SerializerBuilder {
+    /// Creates a builder with the default options.
+    pub fn new() -> Self {
+        SerializerBuilder::default()
+    }
+
+    /// Collects up to `capacity` bytes of output before writing them to the
+    /// underlying `io::Write` object.
+    ///
+    /// By default there is no buffering beyond libyaml's own, which writes
+    /// whenever it has accumulated 16 KiB and at the end of every document.
+    /// A buffer avoids one small write per document when serializing a
+    /// stream of many small documents to an unbuffered writer such as a
+    /// `File`.
+    ///
+    /// The capacity only changes how the output is split into writes, never
+    /// the output itself, which is also the same however many bytes each
+    /// call to `write` accepts.
+    ///
+    /// Buffered output is only written by [`Serializer::flush`] and
+    /// [`Serializer::into_inner`], so one of these must be called once
+    /// serialization is done. Unlike [`BufWriter`](std::io::BufWriter),
+    /// dropping the serializer does not write out the buffer but discards it.
+    pub fn buffer_capacity(mut self, capacity: usize) -> Self {
+        self.buffer_capacity = capacity;
+        self
+    }
+
+    /// Checks that the YAML written for each document parses back to what was
+    /// serialized, and fails with an error if it does not.
+    ///
+    /// The output of each document is parsed again as soon as the document is
+    /// complete, and compared against the structure, tags and scalar values
+    /// that were written. A scalar must also still resolve to the same kind of
+    /// value, so a string that comes back as a number or a boolean because it
+    /// was not quoted is caught. This is a check on this crate and libyaml,
+    /// not on the value being serialized.
+    ///
+    /// Off by default. It roughly doubles the cost of serialization, and is
+    /// meant for tests and canary deployments rather than routine use.
+    ///
+    /// ```
+    /// # fn main() -> serde_yaml::Result<()> {
+    /// use serde::Serialize;
+    ///
+    /// let mut ser = serde_yaml::SerializerBuilder::new()
+    ///     .verify_roundtrip(true)
+    ///     .build(Vec::new())?;
+    /// vec!["---", "true", "0x1"].serialize(&mut ser)?;
+    /// let buffer = ser.into_inner()?;
+    /// assert_eq!(buffer, b"- '---'\n- 'true'\n- '0x1'\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn verify_roundtrip(mut self, verify: bool) -> Self {
+        self.verify_roundtrip = verify;
+        self
+    }
+
+    /// Indents every line of output by `indent` spaces, for splicing it into
+    /// a larger YAML file below a key at a lower indentation.
+    ///
+    /// Empty lines are left empty. Block scalars keep their meaning, since
+    /// their content is indented along with the node they belong to. The one
+    /// exception would be a block scalar with an indentation indicator as the
+    /// whole document, whose indicator does not move with it, so a document
+    /// that is only a string beginning with a space or line break is written
+    /// in double quotes instead.
+    ///
+    /// This is meant for a single document: the `---` before any document
+    /// after the first is indented too, and no longer separates documents.
+    ///
+    /// ```
+    /// # fn main() -> serde_yaml::Result<()> {
+    /// use serde::Serialize;
+    /// use std::collections::BTreeMap;
+    ///
+    /// let mut ser = serde_yaml::SerializerBuilder::new()
+    ///     .prefix_indent(4)
+    ///     .build(Vec::new())?;
+    /// let mut fragment = BTreeMap::new();
+    /// fragment.insert("script", "set -e\n\nmake\n");
+    /// fragment.insert("image", "rust");
+    /// fragment.serialize(&mut ser)?;
+    /// let fragment = String::from_utf8(ser.into_inner()?).unwrap();
+    ///
+    /// let yaml = format!("jobs:\n  build:\n{}", fragment);
+    /// assert_eq!(
+    ///     yaml,
+    ///     "jobs:\n  build:\n    image: rust\n    script: |\n      set -e\n\n      make\n",
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn prefix_indent(mut self, indent: usize) -> Self {
+        self.prefix_indent = indent;
+        self
+    }
+
+    /// Creates a serializer with these options that writes to `writer`.
+    ///
+    /// Fails if libyaml is unable to allocate the emitter.
+    pub fn build<W>(self, writer: W) -> Result<Serializer<W>>
+    where
+        W: io::Write,
+    {
+        let mut emitter = Emitter::new(writer, self.buffer_capacity)?;
+        emitter.prefix_lines(self.prefix_indent);
+        emitter.emit(Event::StreamStart)?;
+        let verifier = if self.verify_roundtrip {
+            emitter.copy_output();
+            Some(Verifier::new())
+        } else {
+            None
+        };
+        Ok(Serializer {
+            depth: 0,
+            state: State::NothingInParticular,
+            emitter,
+            verifier,
+            framing: None,
+            prefix_indent: self.prefix_indent,
+            string_style: None,
+        })
+    }
 }
 
 enum State {
@@ -59,37 +234,101 @@ where
     W: io::Write,
 {
     /// Creates a new YAML serializer.
-    pub fn new(writer: W) -> Self {
-        let mut emitter = Emitter::new(writer);
-        emitter.emit(Event::StreamStart).unwrap();
-        Serializer {
-            depth: 0,
-            state: State::NothingInParticular,
-            emitter,
-        }
+    ///
+    /// Fails if libyaml is unable to allocate the emitter.
+    pub fn new(writer: W) -> Result<Self> {
+        SerializerBuilder::new().build(writer)
     }
 
-    /// Calls [`.flush()`](io::Write::flush) on the underlying `io::Write`
-    /// object.
+    /// Writes out any buffered output, then calls
+    /// [`.flush()`](io::Write::flush) on the underlying `io::Write` object.
     pub fn flush(&mut self) -> Result<()> {
         self.emitter.flush()?;
         Ok(())
     }
 
-    /// Unwrap the underlying `io::Write` object from the `Serializer`.
-    pub fn into_inner(mut self) -> Result<W> {
-        self.emitter.emit(Event::StreamEnd)?;
-        self.emitter.flush()?;
-        Ok(self.emitter.into_inner())
+    /// The number of bytes of YAML this serializer has produced for its
+    /// writer so far.
+    ///
+    /// Output still held in the buffer of a [`SerializerBuilder::buffer_capacity`]
+    /// is counted, since [`flush`](Serializer::flush) or
+    /// [`into_inner`](Serializer::into_inner) writes it as it is. Between
+    /// documents the count is exact, including the newline that ends the
+    /// previous document, so reading it before and after serializing each
+    /// document gives where that document sits in the output.
+    ///
+    /// ```
+    /// # fn main() -> serde_yaml::Result<()> {
+    /// use serde::Serialize;
+    ///
+    /// let mut ser = serde_yaml::SerializerBuilder::new()
+    ///     .buffer_capacity(4096)
+    ///     .build(Vec::new())?;
+    /// let mut offsets = Vec::new();
+    /// for document in ["first", "second", "third"] {
+    ///     offsets.push(ser.bytes_written());
+    ///     document.serialize(&mut ser)?;
+    /// }
+    /// let total = ser.bytes_written();
+    /// let buffer = ser.into_inner()?;
+    ///
+    /// assert_eq!(buffer, b"first\n--- second\n--- third\n");
+    /// assert_eq!(offsets, [0, 6, 17]);
+    /// assert_eq!(total, buffer.len() as u64);
+    /// assert_eq!(&buffer[6..17], b"--- second\n");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn bytes_written(&self) -> u64 {
+        self.emitter.bytes_written()
+    }
+
+    /// Unwrap the underlying `io::Write` object from the `Serializer`, after
+    /// writing out any buffered output.
+    pub fn into_inner(self) -> Result<W> {
+        let (writer, result) = self.into_inner_parts();
+        result.map(|()| writer)
+    }
+
+    /// Unwrap the underlying `io::Write` object from the `Serializer` after
+    /// trying to end the YAML stream and write out any buffered output.
+    ///
+    /// Unlike [`into_inner`](Serializer::into_inner), the writer is returned
+    /// even if that fails, together with the error, so whatever output did
+    /// reach it is not lost along with the serializer.
+    pub fn into_inner_parts(mut self) -> (W, Result<()>) {
+        let result = self.emitter.emit(Event::StreamEnd);
+        let (writer, flushed) = self.emitter.into_inner_parts();
+        (writer, result.and(flushed).map_err(Into::into))
+    }
+
+    fn emit(&mut self, event: Event) -> Result<()> {
+        if let Some(verifier) = &mut self.verifier {
+            verifier.record(&event);
+        }
+        self.emitter.emit(event)?;
+        Ok(())
     }
 
-    fn emit_scalar(&mut self, mut scalar: Scalar) -> Result<()> {
+    fn emit_scalar(&mut self, value: &str, mut style: ScalarStyle) -> Result<()> {
         self.flush_mapping_start()?;
-        if let Some(tag) = self.take_tag() {
-            scalar.tag = Some(tag);
+        if self.prefix_indent > 0
+            && self.depth == 0
+            && matches!(style, ScalarStyle::Literal | ScalarStyle::Folded)
+            && value.starts_with([' ', '\n'])
+        {
+            // The indentation indicator libyaml would write is relative to
+            // the start of the line, not to the prefix.
+            style = ScalarStyle::DoubleQuoted;
         }
+        let scalar = Scalar {
+            anchor: None,
+            tag: self.take_tag(),
+            value: Cow::Borrowed(value.as_bytes()),
+            style,
+        };
         self.value_start()?;
-        self.emitter.emit(Event::Scalar(scalar))?;
+        self.emit(Event::Scalar(scalar))?;
         self.value_end()
     }
 
@@ -97,12 +336,16 @@ where
         self.flush_mapping_start()?;
         self.value_start()?;
         let tag = self.take_tag();
-        self.emitter.emit(Event::SequenceStart(Sequence { tag }))?;
+        self.emit(Event::SequenceStart(SequenceStart {
+            anchor: None,
+            tag,
+            style: CollectionStyle::Any,
+        }))?;
         Ok(())
     }
 
     fn emit_sequence_end(&mut self) -> Result<()> {
-        self.emitter.emit(Event::SequenceEnd)?;
+        self.emit(Event::SequenceEnd)?;
         self.value_end()
     }
 
@@ -110,18 +353,30 @@ where
         self.flush_mapping_start()?;
         self.value_start()?;
         let tag = self.take_tag();
-        self.emitter.emit(Event::MappingStart(Mapping { tag }))?;
+        self.emit(Event::MappingStart(MappingStart {
+            anchor: None,
+            tag,
+            style: CollectionStyle::Any,
+        }))?;
         Ok(())
     }
 
     fn emit_mapping_end(&mut self) -> Result<()> {
-        self.emitter.emit(Event::MappingEnd)?;
+        self.emit(Event::MappingEnd)?;
         self.value_end()
     }
 
     fn value_start(&mut self) -> Result<()> {
         if self.depth == 0 {
-            self.emitter.emit(Event::DocumentStart)?;
+            let start = match &self.framing {
+                Some((start, _end)) => start.clone(),
+                None => DocumentStart {
+                    implicit: true,
+                    version: None,
+                    tag_directives: Vec::new(),
+                },
+            };
+            self.emit(Event::DocumentStart(start))?;
         }
         self.depth += 1;
         Ok(())
@@ -130,18 +385,25 @@ where
     fn value_end(&mut self) -> Result<()> {
         self.depth -= 1;
         if self.depth == 0 {
-            self.emitter.emit(Event::DocumentEnd)?;
+            let end = match self.framing.take() {
+                Some((_start, end)) => end,
+                None => DocumentEnd { implicit: true },
+            };
+            self.emit(Event::DocumentEnd(end))?;
+            if let Some(verifier) = &mut self.verifier {
+                verifier.check(&self.emitter.take_output_copy())?;
+            }
         }
         Ok(())
     }
 
-    fn take_tag(&mut self) -> Option<String> {
+    fn take_tag(&mut self) -> Option<Tag> {
         let state = mem::replace(&mut self.state, State::NothingInParticular);
         if let State::FoundTag(mut tag) = state {
             if !tag.starts_with('!') {
                 tag.insert(0, '!');
             }
-            Some(tag)
+            Some(Tag::from(tag))
         } else {
             self.state = state;
             None
@@ -175,193 +437,85 @@ where
     type SerializeStructVariant = Self;
 
     fn serialize_bool(self, v: bool) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: if v { "true" } else { "false" },
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(if v { "true" } else { "false" }, ScalarStyle::Plain)
     }
 
     fn serialize_i8(self, v: i8) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_i16(self, v: i16) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_i32(self, v: i32) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_i64(self, v: i64) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_i128(self, v: i128) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_u8(self, v: u8) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_u16(self, v: u16) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_u32(self, v: u32) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_u64(self, v: u64) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_u128(self, v: u128) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: itoa::Buffer::new().format(v),
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar(itoa::Buffer::new().format(v), ScalarStyle::Plain)
     }
 
     fn serialize_f32(self, v: f32) -> Result<()> {
         let mut buffer = ryu::Buffer::new();
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: match v.classify() {
+        self.emit_scalar(
+            match v.classify() {
                 num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
                 num::FpCategory::Infinite => "-.inf",
                 num::FpCategory::Nan => ".nan",
                 _ => buffer.format_finite(v),
             },
-            style: ScalarStyle::Plain,
-        })
+            ScalarStyle::Plain,
+        )
     }
 
     fn serialize_f64(self, v: f64) -> Result<()> {
         let mut buffer = ryu::Buffer::new();
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: match v.classify() {
+        self.emit_scalar(
+            match v.classify() {
                 num::FpCategory::Infinite if v.is_sign_positive() => ".inf",
                 num::FpCategory::Infinite => "-.inf",
                 num::FpCategory::Nan => ".nan",
                 _ => buffer.format_finite(v),
             },
-            style: ScalarStyle::Plain,
-        })
+            ScalarStyle::Plain,
+        )
     }
 
     fn serialize_char(self, value: char) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: value.encode_utf8(&mut [0u8; 4]),
-            style: ScalarStyle::SingleQuoted,
-        })
+        self.emit_scalar(value.encode_utf8(&mut [0u8; 4]), ScalarStyle::SingleQuoted)
     }
 
     fn serialize_str(self, value: &str) -> Result<()> {
-        struct InferScalarStyle;
-
-        impl<'de> Visitor<'de> for InferScalarStyle {
-            type Value = ScalarStyle;
-
-            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
-                formatter.write_str("I wonder")
-            }
-
-            fn visit_bool<E>(self, _v: bool) -> Result<Self::Value, E> {
-                Ok(ScalarStyle::SingleQuoted)
-            }
-
-            fn visit_i64<E>(self, _v: i64) -> Result<Self::Value, E> {
-                Ok(ScalarStyle::SingleQuoted)
-            }
-
-            fn visit_i128<E>(self, _v: i128) -> Result<Self::Value, E> {
-                Ok(ScalarStyle::SingleQuoted)
-            }
-
-            fn visit_u64<E>(self, _v: u64) -> Result<Self::Value, E> {
-                Ok(ScalarStyle::SingleQuoted)
-            }
-
-            fn visit_u128<E>(self, _v: u128) -> Result<Self::Value, E> {
-                Ok(ScalarStyle::SingleQuoted)
-            }
-
-            fn visit_f64<E>(self, _v: f64) -> Result<Self::Value, E> {
-                Ok(ScalarStyle::SingleQuoted)
-            }
-
-            fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
-                Ok(if crate::de::digits_but_not_number(v) {
-                    ScalarStyle::SingleQuoted
-                } else {
-                    ScalarStyle::Any
-                })
-            }
-
-            fn visit_unit<E>(self) -> Result<Self::Value, E> {
-                Ok(ScalarStyle::SingleQuoted)
-            }
-        }
-
-        let style = if value.contains('\n') {
-            ScalarStyle::Literal
-        } else {
-            let result = crate::de::visit_untagged_scalar(
-                InferScalarStyle,
-                value,
-                None,
-                libyaml::parser::ScalarStyle::Plain,
-            );
-            result.unwrap_or(ScalarStyle::Any)
+        let style = match self.string_style.take() {
+            Some(style) => restyle_string(value, style),
+            None => infer_scalar_style(value),
         };
-
-        self.emit_scalar(Scalar {
-            tag: None,
-            value,
-            style,
-        })
+        self.emit_scalar(value, style)
     }
 
     fn serialize_bytes(self, _value: &[u8]) -> Result<()> {
@@ -369,11 +523,7 @@ where
     }
 
     fn serialize_unit(self) -> Result<()> {
-        self.emit_scalar(Scalar {
-            tag: None,
-            value: "null",
-            style: ScalarStyle::Plain,
-        })
+        self.emit_scalar("null", ScalarStyle::Plain)
     }
 
     fn serialize_unit_struct(self, _name: &'static str) -> Result<()> {
@@ -389,11 +539,14 @@ where
         self.serialize_str(variant)
     }
 
-    fn serialize_newtype_struct<T>(self, _name: &'static str, value: &T) -> Result<()>
+    fn serialize_newtype_struct<T>(self, name: &'static str, value: &T) -> Result<()>
     where
         T: ?Sized + ser::Serialize,
     {
-        value.serialize(self)
+        self.string_style = styled::style_for_name(name);
+        let result = value.serialize(&mut *self);
+        self.string_style = None;
+        result
     }
 
     fn serialize_newtype_variant<T>(
@@ -406,10 +559,11 @@ where
     where
         T: ?Sized + ser::Serialize,
     {
+        self.flush_mapping_start()?;
         if let State::FoundTag(_) = self.state {
             return Err(error::new(ErrorImpl::SerializeNestedEnum));
         }
-        self.state = State::FoundTag(variant.to_owned());
+        self.state = State::FoundTag(variant_tag(variant));
         value.serialize(&mut *self)
     }
 
@@ -450,15 +604,19 @@ where
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeTupleVariant> {
+        self.flush_mapping_start()?;
         if let State::FoundTag(_) = self.state {
             return Err(error::new(ErrorImpl::SerializeNestedEnum));
         }
-        self.state = State::FoundTag(variant.to_owned());
+        self.state = State::FoundTag(variant_tag(variant));
         self.emit_sequence_start()?;
         Ok(self)
     }
 
     fn serialize_map(self, len: Option<usize>) -> Result<Self::SerializeMap> {
+        // A single-entry map whose start is still held back, because this one
+        // is its key, is not a tag.
+        self.flush_mapping_start()?;
         if len == Some(1) {
             self.state = if let State::FoundTag(_) = self.state {
                 self.emit_mapping_start()?;
@@ -484,10 +642,11 @@ where
         variant: &'static str,
         _len: usize,
     ) -> Result<Self::SerializeStructVariant> {
+        self.flush_mapping_start()?;
         if let State::FoundTag(_) = self.state {
             return Err(error::new(ErrorImpl::SerializeNestedEnum));
         }
-        self.state = State::FoundTag(variant.to_owned());
+        self.state = State::FoundTag(variant_tag(variant));
         self.emit_mapping_start()?;
         Ok(self)
     }
@@ -680,6 +839,15 @@ where
     }
 }
 
+// The tag for an enum variant, allocated at its final size so that take_tag
+// does not need to grow it.
+fn variant_tag(variant: &str) -> String {
+    let mut tag = String::with_capacity(1 + variant.len());
+    tag.push('!');
+    tag.push_str(variant);
+    tag
+}
+
 /// Serialize the given data structure as YAML into the IO stream.
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
@@ -689,19 +857,185 @@ where
     W: io::Write,
     T: ?Sized + ser::Serialize,
 {
-    let mut serializer = Serializer::new(writer);
+    let mut serializer = Serializer::new(writer)?;
     value.serialize(&mut serializer)
 }
 
-/// Serialize the given data structure as a String of YAML.
+/// Serialize the given data structure as YAML into the IO stream, and return
+/// the number of bytes written to it, including the final newline.
+///
+/// Output is flushed before this returns, so the count covers everything the
+/// writer was given.
+///
+/// ```
+/// # fn main() -> serde_yaml::Result<()> {
+/// let mut buffer = Vec::new();
+/// let written = serde_yaml::to_writer_counted(&mut buffer, &["a", "b"])?;
+/// assert_eq!(buffer, b"- a\n- b\n");
+/// assert_eq!(written, 8);
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_writer_counted<W, T>(writer: W, value: &T) -> Result<u64>
+where
+    W: io::Write,
+    T: ?Sized + ser::Serialize,
+{
+    let mut serializer = Serializer::new(writer)?;
+    value.serialize(&mut serializer)?;
+    serializer.flush()?;
+    Ok(serializer.bytes_written())
+}
+
+/// Writes a stream of documents, each with the `---` and `...` markers and
+/// the directives it says it has.
+///
+/// Together with [`Deserializer::document_values`](crate::Deserializer::document_values)
+/// this reproduces how a multi-document stream is framed, though not its
+/// comments or formatting. The emitter adds a `---` where one is required:
+/// before every document but the first, and before any document with
+/// directives.
+pub fn to_writer_documents<W, I>(writer: W, documents: I) -> Result<()>
+where
+    W: io::Write,
+    I: IntoIterator,
+    I::Item: Borrow<DocumentValue>,
+{
+    let mut serializer = Serializer::new(writer)?;
+    for document in documents {
+        let document = document.borrow();
+        serializer.framing = Some((document.start_event(), document.end_event()));
+        ser::Serialize::serialize(&document.value, &mut serializer)?;
+    }
+    serializer.into_inner()?;
+    Ok(())
+}
+
+/// Serializes each item as one document of a stream of YAML, in order.
+///
+/// Documents after the first begin with `---`, and no documents make an empty
+/// stream. The items may be values or references to them. Each is written
+/// before the next is asked for, so the documents coming out of
+/// [`from_reader_multi`](crate::from_reader_multi) can be written as they are
+/// read, without collecting them first.
+///
+/// ```
+/// # fn main() -> serde_yaml::Result<()> {
+/// let documents = vec![vec![1, 2], vec![3]];
+/// let mut buffer = Vec::new();
+/// serde_yaml::to_writer_multi(&mut buffer, &documents)?;
+/// assert_eq!(buffer, b"- 1\n- 2\n---\n- 3\n");
+/// # Ok(())
+/// # }
+/// ```
+pub fn to_writer_multi<W, I>(writer: W, documents: I) -> Result<()>
+where
+    W: io::Write,
+    I: IntoIterator,
+    I::Item: ser::Serialize,
+{
+    let mut serializer = Serializer::new(writer)?;
+    for document in documents {
+        ser::Serialize::serialize(&document, &mut serializer)?;
+    }
+    serializer.into_inner()?;
+    Ok(())
+}
+
+/// Serialize the given data structure as a byte vector of YAML.
 ///
 /// Serialization can fail if `T`'s implementation of `Serialize` decides to
 /// return an error.
-pub fn to_string<T>(value: &T) -> Result<String>
+pub fn to_vec<T>(value: &T) -> Result<Vec<u8>>
 where
     T: ?Sized + ser::Serialize,
 {
     let mut vec = Vec::with_capacity(128);
     to_writer(&mut vec, value)?;
+    Ok(vec)
+}
+
+/// Serialize the given data structure as a String of YAML.
+///
+/// Serialization can fail if `T`'s implementation of `Serialize` decides to
+/// return an error.
+pub fn to_string<T>(value: &T) -> Result<String>
+where
+    T: ?Sized + ser::Serialize,
+{
+    let vec = to_vec(value)?;
     String::from_utf8(vec).map_err(|error| error::new(ErrorImpl::FromUtf8(error)))
 }
+
+// The style in which a string is written so that it reads back as the same
+// string: quoted if it would otherwise be a number, boolean or null, in YAML
+// 1.2 or in YAML 1.1, and as a literal block if it spans lines.
+pub(crate) fn infer_scalar_style(value: &str) -> ScalarStyle {
+    struct InferScalarStyle;
+
+    impl<'de> Visitor<'de> for InferScalarStyle {
+        type Value = ScalarStyle;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("I wonder")
+        }
+
+        fn visit_bool<E>(self, _v: bool) -> Result<Self::Value, E> {
+            Ok(ScalarStyle::SingleQuoted)
+        }
+
+        fn visit_i64<E>(self, _v: i64) -> Result<Self::Value, E> {
+            Ok(ScalarStyle::SingleQuoted)
+        }
+
+        fn visit_i128<E>(self, _v: i128) -> Result<Self::Value, E> {
+            Ok(ScalarStyle::SingleQuoted)
+        }
+
+        fn visit_u64<E>(self, _v: u64) -> Result<Self::Value, E> {
+            Ok(ScalarStyle::SingleQuoted)
+        }
+
+        fn visit_u128<E>(self, _v: u128) -> Result<Self::Value, E> {
+            Ok(ScalarStyle::SingleQuoted)
+        }
+
+        fn visit_f64<E>(self, _v: f64) -> Result<Self::Value, E> {
+            Ok(ScalarStyle::SingleQuoted)
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Self::Value, E> {
+            Ok(
+                if crate::de::digits_but_not_number(v) || yaml11::difference(v).is_some() {
+                    ScalarStyle::SingleQuoted
+                } else {
+                    ScalarStyle::Any
+                },
+            )
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok(ScalarStyle::SingleQuoted)
+        }
+    }
+
+    if value.contains('\n') {
+        ScalarStyle::Literal
+    } else {
+        // The only error is an integer too large to read, which must be quoted
+        // to read back as a string.
+        let result = crate::de::visit_untagged_scalar(InferScalarStyle, value, None);
+        result.unwrap_or(ScalarStyle::SingleQuoted)
+    }
+}
+
+// The style in which a string is written when asked for `style`: that style,
+// unless the string needs quotes that it does not have.
+pub(crate) fn restyle_string(value: &str, style: ScalarStyle) -> ScalarStyle {
+    let inferred = infer_scalar_style(value);
+    match style {
+        ScalarStyle::Any => inferred,
+        ScalarStyle::Plain if inferred == ScalarStyle::SingleQuoted => inferred,
+        style => style,
+    }
+}
@@ -0,0 +1,147 @@
+use crate::error::{Error, Location};
+use std::fmt::{self, Display};
+
+// Lines longer than this many columns are cut down to a window around the
+// caret.
+const MAX_WIDTH: usize = 100;
+const ELLIPSIS: &str = "...";
+const TAB: &str = "    ";
+
+/// Displays an error followed by the line of source it points at, with a caret
+/// under the offending column.
+///
+/// Returned by [`Error::display_with_source`].
+pub struct DisplayWithSource<'a> {
+    pub(crate) error: &'a Error,
+    pub(crate) source: &'a str,
+}
+
+impl<'a> Display for DisplayWithSource<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        Display::fmt(self.error, formatter)?;
+        let location = match self.error.location() {
+            Some(location) => location,
+            None => return Ok(()),
+        };
+        let (line, text, caret) = snippet(self.source, &location);
+        let number = line.to_string();
+        let gutter = " ".repeat(number.len());
+        write!(
+            formatter,
+            "\n{gutter} |\n{number} | {text}\n{gutter} | {caret:>width$}",
+            gutter = gutter,
+            number = number,
+            text = text,
+            caret = '^',
+            width = caret + 1,
+        )
+    }
+}
+
+fn is_break(ch: char) -> bool {
+    matches!(ch, '\n' | '\r' | '\u{85}' | '\u{2028}' | '\u{2029}')
+}
+
+// Returns the line number and text of the line containing `location`,
+// prepared for display, along with the display column of the caret within it.
+//
+// The line is found from the byte index rather than from the line and column
+// reported by libyaml, which at the end of the input may refer to a line past
+// the last one.
+fn snippet(source: &str, location: &Location) -> (usize, String, usize) {
+    let mut index = location.index().min(source.len());
+    while !source.is_char_boundary(index) {
+        index -= 1;
+    }
+    let start = match source[..index].rfind(is_break) {
+        Some(i) => i + source[i..].chars().next().map_or(0, char::len_utf8),
+        None => 0,
+    };
+    let line =
+        1 + source[..start].matches(is_break).count() - source[..start].matches("\r\n").count();
+    let end = source[index..]
+        .find(is_break)
+        .map_or(source.len(), |i| index + i);
+
+    // At most MAX_WIDTH columns on either side of the caret can be shown, so
+    // a line of a megabyte costs no more than one that just overflows. If
+    // either side is cut short here, the window below cuts it further.
+    let mut cells = Vec::new();
+    let mut caret = 0;
+    for ch in source[start..index].chars().rev() {
+        if caret > MAX_WIDTH {
+            break;
+        }
+        cells.push(cell(ch));
+        caret += cells[cells.len() - 1].1;
+    }
+    cells.reverse();
+    let mut total = caret;
+    for ch in source[index..end].chars() {
+        if total - caret > MAX_WIDTH {
+            break;
+        }
+        cells.push(cell(ch));
+        total += cells[cells.len() - 1].1;
+    }
+
+    let mut first = 0;
+    let mut last = cells.len();
+    if total > MAX_WIDTH {
+        while caret > MAX_WIDTH / 2 {
+            caret -= cells[first].1;
+            first += 1;
+        }
+        let budget = MAX_WIDTH - 2 * ELLIPSIS.len();
+        let mut used = 0;
+        last = first;
+        while last < cells.len() && used + cells[last].1 <= budget {
+            used += cells[last].1;
+            last += 1;
+        }
+    }
+
+    let mut text = String::new();
+    if first > 0 {
+        text.push_str(ELLIPSIS);
+        caret += ELLIPSIS.len();
+    }
+    for &(ch, _width) in &cells[first..last] {
+        if ch == '\t' {
+            text.push_str(TAB);
+        } else {
+            text.push(ch);
+        }
+    }
+    if last < cells.len() {
+        text.push_str(ELLIPSIS);
+    }
+    (line, text, caret)
+}
+
+fn cell(ch: char) -> (char, usize) {
+    (ch, if ch == '\t' { TAB.len() } else { width(ch) })
+}
+
+// Number of terminal columns taken up by `ch`. East Asian wide characters and
+// most emoji take two columns and combining marks take none.
+fn width(ch: char) -> usize {
+    match ch as u32 {
+        0x0300..=0x036F | 0x200B..=0x200F | 0xFE00..=0xFE0F => 0,
+        0x1100..=0x115F
+        | 0x2E80..=0x303E
+        | 0x3041..=0x33FF
+        | 0x3400..=0x4DBF
+        | 0x4E00..=0x9FFF
+        | 0xA000..=0xA4CF
+        | 0xAC00..=0xD7A3
+        | 0xF900..=0xFAFF
+        | 0xFE30..=0xFE4F
+        | 0xFF00..=0xFF60
+        | 0xFFE0..=0xFFE6
+        | 0x1F300..=0x1F64F
+        | 0x1F900..=0x1F9FF
+        | 0x20000..=0x3FFFD => 2,
+        _ => 1,
+    }
+}
@@ -0,0 +1,179 @@
+// Reads a stream of YAML from an `io::Read` one document at a time, so that
+// no more than one document of it is in memory at once.
+//
+// libyaml parses from a buffer holding all of its input, so the stream is cut
+// into pieces of one document each before parsing, at lines that YAML does not
+// allow inside of a document: those beginning with a `---` or `...` marker.
+// Each piece is then parsed on its own, with marks shifted to where the piece
+// is in the stream.
+
+use crate::de::{Deserializer, Progress};
+use crate::error::{self, ErrorImpl, Result};
+use crate::libyaml::parser;
+use crate::loader::Loader;
+use serde::de::DeserializeOwned;
+use std::io::{self, BufRead, BufReader};
+use std::marker::PhantomData;
+use std::mem;
+
+pub(crate) struct ReaderDocuments<R, T> {
+    reader: BufReader<R>,
+    // The beginning of the next piece, read while looking for the end of the
+    // one before it.
+    pending: Vec<u8>,
+    // Where the next piece begins: the bytes, lines and documents before it.
+    index: usize,
+    line: usize,
+    documents: usize,
+    // The documents of the current piece.
+    current: Option<Deserializer<'static>>,
+    done: bool,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<R, T> ReaderDocuments<R, T>
+where
+    R: io::Read,
+{
+    pub fn new(reader: R) -> Self {
+        ReaderDocuments {
+            reader: BufReader::new(reader),
+            pending: Vec::new(),
+            index: 0,
+            line: 0,
+            documents: 0,
+            current: None,
+            done: false,
+            marker: PhantomData,
+        }
+    }
+
+    // Reads up to the end of the next document, and returns the piece along
+    // with whether it has anything to parse: a document, or directives that
+    // fail for want of one. A piece of only comments and blank lines, or
+    // nothing at all at the end of the stream, has no document in it.
+    fn read_piece(&mut self) -> io::Result<Option<(Vec<u8>, bool)>> {
+        let mut piece = mem::take(&mut self.pending);
+        // Anything left over from the last piece begins with a `---` line.
+        let mut has_document = !piece.is_empty();
+        let mut has_directives = false;
+        // Whether a `%` line came after the document's content. libyaml reads
+        // it as a directive of the next document, unless it continues a
+        // scalar that spans lines, so the document after it is left in this
+        // piece for libyaml to tell which.
+        let mut ambiguous = false;
+        loop {
+            let start = piece.len();
+            if self.reader.read_until(b'\n', &mut piece)? == 0 {
+                let parse = has_document || has_directives;
+                return Ok((!piece.is_empty()).then_some((piece, parse)));
+            }
+            let mut line = &piece[start..];
+            if self.index == 0 && start == 0 {
+                line = line.strip_prefix(b"\xEF\xBB\xBF").unwrap_or(line);
+            }
+            match classify(line) {
+                Line::Start if has_document && !ambiguous => {
+                    self.pending = piece.split_off(start);
+                    return Ok(Some((piece, true)));
+                }
+                Line::Start => {
+                    has_document = true;
+                    ambiguous = false;
+                }
+                Line::Content => has_document = true,
+                Line::End => return Ok(Some((piece, has_document || has_directives))),
+                Line::Directive if has_document => ambiguous = true,
+                Line::Directive => has_directives = true,
+                Line::Blank => {}
+            }
+        }
+    }
+}
+
+impl<R, T> Iterator for ReaderDocuments<R, T>
+where
+    R: io::Read,
+    T: DeserializeOwned,
+{
+    type Item = Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(documents) = &mut self.current {
+                if let Some(document) = documents.next() {
+                    self.documents += 1;
+                    return Some(T::deserialize(document));
+                }
+                self.current = None;
+            }
+            if self.done {
+                return None;
+            }
+            let (piece, parse) = match self.read_piece() {
+                Ok(Some(piece)) => piece,
+                Ok(None) => {
+                    self.done = true;
+                    return None;
+                }
+                Err(io_error) => {
+                    self.done = true;
+                    return Some(Err(error::new(ErrorImpl::Io(io_error))));
+                }
+            };
+            let (index, line) = (self.index, self.line);
+            self.index += piece.len();
+            self.line += parser::count_lines(&piece) as usize;
+            if !parse {
+                continue;
+            }
+            let mut loader = match Loader::new(Progress::Owned(piece)) {
+                Ok(loader) => loader,
+                Err(err) => return Some(Err(err)),
+            };
+            loader.origin(index, line, self.documents);
+            // So that a document that fails to parse ends where it would
+            // in the whole stream, rather than where the parser gave up.
+            loader.recover_documents(true);
+            self.current = Some(Deserializer::new(Progress::Iterable(loader)));
+        }
+    }
+}
+
+enum Line {
+    // A `---` marker, with or without content after it.
+    Start,
+    // A `...` marker.
+    End,
+    // A `%YAML` or `%TAG` directive, unless it turns out to be part of a
+    // scalar.
+    Directive,
+    // Nothing but whitespace or a comment.
+    Blank,
+    Content,
+}
+
+fn classify(line: &[u8]) -> Line {
+    let marker = |marker: &[u8]| {
+        line.starts_with(marker)
+            && matches!(
+                line.get(marker.len()),
+                None | Some(b' ' | b'\t' | b'\r' | b'\n')
+            )
+    };
+    if marker(b"---") {
+        Line::Start
+    } else if marker(b"...") {
+        Line::End
+    } else if marker(b"%YAML") || marker(b"%TAG") {
+        Line::Directive
+    } else {
+        match line
+            .iter()
+            .find(|&&b| !matches!(b, b' ' | b'\t' | b'\r' | b'\n'))
+        {
+            None | Some(b'#') => Line::Blank,
+            Some(_) => Line::Content,
+        }
+    }
+}
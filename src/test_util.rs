@@ -0,0 +1,309 @@
+//! Assertions for tests of code that produces YAML.
+//!
+//! Enabled by the `test-util` feature.
+
+use crate::raw::{Event, Parser};
+use crate::{Location, Value};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+
+// At most this many differences are listed, and the rest are counted.
+const MAX_DIFFERENCES: usize = 20;
+
+/// Asserts that two strings of YAML hold the same value.
+///
+/// Both sides are parsed into a [`Value`], so formatting, quoting styles,
+/// comments and the order of mapping entries do not matter. On a mismatch,
+/// the panic message lists each place where the values differ, as a pointer
+/// like `/spec/replicas` together with its line on each side. If either side
+/// fails to parse, the message shows the error and the line it points at.
+///
+/// ```should_panic
+/// # use serde_yaml::assert_yaml_eq;
+/// let actual = "spec:\n  replicas: 5\n  image: nginx\n";
+/// let expected = "spec: {image: nginx, replicas: 3}";
+///
+/// // Panics with:
+/// //
+/// //     YAML values differ:
+/// //       at /spec/replicas: expected 3, got 5 (actual line 2, expected line 1)
+/// assert_yaml_eq!(actual, expected);
+/// ```
+#[macro_export]
+macro_rules! assert_yaml_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::test_util::assert_yaml_eq(
+            ::core::convert::AsRef::<str>::as_ref(&$actual),
+            ::core::convert::AsRef::<str>::as_ref(&$expected),
+        )
+    };
+}
+
+/// The function behind [`assert_yaml_eq!`].
+#[track_caller]
+pub fn assert_yaml_eq(actual: &str, expected: &str) {
+    if let Some(message) = diff(actual, expected) {
+        panic!("{}", message);
+    }
+}
+
+/// Describes how two strings of YAML differ, in the form used by
+/// [`assert_yaml_eq!`], or returns `None` if they hold the same value.
+pub fn diff(actual: &str, expected: &str) -> Option<String> {
+    let parsed_actual = crate::from_str::<Value>(actual);
+    let parsed_expected = crate::from_str::<Value>(expected);
+    let (actual_value, expected_value) = match (&parsed_actual, &parsed_expected) {
+        (Ok(actual), Ok(expected)) => (actual, expected),
+        _ => {
+            let mut message = String::new();
+            if let Err(err) = &parsed_actual {
+                let _ = writeln!(
+                    message,
+                    "actual YAML failed to parse: {}",
+                    err.display_with_source(actual),
+                );
+            }
+            if let Err(err) = &parsed_expected {
+                let _ = writeln!(
+                    message,
+                    "expected YAML failed to parse: {}",
+                    err.display_with_source(expected),
+                );
+            }
+            message.pop();
+            return Some(message);
+        }
+    };
+
+    let mut differences = Vec::new();
+    compare(
+        actual_value,
+        expected_value,
+        &mut String::new(),
+        &mut differences,
+    );
+    if differences.is_empty() {
+        return None;
+    }
+
+    let actual_locations = locations(actual);
+    let expected_locations = locations(expected);
+    let mut message = String::from("YAML values differ:");
+    for difference in differences.iter().take(MAX_DIFFERENCES) {
+        let pointer = if difference.pointer.is_empty() {
+            "the root"
+        } else {
+            &difference.pointer
+        };
+        let _ = write!(message, "\n  at {}: {}", pointer, difference.message);
+        let lines = [
+            ("actual", locate(&actual_locations, &difference.pointer)),
+            ("expected", locate(&expected_locations, &difference.pointer)),
+        ];
+        let mut separator = " (";
+        for (side, location) in lines {
+            if let Some(location) = location {
+                let _ = write!(message, "{}{} line {}", separator, side, location.line());
+                separator = ", ";
+            }
+        }
+        if separator == ", " {
+            message.push(')');
+        }
+    }
+    if differences.len() > MAX_DIFFERENCES {
+        let _ = write!(
+            message,
+            "\n  and {} more",
+            differences.len() - MAX_DIFFERENCES,
+        );
+    }
+    Some(message)
+}
+
+struct Difference {
+    pointer: String,
+    message: String,
+}
+
+fn compare(actual: &Value, expected: &Value, pointer: &mut String, out: &mut Vec<Difference>) {
+    if actual == expected {
+        return;
+    }
+    let len = pointer.len();
+    match (actual, expected) {
+        (Value::Mapping(actual), Value::Mapping(expected)) => {
+            for (key, expected) in expected {
+                push_token(pointer, key);
+                match actual.get(key) {
+                    Some(actual) => compare(actual, expected, pointer, out),
+                    None => out.push(Difference {
+                        pointer: pointer.clone(),
+                        message: format!("missing, expected {}", describe(expected)),
+                    }),
+                }
+                pointer.truncate(len);
+            }
+            for (key, actual) in actual {
+                if !expected.contains_key(key) {
+                    push_token(pointer, key);
+                    out.push(Difference {
+                        pointer: pointer.clone(),
+                        message: format!("unexpected {}", describe(actual)),
+                    });
+                    pointer.truncate(len);
+                }
+            }
+        }
+        (Value::Sequence(actual), Value::Sequence(expected)) => {
+            for index in 0..actual.len().max(expected.len()) {
+                let _ = write!(pointer, "/{}", index);
+                let message = match (actual.as_slice().get(index), expected.as_slice().get(index)) {
+                    (Some(actual), Some(expected)) => {
+                        compare(actual, expected, pointer, out);
+                        None
+                    }
+                    (None, Some(expected)) => {
+                        Some(format!("missing, expected {}", describe(expected)))
+                    }
+                    (Some(actual), None) => Some(format!("unexpected {}", describe(actual))),
+                    (None, None) => unreachable!(),
+                };
+                if let Some(message) = message {
+                    out.push(Difference {
+                        pointer: pointer.clone(),
+                        message,
+                    });
+                }
+                pointer.truncate(len);
+            }
+        }
+        (Value::Tagged(actual), Value::Tagged(expected)) if actual.tag == expected.tag => {
+            compare(&actual.value, &expected.value, pointer, out);
+        }
+        _ => out.push(Difference {
+            pointer: pointer.clone(),
+            message: format!("expected {}, got {}", describe(expected), describe(actual)),
+        }),
+    }
+}
+
+// Appends the pointer token for a mapping key, with `~` and `/` escaped.
+fn push_token(pointer: &mut String, key: &Value) {
+    pointer.push('/');
+    let key = match key.as_str() {
+        Some(key) => key.to_owned(),
+        None => key.to_string(),
+    };
+    pointer.push_str(&key.replace('~', "~0").replace('/', "~1"));
+}
+
+fn describe(value: &Value) -> String {
+    match value {
+        Value::Sequence(sequence) if !sequence.is_empty() => match sequence.len() {
+            1 => "a sequence of 1 element".to_owned(),
+            len => format!("a sequence of {} elements", len),
+        },
+        Value::Mapping(mapping) if !mapping.is_empty() => match mapping.len() {
+            1 => "a mapping of 1 entry".to_owned(),
+            len => format!("a mapping of {} entries", len),
+        },
+        Value::Tagged(tagged) => format!("{} {}", tagged.tag, describe(&tagged.value)),
+        _ => value.to_string(),
+    }
+}
+
+// Where each node of the first document begins, by pointer. A mapping value is
+// located at its key. Nodes inside a complex mapping key and below an alias are
+// not included.
+fn locations(input: &str) -> HashMap<String, Location> {
+    enum Frame {
+        Sequence {
+            pointer: String,
+            index: usize,
+        },
+        Mapping {
+            pointer: String,
+            key: Option<(String, Location)>,
+        },
+    }
+
+    let mut locations = HashMap::new();
+    let mut stack: Vec<Frame> = Vec::new();
+    let parser = match Parser::new(input.as_bytes()) {
+        Ok(parser) => parser,
+        Err(_) => return locations,
+    };
+    for event in parser {
+        let (event, location) = match event {
+            Ok(event) => event,
+            Err(_) => break,
+        };
+        let (pointer, location) = match &event {
+            Event::Scalar(_)
+            | Event::SequenceStart(_)
+            | Event::MappingStart(_)
+            | Event::Alias(_) => {
+                match stack.last_mut() {
+                    None => (Some(String::new()), location),
+                    Some(Frame::Sequence { pointer, index }) => {
+                        *index += 1;
+                        (Some(format!("{}/{}", pointer, *index - 1)), location)
+                    }
+                    Some(Frame::Mapping { pointer, key }) => match key.take() {
+                        Some((key, key_location)) => {
+                            (Some(format!("{}/{}", pointer, key)), key_location)
+                        }
+                        None => {
+                            let token = match &event {
+                                Event::Scalar(scalar) => String::from_utf8_lossy(&scalar.value)
+                                    .replace('~', "~0")
+                                    .replace('/', "~1"),
+                                // Matches no pointer that is looked up.
+                                _ => "\0".to_owned(),
+                            };
+                            *key = Some((token, location));
+                            (None, location)
+                        }
+                    },
+                }
+            }
+            _ => (None, location),
+        };
+        if let Some(pointer) = &pointer {
+            locations.entry(pointer.clone()).or_insert(location);
+        }
+        match event {
+            Event::SequenceStart(_) => stack.push(Frame::Sequence {
+                pointer: pointer.unwrap_or_else(|| "\0".to_owned()),
+                index: 0,
+            }),
+            Event::MappingStart(_) => stack.push(Frame::Mapping {
+                pointer: pointer.unwrap_or_else(|| "\0".to_owned()),
+                key: None,
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                stack.pop();
+            }
+            Event::DocumentEnd(_) => break,
+            _ => {}
+        }
+    }
+    locations
+}
+
+// The location of the node at `pointer`, or else of its nearest ancestor other
+// than the root that has one. The root's line says nothing about where a
+// top-level key would be.
+fn locate(locations: &HashMap<String, Location>, mut pointer: &str) -> Option<Location> {
+    if pointer.is_empty() {
+        return locations.get(pointer).copied();
+    }
+    while !pointer.is_empty() {
+        if let Some(location) = locations.get(pointer) {
+            return Some(*location);
+        }
+        pointer = &pointer[..pointer.rfind('/')?];
+    }
+    None
+}
@@ -0,0 +1,329 @@
+use crate::error::{self, ErrorImpl, Result};
+use crate::libyaml::emitter::Emitter;
+use crate::libyaml::error::Mark;
+use crate::libyaml::event::{Anchor, CollectionStyle, Event, Scalar, ScalarStyle};
+use crate::libyaml::parser::Parser;
+use crate::loader;
+use crate::ser::{infer_scalar_style, restyle_string};
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::io;
+use std::str;
+
+/// How [`transcode`] rewrites the YAML that passes through it.
+///
+/// By default the document structure, tags, anchors, aliases and the style of
+/// each node are kept, and only comments and formatting are lost.
+#[derive(Clone, Debug, Default)]
+pub struct EmitOptions {
+    expand_aliases: bool,
+    scalar_style: Option<ScalarStyle>,
+    collection_style: Option<CollectionStyle>,
+}
+
+impl EmitOptions {
+    /// Options that keep everything but comments and formatting.
+    pub fn new() -> Self {
+        EmitOptions::default()
+    }
+
+    /// Replaces each alias with a copy of the node it refers to, and drops the
+    /// anchors.
+    ///
+    /// Expansion is limited in the same way as when deserializing, so that a
+    /// document of nested aliases like the "billion laughs" fails instead of
+    /// expanding without bound.
+    pub fn expand_aliases(mut self, expand: bool) -> Self {
+        self.expand_aliases = expand;
+        self
+    }
+
+    /// Writes string scalars in the given style where that does not change
+    /// what they mean.
+    ///
+    /// A scalar that would not read back as a string without quotes, like
+    /// `'123'` or `"true"`, stays quoted, and one that is not a string at all,
+    /// like `123`, stays plain. Scalars with a tag keep their style. Where
+    /// the style cannot represent a string, such as a block scalar inside a
+    /// flow collection, the emitter picks one that can. [`ScalarStyle::Any`]
+    /// chooses the same styles that serializing a string does.
+    pub fn scalar_style(mut self, style: ScalarStyle) -> Self {
+        self.scalar_style = Some(style);
+        self
+    }
+
+    /// Writes every sequence and mapping in the given style. Collections
+    /// inside a flow collection, and empty ones, are always written in flow
+    /// style.
+    pub fn collection_style(mut self, style: CollectionStyle) -> Self {
+        self.collection_style = Some(style);
+        self
+    }
+}
+
+/// Reads a stream of YAML documents and writes it back out, without
+/// deserializing it into any Rust type.
+///
+/// Events go from the parser straight to the emitter, so the output has the
+/// same documents, structure, tags and scalar values as the input, but none
+/// of its comments, and formatting that is normalized as `options` says. Only
+/// the input is held in memory, together with the document being written if
+/// aliases are expanded.
+///
+/// ```
+/// use serde_yaml::raw::ScalarStyle;
+/// use serde_yaml::EmitOptions;
+///
+/// let input = "# settings\nname: \"web\"  # the service\nport: '8080'\nbase: &b {a: 1}\ncopy: *b\n";
+///
+/// let mut output = Vec::new();
+/// let options = EmitOptions::new()
+///     .scalar_style(ScalarStyle::Plain)
+///     .expand_aliases(true);
+/// serde_yaml::transcode(input.as_bytes(), &mut output, &options).unwrap();
+///
+/// let expected = "name: web\nport: '8080'\nbase: {a: 1}\ncopy: {a: 1}\n";
+/// assert_eq!(String::from_utf8(output).unwrap(), expected);
+/// ```
+pub fn transcode<R, W>(mut reader: R, writer: W, options: &EmitOptions) -> Result<()>
+where
+    R: io::Read,
+    W: io::Write,
+{
+    let mut input = Vec::new();
+    if let Err(io_error) = reader.read_to_end(&mut input) {
+        return Err(error::new(ErrorImpl::Io(io_error)));
+    }
+    let mut parser = Parser::new(Cow::Borrowed(&input))?;
+    let mut output = Output {
+        emitter: Emitter::new(writer, 0)?,
+        options,
+        levels: Vec::new(),
+    };
+    // The events of the current document, if aliases are being expanded.
+    let mut document = Vec::new();
+    loop {
+        let (event, mark) = parser.next()?;
+        let end = matches!(event, Event::StreamEnd);
+        if !options.expand_aliases {
+            output.emit(event)?;
+        } else if let Event::DocumentEnd(_) = event {
+            document.push((event, mark));
+            expand(&document, &mut output)?;
+            document.clear();
+        } else if document.is_empty() && !matches!(event, Event::DocumentStart(_)) {
+            output.emit(event)?;
+        } else {
+            document.push((event, mark));
+        }
+        if end {
+            break;
+        }
+    }
+    output.emitter.flush()?;
+    Ok(())
+}
+
+struct Output<'a, W> {
+    emitter: Emitter<W>,
+    options: &'a EmitOptions,
+    // The collections being written, innermost last.
+    levels: Vec<Level>,
+}
+
+struct Level {
+    flow: bool,
+    mapping: bool,
+    entries: usize,
+}
+
+impl<'a, W> Output<'a, W>
+where
+    W: io::Write,
+{
+    fn emit(&mut self, event: Event) -> Result<()> {
+        let mut event = restyle(event, self.options);
+        let (flow, key) = match self.levels.last_mut() {
+            Some(level) if !matches!(event, Event::SequenceEnd | Event::MappingEnd) => {
+                level.entries += 1;
+                (level.flow, level.mapping && level.entries % 2 == 1)
+            }
+            _ => (false, false),
+        };
+        match &mut event {
+            // libyaml quotes an empty plain scalar where it could not be read
+            // back as empty, which would make null into an empty string.
+            Event::Scalar(scalar)
+                if scalar.tag.is_none()
+                    && matches!(scalar.style, ScalarStyle::Plain | ScalarStyle::Any)
+                    && scalar.value.is_empty()
+                    && (flow || key) =>
+            {
+                scalar.value = Cow::Borrowed(b"~");
+            }
+            Event::SequenceStart(sequence) => self.levels.push(Level {
+                flow: flow || sequence.style == CollectionStyle::Flow,
+                mapping: false,
+                entries: 0,
+            }),
+            Event::MappingStart(mapping) => self.levels.push(Level {
+                flow: flow || mapping.style == CollectionStyle::Flow,
+                mapping: true,
+                entries: 0,
+            }),
+            Event::SequenceEnd | Event::MappingEnd => {
+                self.levels.pop();
+            }
+            _ => {}
+        }
+        self.emitter.emit(event)?;
+        Ok(())
+    }
+}
+
+// Writes one document with each alias replaced by a copy of its node, taking
+// the same number of steps through aliases as the deserializer would and
+// failing at the same point.
+fn expand<W>(document: &[(Event, Mark)], output: &mut Output<W>) -> Result<()>
+where
+    W: io::Write,
+{
+    // Where each alias's node is in the document. Resolved in order, since a
+    // later anchor of the same name only applies to aliases after it.
+    let mut targets = HashMap::new();
+    let mut anchors: HashMap<&Anchor, (usize, Option<usize>)> = HashMap::new();
+    let mut open = Vec::new();
+    for (pos, (event, mark)) in document.iter().enumerate() {
+        let anchor = match event {
+            Event::Alias(anchor) => {
+                match anchors.get(anchor) {
+                    Some(&(start, Some(end))) => targets.insert(pos, (start, end)),
+                    Some((_start, None)) => {
+                        return Err(error::new(ErrorImpl::RecursionLimitExceeded(*mark)));
+                    }
+                    None => return Err(error::new(ErrorImpl::UnknownAnchor(*mark))),
+                };
+                continue;
+            }
+            Event::Scalar(scalar) => scalar.anchor.as_ref(),
+            Event::SequenceStart(sequence) => sequence.anchor.as_ref(),
+            Event::MappingStart(mapping) => mapping.anchor.as_ref(),
+            _ => None,
+        };
+        match event {
+            Event::SequenceStart(_) | Event::MappingStart(_) => open.push((anchor, pos)),
+            Event::SequenceEnd | Event::MappingEnd => {
+                if let Some((Some(anchor), start)) = open.pop() {
+                    // Unless the anchor was defined again within the node.
+                    if let Some((latest, end)) = anchors.get_mut(anchor) {
+                        if *latest == start {
+                            *end = Some(pos + 1);
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+        if let Some(anchor) = anchor {
+            let end = match event {
+                Event::Scalar(_) => Some(pos + 1),
+                _ => None,
+            };
+            anchors.insert(anchor, (pos, end));
+        }
+    }
+
+    // Bytes of scalars before each event, so that what an alias repeats is
+    // known without walking its node.
+    let mut scalar_bytes = Vec::with_capacity(document.len() + 1);
+    scalar_bytes.push(0);
+    for (event, _mark) in document {
+        let bytes = match event {
+            Event::Scalar(scalar) => scalar.value.len(),
+            _ => 0,
+        };
+        scalar_bytes.push(scalar_bytes[scalar_bytes.len() - 1] + bytes);
+    }
+    let len = match (document.first(), document.last()) {
+        (Some((_, first)), Some((_, last))) => last.index() - first.index(),
+        _ => 0,
+    };
+
+    let (mut jumps, mut bytes) = (0, 0);
+    let mut stack = vec![(0, document.len())];
+    while let Some((pos, end)) = stack.last_mut() {
+        if *pos == *end {
+            stack.pop();
+            continue;
+        }
+        let this = *pos;
+        *pos += 1;
+        let event = match &document[this].0 {
+            Event::Alias(_) => {
+                let (start, end) = targets[&this];
+                jumps += 1;
+                bytes += scalar_bytes[end] - scalar_bytes[start];
+                if !loader::repetition_allowed(jumps, bytes, document.len(), len) {
+                    return Err(error::new(ErrorImpl::RepetitionLimitExceeded));
+                }
+                stack.push((start, end));
+                continue;
+            }
+            Event::Scalar(scalar) => {
+                let mut scalar = scalar.clone();
+                scalar.anchor = None;
+                Event::Scalar(scalar)
+            }
+            Event::SequenceStart(sequence) => {
+                let mut sequence = sequence.clone();
+                sequence.anchor = None;
+                Event::SequenceStart(sequence)
+            }
+            Event::MappingStart(mapping) => {
+                let mut mapping = mapping.clone();
+                mapping.anchor = None;
+                Event::MappingStart(mapping)
+            }
+            event => event.clone(),
+        };
+        output.emit(event)?;
+    }
+    Ok(())
+}
+
+fn restyle<'input>(event: Event<'input>, options: &EmitOptions) -> Event<'input> {
+    match event {
+        Event::Scalar(scalar) if scalar.tag.is_none() => match options.scalar_style {
+            Some(style) => Event::Scalar(restyle_scalar(scalar, style)),
+            None => Event::Scalar(scalar),
+        },
+        Event::SequenceStart(mut sequence) => {
+            if let Some(style) = options.collection_style {
+                sequence.style = style;
+            }
+            Event::SequenceStart(sequence)
+        }
+        Event::MappingStart(mut mapping) => {
+            if let Some(style) = options.collection_style {
+                mapping.style = style;
+            }
+            Event::MappingStart(mapping)
+        }
+        event => event,
+    }
+}
+
+fn restyle_scalar(mut scalar: Scalar, style: ScalarStyle) -> Scalar {
+    let value = match str::from_utf8(&scalar.value) {
+        Ok(value) => value,
+        Err(_) => return scalar,
+    };
+    let inferred = infer_scalar_style(value);
+    let is_string = scalar.style != ScalarStyle::Plain || inferred != ScalarStyle::SingleQuoted;
+    if !is_string {
+        // A plain number, boolean or null.
+        return scalar;
+    }
+    scalar.style = restyle_string(value, style);
+    scalar
+}
@@ -0,0 +1,173 @@
+use crate::value::tagged::{nobang, Tag};
+use crate::value::{Number, Value};
+use std::mem;
+
+impl Value {
+    /// Rewrites the value in a canonical form, so that values that mean the
+    /// same thing are serialized and hashed the same however they were
+    /// written.
+    ///
+    /// - Every mapping is sorted by key, in the order used by
+    ///   [`Mapping::sort_keys`](crate::Mapping::sort_keys).
+    /// - A float of negative zero becomes zero. Numbers are otherwise
+    ///   canonical once parsed: `+1` and `0x1` are both the integer 1, and
+    ///   `1.0` stays a float.
+    /// - A tag with the `!!` handle that says no more than the value does,
+    ///   like `!!str` on a string or `!!map` on a mapping, is removed.
+    ///   Deserializing resolves such tags away, so they are only found in a
+    ///   `Value` built in code.
+    ///
+    /// Everything else, including the case of strings and local tags like
+    /// `!Thing`, is kept. If a mapping key loses its tag and becomes equal to
+    /// another key of the same mapping, the later entry is kept.
+    /// Canonicalizing a value a second time does not change it.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut value: Value = serde_yaml::from_str("{b: [+1, -0.0], a: ~}").unwrap();
+    /// assert_eq!(value.to_string(), "b:\n- 1\n- -0.0\na: null");
+    ///
+    /// value.canonicalize();
+    /// assert_eq!(value.to_string(), "a: null\nb:\n- 1\n- 0.0");
+    /// ```
+    pub fn canonicalize(&mut self) {
+        match self {
+            Value::Null | Value::Bool(_) | Value::String(_) => {}
+            Value::Number(number) => {
+                if number.as_f64() == Some(0.0) && number.is_f64() {
+                    *number = Number::from(0.0);
+                }
+            }
+            Value::Sequence(sequence) => {
+                for element in sequence {
+                    element.canonicalize();
+                }
+            }
+            Value::Mapping(mapping) => {
+                for (mut key, mut value) in mem::take(mapping) {
+                    key.canonicalize();
+                    value.canonicalize();
+                    mapping.insert(key, value);
+                }
+                mapping.sort_keys();
+            }
+            Value::Tagged(tagged) => {
+                tagged.value.canonicalize();
+                if is_redundant(&tagged.tag, &tagged.value) {
+                    *self = mem::take(&mut tagged.value);
+                }
+            }
+        }
+    }
+
+    /// A hash of the value's [canonical form](Value::canonicalize), for
+    /// telling whether a document changed in any way that matters.
+    ///
+    /// Unlike the `Hash` impl, this is the same on every platform, version of
+    /// Rust and run of the program, so it can be stored and compared later.
+    /// It is a 64-bit FNV-1a hash, which is fast but not cryptographic, so
+    /// it does not protect against changes made to collide on purpose.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let a: Value = serde_yaml::from_str("port: 80\nhost: web # the host\n").unwrap();
+    /// let b: Value = serde_yaml::from_str("{host: 'web', port: 0x50}").unwrap();
+    /// let c: Value = serde_yaml::from_str("{host: web, port: 81}").unwrap();
+    /// assert_eq!(a.canonical_hash(), b.canonical_hash());
+    /// assert_ne!(a.canonical_hash(), c.canonical_hash());
+    /// ```
+    pub fn canonical_hash(&self) -> u64 {
+        let mut canonical = self.clone();
+        canonical.canonicalize();
+        let mut hasher = Fnv(0xcbf2_9ce4_8422_2325);
+        hasher.value(&canonical);
+        hasher.0
+    }
+}
+
+// Whether deserializing the value with this tag would give back the same
+// value without it.
+fn is_redundant(tag: &Tag, value: &Value) -> bool {
+    let name = match nobang(&tag.string).strip_prefix('!') {
+        Some(name) => name,
+        None => return false,
+    };
+    match value {
+        Value::Null => name == "null",
+        Value::Bool(_) => name == "bool",
+        Value::Number(number) if number.is_f64() => name == "float",
+        Value::Number(_) => name == "int",
+        // Any global tag other than these makes a scalar a string.
+        Value::String(_) => !["null", "bool", "int", "float"].contains(&name),
+        // Global tags have no effect on sequences and mappings.
+        Value::Sequence(_) | Value::Mapping(_) => true,
+        Value::Tagged(_) => false,
+    }
+}
+
+// Hashes an encoding of the value in which each node starts with a byte for
+// its kind and each string and collection with its length, so that no two
+// values are encoded the same.
+struct Fnv(u64);
+
+impl Fnv {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 ^= u64::from(byte);
+            self.0 = self.0.wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn len(&mut self, len: usize) {
+        self.write(&(len as u64).to_le_bytes());
+    }
+
+    fn str(&mut self, string: &str) {
+        self.len(string.len());
+        self.write(string.as_bytes());
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.write(&[0]),
+            Value::Bool(boolean) => self.write(&[1, u8::from(*boolean)]),
+            Value::Number(number) => {
+                if let (false, Some(int)) = (number.is_f64(), number.as_u64()) {
+                    self.write(&[2]);
+                    self.write(&int.to_le_bytes());
+                } else if let (false, Some(int)) = (number.is_f64(), number.as_i64()) {
+                    self.write(&[3]);
+                    self.write(&int.to_le_bytes());
+                } else {
+                    let float = number.as_f64().unwrap_or(f64::NAN);
+                    self.write(&[4]);
+                    self.write(&float.to_bits().to_le_bytes());
+                }
+            }
+            Value::String(string) => {
+                self.write(&[5]);
+                self.str(string);
+            }
+            Value::Sequence(sequence) => {
+                self.write(&[6]);
+                self.len(sequence.len());
+                for element in sequence {
+                    self.value(element);
+                }
+            }
+            Value::Mapping(mapping) => {
+                self.write(&[7]);
+                self.len(mapping.len());
+                for (key, value) in mapping {
+                    self.value(key);
+                    self.value(value);
+                }
+            }
+            Value::Tagged(tagged) => {
+                self.write(&[8]);
+                self.str(nobang(&tagged.tag.string));
+                self.value(&tagged.value);
+            }
+        }
+    }
+}
@@ -1,3 +1,9 @@
+use crate::de::{trailing_elements, trailing_entries, OutOfRange, UnitStructVisitor};
+use crate::document::NodeKind;
+use crate::error;
+use crate::path::Segment;
+use crate::peek::{self, PeekAccess};
+use crate::scalar_info;
 use crate::value::tagged::{self, TagStringVisitor};
 use crate::value::TaggedValue;
 use crate::{number, Error, Mapping, Sequence, Value};
@@ -46,6 +52,26 @@ impl<'de> Deserialize<'de> for Value {
                 Ok(Value::Number(u.into()))
             }
 
+            fn visit_i128<E>(self, i: i128) -> Result<Value, E>
+            where
+                E: de::Error,
+            {
+                match i64::try_from(i) {
+                    Ok(i) => Ok(Value::Number(i.into())),
+                    Err(_) => Err(out_of_range(&i.to_string())),
+                }
+            }
+
+            fn visit_u128<E>(self, u: u128) -> Result<Value, E>
+            where
+                E: de::Error,
+            {
+                match u64::try_from(u) {
+                    Ok(u) => Ok(Value::Number(u.into())),
+                    Err(_) => Err(out_of_range(&u.to_string())),
+                }
+            }
+
             fn visit_f64<E>(self, f: f64) -> Result<Value, E>
             where
                 E: de::Error,
@@ -120,16 +146,49 @@ impl<'de> Deserialize<'de> for Value {
     }
 }
 
+// An integer that a `Value` cannot hold.
+fn out_of_range<E>(text: &str) -> E
+where
+    E: de::Error,
+{
+    E::custom(OutOfRange {
+        text,
+        ty: "serde_yaml::Value",
+        min: i64::MIN.into(),
+        max: u64::MAX.into(),
+    })
+}
+
 impl Value {
     fn deserialize_number<'de, V>(&self, visitor: V) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Number(n) => n.deserialize_any(visitor),
             other => Err(other.invalid_type(&visitor)),
         }
     }
+
+    // The kind and tag of this value, for `peek`.
+    fn peek_node(&self) -> (NodeKind, Option<String>) {
+        let tag = match self {
+            Value::Tagged(tagged) => Some(tagged.tag.string.clone()),
+            _ => None,
+        };
+        let kind = match self.untag() {
+            Value::Sequence(_) => NodeKind::Sequence,
+            Value::Mapping(_) => NodeKind::Mapping,
+            _ => NodeKind::Scalar,
+        };
+        (kind, tag)
+    }
+}
+
+fn scalar_info_unsupported() -> Error {
+    de::Error::custom(
+        "ScalarInfo needs to know how a scalar was written, which a Value does not record",
+    )
 }
 
 fn visit_sequence<'de, V>(sequence: Sequence, visitor: V) -> Result<V::Value, Error>
@@ -143,7 +202,7 @@ where
     if remaining == 0 {
         Ok(seq)
     } else {
-        Err(Error::invalid_length(len, &"fewer elements in sequence"))
+        Err(trailing_elements(len - remaining))
     }
 }
 
@@ -158,7 +217,7 @@ where
     if remaining == 0 {
         Ok(seq)
     } else {
-        Err(Error::invalid_length(len, &"fewer elements in sequence"))
+        Err(trailing_elements(len - remaining))
     }
 }
 
@@ -173,7 +232,7 @@ where
     if remaining == 0 {
         Ok(map)
     } else {
-        Err(Error::invalid_length(len, &"fewer elements in map"))
+        Err(trailing_entries(len - remaining))
     }
 }
 
@@ -188,7 +247,7 @@ where
     if remaining == 0 {
         Ok(map)
     } else {
-        Err(Error::invalid_length(len, &"fewer elements in map"))
+        Err(trailing_entries(len - remaining))
     }
 }
 
@@ -214,7 +273,7 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag() {
+        match self.untag_owned() {
             Value::Bool(v) => visitor.visit_bool(v),
             other => Err(other.invalid_type(&visitor)),
         }
@@ -322,7 +381,7 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag() {
+        match self.untag_owned() {
             Value::String(v) => visitor.visit_string(v),
             other => Err(other.invalid_type(&visitor)),
         }
@@ -339,7 +398,7 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag() {
+        match self.untag_owned() {
             Value::String(v) => visitor.visit_string(v),
             Value::Sequence(v) => visit_sequence(v, visitor),
             other => Err(other.invalid_type(&visitor)),
@@ -366,21 +425,25 @@ impl<'de> Deserializer<'de> for Value {
         }
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_unit(visitor)
+        self.deserialize_any(UnitStructVisitor { name, visitor })
     }
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
+        if name == peek::NAME {
+            let (kind, tag) = self.peek_node();
+            return visitor.visit_seq(PeekAccess::new(kind, tag, self));
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -388,7 +451,7 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag() {
+        match self.untag_owned() {
             Value::Sequence(v) => visit_sequence(v, visitor),
             Value::Null => visit_sequence(Sequence::new(), visitor),
             other => Err(other.invalid_type(&visitor)),
@@ -418,7 +481,7 @@ impl<'de> Deserializer<'de> for Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag() {
+        match self.untag_owned() {
             Value::Mapping(v) => visit_mapping(v, visitor),
             Value::Null => visit_mapping(Mapping::new(), visitor),
             other => Err(other.invalid_type(&visitor)),
@@ -427,13 +490,16 @@ impl<'de> Deserializer<'de> for Value {
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
+        if name == scalar_info::NAME {
+            return Err(scalar_info_unsupported());
+        }
         self.deserialize_map(visitor)
     }
 
@@ -567,12 +633,14 @@ impl<'de> VariantAccess<'de> for VariantDeserializer {
 
 pub(crate) struct SeqDeserializer {
     iter: vec::IntoIter<Value>,
+    index: usize,
 }
 
 impl SeqDeserializer {
-    pub(crate) fn new(vec: Vec<Value>) -> Self {
+    pub(crate) fn new(sequence: Sequence) -> Self {
         SeqDeserializer {
-            iter: vec.into_iter(),
+            iter: sequence.into_iter(),
+            index: 0,
         }
     }
 }
@@ -594,7 +662,7 @@ impl<'de> Deserializer<'de> for SeqDeserializer {
             if remaining == 0 {
                 Ok(ret)
             } else {
-                Err(Error::invalid_length(len, &"fewer elements in sequence"))
+                Err(trailing_elements(len - remaining))
             }
         }
     }
@@ -622,7 +690,13 @@ impl<'de> SeqAccess<'de> for SeqDeserializer {
         T: DeserializeSeed<'de>,
     {
         match self.iter.next() {
-            Some(value) => seed.deserialize(value).map(Some),
+            Some(value) => {
+                let index = self.index;
+                self.index += 1;
+                seed.deserialize(value)
+                    .map(Some)
+                    .map_err(|err| error::in_segment(err, Segment::Seq(index)))
+            }
             None => Ok(None),
         }
     }
@@ -637,7 +711,9 @@ impl<'de> SeqAccess<'de> for SeqDeserializer {
 
 pub(crate) struct MapDeserializer {
     iter: <Mapping as IntoIterator>::IntoIter,
-    value: Option<Value>,
+    // The value of the entry whose key was just deserialized, and the key if
+    // it could be kept, for the path of an error in the value.
+    value: Option<(Option<Value>, Value)>,
 }
 
 impl MapDeserializer {
@@ -658,8 +734,13 @@ impl<'de> MapAccess<'de> for MapDeserializer {
     {
         match self.iter.next() {
             Some((key, value)) => {
-                self.value = Some(value);
-                seed.deserialize(key).map(Some)
+                let mut kept = None;
+                let key = seed.deserialize(KeyDeserializer {
+                    key,
+                    kept: &mut kept,
+                });
+                self.value = Some((kept, value));
+                key.map(Some)
             }
             None => Ok(None),
         }
@@ -670,7 +751,10 @@ impl<'de> MapAccess<'de> for MapDeserializer {
         T: DeserializeSeed<'de>,
     {
         match self.value.take() {
-            Some(value) => seed.deserialize(value),
+            Some((key, value)) => seed.deserialize(value).map_err(|err| {
+                let segment = key.as_ref().map_or(Segment::Unknown, key_segment);
+                error::in_segment(err, segment)
+            }),
             None => panic!("visit_value called before visit_key"),
         }
     }
@@ -731,7 +815,7 @@ impl<'de> Deserializer<'de> for &'de Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Bool(v) => visitor.visit_bool(*v),
             other => Err(other.invalid_type(&visitor)),
         }
@@ -832,7 +916,7 @@ impl<'de> Deserializer<'de> for &'de Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag_ref() {
+        match self.untag() {
             Value::String(v) => visitor.visit_borrowed_str(v),
             other => Err(other.invalid_type(&visitor)),
         }
@@ -849,7 +933,7 @@ impl<'de> Deserializer<'de> for &'de Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag_ref() {
+        match self.untag() {
             Value::String(v) => visitor.visit_borrowed_str(v),
             Value::Sequence(v) => visit_sequence_ref(v, visitor),
             other => Err(other.invalid_type(&visitor)),
@@ -883,21 +967,25 @@ impl<'de> Deserializer<'de> for &'de Value {
         }
     }
 
-    fn deserialize_unit_struct<V>(self, _name: &'static str, visitor: V) -> Result<V::Value, Error>
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
-        self.deserialize_unit(visitor)
+        self.deserialize_any(UnitStructVisitor { name, visitor })
     }
 
     fn deserialize_newtype_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         visitor: V,
     ) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
+        if name == peek::NAME {
+            let (kind, tag) = self.peek_node();
+            return visitor.visit_seq(PeekAccess::new(kind, tag, self));
+        }
         visitor.visit_newtype_struct(self)
     }
 
@@ -906,7 +994,7 @@ impl<'de> Deserializer<'de> for &'de Value {
         V: Visitor<'de>,
     {
         static EMPTY: Sequence = Sequence::new();
-        match self.untag_ref() {
+        match self.untag() {
             Value::Sequence(v) => visit_sequence_ref(v, visitor),
             Value::Null => visit_sequence_ref(&EMPTY, visitor),
             other => Err(other.invalid_type(&visitor)),
@@ -936,7 +1024,7 @@ impl<'de> Deserializer<'de> for &'de Value {
     where
         V: Visitor<'de>,
     {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Mapping(v) => visit_mapping_ref(v, visitor),
             Value::Null => visitor.visit_map(&mut MapRefDeserializer {
                 iter: None,
@@ -948,13 +1036,16 @@ impl<'de> Deserializer<'de> for &'de Value {
 
     fn deserialize_struct<V>(
         self,
-        _name: &'static str,
+        name: &'static str,
         _fields: &'static [&'static str],
         visitor: V,
     ) -> Result<V::Value, Error>
     where
         V: Visitor<'de>,
     {
+        if name == scalar_info::NAME {
+            return Err(scalar_info_unsupported());
+        }
         self.deserialize_map(visitor)
     }
 
@@ -1080,11 +1171,15 @@ impl<'de> VariantAccess<'de> for VariantRefDeserializer<'de> {
 
 pub(crate) struct SeqRefDeserializer<'de> {
     iter: slice::Iter<'de, Value>,
+    index: usize,
 }
 
 impl<'de> SeqRefDeserializer<'de> {
     pub(crate) fn new(slice: &'de [Value]) -> Self {
-        SeqRefDeserializer { iter: slice.iter() }
+        SeqRefDeserializer {
+            iter: slice.iter(),
+            index: 0,
+        }
     }
 }
 
@@ -1105,7 +1200,7 @@ impl<'de> Deserializer<'de> for SeqRefDeserializer<'de> {
             if remaining == 0 {
                 Ok(ret)
             } else {
-                Err(Error::invalid_length(len, &"fewer elements in sequence"))
+                Err(trailing_elements(len - remaining))
             }
         }
     }
@@ -1132,7 +1227,13 @@ impl<'de> SeqAccess<'de> for SeqRefDeserializer<'de> {
         T: DeserializeSeed<'de>,
     {
         match self.iter.next() {
-            Some(value) => seed.deserialize(value).map(Some),
+            Some(value) => {
+                let index = self.index;
+                self.index += 1;
+                seed.deserialize(value)
+                    .map(Some)
+                    .map_err(|err| error::in_segment(err, Segment::Seq(index)))
+            }
             None => Ok(None),
         }
     }
@@ -1147,7 +1248,7 @@ impl<'de> SeqAccess<'de> for SeqRefDeserializer<'de> {
 
 pub(crate) struct MapRefDeserializer<'de> {
     iter: Option<<&'de Mapping as IntoIterator>::IntoIter>,
-    value: Option<&'de Value>,
+    value: Option<(&'de Value, &'de Value)>,
 }
 
 impl<'de> MapRefDeserializer<'de> {
@@ -1168,7 +1269,7 @@ impl<'de> MapAccess<'de> for MapRefDeserializer<'de> {
     {
         match self.iter.as_mut().and_then(Iterator::next) {
             Some((key, value)) => {
-                self.value = Some(value);
+                self.value = Some((key, value));
                 seed.deserialize(key).map(Some)
             }
             None => Ok(None),
@@ -1180,7 +1281,9 @@ impl<'de> MapAccess<'de> for MapRefDeserializer<'de> {
         T: DeserializeSeed<'de>,
     {
         match self.value.take() {
-            Some(value) => seed.deserialize(value),
+            Some((key, value)) => seed
+                .deserialize(value)
+                .map_err(|err| error::in_segment(err, key_segment(key))),
             None => panic!("visit_value called before visit_key"),
         }
     }
@@ -1218,6 +1321,166 @@ impl<'de> Deserializer<'de> for MapRefDeserializer<'de> {
     }
 }
 
+// Deserializes the key of an entry of an owned mapping, and keeps it if that
+// costs nothing, so that an error in the value can name it. A string is lent
+// to a visitor that asks for a string rather than handed over, and a string
+// or scalar that is handed over otherwise is not kept.
+struct KeyDeserializer<'a> {
+    key: Value,
+    kept: &'a mut Option<Value>,
+}
+
+impl KeyDeserializer<'_> {
+    fn into_key(self) -> Value {
+        if let Value::Null | Value::Bool(_) | Value::Number(_) = self.key {
+            *self.kept = Some(self.key.clone());
+        }
+        self.key
+    }
+
+    fn lend_string<'de, V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Value::String(string) => {
+                let result = visitor.visit_str(&string);
+                *self.kept = Some(Value::String(string));
+                result
+            }
+            key => key.deserialize_string(visitor),
+        }
+    }
+}
+
+macro_rules! forward_to_key {
+    ($($method:ident)*) => {
+        $(
+            fn $method<V>(self, visitor: V) -> Result<V::Value, Error>
+            where
+                V: Visitor<'de>,
+            {
+                self.into_key().$method(visitor)
+            }
+        )*
+    };
+}
+
+impl<'de> Deserializer<'de> for KeyDeserializer<'_> {
+    type Error = Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.key {
+            Value::String(_) => self.lend_string(visitor),
+            _ => self.into_key().deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_str<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    fn deserialize_string<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.lend_string(visitor)
+    }
+
+    fn deserialize_identifier<V>(self, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_string(visitor)
+    }
+
+    forward_to_key! {
+        deserialize_bool deserialize_i8 deserialize_i16 deserialize_i32
+        deserialize_i64 deserialize_i128 deserialize_u8 deserialize_u16
+        deserialize_u32 deserialize_u64 deserialize_u128 deserialize_f32
+        deserialize_f64 deserialize_char deserialize_bytes deserialize_byte_buf
+        deserialize_option deserialize_unit deserialize_seq deserialize_map
+        deserialize_ignored_any
+    }
+
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.into_key().deserialize_unit_struct(name, visitor)
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.into_key().deserialize_newtype_struct(name, visitor)
+    }
+
+    fn deserialize_tuple<V>(self, len: usize, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.into_key().deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_tuple_struct<V>(
+        self,
+        name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.into_key().deserialize_tuple_struct(name, len, visitor)
+    }
+
+    fn deserialize_struct<V>(
+        self,
+        name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.into_key().deserialize_struct(name, fields, visitor)
+    }
+
+    fn deserialize_enum<V>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.into_key().deserialize_enum(name, variants, visitor)
+    }
+}
+
+fn key_segment(key: &Value) -> Segment {
+    match key {
+        Value::Null => Segment::Map("null".to_owned()),
+        Value::Bool(b) => Segment::Map(b.to_string()),
+        Value::Number(n) => Segment::Map(n.to_string()),
+        Value::String(s) => Segment::Map(s.clone()),
+        Value::Sequence(_) | Value::Mapping(_) | Value::Tagged(_) => Segment::Unknown,
+    }
+}
+
 impl Value {
     #[cold]
     fn invalid_type<E>(&self, exp: &dyn Expected) -> E
@@ -1228,7 +1491,7 @@ impl Value {
     }
 
     #[cold]
-    pub(crate) fn unexpected(&self) -> Unexpected {
+    pub(crate) fn unexpected(&self) -> Unexpected<'_> {
         match self {
             Value::Null => Unexpected::Unit,
             Value::Bool(b) => Unexpected::Bool(*b),
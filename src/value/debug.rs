@@ -1,5 +1,5 @@
 use crate::mapping::Mapping;
-use crate::value::{Number, Value};
+use crate::value::{Number, Sequence, Value};
 use std::fmt::{self, Debug, Display};
 
 impl Debug for Value {
@@ -9,10 +9,7 @@ impl Debug for Value {
             Value::Bool(boolean) => write!(formatter, "Bool({})", boolean),
             Value::Number(number) => write!(formatter, "Number({})", number),
             Value::String(string) => write!(formatter, "String({:?})", string),
-            Value::Sequence(sequence) => {
-                formatter.write_str("Sequence ")?;
-                formatter.debug_list().entries(sequence).finish()
-            }
+            Value::Sequence(sequence) => Debug::fmt(sequence, formatter),
             Value::Mapping(mapping) => Debug::fmt(mapping, formatter),
             Value::Tagged(tagged) => Debug::fmt(tagged, formatter),
         }
@@ -33,6 +30,13 @@ impl Debug for Number {
     }
 }
 
+impl Debug for Sequence {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("Sequence ")?;
+        formatter.debug_list().entries(self).finish()
+    }
+}
+
 impl Debug for Mapping {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
         formatter.write_str("Mapping ")?;
@@ -176,3 +176,23 @@ impl<T: Into<Value>> FromIterator<T> for Value {
         Value::Sequence(vec)
     }
 }
+
+impl<K: Into<Value>, V: Into<Value>> FromIterator<(K, V)> for Value {
+    /// Convert an iteratable type of key-value pairs to a YAML mapping
+    ///
+    /// If a key appears more than once, the last value wins, and the entry
+    /// stays where the key first appeared.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use serde_yaml::Value;
+    ///
+    /// let v = vec![("lorem", 1), ("ipsum", 2)];
+    /// let x: Value = v.into_iter().collect();
+    /// assert_eq!(x["ipsum"], 2);
+    /// ```
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Value::Mapping(Mapping::from_pairs(iter))
+    }
+}
@@ -27,15 +27,15 @@ pub trait Index: private::Sealed {
 
 impl Index for usize {
     fn index_into<'v>(&self, v: &'v Value) -> Option<&'v Value> {
-        match v.untag_ref() {
-            Value::Sequence(vec) => vec.get(*self),
+        match v.untag() {
+            Value::Sequence(vec) => vec.as_slice().get(*self),
             Value::Mapping(vec) => vec.get(Value::Number((*self).into())),
             _ => None,
         }
     }
     fn index_into_mut<'v>(&self, v: &'v mut Value) -> Option<&'v mut Value> {
         match v.untag_mut() {
-            Value::Sequence(vec) => vec.get_mut(*self),
+            Value::Sequence(vec) => vec.as_mut_slice().get_mut(*self),
             Value::Mapping(vec) => vec.get_mut(Value::Number((*self).into())),
             _ => None,
         }
@@ -45,7 +45,7 @@ impl Index for usize {
             match v {
                 Value::Sequence(vec) => {
                     let len = vec.len();
-                    return vec.get_mut(*self).unwrap_or_else(|| {
+                    return vec.as_mut_slice().get_mut(*self).unwrap_or_else(|| {
                         panic!(
                             "cannot access index {} of YAML sequence of length {}",
                             self, len
@@ -67,7 +67,7 @@ fn index_into_mapping<'v, I>(index: &I, v: &'v Value) -> Option<&'v Value>
 where
     I: ?Sized + mapping::Index,
 {
-    match v.untag_ref() {
+    match v.untag() {
         Value::Mapping(map) => map.get(index),
         _ => None,
     }
@@ -161,7 +161,7 @@ where
 }
 
 /// Used in panic messages.
-struct Type<'a>(&'a Value);
+pub(crate) struct Type<'a>(pub(crate) &'a Value);
 
 impl<'a> fmt::Display for Type<'a> {
     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
@@ -0,0 +1,132 @@
+use crate::error::{self, Error};
+use crate::path::Segment;
+use crate::value::Value;
+use serde::de::{Deserialize, Error as _};
+use std::fmt::{self, Debug};
+use std::iter::FusedIterator;
+use std::marker::PhantomData;
+use std::slice;
+
+impl Value {
+    /// Deserializes each element of a sequence into `T`.
+    ///
+    /// Strings can be borrowed from `self`. The first element that `T` cannot
+    /// be deserialized from fails the whole sequence, with an error that
+    /// gives the element's index, like `.[3]: invalid type: ...`. Use
+    /// [`iter_as`](Value::iter_as) to get past such elements instead.
+    ///
+    /// With `allow_single`, a node other than a sequence is taken as a
+    /// sequence of just that node, and null as an empty sequence, the same as
+    /// [`with::seq_or_single`](crate::with::seq_or_single) does when
+    /// deserializing. Otherwise anything but a sequence is an error.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let value: Value = serde_yaml::from_str("hosts: [a.com, b.com]\nport: 80").unwrap();
+    /// let hosts: Vec<&str> = value["hosts"].as_sequence_of(false).unwrap();
+    /// assert_eq!(hosts, ["a.com", "b.com"]);
+    ///
+    /// let ports: Vec<u16> = value["port"].as_sequence_of(true).unwrap();
+    /// assert_eq!(ports, [80]);
+    ///
+    /// let err = value["port"].as_sequence_of::<u16>(false).unwrap_err();
+    /// assert_eq!(err.to_string(), "invalid type: integer `80`, expected a sequence");
+    /// ```
+    pub fn as_sequence_of<'de, T>(&'de self, allow_single: bool) -> Result<Vec<T>, Error>
+    where
+        T: Deserialize<'de>,
+    {
+        self.iter_as(allow_single).collect()
+    }
+
+    /// Iterates over the elements of a sequence, deserializing each into `T`
+    /// as it is reached.
+    ///
+    /// Each element that `T` cannot be deserialized from gives an error with
+    /// the element's index, and iteration continues with the next element. If
+    /// `self` is not a sequence, and `allow_single` does not make it one as
+    /// in [`as_sequence_of`](Value::as_sequence_of), the only item is an
+    /// error.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let value: Value = serde_yaml::from_str("[1, 2, x, 4]").unwrap();
+    /// let results: Vec<_> = value.iter_as::<u8>(false).collect();
+    /// assert_eq!(results[1].as_ref().unwrap(), &2);
+    /// assert_eq!(
+    ///     results[2].as_ref().unwrap_err().to_string(),
+    ///     ".[2]: invalid type: string \"x\", expected u8",
+    /// );
+    /// assert_eq!(results[3].as_ref().unwrap(), &4);
+    /// ```
+    pub fn iter_as<'de, T>(&'de self, allow_single: bool) -> IterAs<'de, T>
+    where
+        T: Deserialize<'de>,
+    {
+        let (elements, indexed, error) = match self {
+            Value::Sequence(sequence) => (sequence.as_slice().iter(), true, None),
+            Value::Null if allow_single => ([].iter(), false, None),
+            single if allow_single => (slice::from_ref(single).iter(), false, None),
+            other => {
+                let error = Error::invalid_type(other.unexpected(), &"a sequence");
+                ([].iter(), false, Some(error))
+            }
+        };
+        IterAs {
+            elements: elements.enumerate(),
+            indexed,
+            error,
+            marker: PhantomData,
+        }
+    }
+}
+
+/// Iterator over the elements of a sequence deserialized into `T`, created
+/// by [`Value::iter_as`].
+pub struct IterAs<'de, T> {
+    elements: std::iter::Enumerate<slice::Iter<'de, Value>>,
+    // Whether errors say which element they are about, which they do not for
+    // a single node taken as a sequence.
+    indexed: bool,
+    error: Option<Error>,
+    marker: PhantomData<fn() -> T>,
+}
+
+impl<'de, T> Iterator for IterAs<'de, T>
+where
+    T: Deserialize<'de>,
+{
+    type Item = Result<T, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(error) = self.error.take() {
+            return Some(Err(error));
+        }
+        let (index, element) = self.elements.next()?;
+        Some(T::deserialize(element).map_err(|err| {
+            if self.indexed {
+                error::in_segment(err, Segment::Seq(index))
+            } else {
+                err
+            }
+        }))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.elements.len() + usize::from(self.error.is_some());
+        (len, Some(len))
+    }
+}
+
+impl<'de, T> ExactSizeIterator for IterAs<'de, T> where T: Deserialize<'de> {}
+
+impl<'de, T> FusedIterator for IterAs<'de, T> where T: Deserialize<'de> {}
+
+impl<'de, T> Debug for IterAs<'de, T> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter
+            .debug_struct("IterAs")
+            .field("remaining", &self.elements.len())
+            .finish()
+    }
+}
@@ -0,0 +1,105 @@
+use crate::error::{self, Error, ErrorImpl};
+use crate::path::Path;
+use crate::value::{Mapping, Number, Value};
+
+/// Converts a JSON value into the equivalent YAML value.
+///
+/// Integers keep their signedness and floats stay floats. Object keys become
+/// string keys of the YAML mapping, in the same order.
+impl From<serde_json::Value> for Value {
+    fn from(json: serde_json::Value) -> Self {
+        match json {
+            serde_json::Value::Null => Value::Null,
+            serde_json::Value::Bool(b) => Value::Bool(b),
+            serde_json::Value::Number(n) => Value::Number(if let Some(u) = n.as_u64() {
+                Number::from(u)
+            } else if let Some(i) = n.as_i64() {
+                Number::from(i)
+            } else {
+                Number::from(n.as_f64().unwrap_or(f64::NAN))
+            }),
+            serde_json::Value::String(s) => Value::String(s),
+            serde_json::Value::Array(array) => {
+                Value::Sequence(array.into_iter().map(Value::from).collect())
+            }
+            serde_json::Value::Object(object) => Value::Mapping(
+                object
+                    .into_iter()
+                    .map(|(k, v)| (Value::String(k), Value::from(v)))
+                    .collect::<Mapping>(),
+            ),
+        }
+    }
+}
+
+/// Converts a YAML value into the equivalent JSON value.
+///
+/// This fails if the YAML value contains a mapping key that is not a string,
+/// a tagged value, or a float that is infinite or NaN, none of which JSON can
+/// represent. The error message names the path to the offending node.
+///
+/// ```
+/// # use serde_yaml::Value;
+/// let yaml: Value = serde_yaml::from_str("a: [1, {b: .inf}]").unwrap();
+/// let err = serde_json::Value::try_from(yaml).unwrap_err();
+/// assert_eq!(err.to_string(), "a[1].b: infinite or NaN float cannot be represented in JSON");
+/// ```
+impl TryFrom<Value> for serde_json::Value {
+    type Error = Error;
+
+    fn try_from(yaml: Value) -> Result<Self, Self::Error> {
+        to_json(yaml, &Path::Root)
+    }
+}
+
+fn to_json(yaml: Value, path: &Path) -> Result<serde_json::Value, Error> {
+    Ok(match yaml {
+        Value::Null => serde_json::Value::Null,
+        Value::Bool(b) => serde_json::Value::Bool(b),
+        Value::Number(n) => serde_json::Value::Number(if let Some(u) = n.as_u64() {
+            serde_json::Number::from(u)
+        } else if let Some(i) = n.as_i64() {
+            serde_json::Number::from(i)
+        } else {
+            match n.as_f64().and_then(serde_json::Number::from_f64) {
+                Some(f) => f,
+                None => {
+                    return Err(error::new(ErrorImpl::NonFiniteFloatInJson(
+                        path.to_string(),
+                    )))
+                }
+            }
+        }),
+        Value::String(s) => serde_json::Value::String(s),
+        Value::Sequence(sequence) => {
+            let mut array = Vec::with_capacity(sequence.len());
+            for (index, element) in sequence.into_iter().enumerate() {
+                let path = Path::Seq {
+                    parent: path,
+                    index,
+                };
+                array.push(to_json(element, &path)?);
+            }
+            serde_json::Value::Array(array)
+        }
+        Value::Mapping(mapping) => {
+            let mut object = serde_json::Map::with_capacity(mapping.len());
+            for (key, value) in mapping {
+                let key = match key {
+                    Value::String(key) => key,
+                    _ => return Err(error::new(ErrorImpl::NonStringKeyInJson(path.to_string()))),
+                };
+                let value = to_json(
+                    value,
+                    &Path::Map {
+                        parent: path,
+                        key: &key,
+                    },
+                )?;
+                object.insert(key, value);
+            }
+            serde_json::Value::Object(object)
+        }
+        Value::Tagged(_) => return Err(error::new(ErrorImpl::TaggedInJson(path.to_string()))),
+    })
+}
@@ -0,0 +1,117 @@
+use crate::value::{Mapping, Sequence, TaggedValue, Value};
+
+/// Controls how [`Value::merge_from`] combines two values.
+///
+/// The default strategy replaces sequences and keeps explicit nulls from the
+/// overlay.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub struct MergeStrategy {
+    /// How a sequence in the overlay is combined with a sequence in the base.
+    pub sequences: SequenceMerge,
+    /// What an explicit `null` in an overlay mapping does to the base.
+    pub nulls: NullMerge,
+}
+
+/// How [`Value::merge_from`] combines two sequences.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum SequenceMerge {
+    /// The overlay sequence replaces the base sequence.
+    #[default]
+    Replace,
+    /// The overlay elements are appended after the base elements.
+    Append,
+    /// Elements at the same position are merged recursively. Extra elements
+    /// in the overlay are appended.
+    MergeByIndex,
+}
+
+/// What [`Value::merge_from`] does with an explicit `null` found as a value in
+/// an overlay mapping.
+#[derive(Copy, Clone, Default, Debug, PartialEq, Eq)]
+pub enum NullMerge {
+    /// The key is set to `null` in the base mapping.
+    #[default]
+    Set,
+    /// The key is removed from the base mapping.
+    Delete,
+}
+
+impl Value {
+    /// Deep-merges `other` on top of `self`.
+    ///
+    /// Mappings are merged key by key, recursively. Sequences are combined
+    /// according to `strategy.sequences`. Tagged values are merged only if
+    /// their tags are equal. In every other case the value from `other`
+    /// replaces the value in `self`.
+    ///
+    /// ```
+    /// use serde_yaml::value::{MergeStrategy, NullMerge, SequenceMerge, Value};
+    ///
+    /// let mut base: Value = serde_yaml::from_str("
+    ///     server: {host: localhost, port: 8080}
+    ///     features: [a, b]
+    ///     debug: true
+    /// ").unwrap();
+    ///
+    /// let overlay: Value = serde_yaml::from_str("
+    ///     server: {host: example.com}
+    ///     features: [c]
+    ///     debug: null
+    /// ").unwrap();
+    ///
+    /// let strategy = MergeStrategy {
+    ///     sequences: SequenceMerge::Append,
+    ///     nulls: NullMerge::Delete,
+    /// };
+    /// base.merge_from(overlay, strategy);
+    ///
+    /// assert_eq!(base["server"]["host"], "example.com");
+    /// assert_eq!(base["server"]["port"], 8080);
+    /// assert_eq!(base["features"].as_sequence().unwrap().len(), 3);
+    /// assert_eq!(base.get("debug"), None);
+    /// ```
+    pub fn merge_from(&mut self, other: Value, strategy: MergeStrategy) {
+        match (self, other) {
+            (Value::Mapping(base), Value::Mapping(overlay)) => {
+                merge_mapping(base, overlay, strategy);
+            }
+            (Value::Sequence(base), Value::Sequence(overlay)) => {
+                merge_sequence(base, overlay, strategy);
+            }
+            (Value::Tagged(base), Value::Tagged(overlay)) if base.tag == overlay.tag => {
+                let TaggedValue { tag: _, value } = *overlay;
+                base.value.merge_from(value, strategy);
+            }
+            (base, overlay) => *base = overlay,
+        }
+    }
+}
+
+fn merge_mapping(base: &mut Mapping, overlay: Mapping, strategy: MergeStrategy) {
+    for (key, value) in overlay {
+        if let (Value::Null, NullMerge::Delete) = (&value, strategy.nulls) {
+            base.shift_remove(&key);
+            continue;
+        }
+        match base.get_mut(&key) {
+            Some(existing) => existing.merge_from(value, strategy),
+            None => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+fn merge_sequence(base: &mut Sequence, overlay: Sequence, strategy: MergeStrategy) {
+    match strategy.sequences {
+        SequenceMerge::Replace => *base = overlay,
+        SequenceMerge::Append => base.extend(overlay),
+        SequenceMerge::MergeByIndex => {
+            let mut overlay = overlay.into_iter();
+            for (existing, value) in base.iter_mut().zip(&mut overlay) {
+                existing.merge_from(value, strategy);
+            }
+            base.extend(overlay);
+        }
+    }
+}
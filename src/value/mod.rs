@@ -1,28 +1,53 @@
 //! The Value enum, a loosely typed way of representing any valid YAML value.
 
+mod canonical;
 mod de;
 mod debug;
 mod from;
 mod index;
+mod iter_as;
+#[cfg(feature = "json")]
+mod json;
+mod merge;
 mod partial_eq;
+pub(crate) mod pointer;
 mod ser;
+pub(crate) mod styled;
 pub(crate) mod tagged;
+mod walk;
 
 use crate::error::{self, Error, ErrorImpl};
+use crate::mapping;
 use serde::de::{Deserialize, DeserializeOwned, IntoDeserializer};
 use serde::Serialize;
+use std::cmp::Ordering;
+use std::fmt::{self, Debug, Display};
 use std::hash::{Hash, Hasher};
 use std::mem;
+use std::str::FromStr;
 
 pub use self::index::Index;
+pub use self::iter_as::IterAs;
+pub use self::merge::{MergeStrategy, NullMerge, SequenceMerge};
 pub use self::ser::Serializer;
+pub use self::styled::StyledValue;
 pub use self::tagged::{Tag, TaggedValue};
+pub use self::walk::{Path, PathSegment, Walk};
 #[doc(inline)]
 pub use crate::mapping::Mapping;
 pub use crate::number::Number;
+pub use crate::sequence::Sequence;
 
 /// Represents any valid YAML value.
-#[derive(Clone, PartialEq, PartialOrd)]
+///
+/// Values are totally ordered, so that they can be the keys of a `BTreeMap`
+/// or the elements of a `BTreeSet`. The order is the one
+/// [`Mapping::sort_keys`] sorts by: null, then booleans, numbers, strings,
+/// sequences, mappings and tagged values. Integers come before floats, NaN
+/// after every other float, and mappings compare their entries in order of
+/// their keys, as two mappings with the same entries in a different order
+/// are equal.
+#[derive(Clone, PartialEq)]
 pub enum Value {
     /// Represents a YAML null value.
     Null,
@@ -78,9 +103,6 @@ impl Default for Value {
     }
 }
 
-/// A YAML sequence in which the elements are `serde_yaml::Value`.
-pub type Sequence = Vec<Value>;
-
 /// Convert a `T` into `serde_yaml::Value` which is an enum that can represent
 /// any valid YAML data.
 ///
@@ -109,6 +131,10 @@ where
 /// the YAML map or some number is too big to fit in the expected primitive
 /// type.
 ///
+/// A `Value` has no line and column information, so instead the error message
+/// starts with the path to the node where deserialization failed, like
+/// `spec.containers[2].ports[0]: invalid type: ...`.
+///
 /// ```
 /// # use serde_yaml::Value;
 /// let val = Value::String("foo".to_owned());
@@ -189,6 +215,30 @@ impl Value {
         index.index_into_mut(self)
     }
 
+    /// Takes the value out of the `Value`, leaving a `Null` in its place.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut v: Value = serde_yaml::from_str("{x: y}").unwrap();
+    /// assert_eq!(v["x"].take(), "y");
+    /// assert_eq!(v["x"], Value::Null);
+    /// ```
+    pub fn take(&mut self) -> Value {
+        mem::replace(self, Value::Null)
+    }
+
+    /// Replaces the value with `value`, returning the previous value.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut v: Value = serde_yaml::from_str("{x: y}").unwrap();
+    /// assert_eq!(v["x"].replace(Value::from(1)), "y");
+    /// assert_eq!(v["x"], 1);
+    /// ```
+    pub fn replace(&mut self, value: Value) -> Value {
+        mem::replace(self, value)
+    }
+
     /// Returns true if the `Value` is a Null. Returns false otherwise.
     ///
     /// For any Value on which `is_null` returns true, `as_null` is guaranteed
@@ -206,7 +256,7 @@ impl Value {
     /// assert!(!v.is_null());
     /// ```
     pub fn is_null(&self) -> bool {
-        if let Value::Null = self.untag_ref() {
+        if let Value::Null = self.untag() {
             true
         } else {
             false
@@ -227,7 +277,7 @@ impl Value {
     /// assert_eq!(v.as_null(), None);
     /// ```
     pub fn as_null(&self) -> Option<()> {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Null => Some(()),
             _ => None,
         }
@@ -268,12 +318,34 @@ impl Value {
     /// assert_eq!(v.as_bool(), None);
     /// ```
     pub fn as_bool(&self) -> Option<bool> {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Bool(b) => Some(*b),
             _ => None,
         }
     }
 
+    /// If the `Value` is a Boolean, returns a mutable reference to the
+    /// associated bool. Returns None otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut v: Value = serde_yaml::from_str("true").unwrap();
+    /// *v.as_bool_mut().unwrap() = false;
+    /// assert_eq!(v, false);
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut v: Value = serde_yaml::from_str("42").unwrap();
+    /// assert_eq!(v.as_bool_mut(), None);
+    /// ```
+    pub fn as_bool_mut(&mut self) -> Option<&mut bool> {
+        match self.untag_mut() {
+            Value::Bool(b) => Some(b),
+            _ => None,
+        }
+    }
+
     /// Returns true if the `Value` is a Number. Returns false otherwise.
     ///
     /// ```
@@ -288,12 +360,55 @@ impl Value {
     /// assert!(!v.is_number());
     /// ```
     pub fn is_number(&self) -> bool {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Number(_) => true,
             _ => false,
         }
     }
 
+    /// If the `Value` is a Number, returns a reference to it. Returns None
+    /// otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml::{Number, Value};
+    /// let v: Value = serde_yaml::from_str("5").unwrap();
+    /// assert_eq!(v.as_number(), Some(&Number::from(5)));
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("true").unwrap();
+    /// assert_eq!(v.as_number(), None);
+    /// ```
+    pub fn as_number(&self) -> Option<&Number> {
+        match self.untag() {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is a Number, returns a mutable reference to it. Returns
+    /// None otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml::{Number, Value};
+    /// let mut v: Value = serde_yaml::from_str("5").unwrap();
+    /// *v.as_number_mut().unwrap() = Number::from(6);
+    /// assert_eq!(v, 6);
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut v: Value = serde_yaml::from_str("true").unwrap();
+    /// assert_eq!(v.as_number_mut(), None);
+    /// ```
+    pub fn as_number_mut(&mut self) -> Option<&mut Number> {
+        match self.untag_mut() {
+            Value::Number(n) => Some(n),
+            _ => None,
+        }
+    }
+
     /// Returns true if the `Value` is an integer between `i64::MIN` and
     /// `i64::MAX`.
     ///
@@ -330,7 +445,7 @@ impl Value {
     /// assert_eq!(v.as_i64(), None);
     /// ```
     pub fn as_i64(&self) -> Option<i64> {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Number(n) => n.as_i64(),
             _ => None,
         }
@@ -372,7 +487,7 @@ impl Value {
     /// assert_eq!(v.as_u64(), None);
     /// ```
     pub fn as_u64(&self) -> Option<u64> {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Number(n) => n.as_u64(),
             _ => None,
         }
@@ -398,7 +513,7 @@ impl Value {
     /// assert!(!v.is_f64());
     /// ```
     pub fn is_f64(&self) -> bool {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Number(n) => n.is_f64(),
             _ => false,
         }
@@ -419,7 +534,7 @@ impl Value {
     /// assert_eq!(v.as_f64(), None);
     /// ```
     pub fn as_f64(&self) -> Option<f64> {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Number(i) => i.as_f64(),
             _ => None,
         }
@@ -460,12 +575,56 @@ impl Value {
     /// assert_eq!(v.as_str(), None);
     /// ```
     pub fn as_str(&self) -> Option<&str> {
-        match self.untag_ref() {
+        match self.untag() {
+            Value::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` is a String, returns a mutable reference to the
+    /// associated String. Returns None otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut v: Value = serde_yaml::from_str("'lorem'").unwrap();
+    /// v.as_str_mut().unwrap().push_str(" ipsum");
+    /// assert_eq!(v, "lorem ipsum");
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut v: Value = serde_yaml::from_str("false").unwrap();
+    /// assert_eq!(v.as_str_mut(), None);
+    /// ```
+    pub fn as_str_mut(&mut self) -> Option<&mut String> {
+        match self.untag_mut() {
             Value::String(s) => Some(s),
             _ => None,
         }
     }
 
+    /// If the `Value` is a String, returns the associated String. Otherwise
+    /// gives back the original `Value`, including any tags.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("'lorem ipsum'").unwrap();
+    /// assert_eq!(v.into_string(), Ok("lorem ipsum".to_owned()));
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("false").unwrap();
+    /// assert_eq!(v.into_string(), Err(Value::Bool(false)));
+    /// ```
+    #[must_use = "this returns the result of the conversion, without modifying the original"]
+    pub fn into_string(self) -> Result<String, Value> {
+        self.untag_into(|value| match value {
+            Value::String(s) => Ok(s),
+            other => Err(other),
+        })
+    }
+
     /// Returns true if the `Value` is a sequence. Returns false otherwise.
     ///
     /// ```
@@ -489,7 +648,7 @@ impl Value {
     /// ```
     /// # use serde_yaml::{Value, Number};
     /// let v: Value = serde_yaml::from_str("[1, 2]").unwrap();
-    /// assert_eq!(v.as_sequence(), Some(&vec![Value::Number(Number::from(1)), Value::Number(Number::from(2))]));
+    /// assert_eq!(v.as_sequence().unwrap(), &vec![Value::Number(Number::from(1)), Value::Number(Number::from(2))]);
     /// ```
     ///
     /// ```
@@ -498,7 +657,7 @@ impl Value {
     /// assert_eq!(v.as_sequence(), None);
     /// ```
     pub fn as_sequence(&self) -> Option<&Sequence> {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Sequence(seq) => Some(seq),
             _ => None,
         }
@@ -527,6 +686,28 @@ impl Value {
         }
     }
 
+    /// If the `Value` is a sequence, returns the associated sequence. Otherwise
+    /// gives back the original `Value`, including any tags.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("[1, 2]").unwrap();
+    /// assert_eq!(v.into_sequence().unwrap(), vec![Value::from(1), Value::from(2)]);
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("false").unwrap();
+    /// assert_eq!(v.into_sequence(), Err(Value::Bool(false)));
+    /// ```
+    #[must_use = "this returns the result of the conversion, without modifying the original"]
+    pub fn into_sequence(self) -> Result<Sequence, Value> {
+        self.untag_into(|value| match value {
+            Value::Sequence(seq) => Ok(seq),
+            other => Err(other),
+        })
+    }
+
     /// Returns true if the `Value` is a mapping. Returns false otherwise.
     ///
     /// ```
@@ -563,7 +744,7 @@ impl Value {
     /// assert_eq!(v.as_mapping(), None);
     /// ```
     pub fn as_mapping(&self) -> Option<&Mapping> {
-        match self.untag_ref() {
+        match self.untag() {
             Value::Mapping(map) => Some(map),
             _ => None,
         }
@@ -597,6 +778,79 @@ impl Value {
         }
     }
 
+    /// If the `Value` is a mapping, returns the associated mapping. Otherwise
+    /// gives back the original `Value`, including any tags.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("a: 42").unwrap();
+    /// let m = v.into_mapping().unwrap();
+    /// assert_eq!(m["a"], 42);
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("false").unwrap();
+    /// assert_eq!(v.into_mapping(), Err(Value::Bool(false)));
+    /// ```
+    #[must_use = "this returns the result of the conversion, without modifying the original"]
+    pub fn into_mapping(self) -> Result<Mapping, Value> {
+        self.untag_into(|value| match value {
+            Value::Mapping(map) => Ok(map),
+            other => Err(other),
+        })
+    }
+
+    /// Returns true if the `Value` carries a tag. Returns false otherwise.
+    ///
+    /// Unlike the other `is_*` methods, this does not look through tags.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("!Thing 1").unwrap();
+    /// assert!(v.is_tagged());
+    /// ```
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("1").unwrap();
+    /// assert!(!v.is_tagged());
+    /// ```
+    pub fn is_tagged(&self) -> bool {
+        self.as_tagged().is_some()
+    }
+
+    /// If the `Value` carries a tag, returns a reference to the tagged value.
+    /// Returns None otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let v: Value = serde_yaml::from_str("!Thing 1").unwrap();
+    /// assert_eq!(v.as_tagged().unwrap().tag, "Thing");
+    /// ```
+    pub fn as_tagged(&self) -> Option<&TaggedValue> {
+        match self {
+            Value::Tagged(tagged) => Some(tagged),
+            _ => None,
+        }
+    }
+
+    /// If the `Value` carries a tag, returns a mutable reference to the tagged
+    /// value. Returns None otherwise.
+    ///
+    /// ```
+    /// # use serde_yaml::value::{Tag, Value};
+    /// let mut v: Value = serde_yaml::from_str("!Thing 1").unwrap();
+    /// v.as_tagged_mut().unwrap().tag = Tag::new("Other");
+    /// assert_eq!(serde_yaml::to_string(&v).unwrap(), "!Other 1\n");
+    /// ```
+    pub fn as_tagged_mut(&mut self) -> Option<&mut TaggedValue> {
+        match self {
+            Value::Tagged(tagged) => Some(tagged),
+            _ => None,
+        }
+    }
+
     /// Performs merging of `<<` keys into the surrounding mapping.
     ///
     /// The intended use of this in YAML is described in
@@ -672,6 +926,18 @@ impl Value {
 
 impl Eq for Value {}
 
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        mapping::total_cmp(self, other)
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
 // NOTE: This impl must be kept consistent with HashLikeValue's Hash impl in
 // mapping.rs in order for value[str] indexing to work.
 impl Hash for Value {
@@ -689,6 +955,60 @@ impl Hash for Value {
     }
 }
 
+/// Formats the value as a block-style YAML document, the same as
+/// [`to_string`](crate::to_string) but without the trailing newline, unless
+/// the document ends in a string that ends in a newline. Such a string may be
+/// written as a block scalar, which needs the line break to keep its own.
+///
+/// If the value cannot be serialized, its `Debug` representation is written
+/// instead, so formatting never fails.
+///
+/// ```
+/// # use serde_yaml::Value;
+/// let v: Value = serde_yaml::from_str("{a: [1, 2]}").unwrap();
+/// assert_eq!(v.to_string(), "a:\n- 1\n- 2");
+/// ```
+impl Display for Value {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        match crate::to_string(self) {
+            Ok(yaml) if !ends_in_newline(self) => {
+                formatter.write_str(yaml.strip_suffix('\n').unwrap_or(&yaml))
+            }
+            Ok(yaml) => formatter.write_str(&yaml),
+            Err(_) => Debug::fmt(self, formatter),
+        }
+    }
+}
+
+// Whether the last scalar written for a value is a string ending in a newline.
+fn ends_in_newline(value: &Value) -> bool {
+    match value {
+        Value::String(string) => string.ends_with('\n'),
+        Value::Sequence(sequence) => sequence.last().is_some_and(ends_in_newline),
+        Value::Mapping(mapping) => mapping.values().next_back().is_some_and(ends_in_newline),
+        Value::Tagged(tagged) => ends_in_newline(&tagged.value),
+        Value::Null | Value::Bool(_) | Value::Number(_) => false,
+    }
+}
+
+/// Parses a single YAML document, the same as [`from_str`](crate::from_str).
+///
+/// ```
+/// # use serde_yaml::Value;
+/// # fn main() -> serde_yaml::Result<()> {
+/// let v: Value = "a: 1".parse()?;
+/// assert_eq!(v["a"], 1);
+/// # Ok(())
+/// # }
+/// ```
+impl FromStr for Value {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        crate::from_str(s)
+    }
+}
+
 impl<'de> IntoDeserializer<'de, Error> for Value {
     type Deserializer = Self;
 
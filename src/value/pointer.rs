@@ -0,0 +1,212 @@
+use crate::error::{self, Error, ErrorImpl};
+use crate::value::index::Type;
+use crate::value::{to_value, Mapping, Value};
+use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+
+impl Value {
+    /// Looks up a value by a JSON Pointer-style path, like `/spec/replicas/0`.
+    ///
+    /// Each `/`-separated token is a mapping key or a sequence index. Within
+    /// a token, `~1` stands for `/` and `~0` for `~`. A token that is not
+    /// found as a string key is also tried as an integer key. Tags along the
+    /// way are looked through. The empty pointer refers to `self`.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let value: Value = serde_yaml::from_str("{a: [x, {b: 1}]}").unwrap();
+    /// assert_eq!(value.pointer("/a/1/b").unwrap(), 1);
+    /// assert_eq!(value.pointer("/a/2"), None);
+    /// ```
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        resolve(self, pointer).ok()
+    }
+
+    /// Looks up a value by a JSON Pointer-style path and returns a mutable
+    /// reference to it. See [`pointer`](Value::pointer) for the syntax.
+    pub fn pointer_mut(&mut self, pointer: &str) -> Option<&mut Value> {
+        let mut target = self;
+        for token in tokens(pointer).ok()? {
+            target = match target.untag_mut() {
+                Value::Mapping(mapping) => {
+                    let key = mapping_key(mapping, &token)?;
+                    mapping.get_mut(key)?
+                }
+                Value::Sequence(sequence) => {
+                    sequence.as_mut_slice().get_mut(sequence_index(&token)?)?
+                }
+                _ => return None,
+            };
+        }
+        Some(target)
+    }
+
+    /// Deserializes the value found at `pointer` into `T`. See
+    /// [`pointer`](Value::pointer) for the syntax.
+    ///
+    /// Strings can be borrowed from `self`. If nothing is found at the
+    /// pointer, or the value there does not have the shape `T` expects, the
+    /// error message includes the pointer.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let value: Value = serde_yaml::from_str("spec: {replicas: 3, image: nginx}").unwrap();
+    /// assert_eq!(value.get_as::<u32>("/spec/replicas").unwrap(), 3);
+    /// assert_eq!(value.get_as::<&str>("/spec/image").unwrap(), "nginx");
+    ///
+    /// let err = value.get_as::<u32>("/spec/image").unwrap_err();
+    /// assert_eq!(err.to_string(), "/spec/image: invalid type: string \"nginx\", expected u32");
+    /// ```
+    pub fn get_as<'de, T>(&'de self, pointer: &str) -> Result<T, Error>
+    where
+        T: Deserialize<'de>,
+    {
+        let value = resolve(self, pointer).map_err(error::new)?;
+        T::deserialize(value).map_err(|err| error::at_pointer(err, pointer))
+    }
+
+    /// Serializes `value` and stores it at `pointer`, replacing whatever was
+    /// there. See [`pointer`](Value::pointer) for the syntax.
+    ///
+    /// Missing mapping entries along the way are created, and null nodes are
+    /// turned into mappings. A sequence index may be one past the end (or
+    /// `-`) to append.
+    ///
+    /// ```
+    /// # use serde_yaml::Value;
+    /// let mut value = Value::Null;
+    /// value.set_from("/spec/ports", &[80]).unwrap();
+    /// value.set_from("/spec/ports/-", &8080).unwrap();
+    /// value.set_from("/spec/image", "nginx").unwrap();
+    /// assert_eq!(value.to_string(), "spec:\n  ports:\n  - 80\n  - 8080\n  image: nginx");
+    /// ```
+    pub fn set_from<T>(&mut self, pointer: &str, value: &T) -> Result<(), Error>
+    where
+        T: ?Sized + Serialize,
+    {
+        let value = to_value(value)?;
+        *entry(self, pointer).map_err(error::new)? = value;
+        Ok(())
+    }
+}
+
+pub(crate) fn tokens(pointer: &str) -> Result<impl Iterator<Item = Cow<'_, str>>, ErrorImpl> {
+    let rest = match pointer.strip_prefix('/') {
+        Some(rest) => Some(rest),
+        None if pointer.is_empty() => None,
+        None => return Err(ErrorImpl::InvalidPointer(pointer.to_owned())),
+    };
+    Ok(rest
+        .into_iter()
+        .flat_map(|rest| rest.split('/'))
+        .map(|token| {
+            if token.contains('~') {
+                Cow::Owned(token.replace("~1", "/").replace("~0", "~"))
+            } else {
+                Cow::Borrowed(token)
+            }
+        }))
+}
+
+// The part of `pointer` up to and including the `n`th token.
+pub(crate) fn prefix(pointer: &str, n: usize) -> String {
+    match pointer.match_indices('/').nth(n + 1) {
+        Some((end, _)) => pointer[..end].to_owned(),
+        None => pointer.to_owned(),
+    }
+}
+
+fn mapping_key(mapping: &Mapping, token: &str) -> Option<Value> {
+    if mapping.contains_key(token) {
+        return Some(Value::String(token.to_owned()));
+    }
+    if let Ok(n) = token.parse::<u64>() {
+        if mapping.contains_key(n) {
+            return Some(Value::Number(n.into()));
+        }
+    }
+    if let Ok(n) = token.parse::<i64>() {
+        if mapping.contains_key(n) {
+            return Some(Value::Number(n.into()));
+        }
+    }
+    None
+}
+
+pub(crate) fn sequence_index(token: &str) -> Option<usize> {
+    if token.is_empty() || !token.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    token.parse().ok()
+}
+
+fn resolve<'a>(value: &'a Value, pointer: &str) -> Result<&'a Value, ErrorImpl> {
+    let mut target = value;
+    for (n, token) in tokens(pointer)?.enumerate() {
+        target = match target.untag() {
+            Value::Mapping(mapping) => {
+                mapping_key(mapping, &token).and_then(|key| mapping.get(key))
+            }
+            Value::Sequence(sequence) => match sequence_index(&token) {
+                Some(index) if index >= sequence.len() => {
+                    return Err(ErrorImpl::PointerIndexOutOfBounds(
+                        prefix(pointer, n),
+                        sequence.len(),
+                    ));
+                }
+                Some(index) => sequence.as_slice().get(index),
+                None => None,
+            },
+            other => {
+                return Err(ErrorImpl::PointerNotTraversable(
+                    prefix(pointer, n),
+                    Type(other).to_string(),
+                ));
+            }
+        }
+        .ok_or_else(|| ErrorImpl::PointerNotFound(prefix(pointer, n)))?;
+    }
+    Ok(target)
+}
+
+fn entry<'a>(value: &'a mut Value, pointer: &str) -> Result<&'a mut Value, ErrorImpl> {
+    let mut target = value;
+    for (n, token) in tokens(pointer)?.enumerate() {
+        let node = target.untag_mut();
+        if let Value::Null = node {
+            *node = Value::Mapping(Mapping::new());
+        }
+        target = match node {
+            Value::Mapping(mapping) => {
+                let key = mapping_key(mapping, &token)
+                    .unwrap_or_else(|| Value::String(token.into_owned()));
+                mapping.entry(key).or_insert(Value::Null)
+            }
+            Value::Sequence(sequence) => {
+                let len = sequence.len();
+                let index = if token == "-" {
+                    len
+                } else {
+                    match sequence_index(&token) {
+                        Some(index) => index,
+                        None => return Err(ErrorImpl::PointerNotFound(prefix(pointer, n))),
+                    }
+                };
+                if index > len {
+                    return Err(ErrorImpl::PointerIndexOutOfBounds(prefix(pointer, n), len));
+                }
+                if index == len {
+                    sequence.push(Value::Null);
+                }
+                &mut sequence[index]
+            }
+            other => {
+                return Err(ErrorImpl::PointerNotTraversable(
+                    prefix(pointer, n),
+                    Type(other).to_string(),
+                ));
+            }
+        };
+    }
+    Ok(target)
+}
@@ -38,6 +38,12 @@ impl Serialize for Value {
 /// value of type `T` to YAML text, this one goes from `T` to
 /// `serde_yaml::Value`.
 ///
+/// The output is the same `Value` that parsing the YAML text produced by
+/// [`serde_yaml::to_string`][crate::to_string] would give, with two
+/// exceptions. Byte arrays, which the YAML serializer does not support, become
+/// a sequence of integers. Integers outside the range of `i64` and `u64`,
+/// which `Value` cannot hold, are an error.
+///
 /// The `to_value` function is implementable as:
 ///
 /// ```
@@ -53,6 +59,15 @@ impl Serialize for Value {
 /// ```
 pub struct Serializer;
 
+// A tag directly on a tagged value has no YAML representation, so this is
+// rejected the same way the YAML serializer rejects it.
+fn tag_value(tag: Tag, value: Value) -> Result<Value> {
+    if let Value::Tagged(_) = value {
+        return Err(error::new(ErrorImpl::SerializeNestedEnum));
+    }
+    Ok(Value::Tagged(Box::new(TaggedValue { tag, value })))
+}
+
 impl ser::Serializer for Serializer {
     type Ok = Value;
     type Error = Error;
@@ -91,7 +106,7 @@ impl ser::Serializer for Serializer {
         } else if let Ok(v) = i64::try_from(v) {
             self.serialize_i64(v)
         } else {
-            Ok(Value::String(v.to_string()))
+            Err(error::new(ErrorImpl::IntegerOutOfRange(v.to_string())))
         }
     }
 
@@ -115,7 +130,7 @@ impl ser::Serializer for Serializer {
         if let Ok(v) = u64::try_from(v) {
             self.serialize_u64(v)
         } else {
-            Ok(Value::String(v.to_string()))
+            Err(error::new(ErrorImpl::IntegerOutOfRange(v.to_string())))
         }
     }
 
@@ -180,10 +195,7 @@ impl ser::Serializer for Serializer {
         if variant.is_empty() {
             return Err(error::new(ErrorImpl::EmptyTag));
         }
-        Ok(Value::Tagged(Box::new(TaggedValue {
-            tag: Tag::new(variant),
-            value: to_value(value)?,
-        })))
+        tag_value(Tag::new(variant), to_value(value)?)
     }
 
     fn serialize_none(self) -> Result<Value> {
@@ -787,7 +799,7 @@ impl ser::SerializeMap for SerializeMap {
     fn end(self) -> Result<Value> {
         Ok(match self {
             SerializeMap::CheckForTag => Value::Mapping(Mapping::new()),
-            SerializeMap::Tagged(tagged) => Value::Tagged(Box::new(tagged)),
+            SerializeMap::Tagged(tagged) => return tag_value(tagged.tag, tagged.value),
             SerializeMap::Untagged { mapping, .. } => Value::Mapping(mapping),
         })
     }
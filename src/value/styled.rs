@@ -0,0 +1,390 @@
+use crate::libyaml::event::ScalarStyle;
+use crate::scalar_info;
+use crate::value::tagged::{SerializeTag, TagStringVisitor};
+use crate::value::{Mapping, Number, Tag, TaggedValue, Value};
+use serde::de::{
+    self, Deserialize, Deserializer, EnumAccess, MapAccess, SeqAccess, VariantAccess, Visitor,
+};
+use serde::ser::{Serialize, SerializeMap, Serializer};
+use std::fmt;
+
+// The newtype struct name by which `StyledValue` asks this crate's
+// deserializer for the style of each scalar.
+pub(crate) const NAME: &str = "$serde_yaml::StyledValue";
+
+// The newtype struct names by which `StyledValue` asks this crate's
+// serializer to write a string in a style.
+const PLAIN: &str = "$serde_yaml::StyledValue::plain";
+const SINGLE_QUOTED: &str = "$serde_yaml::StyledValue::single-quoted";
+const DOUBLE_QUOTED: &str = "$serde_yaml::StyledValue::double-quoted";
+const LITERAL: &str = "$serde_yaml::StyledValue::literal";
+const FOLDED: &str = "$serde_yaml::StyledValue::folded";
+
+/// A YAML value like [`Value`], whose strings remember the style they were
+/// written in.
+///
+/// Parsing a file into a `Value` and serializing it again writes every string
+/// in whichever style the serializer prefers, so editing one entry of a file
+/// whose strings are quoted rewrites all of them. A `StyledValue`
+/// deserialized from YAML keeps the style of each string, and serializing it
+/// writes each string in that style again unless the string can no longer be
+/// written that way, such as a string changed to `true` that was plain and
+/// now needs quotes.
+///
+/// Only strings keep their style. Numbers, booleans and nulls are written the
+/// way serializing them always is, so `0x1F` comes back as `31`, and
+/// comments, anchors and the style of collections are not kept at all. For
+/// those, see [`Document`](crate::document::Document).
+///
+/// The style is only known to this crate's [`Deserializer`](crate::Deserializer).
+/// Deserializing a `StyledValue` from a [`Value`] or from another format
+/// leaves every style as `None`, and serializing one into another format
+/// serializes just the values.
+///
+/// ```
+/// # fn main() -> serde_yaml::Result<()> {
+/// use serde_yaml::StyledValue;
+///
+/// let input = "\
+/// name: 'web'
+/// version: \"3.10\"
+/// command: |
+///   run
+///   serve
+/// replicas: 1
+/// ";
+/// let mut value: StyledValue = serde_yaml::from_str(input)?;
+/// if let Some(StyledValue::String(name, _style)) = value.get_mut("name") {
+///     *name = "api".to_owned();
+/// }
+///
+/// let expected = "\
+/// name: 'api'
+/// version: \"3.10\"
+/// command: |
+///   run
+///   serve
+/// replicas: 1
+/// ";
+/// assert_eq!(serde_yaml::to_string(&value)?, expected);
+///
+/// // The same as a `Value` loses the quotes that were not needed.
+/// let value: serde_yaml::Value = serde_yaml::from_str(input)?;
+/// assert!(serde_yaml::to_string(&value)?.starts_with("name: web\n"));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone, PartialEq, Debug)]
+pub enum StyledValue {
+    /// Represents a YAML null value.
+    Null,
+    /// Represents a YAML boolean.
+    Bool(bool),
+    /// Represents a YAML numerical value, whether integer or floating point.
+    Number(Number),
+    /// Represents a YAML string, and the style it was written in, if known.
+    String(String, Option<ScalarStyle>),
+    /// Represents a YAML sequence.
+    Sequence(Vec<StyledValue>),
+    /// Represents a YAML mapping, as its entries in order.
+    Mapping(Vec<(StyledValue, StyledValue)>),
+    /// A representation of YAML's `!Tag` syntax.
+    Tagged(Tag, Box<StyledValue>),
+}
+
+impl StyledValue {
+    /// The string this value holds, if it is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            StyledValue::String(string, _style) => Some(string),
+            _ => None,
+        }
+    }
+
+    /// The style of the string this value holds, if it is a string whose
+    /// style is known.
+    pub fn style(&self) -> Option<ScalarStyle> {
+        match self {
+            StyledValue::String(_string, style) => *style,
+            _ => None,
+        }
+    }
+
+    /// The value of the entry with the given string key, if this is a
+    /// mapping with such an entry.
+    pub fn get(&self, key: &str) -> Option<&StyledValue> {
+        match self {
+            StyledValue::Mapping(entries) => entries
+                .iter()
+                .find(|(k, _v)| k.as_str() == Some(key))
+                .map(|(_k, v)| v),
+            _ => None,
+        }
+    }
+
+    /// The value of the entry with the given string key, mutably, if this is
+    /// a mapping with such an entry.
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut StyledValue> {
+        match self {
+            StyledValue::Mapping(entries) => entries
+                .iter_mut()
+                .find(|(k, _v)| k.as_str() == Some(key))
+                .map(|(_k, v)| v),
+            _ => None,
+        }
+    }
+
+    // The value of a scalar that was written in the given style.
+    fn with_style(value: Value, style: ScalarStyle) -> Self {
+        match value {
+            Value::String(string) => StyledValue::String(string, Some(style)),
+            Value::Tagged(tagged) => StyledValue::Tagged(
+                tagged.tag,
+                Box::new(StyledValue::with_style(tagged.value, style)),
+            ),
+            value => StyledValue::from(value),
+        }
+    }
+}
+
+impl From<Value> for StyledValue {
+    /// Converts a `Value` into a `StyledValue` without any styles.
+    fn from(value: Value) -> Self {
+        match value {
+            Value::Null => StyledValue::Null,
+            Value::Bool(b) => StyledValue::Bool(b),
+            Value::Number(n) => StyledValue::Number(n),
+            Value::String(string) => StyledValue::String(string, None),
+            Value::Sequence(sequence) => {
+                StyledValue::Sequence(sequence.into_iter().map(StyledValue::from).collect())
+            }
+            Value::Mapping(mapping) => StyledValue::Mapping(
+                mapping
+                    .into_iter()
+                    .map(|(k, v)| (StyledValue::from(k), StyledValue::from(v)))
+                    .collect(),
+            ),
+            Value::Tagged(tagged) => {
+                StyledValue::Tagged(tagged.tag, Box::new(StyledValue::from(tagged.value)))
+            }
+        }
+    }
+}
+
+impl From<StyledValue> for Value {
+    /// Converts a `StyledValue` into a `Value`, dropping the styles. Of
+    /// entries with equal keys, the last is kept.
+    fn from(value: StyledValue) -> Self {
+        match value {
+            StyledValue::Null => Value::Null,
+            StyledValue::Bool(b) => Value::Bool(b),
+            StyledValue::Number(n) => Value::Number(n),
+            StyledValue::String(string, _style) => Value::String(string),
+            StyledValue::Sequence(sequence) => {
+                Value::Sequence(sequence.into_iter().map(Value::from).collect())
+            }
+            StyledValue::Mapping(entries) => Value::Mapping(
+                entries
+                    .into_iter()
+                    .map(|(k, v)| (Value::from(k), Value::from(v)))
+                    .collect::<Mapping>(),
+            ),
+            StyledValue::Tagged(tag, value) => Value::Tagged(Box::new(TaggedValue {
+                tag,
+                value: Value::from(*value),
+            })),
+        }
+    }
+}
+
+// The style that a newtype struct name asks this crate's serializer to write
+// a string in.
+pub(crate) fn style_for_name(name: &str) -> Option<ScalarStyle> {
+    match name {
+        PLAIN => Some(ScalarStyle::Plain),
+        SINGLE_QUOTED => Some(ScalarStyle::SingleQuoted),
+        DOUBLE_QUOTED => Some(ScalarStyle::DoubleQuoted),
+        LITERAL => Some(ScalarStyle::Literal),
+        FOLDED => Some(ScalarStyle::Folded),
+        _ => None,
+    }
+}
+
+impl Serialize for StyledValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        match self {
+            StyledValue::Null => serializer.serialize_unit(),
+            StyledValue::Bool(b) => serializer.serialize_bool(*b),
+            StyledValue::Number(n) => n.serialize(serializer),
+            StyledValue::String(string, None) => serializer.serialize_str(string),
+            StyledValue::String(string, Some(style)) => {
+                let name = match style {
+                    ScalarStyle::Any | ScalarStyle::Plain => PLAIN,
+                    ScalarStyle::SingleQuoted => SINGLE_QUOTED,
+                    ScalarStyle::DoubleQuoted => DOUBLE_QUOTED,
+                    ScalarStyle::Literal => LITERAL,
+                    ScalarStyle::Folded => FOLDED,
+                };
+                serializer.serialize_newtype_struct(name, string)
+            }
+            StyledValue::Sequence(sequence) => serializer.collect_seq(sequence),
+            StyledValue::Mapping(entries) => {
+                let mut map = serializer.serialize_map(Some(entries.len()))?;
+                for (k, v) in entries {
+                    map.serialize_entry(k, v)?;
+                }
+                map.end()
+            }
+            StyledValue::Tagged(tag, value) => {
+                let mut map = serializer.serialize_map(Some(1))?;
+                map.serialize_entry(&SerializeTag(tag), value)?;
+                map.end()
+            }
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for StyledValue {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_newtype_struct(NAME, StyledValueVisitor)
+    }
+}
+
+struct StyledValueVisitor;
+
+impl<'de> Visitor<'de> for StyledValueVisitor {
+    type Value = StyledValue;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("any YAML value")
+    }
+
+    fn visit_bool<E>(self, b: bool) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::Bool(b))
+    }
+
+    fn visit_i64<E>(self, i: i64) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::Number(i.into()))
+    }
+
+    fn visit_u64<E>(self, u: u64) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::Number(u.into()))
+    }
+
+    fn visit_f64<E>(self, f: f64) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::Number(f.into()))
+    }
+
+    fn visit_str<E>(self, s: &str) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::String(s.to_owned(), None))
+    }
+
+    fn visit_string<E>(self, s: String) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::String(s, None))
+    }
+
+    fn visit_unit<E>(self) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::Null)
+    }
+
+    fn visit_none<E>(self) -> Result<StyledValue, E>
+    where
+        E: de::Error,
+    {
+        Ok(StyledValue::Null)
+    }
+
+    fn visit_some<D>(self, deserializer: D) -> Result<StyledValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Deserialize::deserialize(deserializer)
+    }
+
+    fn visit_newtype_struct<D>(self, deserializer: D) -> Result<StyledValue, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(self)
+    }
+
+    fn visit_seq<A>(self, mut seq: A) -> Result<StyledValue, A::Error>
+    where
+        A: SeqAccess<'de>,
+    {
+        let mut sequence = Vec::with_capacity(seq.size_hint().unwrap_or(0).min(4096));
+        while let Some(element) = seq.next_element()? {
+            sequence.push(element);
+        }
+        Ok(StyledValue::Sequence(sequence))
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<StyledValue, A::Error>
+    where
+        A: MapAccess<'de>,
+    {
+        let first: StyledValue = match map.next_key()? {
+            Some(key) => key,
+            None => return Ok(StyledValue::Mapping(Vec::new())),
+        };
+        // A scalar from this crate's deserializer arrives as the entries of a
+        // `ScalarInfo`, whose keys have no style, unlike keys in the YAML.
+        if let StyledValue::String(key, None) = &first {
+            if key == scalar_info::STYLE {
+                let style: String = map.next_value()?;
+                let style = match scalar_info::parse_style(&style) {
+                    Some(style) => style,
+                    None => {
+                        return Err(de::Error::invalid_value(de::Unexpected::Str(&style), &self))
+                    }
+                };
+                let _text = map.next_entry::<String, String>()?;
+                let _value = map.next_key::<String>()?;
+                let value: Value = map.next_value()?;
+                return Ok(StyledValue::with_style(value, style));
+            }
+        }
+        let mut entries = Vec::with_capacity(map.size_hint().unwrap_or(0).min(4096));
+        entries.push((first, map.next_value()?));
+        while let Some(entry) = map.next_entry()? {
+            entries.push(entry);
+        }
+        Ok(StyledValue::Mapping(entries))
+    }
+
+    fn visit_enum<A>(self, data: A) -> Result<StyledValue, A::Error>
+    where
+        A: EnumAccess<'de>,
+    {
+        let (tag, contents) = data.variant_seed(TagStringVisitor)?;
+        let value = contents.newtype_variant()?;
+        Ok(StyledValue::Tagged(tag, Box::new(value)))
+    }
+}
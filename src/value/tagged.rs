@@ -1,3 +1,7 @@
+use crate::de::UnitStructVisitor;
+use crate::document::NodeKind;
+use crate::error::{self, ErrorImpl};
+use crate::peek::{self, PeekAccess};
 use crate::value::de::{MapDeserializer, MapRefDeserializer, SeqDeserializer, SeqRefDeserializer};
 use crate::value::Value;
 use crate::Error;
@@ -11,11 +15,33 @@ use std::cmp::Ordering;
 use std::fmt::{self, Debug, Display};
 use std::hash::{Hash, Hasher};
 use std::mem;
+use std::str::FromStr;
 
 /// A representation of YAML's `!Tag` syntax, used for enums.
 ///
 /// Refer to the example code on [`TaggedValue`] for an example of deserializing
 /// tagged values.
+///
+/// Only local tags are kept when deserializing into [`Value`]. These are the
+/// tags written with the primary handle, like `!Ref`, or verbatim, like
+/// `!<!Ref>`. Both become `Tag::new("Ref")`, because the leading '!' is not
+/// significant when comparing, hashing, or displaying tags.
+///
+/// Global tags are resolved by the deserializer rather than kept. This covers
+/// tags written with the secondary handle, like `!!str`, and verbatim global
+/// tags, like `!<tag:yaml.org,2002:str>`. The core schema tags `!!bool`,
+/// `!!int`, `!!float` and `!!null` make a scalar that type. Any other global
+/// tag makes a scalar a string, and has no effect on a sequence or mapping.
+///
+/// ```
+/// use serde_yaml::Value;
+///
+/// let value: Value = serde_yaml::from_str("!<!Ref> x").unwrap();
+/// assert!(value.tag().is_some_and(|tag| tag == "Ref"));
+///
+/// let value: Value = serde_yaml::from_str("!!str 1").unwrap();
+/// assert_eq!(value, Value::from("1"));
+/// ```
 #[derive(Clone)]
 pub struct Tag {
     pub(crate) string: String,
@@ -47,7 +73,7 @@ pub struct Tag {
 /// // The leading '!' in tags are not significant. The following is also true.
 /// assert!(data["scalar"].tag == "!Thing");
 /// ```
-#[derive(Clone, PartialEq, PartialOrd, Hash, Debug)]
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Hash, Debug)]
 pub struct TaggedValue {
     #[allow(missing_docs)]
     pub tag: Tag,
@@ -89,32 +115,156 @@ impl Tag {
         assert!(!tag.is_empty(), "empty YAML tag is not allowed");
         Tag { string: tag }
     }
+
+    /// Whether the tag starts with `prefix`. As with equality, a leading '!'
+    /// on either side is not significant.
+    ///
+    /// ```
+    /// use serde_yaml::value::Tag;
+    ///
+    /// let tag = Tag::new("!k8s/Deployment");
+    /// assert!(tag.starts_with("k8s/"));
+    /// assert!(tag.starts_with("!k8s/"));
+    /// assert!(!tag.starts_with("Deployment"));
+    /// ```
+    pub fn starts_with(&self, prefix: &str) -> bool {
+        let prefix = prefix.strip_prefix('!').unwrap_or(prefix);
+        nobang(&self.string).starts_with(prefix)
+    }
+
+    /// The tag as it would be written with one of the handles of a
+    /// document's `%TAG` directives, such as `!x!thing` for `!local-thing`
+    /// given `%TAG !x! !local-`, or else as `!thing` with the primary handle.
+    ///
+    /// The directives of a document are in
+    /// [`DocumentValue::tag_directives`](crate::DocumentValue::tag_directives),
+    /// or [`Deserializer::tag_directives`](crate::Deserializer::tag_directives).
+    /// See [`raw::Tag::shorthand`](crate::raw::Tag::shorthand) for how the
+    /// handle is chosen.
+    ///
+    /// ```
+    /// use serde_yaml::Value;
+    ///
+    /// let directives = [("!x!".to_owned(), "!local-".to_owned())];
+    /// let value: Value = serde_yaml::from_str("%TAG !x! !local-\n--- !x!thing 1\n").unwrap();
+    /// let tag = value.tag().unwrap();
+    /// assert!(*tag == "local-thing");
+    /// assert_eq!(tag.shorthand(&directives).as_deref(), Some("!x!thing"));
+    /// assert_eq!(tag.shorthand(&[]).as_deref(), Some("!local-thing"));
+    /// ```
+    pub fn shorthand(&self, tag_directives: &[(String, String)]) -> Option<String> {
+        let tag = format!("!{}", nobang(&self.string));
+        crate::libyaml::event::Tag::new(tag).shorthand(tag_directives)
+    }
+}
+
+impl From<&str> for Tag {
+    /// Same as [`Tag::new`].
+    fn from(string: &str) -> Self {
+        Tag::new(string)
+    }
+}
+
+impl From<String> for Tag {
+    /// Same as [`Tag::new`].
+    fn from(string: String) -> Self {
+        Tag::new(string)
+    }
+}
+
+impl FromStr for Tag {
+    type Err = Error;
+
+    /// Same as [`Tag::new`], but returns an error instead of panicking if the
+    /// string is empty.
+    fn from_str(string: &str) -> Result<Self, Error> {
+        if string.is_empty() {
+            return Err(error::new(ErrorImpl::EmptyTag));
+        }
+        Ok(Tag::new(string))
+    }
 }
 
 impl Value {
-    pub(crate) fn untag(self) -> Self {
+    /// Creates a tagged value. Shorthand for building a
+    /// [`TaggedValue`] by hand.
+    ///
+    /// ```
+    /// use serde_yaml::Value;
+    ///
+    /// let value = Value::tagged("!Ref", Value::from("AWS::Region"));
+    /// assert_eq!(serde_yaml::to_string(&value).unwrap(), "!Ref AWS::Region\n");
+    /// ```
+    ///
+    /// # Panics
+    ///
+    /// Panics if the tag is empty, as does [`Tag::new`].
+    pub fn tagged(tag: impl Into<Tag>, value: Value) -> Self {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: tag.into(),
+            value,
+        }))
+    }
+
+    /// Returns the tag of this value, or `None` if it is not tagged.
+    ///
+    /// ```
+    /// use serde_yaml::Value;
+    ///
+    /// let value: Value = serde_yaml::from_str("!Ref AWS::Region").unwrap();
+    /// assert!(value.tag().is_some_and(|tag| tag == "Ref"));
+    ///
+    /// let value: Value = serde_yaml::from_str("AWS::Region").unwrap();
+    /// assert_eq!(value.tag(), None);
+    /// ```
+    pub fn tag(&self) -> Option<&Tag> {
+        match self {
+            Value::Tagged(tagged) => Some(&tagged.tag),
+            _ => None,
+        }
+    }
+
+    /// Returns the value underneath any tags. Untagged values are returned as
+    /// they are.
+    pub fn untag(&self) -> &Self {
         let mut cur = self;
         while let Value::Tagged(tagged) = cur {
-            cur = tagged.value;
+            cur = &tagged.value;
         }
         cur
     }
 
-    pub(crate) fn untag_ref(&self) -> &Self {
+    /// Returns a mutable reference to the value underneath any tags.
+    pub fn untag_mut(&mut self) -> &mut Self {
         let mut cur = self;
         while let Value::Tagged(tagged) = cur {
-            cur = &tagged.value;
+            cur = &mut tagged.value;
         }
         cur
     }
 
-    pub(crate) fn untag_mut(&mut self) -> &mut Self {
+    /// Discards any tags and returns the value underneath.
+    pub fn untag_owned(self) -> Self {
         let mut cur = self;
         while let Value::Tagged(tagged) = cur {
-            cur = &mut tagged.value;
+            cur = tagged.value;
         }
         cur
     }
+
+    /// Applies `f` to the value underneath any tags. If `f` gives the value
+    /// back, the tags are reattached so the caller gets back what it passed in.
+    pub(crate) fn untag_into<T>(self, f: fn(Value) -> Result<T, Value>) -> Result<T, Value> {
+        match self {
+            Value::Tagged(tagged) => {
+                let TaggedValue { tag, value } = *tagged;
+                value
+                    .untag_into(f)
+                    .map_err(|value| Value::Tagged(Box::new(TaggedValue { tag, value })))
+            }
+            other => f(other),
+        }
+    }
 }
 
 pub(crate) fn nobang(maybe_banged: &str) -> &str {
@@ -171,22 +321,24 @@ impl Debug for Tag {
     }
 }
 
-impl Serialize for TaggedValue {
+// A tag as the key of the single entry of a map, which is how this crate's
+// serializer is told to write a tag.
+pub(crate) struct SerializeTag<'a>(pub &'a Tag);
+
+impl<'a> Serialize for SerializeTag<'a> {
     fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
     where
         S: Serializer,
     {
-        struct SerializeTag<'a>(&'a Tag);
-
-        impl<'a> Serialize for SerializeTag<'a> {
-            fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
-            where
-                S: Serializer,
-            {
-                serializer.collect_str(self.0)
-            }
-        }
+        serializer.collect_str(self.0)
+    }
+}
 
+impl Serialize for TaggedValue {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
         let mut map = serializer.serialize_map(Some(1))?;
         map.serialize_entry(&SerializeTag(&self.tag), &self.value)?;
         map.end()
@@ -221,6 +373,18 @@ impl<'de> Deserialize<'de> for TaggedValue {
     }
 }
 
+impl TaggedValue {
+    // The kind and tag of the node, for `Peek`.
+    fn peek_node(&self) -> (NodeKind, Option<String>) {
+        let kind = match self.value.untag() {
+            Value::Sequence(_) => NodeKind::Sequence,
+            Value::Mapping(_) => NodeKind::Mapping,
+            _ => NodeKind::Scalar,
+        };
+        (kind, Some(self.tag.string.clone()))
+    }
+}
+
 impl<'de> Deserializer<'de> for TaggedValue {
     type Error = Error;
 
@@ -239,10 +403,31 @@ impl<'de> Deserializer<'de> for TaggedValue {
         visitor.visit_unit()
     }
 
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(UnitStructVisitor { name, visitor })
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        if name == peek::NAME {
+            let (kind, tag) = self.peek_node();
+            return visitor.visit_seq(PeekAccess::new(kind, tag, self));
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct
-        map struct enum identifier
+        byte_buf option unit seq tuple tuple_struct map struct enum identifier
     }
 }
 
@@ -318,10 +503,31 @@ impl<'de> Deserializer<'de> for &'de TaggedValue {
         visitor.visit_unit()
     }
 
+    fn deserialize_unit_struct<V>(self, name: &'static str, visitor: V) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_any(UnitStructVisitor { name, visitor })
+    }
+
+    fn deserialize_newtype_struct<V>(
+        self,
+        name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error>
+    where
+        V: Visitor<'de>,
+    {
+        if name == peek::NAME {
+            let (kind, tag) = self.peek_node();
+            return visitor.visit_seq(PeekAccess::new(kind, tag, self));
+        }
+        visitor.visit_newtype_struct(self)
+    }
+
     forward_to_deserialize_any! {
         bool i8 i16 i32 i64 u8 u16 u32 u64 f32 f64 char str string bytes
-        byte_buf option unit unit_struct newtype_struct seq tuple tuple_struct
-        map struct enum identifier
+        byte_buf option unit seq tuple tuple_struct map struct enum identifier
     }
 }
 
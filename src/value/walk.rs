@@ -0,0 +1,203 @@
+use crate::value::Value;
+use std::fmt::{self, Display};
+
+/// Controls how [`Value::walk_mut`] proceeds after visiting a node.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum Walk {
+    /// Visit the children of this node, then carry on.
+    Continue,
+    /// Do not visit the children of this node, but carry on with its
+    /// siblings.
+    SkipChildren,
+    /// Stop walking immediately.
+    Stop,
+}
+
+/// One step in a [`Path`]: either a mapping key or a sequence index.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum PathSegment<'a> {
+    /// The value under this key of a mapping.
+    Key(&'a Value),
+    /// The element at this index of a sequence.
+    Index(usize),
+}
+
+/// The location of a node visited by [`Value::walk`] or [`Value::walk_mut`],
+/// relative to the value the walk started from.
+///
+/// The `Display` representation looks like `spec.containers[0].image`. The
+/// root is displayed as `.`.
+#[derive(Copy, Clone, Debug)]
+pub struct Path<'a> {
+    segments: &'a [PathSegment<'a>],
+}
+
+impl<'a> Path<'a> {
+    /// The segments leading from the root to this node. Empty for the root.
+    pub fn segments(&self) -> &'a [PathSegment<'a>] {
+        self.segments
+    }
+
+    /// The last segment of the path, or `None` for the root.
+    pub fn last(&self) -> Option<&'a PathSegment<'a>> {
+        self.segments.last()
+    }
+
+    /// The mapping key this node is stored under, if its parent is a mapping.
+    pub fn key(&self) -> Option<&'a Value> {
+        match self.last() {
+            Some(PathSegment::Key(key)) => Some(key),
+            _ => None,
+        }
+    }
+
+    /// The number of segments in the path. The root has depth 0.
+    pub fn depth(&self) -> usize {
+        self.segments.len()
+    }
+}
+
+impl<'a> Display for Path<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        if self.segments.is_empty() {
+            return formatter.write_str(".");
+        }
+        for (i, segment) in self.segments.iter().enumerate() {
+            match segment {
+                PathSegment::Key(key) => {
+                    if i > 0 {
+                        formatter.write_str(".")?;
+                    }
+                    match key.as_str() {
+                        Some(key) => formatter.write_str(key)?,
+                        None => write!(formatter, "{}", key)?,
+                    }
+                }
+                PathSegment::Index(index) => write!(formatter, "[{}]", index)?,
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Value {
+    /// Visits every node of the value in depth-first order, parents before
+    /// children.
+    ///
+    /// Mapping entries are visited in insertion order and sequence elements in
+    /// index order. A tagged node is visited once; its children are those of
+    /// the value inside the tag.
+    ///
+    /// ```
+    /// use serde_yaml::Value;
+    ///
+    /// let value: Value = serde_yaml::from_str("{a: [1, 2], b: 3}").unwrap();
+    /// let mut paths = Vec::new();
+    /// value.walk(|path, _value| paths.push(path.to_string()));
+    /// assert_eq!(paths, [".", "a", "a[0]", "a[1]", "b"]);
+    /// ```
+    pub fn walk<F>(&self, mut f: F)
+    where
+        F: FnMut(&Path, &Value),
+    {
+        walk(self, &mut Vec::new(), &mut f);
+    }
+
+    /// Visits every node of the value in depth-first order, parents before
+    /// children, allowing each node to be modified.
+    ///
+    /// The closure's return value decides whether the children of the node are
+    /// visited. Children are looked up after the closure returns, so if the
+    /// closure replaces a node, the children of the old node are never
+    /// visited; return [`Walk::SkipChildren`] to also skip those of the new
+    /// one.
+    ///
+    /// ```
+    /// use serde_yaml::value::{Value, Walk};
+    ///
+    /// let mut value: Value = serde_yaml::from_str("
+    ///     database:
+    ///       user: admin
+    ///       password: hunter2
+    ///     replicas:
+    ///       - password: swordfish
+    /// ").unwrap();
+    ///
+    /// value.walk_mut(|path, value| {
+    ///     if path.key().and_then(Value::as_str) == Some("password") {
+    ///         *value = Value::from("<redacted>");
+    ///     }
+    ///     Walk::Continue
+    /// });
+    ///
+    /// assert_eq!(value["database"]["user"], "admin");
+    /// assert_eq!(value["database"]["password"], "<redacted>");
+    /// assert_eq!(value["replicas"][0]["password"], "<redacted>");
+    /// ```
+    pub fn walk_mut<F>(&mut self, mut f: F)
+    where
+        F: FnMut(&Path, &mut Value) -> Walk,
+    {
+        walk_mut(self, &mut Vec::new(), &mut f);
+    }
+}
+
+fn walk<'a, F>(value: &'a Value, stack: &mut Vec<PathSegment<'a>>, f: &mut F)
+where
+    F: FnMut(&Path, &Value),
+{
+    f(&Path { segments: stack }, value);
+    match value.untag() {
+        Value::Sequence(sequence) => {
+            for (index, element) in sequence.iter().enumerate() {
+                stack.push(PathSegment::Index(index));
+                walk(element, stack, f);
+                stack.pop();
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                stack.push(PathSegment::Key(key));
+                walk(value, stack, f);
+                stack.pop();
+            }
+        }
+        _ => {}
+    }
+}
+
+// Returns false if the walk was stopped.
+fn walk_mut<'a, F>(value: &'a mut Value, stack: &mut Vec<PathSegment<'a>>, f: &mut F) -> bool
+where
+    F: FnMut(&Path, &mut Value) -> Walk,
+{
+    match f(&Path { segments: stack }, value) {
+        Walk::Continue => {}
+        Walk::SkipChildren => return true,
+        Walk::Stop => return false,
+    }
+    match value.untag_mut() {
+        Value::Sequence(sequence) => {
+            for (index, element) in sequence.iter_mut().enumerate() {
+                stack.push(PathSegment::Index(index));
+                let keep_going = walk_mut(element, stack, f);
+                stack.pop();
+                if !keep_going {
+                    return false;
+                }
+            }
+        }
+        Value::Mapping(mapping) => {
+            for (key, value) in mapping {
+                stack.push(PathSegment::Key(key));
+                let keep_going = walk_mut(value, stack, f);
+                stack.pop();
+                if !keep_going {
+                    return false;
+                }
+            }
+        }
+        _ => {}
+    }
+    true
+}
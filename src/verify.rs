@@ -0,0 +1,186 @@
+//! Checks that serialized output parses back to the events it was written
+//! from, for `SerializerBuilder::verify_roundtrip`.
+
+use crate::error::{self, ErrorImpl, Quoted};
+use crate::libyaml::event::{CollectionStyle, Event, Scalar, ScalarStyle};
+use crate::libyaml::parser::Parser;
+use serde::de::Visitor;
+use std::borrow::Cow;
+use std::fmt;
+use std::str;
+
+pub(crate) struct Verifier {
+    // The events of the document being serialized, normalized.
+    events: Vec<Event<'static>>,
+}
+
+impl Verifier {
+    pub fn new() -> Self {
+        Verifier { events: Vec::new() }
+    }
+
+    pub fn record(&mut self, event: &Event) {
+        self.events.push(normalize(event));
+    }
+
+    // Parses the output of one document and compares it against the events
+    // recorded since the last check.
+    pub fn check(&mut self, output: &[u8]) -> crate::Result<()> {
+        let mut expected = self.events.drain(..);
+        let mut parser = Parser::new(Cow::Borrowed(output))?;
+        loop {
+            let found = match parser.next() {
+                Ok((Event::StreamStart, _mark)) => continue,
+                Ok((Event::StreamEnd, _mark)) => None,
+                Ok((event, _mark)) => Some(normalize(&event)),
+                Err(err) => return Err(mismatch(format_args!("{}", err))),
+            };
+            let expected = expected.next();
+            if found != expected {
+                return Err(mismatch(format_args!(
+                    "expected {}, found {}",
+                    Describe(expected.as_ref()),
+                    Describe(found.as_ref()),
+                )));
+            }
+            if found.is_none() {
+                return Ok(());
+            }
+        }
+    }
+}
+
+fn mismatch(detail: fmt::Arguments) -> crate::Error {
+    error::new(ErrorImpl::RoundTripMismatch(detail.to_string()))
+}
+
+// Clears the details that the emitter is free to choose, and marks each scalar
+// as Plain if it resolves to something other than a string, or Any if it is a
+// string. A scalar the serializer asked to be Plain is a null, boolean or
+// number; any other style was asked for to write a string.
+fn normalize(event: &Event) -> Event<'static> {
+    match event {
+        Event::StreamStart => Event::StreamStart,
+        Event::StreamEnd => Event::StreamEnd,
+        Event::DocumentStart(document_start) => {
+            let mut document_start = document_start.clone();
+            document_start.implicit = false;
+            Event::DocumentStart(document_start)
+        }
+        Event::DocumentEnd(document_end) => {
+            let mut document_end = document_end.clone();
+            document_end.implicit = false;
+            Event::DocumentEnd(document_end)
+        }
+        Event::Alias(anchor) => Event::Alias(anchor.clone()),
+        Event::Scalar(scalar) => {
+            let plain = scalar.style == ScalarStyle::Plain;
+            let style = if plain && kind(&scalar.value) != "string" {
+                ScalarStyle::Plain
+            } else {
+                ScalarStyle::Any
+            };
+            Event::Scalar(Scalar {
+                anchor: scalar.anchor.clone(),
+                tag: scalar.tag.clone(),
+                value: Cow::Owned(scalar.value.to_vec()),
+                style,
+            })
+        }
+        Event::SequenceStart(sequence) => {
+            let mut sequence = sequence.clone();
+            sequence.style = CollectionStyle::Any;
+            Event::SequenceStart(sequence)
+        }
+        Event::SequenceEnd => Event::SequenceEnd,
+        Event::MappingStart(mapping) => {
+            let mut mapping = mapping.clone();
+            mapping.style = CollectionStyle::Any;
+            Event::MappingStart(mapping)
+        }
+        Event::MappingEnd => Event::MappingEnd,
+    }
+}
+
+// What a plain scalar resolves to.
+fn kind(value: &[u8]) -> &'static str {
+    struct Kind;
+
+    impl<'de> Visitor<'de> for Kind {
+        type Value = &'static str;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a scalar")
+        }
+
+        fn visit_unit<E>(self) -> Result<Self::Value, E> {
+            Ok("null")
+        }
+
+        fn visit_bool<E>(self, _v: bool) -> Result<Self::Value, E> {
+            Ok("boolean")
+        }
+
+        fn visit_i64<E>(self, _v: i64) -> Result<Self::Value, E> {
+            Ok("integer")
+        }
+
+        fn visit_i128<E>(self, _v: i128) -> Result<Self::Value, E> {
+            Ok("integer")
+        }
+
+        fn visit_u64<E>(self, _v: u64) -> Result<Self::Value, E> {
+            Ok("integer")
+        }
+
+        fn visit_u128<E>(self, _v: u128) -> Result<Self::Value, E> {
+            Ok("integer")
+        }
+
+        fn visit_f64<E>(self, _v: f64) -> Result<Self::Value, E> {
+            Ok("float")
+        }
+
+        fn visit_str<E>(self, _v: &str) -> Result<Self::Value, E> {
+            Ok("string")
+        }
+    }
+
+    match str::from_utf8(value) {
+        Ok(value) => crate::de::visit_untagged_scalar(Kind, value, None).unwrap_or("string"),
+        Err(_) => "string",
+    }
+}
+
+struct Describe<'a>(Option<&'a Event<'static>>);
+
+impl<'a> fmt::Display for Describe<'a> {
+    fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        let event = match self.0 {
+            Some(event) => event,
+            None => return formatter.write_str("end of output"),
+        };
+        match event {
+            Event::StreamStart => formatter.write_str("start of stream"),
+            Event::StreamEnd => formatter.write_str("end of stream"),
+            Event::DocumentStart(_) => formatter.write_str("start of document"),
+            Event::DocumentEnd(_) => formatter.write_str("end of document"),
+            Event::Alias(anchor) => write!(formatter, "alias {:?}", anchor),
+            Event::Scalar(scalar) => {
+                if let Some(tag) = &scalar.tag {
+                    write!(formatter, "{:?} ", tag)?;
+                }
+                let kind = match scalar.style {
+                    ScalarStyle::Plain => kind(&scalar.value),
+                    _ => "string",
+                };
+                let value = String::from_utf8_lossy(&scalar.value);
+                write!(formatter, "{} {}", kind, Quoted(&value))
+            }
+            Event::SequenceStart(_) => formatter.write_str("start of sequence"),
+            Event::SequenceEnd => formatter.write_str("end of sequence"),
+            Event::MappingStart(_) => formatter.write_str("start of mapping"),
+            Event::MappingEnd => formatter.write_str("end of mapping"),
+        }
+    }
+}
@@ -923,7 +923,7 @@ pub mod singleton_map {
 ///     };
 ///
 ///     let mut buf = Vec::new();
-///     let mut serializer = serde_yaml::Serializer::new(&mut buf);
+///     let mut serializer = serde_yaml::Serializer::new(&mut buf).unwrap();
 ///     serde_yaml::with::singleton_map_recursive::serialize(&object, &mut serializer).unwrap();
 ///
 ///     io::stdout().write_all(&buf).unwrap();
@@ -2123,3 +2123,565 @@ pub mod singleton_map_recursive {
         }
     }
 }
+
+/// Serialize/deserialize a `Vec<T>` that may be written in YAML either as a
+/// sequence or, when it holds one element, as that element on its own.
+///
+/// Any node other than an untagged sequence deserializes as a single element,
+/// so the elements may themselves be mappings, tagged enums, or anything else
+/// that `T` deserializes from. A null, including an empty value, deserializes
+/// as an empty `Vec`.
+///
+/// A `Vec` with exactly one element serializes as that element, and any other
+/// length as a sequence. Use [`seq_or_single_as_seq`] to accept both forms but
+/// always serialize a sequence.
+///
+/// # Example
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Config {
+///     #[serde(with = "serde_yaml::with::seq_or_single")]
+///     host: Vec<String>,
+/// }
+///
+/// fn main() {
+///     let one: Config = serde_yaml::from_str("host: example.com").unwrap();
+///     assert_eq!(one.host, ["example.com"]);
+///
+///     let two: Config = serde_yaml::from_str("host: [a.com, b.com]").unwrap();
+///     assert_eq!(two.host, ["a.com", "b.com"]);
+///
+///     assert_eq!(serde_yaml::to_string(&one).unwrap(), "host: example.com\n");
+///     assert_eq!(serde_yaml::to_string(&two).unwrap(), "host:\n- a.com\n- b.com\n");
+/// }
+/// ```
+pub mod seq_or_single {
+    use serde::de::value::{
+        BorrowedBytesDeserializer, BorrowedStrDeserializer, BytesDeserializer,
+        EnumAccessDeserializer, MapAccessDeserializer,
+    };
+    use serde::de::{
+        self, Deserialize, Deserializer, EnumAccess, IntoDeserializer, MapAccess, SeqAccess,
+        Visitor,
+    };
+    use serde::ser::{Serialize, Serializer};
+    use std::fmt;
+    use std::marker::PhantomData;
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        match value {
+            [single] => single.serialize(serializer),
+            _ => value.serialize(serializer),
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(SeqOrSingle(PhantomData))
+    }
+
+    struct SeqOrSingle<T>(PhantomData<T>);
+
+    impl<T> SeqOrSingle<T> {
+        fn single<'de, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+        where
+            T: Deserialize<'de>,
+            D: Deserializer<'de>,
+        {
+            T::deserialize(deserializer).map(|single| vec![single])
+        }
+    }
+
+    impl<'de, T> Visitor<'de> for SeqOrSingle<T>
+    where
+        T: Deserialize<'de>,
+    {
+        type Value = Vec<T>;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a sequence or a single element")
+        }
+
+        fn visit_bool<E>(self, v: bool) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_i128<E>(self, v: i128) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_u128<E>(self, v: u128) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_f64<E>(self, v: f64) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_borrowed_str<E>(self, v: &'de str) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(BorrowedStrDeserializer::new(v))
+        }
+
+        fn visit_string<E>(self, v: String) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(v.into_deserializer())
+        }
+
+        fn visit_bytes<E>(self, v: &[u8]) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(BytesDeserializer::new(v))
+        }
+
+        fn visit_borrowed_bytes<E>(self, v: &'de [u8]) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Self::single(BorrowedBytesDeserializer::new(v))
+        }
+
+        fn visit_unit<E>(self) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Ok(Vec::new())
+        }
+
+        fn visit_none<E>(self) -> Result<Vec<T>, E>
+        where
+            E: de::Error,
+        {
+            Ok(Vec::new())
+        }
+
+        fn visit_some<D>(self, deserializer: D) -> Result<Vec<T>, D::Error>
+        where
+            D: Deserializer<'de>,
+        {
+            deserialize(deserializer)
+        }
+
+        fn visit_seq<A>(self, mut seq: A) -> Result<Vec<T>, A::Error>
+        where
+            A: SeqAccess<'de>,
+        {
+            let mut vec = Vec::new();
+            while let Some(element) = seq.next_element()? {
+                vec.push(element);
+            }
+            Ok(vec)
+        }
+
+        fn visit_map<A>(self, map: A) -> Result<Vec<T>, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            Self::single(MapAccessDeserializer::new(map))
+        }
+
+        fn visit_enum<A>(self, data: A) -> Result<Vec<T>, A::Error>
+        where
+            A: EnumAccess<'de>,
+        {
+            Self::single(EnumAccessDeserializer::new(data))
+        }
+    }
+}
+
+/// Like [`seq_or_single`], but always serializes a sequence, even of one
+/// element.
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Config {
+///     #[serde(with = "serde_yaml::with::seq_or_single_as_seq")]
+///     host: Vec<String>,
+/// }
+///
+/// fn main() {
+///     let config: Config = serde_yaml::from_str("host: example.com").unwrap();
+///     assert_eq!(serde_yaml::to_string(&config).unwrap(), "host:\n- example.com\n");
+/// }
+/// ```
+pub mod seq_or_single_as_seq {
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &[T], serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        value.serialize(serializer)
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Vec<T>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        super::seq_or_single::deserialize(deserializer)
+    }
+}
+
+/// Deserialize a struct from either a mapping or a scalar shorthand for it.
+///
+/// A mapping is deserialized with `T`'s `Deserialize` impl, and a scalar is
+/// parsed with its `FromStr` impl. Scalars that YAML resolves to a number or
+/// boolean are passed to `FromStr` in their canonical form, so `0x10` becomes
+/// `"16"`; quote them to keep the text as written.
+///
+/// Serialization always uses `T`'s `Serialize` impl. Use
+/// [`string_or_struct_shorthand`] to serialize the scalar form when it
+/// represents the value exactly.
+///
+/// # Example
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+/// use std::convert::Infallible;
+/// use std::str::FromStr;
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Build {
+///     context: String,
+///     dockerfile: Option<String>,
+/// }
+///
+/// // `build: ./dir` is short for `build: {context: ./dir}`.
+/// impl FromStr for Build {
+///     type Err = Infallible;
+///
+///     fn from_str(s: &str) -> Result<Self, Self::Err> {
+///         Ok(Build {
+///             context: s.to_owned(),
+///             dockerfile: None,
+///         })
+///     }
+/// }
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Service {
+///     #[serde(with = "serde_yaml::with::string_or_struct")]
+///     build: Build,
+/// }
+///
+/// fn main() {
+///     let short: Service = serde_yaml::from_str("build: ./dir").unwrap();
+///     let long: Service = serde_yaml::from_str("build: {context: ./dir}").unwrap();
+///     assert_eq!(short, long);
+///
+///     let custom: Service = serde_yaml::from_str(
+///         "build:\n  context: ./dir\n  dockerfile: Dockerfile.dev\n",
+///     )
+///     .unwrap();
+///     assert_eq!(custom.build.dockerfile.as_deref(), Some("Dockerfile.dev"));
+/// }
+/// ```
+pub mod string_or_struct {
+    use serde::de::value::MapAccessDeserializer;
+    use serde::de::{self, Deserialize, Deserializer, MapAccess, Visitor};
+    use serde::ser::{Serialize, Serializer};
+    use std::fmt::{self, Display};
+    use std::marker::PhantomData;
+    use std::str::FromStr;
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        value.serialize(serializer)
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: Deserialize<'de> + FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_any(StringOrStruct(PhantomData))
+    }
+
+    struct StringOrStruct<T>(PhantomData<T>);
+
+    impl<T> StringOrStruct<T>
+    where
+        T: FromStr,
+        T::Err: Display,
+    {
+        fn parse<E>(s: &str) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            T::from_str(s).map_err(E::custom)
+        }
+    }
+
+    impl<'de, T> Visitor<'de> for StringOrStruct<T>
+    where
+        T: Deserialize<'de> + FromStr,
+        T::Err: Display,
+    {
+        type Value = T;
+
+        fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+            formatter.write_str("a string or a map")
+        }
+
+        fn visit_bool<E>(self, v: bool) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            Self::parse(if v { "true" } else { "false" })
+        }
+
+        fn visit_i64<E>(self, v: i64) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            Self::parse(itoa::Buffer::new().format(v))
+        }
+
+        fn visit_u64<E>(self, v: u64) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            Self::parse(itoa::Buffer::new().format(v))
+        }
+
+        fn visit_f64<E>(self, v: f64) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            Self::parse(&v.to_string())
+        }
+
+        fn visit_str<E>(self, v: &str) -> Result<T, E>
+        where
+            E: de::Error,
+        {
+            Self::parse(v)
+        }
+
+        fn visit_map<A>(self, map: A) -> Result<T, A::Error>
+        where
+            A: MapAccess<'de>,
+        {
+            T::deserialize(MapAccessDeserializer::new(map))
+        }
+    }
+}
+
+/// Like [`string_or_struct`], but serializes the scalar shorthand whenever it
+/// parses back to an equal value.
+///
+/// The shorthand is the value's `Display` output. It is used if passing that
+/// to `FromStr` produces a value equal to the original, and otherwise the
+/// value is serialized with its `Serialize` impl.
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+/// use std::convert::Infallible;
+/// use std::fmt::{self, Display};
+/// use std::str::FromStr;
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Build {
+///     context: String,
+///     dockerfile: Option<String>,
+/// }
+///
+/// impl FromStr for Build {
+///     type Err = Infallible;
+///
+///     fn from_str(s: &str) -> Result<Self, Self::Err> {
+///         Ok(Build {
+///             context: s.to_owned(),
+///             dockerfile: None,
+///         })
+///     }
+/// }
+///
+/// impl Display for Build {
+///     fn fmt(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+///         formatter.write_str(&self.context)
+///     }
+/// }
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Service {
+///     #[serde(with = "serde_yaml::with::string_or_struct_shorthand")]
+///     build: Build,
+/// }
+///
+/// fn main() {
+///     let service: Service = serde_yaml::from_str("build: {context: ./dir}").unwrap();
+///     assert_eq!(serde_yaml::to_string(&service).unwrap(), "build: ./dir\n");
+///
+///     let service = Service {
+///         build: Build {
+///             context: "./dir".to_owned(),
+///             dockerfile: Some("Dockerfile.dev".to_owned()),
+///         },
+///     };
+///     let expected = "build:\n  context: ./dir\n  dockerfile: Dockerfile.dev\n";
+///     assert_eq!(serde_yaml::to_string(&service).unwrap(), expected);
+/// }
+/// ```
+pub mod string_or_struct_shorthand {
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+    use std::fmt::Display;
+    use std::str::FromStr;
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &T, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize + Display + FromStr + PartialEq,
+        S: Serializer,
+    {
+        let shorthand = value.to_string();
+        match T::from_str(&shorthand) {
+            Ok(parsed) if parsed == *value => serializer.serialize_str(&shorthand),
+            _ => value.serialize(serializer),
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<T, D::Error>
+    where
+        T: Deserialize<'de> + FromStr,
+        T::Err: Display,
+        D: Deserializer<'de>,
+    {
+        super::string_or_struct::deserialize(deserializer)
+    }
+}
+
+/// Deserialize `Option<Option<T>>` so that an explicit null is told apart from
+/// a missing field, as in a JSON Merge Patch.
+///
+/// Serde reads a null as `None` whatever the depth of the option, because the
+/// deserializer cannot see that the `Some` it would otherwise produce holds
+/// another option. With this module, a missing field is `None`, a null is
+/// `Some(None)` and any other value is `Some(Some(value))`.
+///
+/// The field needs `#[serde(default)]`, so that a missing field is `None`
+/// rather than an error. To write the three states back out, add
+/// `skip_serializing_if = "Option::is_none"` too; otherwise `None` is
+/// serialized as null like `Some(None)`.
+///
+/// ```
+/// # use serde_derive::{Deserialize, Serialize};
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Serialize, Deserialize, PartialEq, Debug)]
+/// struct Patch {
+///     #[serde(
+///         default,
+///         skip_serializing_if = "Option::is_none",
+///         with = "serde_yaml::with::double_option",
+///     )]
+///     nickname: Option<Option<String>>,
+/// }
+///
+/// fn main() {
+///     let keep: Patch = serde_yaml::from_str("{}").unwrap();
+///     let clear: Patch = serde_yaml::from_str("nickname: null").unwrap();
+///     let set: Patch = serde_yaml::from_str("nickname: Bob").unwrap();
+///     assert_eq!(keep.nickname, None);
+///     assert_eq!(clear.nickname, Some(None));
+///     assert_eq!(set.nickname, Some(Some("Bob".to_owned())));
+///
+///     assert_eq!(serde_yaml::to_string(&keep).unwrap(), "{}\n");
+///     assert_eq!(serde_yaml::to_string(&clear).unwrap(), "nickname: null\n");
+///     assert_eq!(serde_yaml::to_string(&set).unwrap(), "nickname: Bob\n");
+/// }
+/// ```
+pub mod double_option {
+    use serde::de::{Deserialize, Deserializer};
+    use serde::ser::{Serialize, Serializer};
+
+    #[allow(missing_docs)]
+    pub fn serialize<T, S>(value: &Option<Option<T>>, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        T: Serialize,
+        S: Serializer,
+    {
+        match value {
+            None | Some(None) => serializer.serialize_none(),
+            Some(Some(value)) => serializer.serialize_some(value),
+        }
+    }
+
+    #[allow(missing_docs)]
+    pub fn deserialize<'de, T, D>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+    where
+        T: Deserialize<'de>,
+        D: Deserializer<'de>,
+    {
+        Option::<T>::deserialize(deserializer).map(Some)
+    }
+}
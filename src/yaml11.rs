@@ -0,0 +1,335 @@
+// How YAML 1.1 resolves plain scalars, for the places that care where that
+// differs from the YAML 1.2 core schema this crate follows: the diagnostic
+// for such scalars, the serializer quoting strings so that either version
+// reads them back as strings, and the deserializer's legacy resolution.
+
+use crate::de;
+use crate::libyaml::event::{Scalar, ScalarStyle, Tag};
+use serde::de::Visitor;
+use std::borrow::Cow;
+use std::fmt;
+
+// What YAML 1.1 reads a plain scalar as.
+enum Yaml11 {
+    Str,
+    // The "value" type of `=`, which few parsers can deserialize.
+    DefaultValue,
+    // What it is, and a plain scalar that YAML 1.2 reads as the same thing.
+    Resolved(&'static str, String),
+}
+
+// How YAML 1.1 and YAML 1.2 read the same plain scalar differently: what it
+// is in each.
+pub(crate) struct Difference {
+    pub yaml12: &'static str,
+    pub yaml11: &'static str,
+}
+
+pub(crate) fn difference(scalar: &str) -> Option<Difference> {
+    let yaml12 = Yaml12::resolve(scalar);
+    let yaml11 = match resolve(scalar) {
+        Yaml11::Str if yaml12 == Yaml12::Str => return None,
+        Yaml11::Str => "a string",
+        Yaml11::DefaultValue => "the default value",
+        Yaml11::Resolved(_, canonical) if Yaml12::resolve(&canonical) == yaml12 => return None,
+        Yaml11::Resolved(meaning, _) => meaning,
+    };
+    Some(Difference {
+        yaml12: yaml12.describe(),
+        yaml11,
+    })
+}
+
+// Rewrites a plain untagged scalar so that YAML 1.2 resolution reads it as
+// YAML 1.1 would, or returns false for the default value `=`, which has no
+// YAML 1.2 equivalent.
+pub(crate) fn resolve_as_yaml11(scalar: &mut Scalar) -> bool {
+    if scalar.style != ScalarStyle::Plain || scalar.tag.is_some() {
+        return true;
+    }
+    let value = match std::str::from_utf8(&scalar.value) {
+        Ok(value) => value,
+        Err(_) => return true,
+    };
+    match resolve(value) {
+        Yaml11::Str => {
+            if Yaml12::resolve(value) != Yaml12::Str {
+                scalar.tag = Some(Tag::new(Tag::STR));
+            }
+        }
+        Yaml11::DefaultValue => return false,
+        Yaml11::Resolved(_, canonical) => {
+            if Yaml12::resolve(&canonical) != Yaml12::resolve(value) {
+                scalar.value = Cow::Owned(canonical.into_bytes());
+            }
+        }
+    }
+    true
+}
+
+fn resolve(scalar: &str) -> Yaml11 {
+    match scalar {
+        "" | "~" | "null" | "Null" | "NULL" => {
+            return Yaml11::Resolved("null", "null".to_owned());
+        }
+        // The YAML 1.1 spec also has `y` and `n`, but the parsers that follow
+        // it, like PyYAML, leave them out, and a coordinate named `y` is far
+        // more common than a boolean written that way.
+        "yes" | "Yes" | "YES" | "true" | "True" | "TRUE" | "on" | "On" | "ON" => {
+            return Yaml11::Resolved("a boolean", "true".to_owned());
+        }
+        "no" | "No" | "NO" | "false" | "False" | "FALSE" | "off" | "Off" | "OFF" => {
+            return Yaml11::Resolved("a boolean", "false".to_owned());
+        }
+        "=" => return Yaml11::DefaultValue,
+        ".nan" | ".NaN" | ".NAN" => return Yaml11::Resolved("a float", ".nan".to_owned()),
+        _ => {}
+    }
+    let (negative, unsigned) = match scalar.as_bytes().first() {
+        Some(b'-') => (true, &scalar[1..]),
+        Some(b'+') => (false, &scalar[1..]),
+        _ => (false, scalar),
+    };
+    let sign = if negative { "-" } else { "" };
+    if let ".inf" | ".Inf" | ".INF" = unsigned {
+        return Yaml11::Resolved("a float", format!("{}.inf", sign));
+    }
+    if let Some(digits) = unsigned.strip_prefix("0b") {
+        return int("an integer", negative, digits, 2);
+    }
+    if let Some(digits) = unsigned.strip_prefix("0x") {
+        return int("an integer", negative, digits, 16);
+    }
+    if unsigned.len() > 1 && unsigned.starts_with('0') && !unsigned.contains(['.', ':']) {
+        return int("an octal integer", negative, &unsigned[1..], 8);
+    }
+    if unsigned.contains(':') {
+        return base60(negative, unsigned);
+    }
+    if unsigned.contains('.') {
+        return float(negative, unsigned);
+    }
+    if unsigned.starts_with(|ch: char| ch.is_ascii_digit()) {
+        return int("an integer", negative, unsigned, 10);
+    }
+    Yaml11::Str
+}
+
+// Digits in the given radix, with underscores anywhere among them.
+fn int(meaning: &'static str, negative: bool, digits: &str, radix: u32) -> Yaml11 {
+    let mut int = Some(0u128);
+    let mut any = false;
+    for ch in digits.chars() {
+        if ch == '_' {
+            continue;
+        }
+        let digit = match ch.to_digit(radix) {
+            Some(digit) => digit,
+            None => return Yaml11::Str,
+        };
+        any = true;
+        int = int
+            .and_then(|int| int.checked_mul(u128::from(radix)))
+            .and_then(|int| int.checked_add(u128::from(digit)));
+    }
+    if !any {
+        return Yaml11::Str;
+    }
+    if int.is_none() {
+        // Too large for YAML 1.2 to read either, written so that it fails the
+        // same way.
+        let prefix = match radix {
+            16 => "0x",
+            8 => "0o",
+            2 => "0b",
+            _ => "",
+        };
+        let sign = if negative { "-" } else { "" };
+        let digits: String = digits.chars().filter(|&ch| ch != '_').collect();
+        return Yaml11::Resolved(meaning, format!("{}{}{}", sign, prefix, digits));
+    }
+    Yaml11::Resolved(meaning, number(negative, int, 0.0))
+}
+
+// Like `1:30` for 90, or `1:30.5` for 90.5: digits, then one or more groups
+// of a number below 60 after a colon, the last of which may have a fraction.
+fn base60(negative: bool, unsigned: &str) -> Yaml11 {
+    let (whole, fraction) = match unsigned.split_once('.') {
+        Some((whole, fraction)) => (whole, Some(fraction)),
+        None => (unsigned, None),
+    };
+    let mut groups = whole.split(':');
+    let first = groups.next().unwrap_or("");
+    if !first.starts_with(|ch: char| ch.is_ascii_digit())
+        || !first.bytes().all(|b| b.is_ascii_digit() || b == b'_')
+        || (fraction.is_none() && first.starts_with('0'))
+    {
+        return Yaml11::Str;
+    }
+    let digits: String = first.chars().filter(|&ch| ch != '_').collect();
+    let mut int = digits.parse::<u128>().ok();
+    let mut float = digits.parse::<f64>().unwrap_or(f64::INFINITY);
+    for group in groups {
+        let sixtieths = match group.parse::<u8>() {
+            Ok(n) if n < 60 && matches!(group.len(), 1 | 2) && !group.starts_with('+') => n,
+            _ => return Yaml11::Str,
+        };
+        int = int
+            .and_then(|int| int.checked_mul(60))
+            .and_then(|int| int.checked_add(u128::from(sixtieths)));
+        float = float * 60.0 + f64::from(sixtieths);
+    }
+    match fraction {
+        None => Yaml11::Resolved("a base 60 integer", number(negative, int, float)),
+        Some(fraction) => {
+            if !fraction.bytes().all(|b| b.is_ascii_digit() || b == b'_') {
+                return Yaml11::Str;
+            }
+            let fraction: String = fraction.chars().filter(|&ch| ch != '_').collect();
+            let fraction = format!("0.{}", fraction).parse::<f64>().unwrap_or(0.0);
+            let float = if negative {
+                -(float + fraction)
+            } else {
+                float + fraction
+            };
+            Yaml11::Resolved("a base 60 float", format_float(float))
+        }
+    }
+}
+
+// Digits and underscores around a dot, with a digit on at least one side and
+// a sign required on the exponent, like `1.5`, `.5`, `1.` or `6.02e+23`.
+fn float(negative: bool, unsigned: &str) -> Yaml11 {
+    let (mantissa, exponent) = match unsigned.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, Some(exponent)),
+        None => (unsigned, None),
+    };
+    let (whole, fraction) = match mantissa.split_once('.') {
+        Some(parts) => parts,
+        None => return Yaml11::Str,
+    };
+    let digits = |s: &str| s.bytes().all(|b| b.is_ascii_digit() || b == b'_');
+    if !digits(whole)
+        || !digits(fraction)
+        || whole.starts_with('_')
+        || (whole.is_empty() && !fraction.starts_with(|ch: char| ch.is_ascii_digit()))
+    {
+        return Yaml11::Str;
+    }
+    if let Some(exponent) = exponent {
+        let valid = exponent.starts_with(['-', '+'])
+            && exponent.len() > 1
+            && exponent[1..].bytes().all(|b| b.is_ascii_digit());
+        if !valid {
+            return Yaml11::Str;
+        }
+    }
+    let text: String = unsigned.chars().filter(|&ch| ch != '_').collect();
+    let text = if text.starts_with('.') {
+        format!("0{}", text)
+    } else {
+        text
+    };
+    match text.parse::<f64>() {
+        Ok(float) => {
+            let float = if negative { -float } else { float };
+            Yaml11::Resolved("a float", format_float(float))
+        }
+        Err(_) => Yaml11::Str,
+    }
+}
+
+// An integer as YAML 1.2 writes it, or as a float if it is too big for this
+// crate to read as an integer.
+fn number(negative: bool, int: Option<u128>, float: f64) -> String {
+    match int {
+        Some(0) => "0".to_owned(),
+        Some(int) if negative => format!("-{}", int),
+        Some(int) => int.to_string(),
+        None => format_float(if negative { -float } else { float }),
+    }
+}
+
+fn format_float(float: f64) -> String {
+    if float.is_infinite() {
+        if float.is_sign_negative() {
+            "-.inf".to_owned()
+        } else {
+            ".inf".to_owned()
+        }
+    } else {
+        format!("{:?}", float)
+    }
+}
+
+// What YAML 1.2 reads a plain scalar as, in enough detail to tell whether two
+// scalars mean the same.
+#[derive(PartialEq)]
+enum Yaml12 {
+    Null,
+    Bool(bool),
+    // Whether it is negative, and its magnitude.
+    Int(bool, u128),
+    // The bits of the float, with every NaN the same.
+    Float(u64),
+    Str,
+}
+
+impl Yaml12 {
+    fn resolve(scalar: &str) -> Self {
+        de::visit_untagged_scalar(Yaml12Visitor, scalar, None).unwrap_or(Yaml12::Str)
+    }
+
+    fn describe(&self) -> &'static str {
+        match self {
+            Yaml12::Null => "null",
+            Yaml12::Bool(_) => "a boolean",
+            Yaml12::Int(..) => "an integer",
+            Yaml12::Float(_) => "a float",
+            Yaml12::Str => "a string",
+        }
+    }
+}
+
+struct Yaml12Visitor;
+
+impl<'de> Visitor<'de> for Yaml12Visitor {
+    type Value = Yaml12;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+        formatter.write_str("a plain scalar")
+    }
+
+    fn visit_unit<E>(self) -> Result<Self::Value, E> {
+        Ok(Yaml12::Null)
+    }
+
+    fn visit_bool<E>(self, v: bool) -> Result<Self::Value, E> {
+        Ok(Yaml12::Bool(v))
+    }
+
+    fn visit_i64<E>(self, v: i64) -> Result<Self::Value, E> {
+        Ok(Yaml12::Int(v < 0, u128::from(v.unsigned_abs())))
+    }
+
+    fn visit_i128<E>(self, v: i128) -> Result<Self::Value, E> {
+        Ok(Yaml12::Int(v < 0, v.unsigned_abs()))
+    }
+
+    fn visit_u64<E>(self, v: u64) -> Result<Self::Value, E> {
+        Ok(Yaml12::Int(false, u128::from(v)))
+    }
+
+    fn visit_u128<E>(self, v: u128) -> Result<Self::Value, E> {
+        Ok(Yaml12::Int(false, v))
+    }
+
+    fn visit_f64<E>(self, v: f64) -> Result<Self::Value, E> {
+        let v = if v.is_nan() { f64::NAN } else { v };
+        Ok(Yaml12::Float(v.to_bits()))
+    }
+
+    fn visit_str<E>(self, _v: &str) -> Result<Self::Value, E> {
+        Ok(Yaml12::Str)
+    }
+}
@@ -0,0 +1,410 @@
+use serde::de::IgnoredAny;
+use serde_derive::{Deserialize, Serialize};
+use serde_yaml::Value;
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::cell::Cell;
+use std::io;
+
+struct Counting;
+
+// Counted per thread, since the tests in this file run concurrently: how
+// many allocations there have been, and how many bytes are allocated now and
+// at most since the last reset.
+thread_local! {
+    static ALLOCATIONS: Cell<usize> = const { Cell::new(0) };
+    static LIVE: Cell<isize> = const { Cell::new(0) };
+    static PEAK: Cell<isize> = const { Cell::new(0) };
+}
+
+fn record(allocations: usize, grown: isize) {
+    let _ = ALLOCATIONS.try_with(|count| count.set(count.get() + allocations));
+    let _ = LIVE.try_with(|live| {
+        live.set(live.get() + grown);
+        let _ = PEAK.try_with(|peak| peak.set(peak.get().max(live.get())));
+    });
+}
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        record(1, layout.size() as isize);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        record(0, -(layout.size() as isize));
+        unsafe { System.dealloc(ptr, layout) }
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        record(1, new_size as isize - layout.size() as isize);
+        unsafe { System.realloc(ptr, layout, new_size) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+fn count(f: impl FnOnce()) -> usize {
+    let before = ALLOCATIONS.with(Cell::get);
+    f();
+    ALLOCATIONS.with(Cell::get) - before
+}
+
+// The most bytes allocated at once while running `f`, beyond what was
+// allocated before.
+fn peak(f: impl FnOnce()) -> usize {
+    let before = LIVE.with(Cell::get);
+    PEAK.with(|peak| peak.set(before));
+    f();
+    (PEAK.with(Cell::get) - before) as usize
+}
+
+#[test]
+fn test_borrowed_scalars_are_not_copied() {
+    const N: usize = 1000;
+    let mut yaml = String::new();
+    for i in 0..N {
+        yaml += &format!("- 'scalar {}'\n", i);
+    }
+
+    // Reading from an io::Read means the input is owned by the parser, so
+    // every scalar value is copied out of libyaml's buffer. From a &str they
+    // are borrowed from the input instead.
+    let owned = count(|| {
+        let _: IgnoredAny = serde_yaml::from_reader(yaml.as_bytes()).unwrap();
+    });
+    let borrowed = count(|| {
+        let _: IgnoredAny = serde_yaml::from_str(&yaml).unwrap();
+    });
+    assert!(owned - borrowed >= N, "{} vs {}", owned, borrowed);
+
+    let strings = count(|| {
+        let _: Vec<&str> = serde_yaml::from_str(&yaml).unwrap();
+    });
+    assert!(strings < owned, "{} vs {}", strings, owned);
+}
+
+#[test]
+fn test_aliases_share_anchor() {
+    const N: usize = 1000;
+    let mut yaml = String::from("- &anchor x\n");
+    for _ in 0..N {
+        yaml += "- *anchor\n";
+    }
+
+    // libyaml allocates a copy of the anchor name for each alias event, but
+    // that should be the only allocation per alias.
+    let allocations = count(|| {
+        let _: IgnoredAny = serde_yaml::from_str(&yaml).unwrap();
+    });
+    assert!(allocations < N * 3 / 2, "{}", allocations);
+}
+
+#[test]
+fn test_core_tags_are_not_copied() {
+    const N: usize = 1000;
+    let mut core = String::new();
+    let mut custom = String::new();
+    for _ in 0..N {
+        core += "- !!str x\n";
+        custom += "- !abc x\n";
+    }
+
+    let core = count(|| {
+        let _: IgnoredAny = serde_yaml::from_str(&core).unwrap();
+    });
+    let custom = count(|| {
+        let _: IgnoredAny = serde_yaml::from_str(&custom).unwrap();
+    });
+    assert!(custom - core >= N, "{} vs {}", custom, core);
+}
+
+#[test]
+fn test_tagged_scalars() {
+    #[derive(Serialize)]
+    enum Enum {
+        Variant(usize),
+    }
+
+    const N: usize = 1000;
+    let tagged: Vec<Enum> = (0..N).map(Enum::Variant).collect();
+    let untagged: Vec<usize> = (0..N).collect();
+
+    let mut expected = String::new();
+    for i in 0..N {
+        expected += &format!("- !Variant {}\n", i);
+    }
+    assert_eq!(expected, serde_yaml::to_string(&tagged).unwrap());
+
+    let tagged = count(|| {
+        serde_yaml::to_string(&tagged).unwrap();
+    });
+    let untagged = count(|| {
+        serde_yaml::to_string(&untagged).unwrap();
+    });
+    // One allocation per value for the tag and one for libyaml's copy of it,
+    // plus a few that happen once.
+    assert!(
+        tagged - untagged <= 2 * N + 16,
+        "{} vs {}",
+        tagged,
+        untagged
+    );
+}
+
+#[test]
+fn test_numbers() {
+    const N: usize = 1000;
+    let floats: Vec<f64> = (0..N).map(|i| i as f64 / 7.0).collect();
+    let ints: Vec<u64> = (0..N as u64).map(|i| i * 7919).collect();
+    let mut output = Vec::with_capacity(1 << 20);
+
+    // Numbers are formatted on the stack, so the only allocation per number
+    // is libyaml's copy of the scalar.
+    let allocations = count(|| serde_yaml::to_writer(&mut output, &floats).unwrap());
+    assert!(allocations <= N + 16, "{}", allocations);
+
+    output.clear();
+    let allocations = count(|| serde_yaml::to_writer(&mut output, &ints).unwrap());
+    assert!(allocations <= N + 16, "{}", allocations);
+}
+
+#[test]
+fn test_numeric_fields() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Numbers {
+        f00: u32,
+        f01: f64,
+        f02: u32,
+        f03: f64,
+        f04: u32,
+        f05: f64,
+        f06: u32,
+        f07: f64,
+        f08: u32,
+        f09: f64,
+        f10: u32,
+        f11: f64,
+        f12: u32,
+        f13: f64,
+        f14: u32,
+        f15: f64,
+        f16: u32,
+        f17: f64,
+        f18: u32,
+        f19: f64,
+        f20: u32,
+        f21: f64,
+        f22: u32,
+        f23: f64,
+        f24: u32,
+        f25: f64,
+        f26: u32,
+        f27: f64,
+        f28: u32,
+        f29: f64,
+        f30: u32,
+        f31: f64,
+        f32: u32,
+        f33: f64,
+        f34: u32,
+        f35: f64,
+        f36: u32,
+        f37: f64,
+        f38: u32,
+        f39: f64,
+        f40: u32,
+        f41: f64,
+        f42: u32,
+        f43: f64,
+        f44: u32,
+        f45: f64,
+        f46: u32,
+        f47: f64,
+        f48: u32,
+        f49: f64,
+    }
+
+    let mut yaml = String::new();
+    for i in 0..50 {
+        yaml += &format!("f{:02}: {}\n", i, i);
+    }
+    let yaml = yaml.as_str();
+
+    // Field names and numbers are handed to the visitor as &str, so nothing
+    // is allocated beyond what parsing the document takes.
+    let ignored = count(|| {
+        let _: IgnoredAny = serde_yaml::from_str(yaml).unwrap();
+    });
+    let typed = count(|| {
+        let _: Numbers = serde_yaml::from_str(yaml).unwrap();
+    });
+    assert!(typed <= ignored, "{} vs {}", typed, ignored);
+
+    let ignored = count(|| {
+        let _: IgnoredAny = serde_yaml::from_reader(yaml.as_bytes()).unwrap();
+    });
+    let typed = count(|| {
+        let _: Numbers = serde_yaml::from_reader(yaml.as_bytes()).unwrap();
+    });
+    assert!(typed <= ignored, "{} vs {}", typed, ignored);
+}
+
+#[test]
+fn test_value_keys_are_not_copied() {
+    #[derive(Deserialize)]
+    #[allow(dead_code)]
+    struct Point {
+        x: u32,
+        y: u32,
+    }
+
+    // Field names are lent to the visitor, and kept for the path of an error
+    // without copying them.
+    let value: Value = serde_yaml::from_str("{x: 1, y: 2}").unwrap();
+    let allocations = count(|| {
+        let _: Point = serde_yaml::from_value(value).unwrap();
+    });
+    assert_eq!(allocations, 0);
+}
+
+#[test]
+fn test_peak_is_proportional_to_input() {
+    // Nothing is reserved up front from how many elements a collection looks
+    // like it will have, so memory grows with the elements actually read.
+    for n in [10_000, 100_000] {
+        let yaml = format!("[{}]", "0,".repeat(n));
+        let bytes = peak(|| drop(serde_yaml::from_str::<Value>(&yaml).unwrap()));
+        assert!(bytes < 512 * n, "{} bytes for {} elements", bytes, n);
+
+        let yaml = "- 0\n".repeat(n);
+        let bytes = peak(|| drop(serde_yaml::from_str::<Vec<u8>>(&yaml).unwrap()));
+        assert!(bytes < 512 * n, "{} bytes for {} elements", bytes, n);
+
+        // A syntax error after the first element.
+        let yaml = format!("[{}]", ",".repeat(n));
+        let bytes = peak(|| drop(serde_yaml::from_str::<Value>(&yaml).unwrap_err()));
+        assert!(bytes < 1 << 20, "{} bytes for {} commas", bytes, n);
+
+        // A duplicate key as the second entry.
+        let yaml = format!("{{{}}}", "a: 0,".repeat(n));
+        let bytes = peak(|| drop(serde_yaml::from_str::<Value>(&yaml).unwrap_err()));
+        assert!(bytes < 512 * n, "{} bytes for {} entries", bytes, n);
+    }
+}
+
+#[test]
+fn test_long_scalar_aliases() {
+    // Few aliases, but each repeats a megabyte, 5 GB in all. Aliases may
+    // repeat up to 16 MiB of scalars in a document this short, so that and
+    // not much more than parsing the scalar alone is all they can cost.
+    let text = "x".repeat(1 << 20);
+    let mut yaml = format!("text: &text {}\naliases: [", text);
+    for _ in 0..5000 {
+        yaml.push_str("*text, ");
+    }
+    yaml.push_str("]\n");
+
+    let alone = format!("text: {}\n", text);
+    let baseline = peak(|| drop(serde_yaml::from_str::<Value>(&alone).unwrap()));
+    let bytes = peak(|| drop(serde_yaml::from_str::<Value>(&yaml).unwrap_err()));
+    assert!(
+        bytes < (1 << 24) + baseline * 2,
+        "{} bytes compared to {} for the scalar alone",
+        bytes,
+        baseline,
+    );
+}
+
+#[test]
+fn test_ignored_scalar_is_not_copied() {
+    #[derive(Deserialize)]
+    struct Header {
+        name: String,
+    }
+
+    let len = 12 << 20;
+    for quote in ["", "\""] {
+        let mut yaml = format!("name: blob\ndata: {}", quote).into_bytes();
+        yaml.resize(yaml.len() + len, b'x');
+        yaml.extend_from_slice(format!("{}\n", quote).as_bytes());
+
+        // The reader is read into a buffer as long as the input, and libyaml
+        // assembles the scalar in a buffer of its own that doubles as it
+        // grows. Copying the value out once more would add another `len`.
+        let expected = yaml.len() + len.next_power_of_two();
+        let bytes = peak(|| {
+            let header: Header = serde_yaml::from_reader(yaml.as_slice()).unwrap();
+            assert_eq!(header.name, "blob");
+        });
+        assert!(
+            bytes < expected + len / 2,
+            "{} bytes for {} byte scalar",
+            bytes,
+            len
+        );
+    }
+}
+
+#[test]
+fn test_reader_multi_holds_one_document() {
+    // Writes `count` documents as they are read, without the whole stream
+    // ever being in memory.
+    struct Stream {
+        count: usize,
+        next: usize,
+        buffer: Vec<u8>,
+    }
+
+    impl io::Read for Stream {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.buffer.is_empty() && self.next < self.count {
+                let i = self.next;
+                let document =
+                    format!("---\nid: {}\nname: service-{}\ntags: [a, b, {}]\n", i, i, i);
+                self.buffer = document.into_bytes();
+                self.next += 1;
+            }
+            let n = self.buffer.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.buffer[..n]);
+            self.buffer.drain(..n);
+            Ok(n)
+        }
+    }
+
+    let mut peaks = Vec::new();
+    for count in [1_000, 10_000] {
+        let mut written = 0;
+        let bytes = peak(|| {
+            let stream = Stream {
+                count,
+                next: 0,
+                buffer: Vec::new(),
+            };
+            let documents = serde_yaml::from_reader_multi::<_, Value>(stream).map(Result::unwrap);
+            let mut output = io::sink();
+            let mut counting = Counted(&mut output, &mut written);
+            serde_yaml::to_writer_multi(&mut counting, documents).unwrap();
+        });
+        assert!(written > count * 40, "{}", written);
+        assert!(bytes < 256 << 10, "{} bytes for {} documents", bytes, count);
+        peaks.push(bytes);
+    }
+    // Buffers of a fixed size, however long the stream.
+    assert!(peaks[1] < peaks[0] + 1024, "{:?}", peaks);
+}
+
+struct Counted<'a, W>(W, &'a mut usize);
+
+impl<W: io::Write> io::Write for Counted<'_, W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.0.write(buf)?;
+        *self.1 += n;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
@@ -7,10 +7,15 @@
 )]
 
 use indoc::indoc;
+use serde::de::{Deserialize as _, IgnoredAny};
 use serde_derive::Deserialize;
-use serde_yaml::{Deserializer, Number, Value};
+use serde_yaml::diagnostic::{Code, Options, Severity};
+use serde_yaml::{Deserializer, DuplicateAnchors, Number, Schema, Value};
+use std::borrow::Cow;
 use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::sync::Mutex;
+use std::thread;
 
 fn test_de<T>(yaml: &str, expected: &T)
 where
@@ -69,6 +74,95 @@ fn test_borrowed() {
     test_de_no_value(yaml, &expected);
 }
 
+#[test]
+fn test_from_cow() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Borrowing<'a> {
+        name: &'a str,
+        #[serde(borrow)]
+        note: Cow<'a, str>,
+        #[serde(borrow)]
+        escaped: Cow<'a, str>,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Flexible<'a> {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+    }
+
+    let yaml = "name: x\nnote: 'y'\nescaped: \"a\\tb\"\n";
+
+    let borrowed: Borrowing = serde_yaml::from_cow(Cow::Borrowed(yaml.as_bytes())).unwrap();
+    assert_eq!(borrowed.name, "x");
+    assert!(matches!(borrowed.note, Cow::Borrowed("y")));
+    // An escape sequence has to be decoded into a new string.
+    assert!(matches!(&borrowed.escaped, Cow::Owned(escaped) if escaped == "a\tb"));
+
+    let err = serde_yaml::from_cow::<Borrowing>(yaml.as_bytes().to_vec()).unwrap_err();
+    assert_eq!(
+        "name: invalid type: string \"x\", expected a borrowed string at line 1 column 7",
+        err.to_string(),
+    );
+
+    let owned: Flexible = serde_yaml::from_cow(yaml.as_bytes().to_vec()).unwrap();
+    assert!(matches!(owned.name, Cow::Owned(name) if name == "x"));
+    let borrowed: Flexible = serde_yaml::from_cow(yaml.as_bytes()).unwrap();
+    assert!(matches!(borrowed.name, Cow::Borrowed("x")));
+}
+
+#[test]
+fn test_from_cow_str() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Flexible<'a> {
+        #[serde(borrow)]
+        name: Cow<'a, str>,
+    }
+
+    let yaml = "name: x\n";
+
+    let borrowed: Flexible = serde_yaml::from_cow_str(Cow::Borrowed(yaml)).unwrap();
+    assert!(matches!(borrowed.name, Cow::Borrowed("x")));
+    let owned: Flexible = serde_yaml::from_cow_str(Cow::<str>::Owned(yaml.to_owned())).unwrap();
+    assert!(matches!(owned.name, Cow::Owned(name) if name == "x"));
+
+    let borrowed: Flexible = serde_yaml::from_cow_str(yaml).unwrap();
+    assert!(matches!(borrowed.name, Cow::Borrowed("x")));
+    let owned: Flexible = serde_yaml::from_cow_str(yaml.to_owned()).unwrap();
+    assert!(matches!(owned.name, Cow::Owned(name) if name == "x"));
+
+    let err = serde_yaml::from_cow_str::<&str>(Cow::<str>::Owned("x".to_owned())).unwrap_err();
+    assert_eq!(
+        "invalid type: string \"x\", expected a borrowed string",
+        err.to_string(),
+    );
+
+    let value = Value::deserialize(Deserializer::from_cow_str(yaml.to_owned())).unwrap();
+    assert_eq!(value["name"], "x");
+}
+
+#[test]
+fn test_from_cow_long_scalars() {
+    // Long scalars of owned input are left in it until they are needed.
+    let long = "x".repeat(5000);
+    let yaml = format!(
+        "plain: &a {0}\nsingle: '{0}'\ndouble: \"{0}\"\ntagged: !!str {0}\nalias: *a\n? {0}\n: key\n",
+        long,
+    );
+    let expected: Value = serde_yaml::from_str(&yaml).unwrap();
+    assert_eq!(expected["alias"], Value::String(long.clone()));
+    let owned: Value = serde_yaml::from_cow(yaml.clone().into_bytes()).unwrap();
+    assert_eq!(owned, expected);
+
+    let yaml = format!("---\n{0}\n---\n- {0}\n", long);
+    let documents = Deserializer::from_cow(yaml.into_bytes())
+        .map(|de| Value::deserialize(de).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(documents[0], Value::String(long.clone()));
+    assert_eq!(documents[1][0], Value::String(long));
+}
+
 #[test]
 fn test_alias() {
     let yaml = indoc! {"
@@ -86,6 +180,302 @@ fn test_alias() {
     test_de(yaml, &expected);
 }
 
+#[test]
+fn test_shadowed_alias() {
+    let yaml = indoc! {"
+        - &a 1
+        - *a
+        - &a 2
+        - &b 3
+        - *a
+        - *b
+    "};
+    let expected = vec![1, 1, 2, 3, 2, 3];
+    test_de(yaml, &expected);
+}
+
+#[test]
+fn test_ignored_callback() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        name: String,
+        servers: Vec<Server>,
+        mode: Mode,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Server {
+        port: u16,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    enum Mode {
+        Fast { level: u8 },
+    }
+
+    let yaml = indoc! {"
+        name: x
+        colour: red
+        servers:
+          - port: 80
+          - port: 81
+            host:
+              ignored: [1, 2]
+        mode: !Fast
+          level: 1
+          levle: 2
+    "};
+    let mut ignored = Vec::new();
+    let config: Config = serde_yaml::from_str_with_ignored_callback(yaml, |path, location| {
+        ignored.push((path.to_owned(), location.line(), location.column()));
+    })
+    .unwrap();
+    let expected = Config {
+        name: "x".to_owned(),
+        servers: vec![Server { port: 80 }, Server { port: 81 }],
+        mode: Mode::Fast { level: 1 },
+    };
+    assert_eq!(expected, config);
+    let expected = [
+        ("colour".to_owned(), 2, 1),
+        ("servers[1].host".to_owned(), 6, 5),
+        ("mode.levle".to_owned(), 10, 3),
+    ];
+    assert_eq!(expected, *ignored);
+
+    let mut ignored = 0;
+    serde_yaml::from_str_with_ignored_callback::<IgnoredAny, _>(yaml, |_, _| ignored += 1).unwrap();
+    assert_eq!(0, ignored);
+}
+
+#[test]
+fn test_diagnostics() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        mode: String,
+        ports: Vec<String>,
+        base: BTreeMap<String, u16>,
+        copy: BTreeMap<String, u16>,
+    }
+
+    let yaml = indoc! {"
+        mode: on
+        ports: [0755, 1:30, 8_080, '0644', !!str 0600]
+        base: &base {port: 80}
+        copy: *base
+        unused: &spare 1
+        again: &base {port: 81}
+    "};
+    let (config, diagnostics) =
+        serde_yaml::from_str_with_diagnostics::<Config>(yaml, &Options::new()).unwrap();
+    assert_eq!(config.mode, "on");
+    assert_eq!(config.ports, ["0755", "1:30", "8_080", "0644", "0600"]);
+    assert_eq!(config.copy["port"], 80);
+
+    let found: Vec<_> = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            let location = diagnostic.location();
+            (
+                diagnostic.code().as_str(),
+                location.line(),
+                location.column(),
+            )
+        })
+        .collect();
+    let expected = [
+        ("yaml-1.1-scalar", 1, 7),
+        ("yaml-1.1-scalar", 2, 9),
+        ("yaml-1.1-scalar", 2, 15),
+        ("yaml-1.1-scalar", 2, 21),
+        ("unknown-field", 5, 1),
+        ("unused-anchor", 5, 9),
+        ("unknown-field", 6, 1),
+        ("duplicate-anchor", 6, 8),
+        ("unused-anchor", 6, 8),
+    ];
+    assert_eq!(found, expected);
+
+    let messages: Vec<_> = diagnostics.iter().map(ToString::to_string).collect();
+    assert_eq!(
+        messages[0],
+        "plain scalar \"on\" is a string, but a boolean in YAML 1.1 at line 1 column 7"
+    );
+    assert_eq!(messages[4], "unknown field unused at line 5 column 1");
+    assert_eq!(messages[5], "unused anchor \"spare\" at line 5 column 9");
+    assert_eq!(messages[7], "duplicate anchor \"base\" at line 6 column 8");
+    assert_eq!(diagnostics[0].severity(), Severity::Warning);
+    assert_eq!(diagnostics[5].severity(), Severity::Note);
+
+    // Denied codes become the error, and the existing option for duplicate
+    // anchors gives its usual error.
+    let options = Options::new().deny(Code::UnusedAnchor);
+    let err = serde_yaml::from_str_with_diagnostics::<Config>(yaml, &options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "unused anchor \"spare\" at line 5 column 9"
+    );
+    let location = err.location().unwrap();
+    assert_eq!((location.line(), location.column()), (5, 9));
+
+    let options = Options::new().deny(Code::UnknownField);
+    let err = serde_yaml::from_str_with_diagnostics::<Config>(yaml, &options).unwrap_err();
+    assert_eq!(err.to_string(), "unknown field unused at line 5 column 1");
+
+    let options = Options::new().duplicate_anchors(DuplicateAnchors::Error);
+    let err = serde_yaml::from_str_with_diagnostics::<Config>(yaml, &options).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "duplicate anchor \"base\" at line 6 column 8, first defined at line 3 column 7",
+    );
+
+    let options = Options::new()
+        .deny(Code::Yaml11Scalar)
+        .duplicate_anchors(DuplicateAnchors::Allow);
+    let (_config, diagnostics) =
+        serde_yaml::from_str_with_diagnostics::<Value>("a: 'yes'\nb: true\n", &options).unwrap();
+    assert!(diagnostics.is_empty());
+}
+
+#[test]
+fn test_from_str_partial() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Head<'a> {
+        kind: &'a str,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Message<'a> {
+        kind: &'a str,
+        body: Vec<&'a str>,
+        from: &'a str,
+    }
+
+    let yaml = indoc! {"
+        body: &b [a, b]
+        kind: note
+        from: *b
+    "};
+    let (head, rest) = serde_yaml::from_str_partial::<Head>(yaml).unwrap();
+    assert_eq!(Head { kind: "note" }, head);
+
+    let err = rest.deserialize::<Message>().unwrap_err();
+    assert_eq!(
+        "from: invalid type: sequence, expected a borrowed string at line 1 column 7",
+        err.to_string(),
+    );
+    let value: Value = rest.deserialize().unwrap();
+    assert_eq!(value["body"][1], "b");
+    assert_eq!(value["from"][0], "a");
+
+    let yaml = "kind: note\nbody: [a]\nfrom: me\n";
+    let (_head, rest) = serde_yaml::from_str_partial::<Head>(yaml).unwrap();
+    let expected = Message {
+        kind: "note",
+        body: vec!["a"],
+        from: "me",
+    };
+    assert_eq!(expected, rest.deserialize().unwrap());
+
+    let yaml = "---\nkind: a\n---\nkind: b\n";
+    let err = serde_yaml::from_str_partial::<Head>(yaml).unwrap_err();
+    assert_eq!(
+        "deserializing from YAML containing more than one document is not supported",
+        err.to_string(),
+    );
+}
+
+#[test]
+fn test_flatten_catch_all() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Data {
+        a: u8,
+        #[serde(flatten)]
+        rest: BTreeMap<String, Vec<u8>>,
+    }
+
+    let yaml = indoc! {"
+        a: 1
+        b: [2, 3]
+        c: []
+    "};
+    let mut rest = BTreeMap::new();
+    rest.insert("b".to_owned(), vec![2, 3]);
+    rest.insert("c".to_owned(), vec![]);
+    let expected = Data { a: 1, rest };
+    test_de(yaml, &expected);
+}
+
+#[test]
+fn test_unit_struct() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Marker;
+
+    for yaml in [
+        "null",
+        "~",
+        "",
+        "{}",
+        "!Marker",
+        "!Marker ~",
+        "!Marker {}",
+        "Marker: null",
+        "Marker:",
+        "Marker: {}",
+    ] {
+        test_de(yaml, &Marker);
+    }
+
+    let cases = [
+        (
+            "!Other",
+            "invalid value: tag `!Other`, expected unit struct Marker",
+        ),
+        (
+            "Marker",
+            "invalid type: string \"Marker\", expected unit struct Marker",
+        ),
+        (
+            "'~'",
+            "invalid type: string \"~\", expected unit struct Marker",
+        ),
+        ("[]", "invalid type: sequence, expected unit struct Marker"),
+        (
+            "Other: ~",
+            "invalid value: string \"Other\", expected unit struct Marker",
+        ),
+        (
+            "!Marker x",
+            "invalid type: string \"x\", expected null or an empty mapping",
+        ),
+        (
+            "Marker: 1",
+            "Marker: invalid type: integer `1`, expected null or an empty mapping",
+        ),
+        (
+            "{Marker: ~, other: ~}",
+            "invalid length 2, expected unit struct Marker",
+        ),
+    ];
+    for (yaml, expected) in cases {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        let err = serde_yaml::from_value::<Marker>(value).unwrap_err();
+        assert_eq!(err.to_string(), expected, "{}", yaml);
+        assert!(serde_yaml::from_str::<Marker>(yaml).is_err(), "{}", yaml);
+    }
+
+    let err = serde_yaml::from_str::<Vec<Marker>>("- ~\n- !Other\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ".[1]: invalid value: tag `!Other`, expected unit struct Marker at line 2 column 3",
+    );
+    let err = serde_yaml::from_str::<Marker>("!Other").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid value: tag `!Other`, expected unit struct Marker",
+    );
+}
+
 #[test]
 fn test_option() {
     #[derive(Deserialize, PartialEq, Debug)]
@@ -255,7 +645,12 @@ fn test_number_as_string() {
     let expected = Num {
         value: "340282366920938463463374607431768211457".to_owned(),
     };
-    test_de_no_value(yaml, &expected);
+    let deserialized: Num = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(expected, deserialized);
+    serde_yaml::from_str::<serde::de::IgnoredAny>(yaml).unwrap();
+
+    // Though as a `Value` it is an integer too large to hold.
+    serde_yaml::from_str::<Value>(yaml).unwrap_err();
 }
 
 #[test]
@@ -349,6 +744,52 @@ fn test_de_mapping() {
     test_de(yaml, &expected);
 }
 
+#[test]
+fn test_line_breaks() {
+    // Line breaks in the input are read as \n whether they are written as
+    // \r\n, \r or \n. Escaped ones in double quotes are kept as written.
+    for newline in ["\r\n", "\r", "\n"] {
+        let yaml = [
+            "plain: a",
+            "  b",
+            "",
+            "  c",
+            "single: 'a",
+            "  b",
+            "",
+            "  c'",
+            "double: \"a",
+            "  b\\",
+            "  c\\r\\n\"",
+            "literal: |",
+            "  a",
+            "",
+            "  b",
+            "folded: >-",
+            "  a",
+            "  b",
+            "",
+            "  c",
+            "",
+        ]
+        .join(newline);
+        let mut expected = BTreeMap::new();
+        expected.insert("plain".to_owned(), "a b\nc".to_owned());
+        expected.insert("single".to_owned(), "a b\nc".to_owned());
+        expected.insert("double".to_owned(), "a bc\r\n".to_owned());
+        expected.insert("literal".to_owned(), "a\n\nb\n".to_owned());
+        expected.insert("folded".to_owned(), "a b\nc".to_owned());
+        test_de(&yaml, &expected);
+    }
+
+    // Carriage returns in content are written escaped, so they survive.
+    for string in ["a\r\nb\r\n", "a\rb", "a\nb\r"] {
+        let yaml = serde_yaml::to_string(string).unwrap();
+        assert!(!yaml.trim_end_matches('\n').contains(['\r', '\n']));
+        assert_eq!(serde_yaml::from_str::<String>(&yaml).unwrap(), string);
+    }
+}
+
 #[test]
 fn test_byte_order_mark() {
     let yaml = "\u{feff}- 0\n";
@@ -688,6 +1129,603 @@ fn test_tag_resolution() {
     test_de(yaml, &expected);
 }
 
+#[test]
+fn test_yaml11_scalars() {
+    // Plain scalars that YAML 1.2 and YAML 1.1 read differently, and what
+    // each reads them as.
+    let differ = [
+        ("=", "a string", "the default value"),
+        ("yes", "a string", "a boolean"),
+        ("Off", "a string", "a boolean"),
+        ("012", "a string", "an octal integer"),
+        ("-0_7", "a string", "an octal integer"),
+        ("1:00", "a string", "a base 60 integer"),
+        ("1:30.5", "a string", "a base 60 float"),
+        ("1_000", "a string", "an integer"),
+        ("0x_1f", "a string", "an integer"),
+        ("1e3", "a float", "a string"),
+        ("0o17", "an integer", "a string"),
+    ];
+    for (scalar, yaml12, yaml11) in differ {
+        let yaml = format!("- {}\n", scalar);
+        let (_value, diagnostics) =
+            serde_yaml::from_str_with_diagnostics::<Value>(&yaml, &Options::new()).unwrap();
+        let messages: Vec<_> = diagnostics.iter().map(|d| d.message()).collect();
+        let expected = format!(
+            "plain scalar {:?} is {}, but {} in YAML 1.1",
+            scalar, yaml12, yaml11,
+        );
+        assert_eq!(messages, [expected]);
+
+        // Serializing the string quotes it, so that either reads it back as
+        // a string.
+        let serialized = serde_yaml::to_string(scalar).unwrap();
+        assert_eq!(serialized, format!("'{}'\n", scalar));
+    }
+
+    // Read the same by both, including the casings of `.nan` that either
+    // reads as a float and the one that neither does.
+    let same = [
+        ".nan", ".NaN", ".NAN", ".Nan", "+.nan", ".inf", "-.INF", "true", "~", "12", "-12", "0x1f",
+        "1.5", "09", "y", "n", "web",
+    ];
+    for scalar in same {
+        let yaml = format!("- {}\n", scalar);
+        let (_value, diagnostics) =
+            serde_yaml::from_str_with_diagnostics::<Value>(&yaml, &Options::new()).unwrap();
+        assert!(diagnostics.is_empty(), "{}: {:?}", scalar, diagnostics);
+
+        let serialized = serde_yaml::to_string(scalar).unwrap();
+        let value: Value = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(value, scalar, "{}", serialized);
+    }
+    assert_eq!(serde_yaml::to_string("y").unwrap(), "y\n");
+    assert_eq!(serde_yaml::to_string(".Nan").unwrap(), ".Nan\n");
+}
+
+#[test]
+fn test_legacy_resolution() {
+    let yaml = indoc! {"
+        - yes
+        - Off
+        - 012
+        - 1:30
+        - -1:30.5
+        - 1_000
+        - 0x_1f
+        - 1e3
+        - 0o17
+        - 6.02e+23
+        - .5
+        - y
+        - '0755'
+        - !!str yes
+        - =x
+    "};
+    let expected = vec![
+        Value::Bool(true),
+        Value::Bool(false),
+        Value::Number(10.into()),
+        Value::Number(90.into()),
+        Value::Number((-90.5).into()),
+        Value::Number(1000.into()),
+        Value::Number(31.into()),
+        Value::String("1e3".to_owned()),
+        Value::String("0o17".to_owned()),
+        Value::Number(6.02e23.into()),
+        Value::Number(0.5.into()),
+        Value::String("y".to_owned()),
+        Value::String("0755".to_owned()),
+        Value::String("yes".to_owned()),
+        Value::String("=x".to_owned()),
+    ];
+    let de = Deserializer::from_str(yaml).legacy_resolution(true);
+    assert_eq!(Vec::<Value>::deserialize(de).unwrap(), expected);
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        enabled: bool,
+        mode: u32,
+        timeout: u64,
+        version: String,
+    }
+    let yaml = "enabled: on\nmode: 0644\ntimeout: 1:00:00\nversion: 1e3\n";
+    let de = Deserializer::from_str(yaml).legacy_resolution(true);
+    let expected = Config {
+        enabled: true,
+        mode: 0o644,
+        timeout: 3600,
+        version: "1e3".to_owned(),
+    };
+    assert_eq!(Config::deserialize(de).unwrap(), expected);
+
+    // The default value is a string under YAML 1.2, and cannot be
+    // deserialized under YAML 1.1.
+    let value: Value = serde_yaml::from_str("a: =\n").unwrap();
+    assert_eq!(value["a"], "=");
+    let de = Deserializer::from_str("a: =\n").legacy_resolution(true);
+    let err = Value::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "YAML 1.1 default value `=` cannot be deserialized at line 1 column 4",
+    );
+    assert!(err.is_data());
+}
+
+#[test]
+fn test_schema() {
+    fn s(string: &str) -> Value {
+        Value::String(string.to_owned())
+    }
+
+    // Each scalar as the core, JSON and failsafe schemas read it.
+    let cases = [
+        ("null", Value::Null, Value::Null, s("null")),
+        ("~", Value::Null, s("~"), s("~")),
+        ("", Value::Null, s(""), s("")),
+        ("Null", Value::Null, s("Null"), s("Null")),
+        ("true", Value::Bool(true), Value::Bool(true), s("true")),
+        ("True", Value::Bool(true), s("True"), s("True")),
+        ("yes", s("yes"), s("yes"), s("yes")),
+        ("0", Value::from(0), Value::from(0), s("0")),
+        ("-12", Value::from(-12), Value::from(-12), s("-12")),
+        ("+12", Value::from(12), s("+12"), s("+12")),
+        ("012", s("012"), s("012"), s("012")),
+        ("0x10", Value::from(16), s("0x10"), s("0x10")),
+        ("0o17", Value::from(15), s("0o17"), s("0o17")),
+        ("3.10", Value::from(3.1), Value::from(3.1), s("3.10")),
+        (
+            "-0.5e+3",
+            Value::from(-500.0),
+            Value::from(-500.0),
+            s("-0.5e+3"),
+        ),
+        (".5", Value::from(0.5), s(".5"), s(".5")),
+        (".inf", Value::from(f64::INFINITY), s(".inf"), s(".inf")),
+        ("1_000", s("1_000"), s("1_000"), s("1_000")),
+        ("web", s("web"), s("web"), s("web")),
+        ("'true'", s("true"), s("true"), s("true")),
+        (
+            "!!int 42",
+            Value::from(42),
+            Value::from(42),
+            Value::from(42),
+        ),
+        ("!!null ~", Value::Null, Value::Null, Value::Null),
+    ];
+    for (yaml, core, json, failsafe) in cases {
+        let yaml = format!("k: {}\n", yaml);
+        for (schema, expected) in [
+            (Schema::Core, core.clone()),
+            (Schema::Json, json),
+            (Schema::Failsafe, failsafe),
+        ] {
+            let de = Deserializer::from_str(&yaml).schema(schema);
+            let value = Value::deserialize(de).unwrap();
+            assert_eq!(value["k"], expected, "{:?} {}", schema, yaml);
+        }
+        let value: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(value["k"], core);
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        version: String,
+        port: u16,
+        debug: Option<bool>,
+    }
+    let yaml = "version: 3.10\nport: 8080\ndebug: ~\n";
+    let de = Deserializer::from_str(yaml).schema(Schema::Json);
+    let err = Config::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "debug: invalid type: string \"~\", expected a boolean at line 3 column 8",
+    );
+    let yaml = "version: 3.10\nport: 8080\ndebug: null\n";
+    let de = Deserializer::from_str(yaml).schema(Schema::Json);
+    let expected = Config {
+        version: "3.10".to_owned(),
+        port: 8080,
+        debug: None,
+    };
+    assert_eq!(Config::deserialize(de).unwrap(), expected);
+
+    let de = Deserializer::from_str(yaml).schema(Schema::Failsafe);
+    let err = Config::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "port: invalid type: string \"8080\", expected u16 at line 2 column 7",
+    );
+    let yaml = "version: 3.10\nport: !!int 8080\n";
+    let de = Deserializer::from_str(yaml).schema(Schema::Failsafe);
+    let expected = Config {
+        version: "3.10".to_owned(),
+        port: 8080,
+        debug: None,
+    };
+    assert_eq!(Config::deserialize(de).unwrap(), expected);
+
+    // YAML 1.1 resolution only applies to the core schema.
+    let de = Deserializer::from_str("k: yes\n")
+        .schema(Schema::Failsafe)
+        .legacy_resolution(true);
+    assert_eq!(Value::deserialize(de).unwrap()["k"], "yes");
+}
+
+#[test]
+fn test_resolvers() {
+    fn duration(scalar: &str) -> Option<&'static str> {
+        let digits = scalar.trim_end_matches(['m', 's', 'h']);
+        let unit = &scalar[digits.len()..];
+        let valid = !digits.is_empty()
+            && digits.bytes().all(|b| b.is_ascii_digit())
+            && ["ms", "s", "m", "h"].contains(&unit);
+        valid.then_some("!duration")
+    }
+
+    // A `#` after a space starts a comment, so colors are written without.
+    fn color(scalar: &str) -> Option<&'static str> {
+        let hex = scalar;
+        let valid = hex.len() == 6 && hex.bytes().all(|b| matches!(b, b'0'..=b'9' | b'a'..=b'f'));
+        valid.then_some("!color")
+    }
+
+    // Anything that starts with a digit.
+    fn digits(scalar: &str) -> Option<&'static str> {
+        scalar
+            .starts_with(|ch: char| ch.is_ascii_digit())
+            .then_some("!digits")
+    }
+
+    let yaml = indoc! {"
+        timeout: 30s
+        fg: 'ff0000'
+        bg: \"00ff00\"
+        retries: 3
+        interval: !!str 5m
+        palette: [000000, 1h, !other 2h, 10x]
+    "};
+    let de = Deserializer::from_str(yaml)
+        .resolver(duration)
+        .resolver(color);
+    let value = Value::deserialize(de).unwrap();
+    let expected = indoc! {"
+        timeout: !duration 30s
+        fg: ff0000
+        bg: 00ff00
+        retries: 3
+        interval: 5m
+        palette:
+        - !color '000000'
+        - !duration 1h
+        - !other 2h
+        - 10x
+    "};
+    assert_eq!(serde_yaml::to_string(&value).unwrap(), expected);
+
+    // The content of a tagged scalar is still resolved as usual.
+    let de = Deserializer::from_str("[123456, 1234567]").resolver(color);
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(
+        serde_yaml::to_string(&value).unwrap(),
+        "- !color 123456\n- 1234567\n"
+    );
+
+    // The first resolver to give a tag wins.
+    let yaml = "[30s, 3, x]\n";
+    let de = Deserializer::from_str(yaml)
+        .resolver(duration)
+        .resolver(digits);
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(
+        serde_yaml::to_string(&value).unwrap(),
+        "- !duration 30s\n- !digits 3\n- x\n"
+    );
+    let de = Deserializer::from_str(yaml)
+        .resolver(digits)
+        .resolver(duration);
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(
+        serde_yaml::to_string(&value).unwrap(),
+        "- !digits 30s\n- !digits 3\n- x\n"
+    );
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    #[serde(rename_all = "lowercase")]
+    enum Setting {
+        Duration(String),
+        Color(String),
+        Text(String),
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Theme {
+        fg: Setting,
+        fade: Setting,
+    }
+
+    let de = Deserializer::from_str("fg: ffffff\nfade: 250ms\n")
+        .resolver(duration)
+        .resolver(color);
+    let expected = Theme {
+        fg: Setting::Color("ffffff".to_owned()),
+        fade: Setting::Duration("250ms".to_owned()),
+    };
+    assert_eq!(Theme::deserialize(de).unwrap(), expected);
+
+    let de = Deserializer::from_str("fg: ffffff\nfade: slow\n")
+        .resolver(duration)
+        .resolver(color);
+    let err = Theme::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "fade: unknown variant `slow`, expected one of `duration`, `color`, `text` at line 2 column 7",
+    );
+}
+
+#[test]
+fn test_tag_handlers() {
+    fn include(path: &str) -> serde_yaml::Result<Value> {
+        let ten = |path: &str| format!("[{}]", vec![format!("!include {}", path); 10].join(", "));
+        let yaml = match path {
+            "db.yaml" => "host: localhost\nport: !include port.yaml\n",
+            "port.yaml" => "5432",
+            "yes.yaml" => "'yes'",
+            "self.yaml" => "!include self.yaml",
+            "wide.yaml" => &ten("wider.yaml"),
+            "wider.yaml" => &ten("widest.yaml"),
+            "widest.yaml" => &ten("port.yaml"),
+            "broken.yaml" => "[1, 2",
+            _ => return Err(serde::de::Error::custom(format!("{} not found", path))),
+        };
+        serde_yaml::from_str(yaml)
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Database {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Config {
+        primary: Database,
+        replica: Database,
+        enabled: String,
+        tags: Value,
+    }
+
+    // Included values are deserialized as if they were in the document, and
+    // aliases to an include repeat what it was replaced by.
+    let yaml = indoc! {"
+        primary: &db !include db.yaml
+        replica: *db
+        enabled: !include yes.yaml
+        tags: !include [a, b]
+    "};
+    let de = Deserializer::from_str(yaml).tag_handler("!include", include);
+    let config = Config::deserialize(de).unwrap();
+    let db = Database {
+        host: "localhost".to_owned(),
+        port: 5432,
+    };
+    assert_eq!(config.primary, db);
+    assert_eq!(config.replica, db);
+    assert_eq!(config.enabled, "yes");
+    assert_eq!(
+        serde_yaml::to_string(&config.tags).unwrap(),
+        "!include\n- a\n- b\n",
+    );
+
+    // Without a handler the tag is kept.
+    let value: Value = serde_yaml::from_str("!include db.yaml").unwrap();
+    assert_eq!(value.to_string(), "!include db.yaml");
+    let de = Deserializer::from_str("!other db.yaml").tag_handler("!include", include);
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(value.to_string(), "!other db.yaml");
+
+    // Errors are at the tagged scalar, whatever went wrong inside.
+    let cases = [
+        (
+            "a: 1\nb: !include missing.yaml\n",
+            "!include missing.yaml at line 2 column 4: missing.yaml not found",
+            (2, 4),
+        ),
+        (
+            "- !include broken.yaml\n",
+            "!include broken.yaml at line 1 column 3: flow sequence started at line 1 column 1 was never closed",
+            (1, 3),
+        ),
+        (
+            "x: !include self.yaml\n",
+            "recursion limit exceeded at line 1 column 4",
+            (1, 4),
+        ),
+    ];
+    for (yaml, expected, (line, column)) in cases {
+        let de = Deserializer::from_str(yaml).tag_handler("!include", include);
+        let err = Value::deserialize(de).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+        let location = err.location().unwrap();
+        assert_eq!((location.line(), location.column()), (line, column));
+    }
+
+    // Includes that fan out are limited like aliases that do.
+    let de = Deserializer::from_str("x: !include wide.yaml\n").tag_handler("!include", include);
+    let err = Value::deserialize(de).unwrap_err();
+    assert_eq!(err.to_string(), "repetition limit exceeded");
+}
+
+#[test]
+fn test_core_tags() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Data {
+        zip: String,
+        port: u16,
+        ratio: f64,
+        enabled: bool,
+        nothing: Option<String>,
+        text: String,
+    }
+
+    let yaml = indoc! {r#"
+        zip: !!str 007
+        port: !!int "42"
+        ratio: !!float '0.5'
+        enabled: !!bool "true"
+        nothing: !!null ""
+        text: !!str true
+    "#};
+    let expected = Data {
+        zip: "007".to_owned(),
+        port: 42,
+        ratio: 0.5,
+        enabled: true,
+        nothing: None,
+        text: "true".to_owned(),
+    };
+    let deserialized: Data = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(deserialized, expected);
+
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(value["zip"], Value::String("007".to_owned()));
+    assert_eq!(value["port"], 42);
+    assert_eq!(value["ratio"], 0.5);
+    assert_eq!(value["enabled"], true);
+    assert_eq!(value["nothing"], Value::Null);
+    assert_eq!(value["text"], Value::String("true".to_owned()));
+    assert_eq!(serde_yaml::from_value::<Data>(value).unwrap(), expected);
+
+    // An integer is also a float, but a string is neither.
+    assert_eq!(serde_yaml::from_str::<f64>("!!int 3").unwrap(), 3.0);
+    assert!(serde_yaml::from_str::<f64>("!!str 3").is_err());
+    assert!(serde_yaml::from_str::<bool>("!!str true").is_err());
+}
+
+#[test]
+fn test_stringify_scalars() {
+    let yaml = indoc! {"
+        - 3
+        - 3.10
+        - 0x1F
+        - 1e3
+        - -0
+        - true
+        - False
+        - .inf
+        - null
+        - ~
+        -
+        - '3'
+        - !!int 7
+    "};
+    let expected = [
+        "3", "3.10", "0x1F", "1e3", "-0", "true", "False", ".inf", "null", "~", "", "3", "7",
+    ];
+    let strings: Vec<String> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(strings, expected);
+    let de = Deserializer::from_str(yaml).stringify_scalars(true);
+    let strings = Vec::<String>::deserialize(de).unwrap();
+    assert_eq!(strings, expected);
+
+    // A `Value` resolves the same scalars as usual.
+    let de = Deserializer::from_str(yaml).stringify_scalars(true);
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(value[0], 3);
+    assert_eq!(value[5], true);
+    assert_eq!(value[8], Value::Null);
+
+    let de = Deserializer::from_str("[[3]]").stringify_scalars(true);
+    let err = Vec::<String>::deserialize(de).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ".[0]: invalid type: sequence, expected a string at line 1 column 2",
+    );
+
+    let de = Deserializer::from_str("- '3'\n- !!str 4\n- five\n").stringify_scalars(false);
+    let strings = Vec::<String>::deserialize(de).unwrap();
+    assert_eq!(strings, ["3", "4", "five"]);
+    for (yaml, expected) in [
+        ("3", "invalid type: integer `3`, expected a string"),
+        (
+            "3.10",
+            "invalid type: floating point `3.1`, expected a string",
+        ),
+        ("true", "invalid type: boolean `true`, expected a string"),
+        ("~", "invalid type: unit value, expected a string"),
+    ] {
+        let de = Deserializer::from_str(yaml).stringify_scalars(false);
+        let err = String::deserialize(de).unwrap_err();
+        assert_eq!(err.to_string(), expected);
+    }
+}
+
+#[test]
+fn test_scalar_info() {
+    use serde_yaml::raw::ScalarStyle;
+    use serde_yaml::ScalarInfo;
+
+    #[derive(Deserialize, Debug)]
+    struct Ports {
+        plain: ScalarInfo<u16>,
+        quoted: ScalarInfo<String>,
+        single: ScalarInfo<String>,
+        literal: ScalarInfo<String>,
+        folded: ScalarInfo<String>,
+        hex: ScalarInfo<u16>,
+        alias: ScalarInfo<String>,
+        missing: ScalarInfo<Option<u16>>,
+    }
+
+    let yaml = indoc! {r#"
+        plain: &port 8080
+        quoted: "8080"
+        single: 'it''s'
+        literal: |
+          8080
+        folded: >-
+          80
+          80
+        hex: 0x1F
+        alias: *port
+        missing:
+    "#};
+    let ports: Ports = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(*ports.plain, 8080);
+    assert_eq!(ports.plain.style(), ScalarStyle::Plain);
+    assert_eq!(ports.plain.text(), "8080");
+    assert_eq!(ports.quoted.as_str(), "8080");
+    assert_eq!(ports.quoted.style(), ScalarStyle::DoubleQuoted);
+    assert_eq!(ports.single.style(), ScalarStyle::SingleQuoted);
+    assert_eq!(ports.single.text(), "it's");
+    assert_eq!(ports.literal.style(), ScalarStyle::Literal);
+    assert_eq!(ports.literal.text(), "8080\n");
+    assert_eq!(ports.folded.style(), ScalarStyle::Folded);
+    assert_eq!(ports.folded.text(), "80 80");
+    assert_eq!(ports.hex.into_inner(), 31);
+    assert_eq!(ports.alias.style(), ScalarStyle::Plain);
+    assert_eq!(ports.alias.get_ref(), "8080");
+    assert_eq!(*ports.missing, None);
+    assert_eq!(ports.missing.text(), "");
+
+    let err =
+        serde_yaml::from_str::<BTreeMap<String, ScalarInfo<String>>>("port: [8080]\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "port: invalid type: sequence, expected a YAML scalar at line 1 column 7",
+    );
+
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let err = ScalarInfo::<u16>::deserialize(&value["plain"]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "ScalarInfo needs to know how a scalar was written, which a Value does not record",
+    );
+
+    let yaml = serde_yaml::to_string(&ports.quoted).unwrap();
+    assert_eq!(yaml, "'8080'\n");
+}
+
 #[test]
 fn test_parse_number() {
     let n = "111".parse::<Number>().unwrap();
@@ -715,3 +1753,145 @@ fn test_parse_number() {
     let err = " 1 ".parse::<Number>().unwrap_err();
     assert_eq!(err.to_string(), "failed to parse YAML number");
 }
+
+#[test]
+fn test_progress() {
+    static REPORTS: Mutex<Vec<u64>> = Mutex::new(Vec::new());
+
+    fn report(bytes: u64) {
+        REPORTS.lock().unwrap().push(bytes);
+    }
+
+    let mut yaml = String::new();
+    for i in 0..20_000 {
+        yaml += &format!(
+            "- id: {}\n  name: item number {}\n  tags: [a, b, c]\n",
+            i, i
+        );
+    }
+    let every = 16 * 1024;
+    let de = Deserializer::from_slice(yaml.as_bytes()).progress(every, report);
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(value.as_sequence().unwrap().len(), 20_000);
+
+    let reports = REPORTS.lock().unwrap();
+    assert!(
+        reports.windows(2).all(|pair| pair[0] < pair[1]),
+        "{:?}",
+        reports
+    );
+    assert_eq!(reports.last(), Some(&(yaml.len() as u64)));
+    // One report for each `every` bytes, each within an event of the last.
+    assert!(reports.len() as u64 >= yaml.len() as u64 / every);
+    assert!(reports
+        .windows(2)
+        .all(|pair| pair[1] - pair[0] < every + 64));
+
+    // Iterating reports how far the parser is.
+    let yaml = "--- 1\n--- [2, 3]\n--- {a: 4}\n";
+    let mut documents = Deserializer::from_str(yaml);
+    let mut consumed = vec![documents.bytes_consumed()];
+    while let Some(document) = documents.next() {
+        Value::deserialize(document).unwrap();
+        consumed.push(documents.bytes_consumed());
+    }
+    assert_eq!(consumed, [0, 6, 17, 28]);
+}
+
+#[test]
+fn test_send() {
+    fn assert_send<T: Send>() {}
+    assert_send::<Deserializer>();
+    assert_send::<serde_yaml::Serializer<Vec<u8>>>();
+
+    let yaml = "---\na: 1\n---\nb: 2\n---\nc: 3\n";
+    let mut documents = Deserializer::from_reader(yaml.as_bytes());
+    let first = Value::deserialize(documents.next().unwrap()).unwrap();
+    let rest = thread::spawn(move || {
+        documents
+            .map(|document| Value::deserialize(document).unwrap())
+            .collect::<Vec<_>>()
+    })
+    .join()
+    .unwrap();
+    assert_eq!(first["a"], 1);
+    assert_eq!(rest.len(), 2);
+    assert_eq!(rest[0]["b"], 2);
+    assert_eq!(rest[1]["c"], 3);
+}
+
+#[test]
+fn test_peek() {
+    use serde_yaml::document::NodeKind;
+    use serde_yaml::value::Tag;
+    use std::marker::PhantomData;
+
+    // Deserializes a node as a `Value`, together with what `peek` saw of it.
+    #[derive(PartialEq, Debug)]
+    struct Peeked {
+        kind: NodeKind,
+        tag: Option<Tag>,
+        value: Value,
+    }
+
+    impl<'de> serde::Deserialize<'de> for Peeked {
+        fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+        where
+            D: serde::Deserializer<'de>,
+        {
+            let mut kind = None;
+            let mut tag = None;
+            let value = serde_yaml::peek(deserializer, |peeked| {
+                kind = Some(peeked.node_kind());
+                tag = peeked.tag().cloned();
+                PhantomData::<Value>
+            })?;
+            Ok(Peeked {
+                kind: kind.unwrap(),
+                tag,
+                value,
+            })
+        }
+    }
+
+    let yaml = indoc! {"
+        scalar: &anchor 1
+        sequence: [a]
+        mapping: {a: b}
+        tagged: !Point {x: 1}
+        core: !!str 1
+        alias: *anchor
+        empty:
+    "};
+    let expected = [
+        ("scalar", NodeKind::Scalar, None),
+        ("sequence", NodeKind::Sequence, None),
+        ("mapping", NodeKind::Mapping, None),
+        ("tagged", NodeKind::Mapping, Some("!Point")),
+        ("core", NodeKind::Scalar, Some("tag:yaml.org,2002:str")),
+        ("alias", NodeKind::Scalar, None),
+        ("empty", NodeKind::Scalar, None),
+    ];
+    let from_str: BTreeMap<String, Peeked> = serde_yaml::from_str(yaml).unwrap();
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let from_ref = BTreeMap::<String, Peeked>::deserialize(&value).unwrap();
+    let from_value: BTreeMap<String, Peeked> = serde_yaml::from_value(value.clone()).unwrap();
+    for (key, kind, tag) in expected {
+        let peeked = &from_str[key];
+        assert_eq!(peeked.kind, kind, "{}", key);
+        assert_eq!(peeked.tag, tag.map(Tag::new), "{}", key);
+        assert_eq!(peeked.value, value[key], "{}", key);
+        if key != "core" {
+            // A `Value` does not keep core tags.
+            assert_eq!(from_ref[key], *peeked, "{}", key);
+            assert_eq!(from_value[key], *peeked, "{}", key);
+        }
+    }
+
+    let deserializer = serde::de::value::StrDeserializer::<serde::de::value::Error>::new("x");
+    let err = serde_yaml::peek(deserializer, |_| PhantomData::<String>).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: string \"x\", expected serde_yaml's deserializer, which serde_yaml::peek requires",
+    );
+}
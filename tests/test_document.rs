@@ -0,0 +1,345 @@
+#![allow(clippy::uninlined_format_args)]
+
+use indoc::indoc;
+use serde::Serialize;
+use serde_yaml::document::{Document, NodeKind};
+use serde_yaml::raw::{CollectionStyle, ScalarStyle};
+use serde_yaml::Value;
+use std::fmt::Debug;
+
+const DEPLOYMENT: &str = indoc! {r#"
+    # Deployed by the release pipeline; edit with care.
+    apiVersion: apps/v1
+    kind: Deployment
+    metadata:
+      name: web   # must match the service selector
+      labels: {app: web, tier: "frontend"}
+    spec:
+      replicas: 2
+
+      template:
+        spec:
+          containers:
+            - name: web
+              image: "registry.example.com/web:1.4.2"
+              args: ['--port', '8080']
+              resources:
+                limits: &limits
+                  cpu: 500m
+                  memory: 256Mi
+                requests: *limits
+"#};
+
+const WORKFLOW: &str = indoc! {"
+    name: CI
+    on:
+      push:
+        branches: [ main ]
+    env:
+      RUST_BACKTRACE: 1
+      FEATURES: !!str 0600
+    jobs:
+      test:
+        runs-on: ubuntu-latest
+        timeout-minutes:
+        steps:
+          - uses: actions/checkout@v4
+          - run: |
+              cargo test
+              cargo clippy
+          - run: echo done # last step
+"};
+
+const COMPOSE: &str = indoc! {"
+    services:
+      db:
+        image: postgres:16
+        environment:
+          POSTGRES_PASSWORD: 'it''s secret'
+        ports:
+          - 5432:5432
+      cache: {image: redis, command: [redis-server, --save, '']}
+"};
+
+// Applies one edit and checks that the only line to change is `expected`.
+fn test_edit<T>(input: &str, pointer: &str, value: &T, expected: &str)
+where
+    T: ?Sized + Serialize + Debug,
+{
+    let mut doc = Document::parse(input).unwrap();
+    doc.set(pointer, value).unwrap();
+    let output = doc.as_str();
+
+    let before: Vec<&str> = input.split_inclusive('\n').collect();
+    let after: Vec<&str> = output.split_inclusive('\n').collect();
+    assert_eq!(before.len(), after.len(), "{}", output);
+    let changed: Vec<&str> = before
+        .iter()
+        .zip(&after)
+        .filter(|(before, after)| before != after)
+        .map(|(_before, after)| after.trim_end_matches('\n'))
+        .collect();
+    assert_eq!(changed, [expected], "{}", output);
+
+    let parsed: Value = serde_yaml::from_str(output).unwrap();
+    let value = serde_yaml::to_value(value).unwrap();
+    assert_eq!(parsed.pointer(pointer), Some(&value));
+}
+
+#[test]
+fn test_edit_one_line() {
+    test_edit(DEPLOYMENT, "/spec/replicas", &3, "  replicas: 3");
+    test_edit(
+        DEPLOYMENT,
+        "/metadata/name",
+        "api",
+        "  name: api   # must match the service selector",
+    );
+    test_edit(
+        DEPLOYMENT,
+        "/metadata/labels/tier",
+        "back end",
+        r#"  labels: {app: web, tier: "back end"}"#,
+    );
+    test_edit(
+        DEPLOYMENT,
+        "/metadata/labels/app",
+        "a,b",
+        r#"  labels: {app: "a,b", tier: "frontend"}"#,
+    );
+    test_edit(
+        DEPLOYMENT,
+        "/spec/template/spec/containers/0/image",
+        "registry.example.com/web:1.5.0",
+        r#"          image: "registry.example.com/web:1.5.0""#,
+    );
+    test_edit(
+        DEPLOYMENT,
+        "/spec/template/spec/containers/0/args/1",
+        "it's",
+        "          args: ['--port', 'it''s']",
+    );
+    test_edit(
+        DEPLOYMENT,
+        "/spec/template/spec/containers/0/resources/limits/cpu",
+        "1",
+        "              cpu: '1'",
+    );
+
+    test_edit(
+        WORKFLOW,
+        "/on/push/branches/0",
+        "trunk",
+        "    branches: [ trunk ]",
+    );
+    test_edit(
+        WORKFLOW,
+        "/env/RUST_BACKTRACE",
+        "full",
+        "  RUST_BACKTRACE: full",
+    );
+    test_edit(
+        WORKFLOW,
+        "/env/FEATURES",
+        "0700",
+        "  FEATURES: !!str '0700'",
+    );
+    test_edit(
+        WORKFLOW,
+        "/jobs/test/timeout-minutes",
+        &30,
+        "    timeout-minutes: 30",
+    );
+    test_edit(
+        WORKFLOW,
+        "/jobs/test/steps/2/run",
+        "echo finished",
+        "      - run: echo finished # last step",
+    );
+    test_edit(
+        WORKFLOW,
+        "/on/push/branches/0",
+        &true,
+        "    branches: [ true ]",
+    );
+
+    test_edit(
+        COMPOSE,
+        "/services/db/environment/POSTGRES_PASSWORD",
+        "new\tsecret",
+        r#"      POSTGRES_PASSWORD: "new\tsecret""#,
+    );
+    test_edit(
+        COMPOSE,
+        "/services/db/ports/0",
+        "6543:5432",
+        "      - 6543:5432",
+    );
+    test_edit(
+        COMPOSE,
+        "/services/cache/command/2",
+        "60 1",
+        "  cache: {image: redis, command: [redis-server, --save, '60 1']}",
+    );
+    test_edit(
+        COMPOSE,
+        "/services/cache/image",
+        "true",
+        "  cache: {image: 'true', command: [redis-server, --save, '']}",
+    );
+}
+
+#[test]
+fn test_edit_block_scalar() {
+    let mut doc = Document::parse(WORKFLOW).unwrap();
+    doc.set("/jobs/test/steps/1/run", "cargo test --all")
+        .unwrap();
+    let expected = concat!(
+        "      - uses: actions/checkout@v4\n",
+        "      - run: cargo test --all\n",
+        "      - run: echo done # last step\n",
+    );
+    assert!(doc.as_str().ends_with(expected), "{}", doc);
+
+    // Several lines stay a literal block, at the same indentation.
+    let mut doc = Document::parse(WORKFLOW).unwrap();
+    doc.set("/jobs/test/steps/1/run", "cargo build\n\ncargo test\n")
+        .unwrap();
+    let expected = concat!(
+        "      - uses: actions/checkout@v4\n",
+        "      - run: |\n",
+        "          cargo build\n",
+        "\n",
+        "          cargo test\n",
+        "      - run: echo done # last step\n",
+    );
+    assert!(doc.as_str().ends_with(expected), "{}", doc);
+
+    let mut doc = Document::parse("a: |\n  x\n\n\nb: 1\n").unwrap();
+    doc.set("/a", "two\n  lines").unwrap();
+    assert_eq!(doc.as_str(), "a: |-\n  two\n    lines\n\n\nb: 1\n");
+
+    // Unless a literal block cannot hold the string.
+    for (string, quoted) in [
+        (" two\nlines", "\" two\\nlines\""),
+        ("two\nlines\n\n", "\"two\\nlines\\n\\n\""),
+        ("two\r\nlines", "\"two\\r\\nlines\""),
+    ] {
+        let mut doc = Document::parse("a: |\n  x\nb: 1\n").unwrap();
+        doc.set("/a", string).unwrap();
+        assert_eq!(doc.as_str(), format!("a: {}\nb: 1\n", quoted));
+    }
+}
+
+#[test]
+fn test_edit_alias() {
+    let mut doc = Document::parse(DEPLOYMENT).unwrap();
+    let limits = "/spec/template/spec/containers/0/resources/limits";
+    doc.set(&format!("{}/memory", limits), "512Mi").unwrap();
+    let value: Value = doc.deserialize().unwrap();
+    let requests = value
+        .pointer("/spec/template/spec/containers/0/resources/requests/memory")
+        .unwrap();
+    assert_eq!(requests, "512Mi");
+
+    let requests = "/spec/template/spec/containers/0/resources/requests";
+    assert_eq!(doc.get(requests).unwrap().kind(), NodeKind::Alias);
+    doc.set(requests, "none").unwrap();
+    assert!(doc.as_str().contains("        requests: none\n"));
+    assert!(doc.as_str().contains("        limits: &limits\n"));
+}
+
+#[test]
+fn test_nodes() {
+    let doc = Document::parse(DEPLOYMENT).unwrap();
+
+    let root = doc.root();
+    assert_eq!(root.kind(), NodeKind::Mapping);
+    assert_eq!(root.collection_style(), Some(CollectionStyle::Block));
+    assert_eq!((root.location().line(), root.location().column()), (2, 1));
+
+    let name = doc.get("/metadata/name").unwrap();
+    assert_eq!(name.kind(), NodeKind::Scalar);
+    assert_eq!(name.as_str(), Some("web"));
+    assert_eq!(name.comment(), Some("must match the service selector"));
+    assert_eq!(name.scalar_style(), Some(ScalarStyle::Plain));
+
+    let tier = doc.get("/metadata/labels/tier").unwrap();
+    assert_eq!(tier.scalar_style(), Some(ScalarStyle::DoubleQuoted));
+    assert_eq!(tier.source(), Some("\"frontend\""));
+    assert_eq!(tier.comment(), None);
+
+    let labels = doc.get("/metadata/labels").unwrap();
+    assert_eq!(labels.collection_style(), Some(CollectionStyle::Flow));
+    assert_eq!(labels.source(), None);
+
+    let limits = doc
+        .get("/spec/template/spec/containers/0/resources/limits")
+        .unwrap();
+    assert_eq!(limits.anchor(), Some("limits"));
+
+    let doc = Document::parse(WORKFLOW).unwrap();
+    let features = doc.get("/env/FEATURES").unwrap();
+    assert_eq!(features.tag().unwrap().as_bytes(), b"tag:yaml.org,2002:str");
+    assert_eq!(features.source(), Some("0600"));
+    let run = doc.get("/jobs/test/steps/1/run").unwrap();
+    assert_eq!(run.scalar_style(), Some(ScalarStyle::Literal));
+    assert_eq!(run.as_str(), Some("cargo test\ncargo clippy\n"));
+    assert_eq!(run.comment(), None);
+    let last = doc.get("/jobs/test/steps/2/run").unwrap();
+    assert_eq!(last.comment(), Some("last step"));
+
+    assert!(doc.get("/jobs/build").is_none());
+    assert!(doc.get("/jobs/test/steps/3").is_none());
+    assert!(doc.get("jobs").is_none());
+}
+
+#[test]
+fn test_edit_errors() {
+    let mut doc = Document::parse(WORKFLOW).unwrap();
+
+    let err = doc.set("/jobs/build/name", "x").unwrap_err();
+    assert_eq!(err.to_string(), "no value found at /jobs/build");
+
+    let err = doc.set("/jobs/test/steps", "x").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "/jobs/test/steps: replacing a sequence is not supported, only a scalar",
+    );
+
+    let err = doc.set("/name", &[1, 2]).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "/name: writing a sequence in place of a scalar is not supported",
+    );
+
+    // The tag would make the number a string.
+    let err = doc.set("/env/FEATURES", &600).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "/env/FEATURES: writing 600 here would not read back the same",
+    );
+
+    assert_eq!(doc.as_str(), WORKFLOW);
+}
+
+#[test]
+fn test_parse_document() {
+    let doc: Document = "".parse().unwrap();
+    assert_eq!(doc.root().as_str(), Some(""));
+    let mut doc = Document::parse("# nothing here\n").unwrap();
+    doc.set("", "something").unwrap();
+    assert_eq!(doc.as_str(), "# nothing here\nsomething");
+
+    let err = Document::parse("a: 1\n---\nb: 2\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "deserializing from YAML containing more than one document is not supported",
+    );
+
+    let err = Document::parse("a: [1\n").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "flow sequence started at line 1 column 4 was never closed",
+    );
+}
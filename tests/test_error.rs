@@ -5,13 +5,18 @@ use serde::de::Deserialize;
 #[cfg(not(miri))]
 use serde::de::{SeqAccess, Visitor};
 use serde_derive::{Deserialize, Serialize};
+use serde_yaml::error::Category;
 use serde_yaml::value::{Tag, TaggedValue};
-use serde_yaml::{Deserializer, Value};
-#[cfg(not(miri))]
-use std::collections::BTreeMap;
+use serde_yaml::{Deserializer, DuplicateAnchors, Value};
+use std::collections::{BTreeMap, HashSet};
+use std::error::Error as _;
 #[cfg(not(miri))]
 use std::fmt;
 use std::fmt::Debug;
+use std::io;
+use std::mem::size_of;
+#[cfg(not(miri))]
+use std::time::{Duration, Instant};
 
 fn test_error<'de, T>(yaml: &'de str, expected: &str)
 where
@@ -36,6 +41,104 @@ fn test_scan_error() {
     test_error::<Value>(yaml, expected);
 }
 
+#[test]
+fn test_unclosed() {
+    // Each is opened on line 3 and never closed, which the parser only finds
+    // out at the end of the input. Quoted scalars go on over any lines after
+    // them.
+    let cases = [
+        (
+            "key: \"unterminated\n  more: 3\nc: 4",
+            "double-quoted scalar started at line 3 column 6 was never closed",
+            (3, 6),
+        ),
+        (
+            "key: 'unterminated\n  more: 3\nc: 4",
+            "single-quoted scalar started at line 3 column 6 was never closed",
+            (3, 6),
+        ),
+        (
+            "key: [1, 2,\n  3,",
+            "flow sequence started at line 3 column 6 was never closed",
+            (3, 6),
+        ),
+        (
+            "key: {a: [1],\n  b: 2",
+            "flow mapping started at line 3 column 6 was never closed",
+            (3, 6),
+        ),
+        (
+            "key: {a: [1, b: 2",
+            "flow sequence started at line 3 column 10 was never closed",
+            (3, 10),
+        ),
+        (
+            "key: [1, \"a\nb",
+            "double-quoted scalar started at line 3 column 10 was never closed",
+            (3, 10),
+        ),
+    ];
+    for (line, expected, (context_line, context_column)) in cases {
+        let yaml = format!("a: 1\nb: 2\n{}\n", line);
+        test_error::<Value>(&yaml, expected);
+
+        let err = serde_yaml::from_str::<Value>(&yaml).unwrap_err();
+        assert_eq!(err.classify(), Category::Eof);
+        assert!(err.context().is_some());
+        let context = err.context_location().unwrap();
+        assert_eq!(
+            (context.line(), context.column()),
+            (context_line, context_column)
+        );
+        let location = err.location().unwrap();
+        let lines = yaml.lines().count();
+        assert_eq!((location.index(), location.line()), (yaml.len(), lines + 1));
+    }
+
+    // At the very start of the input.
+    test_error::<Value>(
+        "[1, 2",
+        "flow sequence started at line 1 column 1 was never closed",
+    );
+
+    // Not closed before something else that cannot be in it, which is where
+    // the parser gives up.
+    let yaml = "a: 1\nb: [1, 2,\n  3\nc: 4\n";
+    let expected = "did not find expected ',' or ']' at line 4 column 2, while parsing a flow sequence at line 2 column 4";
+    test_error::<Value>(yaml, expected);
+}
+
+#[test]
+fn test_invalid_escape() {
+    let yaml = r#"x: "ab\uD800""#;
+    let expected = r#"invalid escape "\uD800" (U+D800 is a UTF-16 surrogate, not a character) at line 1 column 7, while parsing a quoted scalar at line 1 column 4"#;
+    test_error::<Value>(yaml, expected);
+
+    let yaml = r#""\uDE00""#;
+    let expected = r#"invalid escape "\uDE00" (U+DE00 is a UTF-16 surrogate, not a character) at line 1 column 2, while parsing a quoted scalar at line 1 column 1"#;
+    test_error::<Value>(yaml, expected);
+
+    let yaml = r#""\U00110000""#;
+    let expected = r#"invalid escape "\U00110000" (U+110000 is beyond the last code point, U+10FFFF) at line 1 column 2, while parsing a quoted scalar at line 1 column 1"#;
+    test_error::<Value>(yaml, expected);
+
+    // Escapes name code points, so the UTF-16 pair that JSON would use for a
+    // character outside the Basic Multilingual Plane is rejected too.
+    let yaml = r#""\uD83D\uDE00""#;
+    let expected = r#"invalid escape "\uD83D" (U+D83D is half of a UTF-16 surrogate pair; write the character as \U0001F600) at line 1 column 2, while parsing a quoted scalar at line 1 column 1"#;
+    test_error::<Value>(yaml, expected);
+
+    // `\x` names a code point too, not a byte of UTF-8.
+    let yaml = r#""\U0001F600 \x41 \xFF""#;
+    assert_eq!(
+        "\u{1F600} A \u{FF}",
+        serde_yaml::from_str::<String>(yaml).unwrap()
+    );
+
+    let err = serde_yaml::from_str::<Value>(r#""\uD800""#).unwrap_err();
+    assert_eq!(err.classify(), Category::Syntax);
+}
+
 #[test]
 fn test_incorrect_type() {
     let yaml = indoc! {"
@@ -71,6 +174,55 @@ fn test_incorrect_nested_type() {
     test_error::<A>(yaml, expected);
 }
 
+#[test]
+fn test_from_value_path() {
+    #[derive(Deserialize, Debug)]
+    pub struct Spec {
+        #[allow(dead_code)]
+        pub containers: Vec<Container>,
+    }
+    #[derive(Deserialize, Debug)]
+    pub struct Container {
+        #[allow(dead_code)]
+        pub ports: BTreeMap<u16, Port>,
+    }
+    #[derive(Deserialize, Debug)]
+    pub struct Port {
+        #[allow(dead_code)]
+        pub protocol: String,
+    }
+    let yaml = indoc! {"
+        containers:
+          - ports: {}
+          - ports:
+              80: {protocol: TCP}
+              443: {protocol: [TCP]}
+    "};
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let expected = "containers[1].ports.443.protocol: invalid type: sequence, expected a string";
+    let error = serde_yaml::from_value::<Spec>(value.clone()).unwrap_err();
+    assert_eq!(error.to_string(), expected);
+    let error = Spec::deserialize(&value).unwrap_err();
+    assert_eq!(error.to_string(), expected);
+
+    let value: Value = serde_yaml::from_str("http: {protocol: [TCP]}").unwrap();
+    let error = serde_yaml::from_value::<BTreeMap<String, Port>>(value).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "http.protocol: invalid type: sequence, expected a string",
+    );
+    let value: Value = serde_yaml::from_str("? [a]\n: {protocol: [TCP]}").unwrap();
+    let error = serde_yaml::from_value::<BTreeMap<Vec<String>, Port>>(value).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "?.protocol: invalid type: sequence, expected a string",
+    );
+
+    let value: Value = serde_yaml::from_str("containers: [{}]").unwrap();
+    let error = serde_yaml::from_value::<Spec>(value).unwrap_err();
+    assert_eq!(error.to_string(), "containers[0]: missing field `ports`");
+}
+
 #[test]
 fn test_empty() {
     let expected = "EOF while parsing a value";
@@ -90,8 +242,25 @@ fn test_missing_field() {
         ---
         v: true
     "};
-    let expected = "missing field `w` at line 2 column 1";
+    let expected = "missing field `w` in mapping starting at line 2 column 1";
     test_error::<Basic>(yaml, expected);
+
+    let expected = "missing field `w` in mapping starting at line 1 column 1";
+    test_error::<Basic>("v: true", expected);
+
+    #[derive(Deserialize, Debug)]
+    pub struct Outer {
+        #[allow(dead_code)]
+        pub basic: Vec<Basic>,
+    }
+    let yaml = indoc! {"
+        basic:
+          - v: true
+            w: false
+          - w: false
+    "};
+    let expected = "basic[1]: missing field `v` in mapping starting at line 4 column 5";
+    test_error::<Outer>(yaml, expected);
 }
 
 #[test]
@@ -158,6 +327,359 @@ fn test_second_document_syntax_error() {
     assert_eq!(expected, result.unwrap_err().to_string());
 }
 
+#[test]
+fn test_recover_documents() {
+    let yaml = indoc! {"
+        ---
+        a: 1
+        ---
+        b: 2
+        ---
+        c: [3
+        d: 4
+        ---
+        e: 5
+        ---
+        f: *g
+        g: [6]
+        ---
+        h: 7
+    "};
+
+    let mut results = Vec::new();
+    for document in Deserializer::from_str(yaml).recover_documents(true) {
+        results.push(Value::deserialize(document));
+    }
+    assert_eq!(6, results.len());
+    assert_eq!(results[0].as_ref().unwrap()["a"], 1);
+    assert_eq!(results[1].as_ref().unwrap()["b"], 2);
+    let expected = "did not find expected ',' or ']' at line 7 column 2, while parsing a flow sequence at line 6 column 4";
+    assert_eq!(expected, results[2].as_ref().unwrap_err().to_string());
+    let location = results[2].as_ref().unwrap_err().location().unwrap();
+    assert_eq!((7, 2), (location.line(), location.column()));
+    assert_eq!(results[3].as_ref().unwrap()["e"], 5);
+    let expected = "unknown anchor at line 11 column 4";
+    assert_eq!(expected, results[4].as_ref().unwrap_err().to_string());
+    assert_eq!(results[5].as_ref().unwrap()["h"], 7);
+
+    // Without recovery, the error repeats.
+    let mut de = Deserializer::from_str(yaml);
+    for _ in 0..2 {
+        let _ = Value::deserialize(de.next().unwrap()).unwrap();
+    }
+    for _ in 0..2 {
+        Value::deserialize(de.next().unwrap()).unwrap_err();
+    }
+}
+
+#[test]
+fn test_document_errors() {
+    fn deserialize_each(yaml: &str, recover: bool) -> Vec<Option<serde_yaml::Error>> {
+        Deserializer::from_str(yaml)
+            .recover_documents(recover)
+            .take(4)
+            .map(|document| BTreeMap::<String, Vec<u32>>::deserialize(document).err())
+            .collect()
+    }
+
+    // Without a `---`, then ended by `...`, then to the end of the input.
+    let yaml = "a: [1]\n---\nb: [2,\n   x]\n...\n---\nc: [3]\n";
+    let errors = deserialize_each(yaml, false);
+    assert_eq!(errors.len(), 3);
+    assert!(errors[0].is_none() && errors[2].is_none());
+    let err = errors[1].as_ref().unwrap();
+    assert_eq!(
+        err.to_string(),
+        "b[1]: invalid type: string \"x\", expected u32 at line 4 column 4"
+    );
+    assert_eq!(err.document_index(), Some(1));
+    assert_eq!(err.document_range(), Some(7..27));
+    assert_eq!(&yaml[7..27], "---\nb: [2,\n   x]\n...");
+    let location = err.location().unwrap();
+    assert_eq!(
+        (location.index(), location.line(), location.column()),
+        (21, 4, 4)
+    );
+    let location = err.document_location().unwrap();
+    assert_eq!(
+        (location.index(), location.line(), location.column()),
+        (14, 3, 4)
+    );
+
+    // A syntax error ends the document where the parser gave up.
+    let yaml = "--- {a: [1]}\n--- {b: [2}\n--- {c: [3]}\n";
+    for (recover, later) in [(false, Some(1)), (true, None)] {
+        let errors = deserialize_each(yaml, recover);
+        assert_eq!(errors[1].as_ref().unwrap().document_index(), Some(1));
+        assert_eq!(errors[1].as_ref().unwrap().document_range(), Some(13..23));
+        let location = errors[1].as_ref().unwrap().document_location().unwrap();
+        assert_eq!((location.line(), location.column()), (1, 11));
+        // Then repeats as the same error, or recovers.
+        let repeated = errors[2]
+            .as_ref()
+            .and_then(serde_yaml::Error::document_index);
+        assert_eq!(repeated, later);
+    }
+
+    // Found by the loader, rather than the parser.
+    let yaml = "--- [1]\n--- {b: *x}\n";
+    let errors = deserialize_each(yaml, true);
+    let err = errors[1].as_ref().unwrap();
+    assert_eq!(err.to_string(), "unknown anchor at line 2 column 9");
+    assert_eq!(err.document_index(), Some(1));
+    assert_eq!(err.document_range(), Some(8..20));
+    let location = err.document_location().unwrap();
+    assert_eq!(
+        (location.index(), location.line(), location.column()),
+        (8, 1, 9)
+    );
+
+    let yaml = "--- [1]\n--- [*x]\n--- [3]\n";
+    let err = Deserializer::from_str(yaml).validate().unwrap_err();
+    assert_eq!(err.document_index(), Some(1));
+    assert_eq!(err.document_range(), Some(8..15));
+
+    // Not known outside of a stream.
+    let err = serde_yaml::from_str::<Vec<u32>>("[x]").unwrap_err();
+    assert_eq!(err.document_index(), None);
+    assert_eq!(err.document_range(), None);
+    assert_eq!(err.document_location(), None);
+}
+
+#[test]
+fn test_reader_multi_errors() {
+    // Documents read one at a time fail where reading the whole stream with
+    // recovery does, at the same position in the stream.
+    let streams = [
+        "a: [1]\n---\nb: [2,\n   x]\n...\n---\nc: [3]\n",
+        "--- {a: [1]}\n--- {b: [2}\n--- {c: [3]}\n",
+        "--- [1]\n--- {b: *x}\n--- {c: [3]}\n",
+        "# header\n\na: [1]\n...\n# between\n...\n%YAML 1.2\n---\nb: [\"2\n",
+        "a: [1]\n%YAML 1.2\n---\nb: 2\n",
+    ];
+    for yaml in streams {
+        let whole: Vec<_> = Deserializer::from_str(yaml)
+            .recover_documents(true)
+            .map(BTreeMap::<String, Vec<u32>>::deserialize)
+            .collect();
+        let multi: Vec<serde_yaml::Result<BTreeMap<String, Vec<u32>>>> =
+            serde_yaml::from_reader_multi(yaml.as_bytes()).collect();
+        assert_eq!(multi.len(), whole.len(), "{:?}", yaml);
+        for (multi, whole) in multi.iter().zip(&whole) {
+            match (multi, whole) {
+                (Ok(multi), Ok(whole)) => assert_eq!(multi, whole),
+                (Err(multi), Err(whole)) => {
+                    assert_eq!(multi.to_string(), whole.to_string());
+                    assert_eq!(multi.location(), whole.location());
+                    assert_eq!(multi.document_index(), whole.document_index());
+                    assert_eq!(multi.document_range(), whole.document_range());
+                }
+                _ => panic!("{:?}: {:?} != {:?}", yaml, multi, whole),
+            }
+        }
+    }
+
+    // An I/O error ends the stream.
+    struct Failing<'a>(&'a [u8]);
+
+    impl io::Read for Failing<'_> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.0.is_empty() {
+                return Err(io::Error::other("disconnected"));
+            }
+            let n = self.0.len().min(buf.len());
+            buf[..n].copy_from_slice(&self.0[..n]);
+            self.0 = &self.0[n..];
+            Ok(n)
+        }
+    }
+
+    let documents: Vec<serde_yaml::Result<u32>> =
+        serde_yaml::from_reader_multi(Failing(b"--- 1\n--- 2\n")).collect();
+    assert_eq!(documents.len(), 2);
+    assert_eq!(*documents[0].as_ref().unwrap(), 1);
+    assert_eq!(
+        documents[1].as_ref().unwrap_err().to_string(),
+        "disconnected"
+    );
+}
+
+#[cfg(not(miri))]
+#[test]
+fn test_stream_limits() {
+    let stream = "--- 1\n".repeat(1_000_000);
+
+    let mut documents = Deserializer::from_str(&stream).max_documents(1000);
+    let mut results = Vec::new();
+    for document in &mut documents {
+        let result = Value::deserialize(document);
+        let failed = result.is_err();
+        results.push(result);
+        if failed {
+            break;
+        }
+    }
+    assert_eq!(results.len(), 1001);
+    assert!(results[..1000].iter().all(Result::is_ok));
+    let err = results[1000].as_ref().unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "document 1001 exceeds the limit of 1000 documents at line 1001 column 1",
+    );
+    assert_eq!(err.classify(), Category::Limit);
+
+    // Parsing stopped at the start of the document over the limit instead of
+    // going on through the rest of the stream.
+    assert_eq!(documents.bytes_consumed(), 6000);
+
+    // The error ends the stream, even when recovering.
+    let count = Deserializer::from_str(&stream).max_documents(1000).count();
+    assert_eq!(count, 1001);
+    let mut documents = Deserializer::from_str("--- 1\n--- 2\n--- 3\n")
+        .max_documents(1)
+        .recover_documents(true);
+    Value::deserialize(documents.next().unwrap()).unwrap();
+    let err = Value::deserialize(documents.next().unwrap()).unwrap_err();
+    assert_eq!(err.classify(), Category::Limit);
+    assert!(documents.next().is_none());
+    let documents = Deserializer::from_str("--- 1\n--- 2\n--- 3\n").max_documents(1);
+    assert_eq!(documents.count(), 2);
+
+    // Exactly at the limit is fine.
+    let documents = Deserializer::from_str("--- 1\n--- 2\n").max_documents(2);
+    assert_eq!(documents.map(Value::deserialize).count(), 2);
+
+    let de = Deserializer::from_str(&stream).max_input_bytes(1 << 20);
+    let err = Value::deserialize(de).unwrap_err();
+    let expected = "input of 6000000 bytes exceeds the limit of 1048576 bytes";
+    assert_eq!(err.to_string(), expected);
+    assert_eq!(err.classify(), Category::Limit);
+
+    let mut documents = Deserializer::from_slice(stream.as_bytes()).max_input_bytes(1 << 20);
+    let err = Value::deserialize(documents.next().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), expected);
+
+    // A reader is only read a byte past the limit.
+    struct Counting<'a> {
+        input: &'a [u8],
+        read: usize,
+    }
+
+    impl<'a> io::Read for Counting<'a> {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            let n = self.input.read(buf)?;
+            self.read += n;
+            Ok(n)
+        }
+    }
+
+    let mut reader = Counting {
+        input: stream.as_bytes(),
+        read: 0,
+    };
+    let mut documents = Deserializer::from_reader_with_limit(&mut reader, 1 << 20);
+    let err = Value::deserialize(documents.next().unwrap()).unwrap_err();
+    assert_eq!(err.to_string(), "input exceeds the limit of 1048576 bytes");
+    assert_eq!(err.classify(), Category::Limit);
+    drop(documents);
+    assert_eq!(reader.read, (1 << 20) + 1);
+
+    let de = Deserializer::from_reader_with_limit(&stream.as_bytes()[..6000], 6000);
+    assert!(de.map(Value::deserialize).all(|result| result.is_ok()));
+}
+
+#[cfg(not(miri))]
+#[test]
+fn test_recover_scaling() {
+    // Time to read every document of `yaml`, recovering from broken ones.
+    fn recover(yaml: &str, documents: usize) -> Duration {
+        (0..3)
+            .map(|_| {
+                let start = Instant::now();
+                let de = Deserializer::from_str(yaml).recover_documents(true);
+                assert_eq!(de.map(Value::deserialize).count(), documents);
+                start.elapsed()
+            })
+            .min()
+            .unwrap()
+    }
+
+    // A long document and then many broken ones. Each broken document starts
+    // a new parser, which must not look at the input all the way from the
+    // beginning again.
+    let long = format!("--- {}\n", "x".repeat(4 << 20));
+    let broken = "--- [1\n".repeat(2000);
+    let both = long.clone() + &broken;
+    let last_error = |yaml: &str, n: usize| {
+        let error = Deserializer::from_str(yaml)
+            .recover_documents(true)
+            .nth(n)
+            .map(Value::deserialize)
+            .unwrap()
+            .unwrap_err();
+        let location = error.location().unwrap();
+        (location.line(), location.column())
+    };
+    assert_eq!(last_error(&broken, 1999), (2001, 1));
+    assert_eq!(last_error(&both, 2000), (2002, 1));
+
+    let separately = recover(&long, 1) + recover(&broken, 2000);
+    let together = recover(&both, 2001);
+    assert!(
+        together < separately * 4,
+        "{:?} together compared to {:?} separately",
+        together,
+        separately,
+    );
+}
+
+#[test]
+fn test_duplicate_anchor() {
+    let yaml = indoc! {"
+        a: &x 1
+        b: [&y 2, &x 3]
+        c: *x
+    "};
+
+    let de = Deserializer::from_str(yaml).duplicate_anchors(DuplicateAnchors::Error);
+    let err = Value::deserialize(de).unwrap_err();
+    let expected = "duplicate anchor \"x\" at line 2 column 11, first defined at line 1 column 4";
+    assert_eq!(expected, err.to_string());
+    assert!(err.is_syntax());
+    let location = err.location().unwrap();
+    assert_eq!((2, 11), (location.line(), location.column()));
+    let first = err.context_location().unwrap();
+    assert_eq!((1, 4), (first.line(), first.column()));
+
+    let de = Deserializer::from_str(yaml).duplicate_anchors(DuplicateAnchors::Allow);
+    let value = Value::deserialize(de).unwrap();
+    assert_eq!(value["c"], 3);
+}
+
+#[test]
+fn test_duplicate_anchor_in_later_document() {
+    let yaml = indoc! {"
+        ---
+        a: &x 1
+        b: *x
+        ---
+        a: &x 2
+        b: *x
+        ---
+        a: &x 3
+        b: &x 4
+    "};
+
+    let de = Deserializer::from_str(yaml).duplicate_anchors(DuplicateAnchors::Error);
+    let results: Vec<_> = de.take(4).map(Value::deserialize).collect();
+    assert_eq!(results[0].as_ref().unwrap()["b"], 1);
+    assert_eq!(results[1].as_ref().unwrap()["b"], 2);
+    let expected = "duplicate anchor \"x\" at line 9 column 4, first defined at line 8 column 4";
+    assert_eq!(expected, results[2].as_ref().unwrap_err().to_string());
+    // Without recovery, the error repeats.
+    assert_eq!(expected, results[3].as_ref().unwrap_err().to_string());
+}
+
 #[test]
 fn test_missing_enum_tag() {
     #[derive(Deserialize, Debug)]
@@ -190,14 +712,20 @@ fn test_serialize_nested_enum() {
     let e = Outer::Inner(Inner::Newtype(0));
     let error = serde_yaml::to_string(&e).unwrap_err();
     assert_eq!(error.to_string(), expected);
+    let error = serde_yaml::to_value(&e).unwrap_err();
+    assert_eq!(error.to_string(), expected);
 
     let e = Outer::Inner(Inner::Tuple(0, 0));
     let error = serde_yaml::to_string(&e).unwrap_err();
     assert_eq!(error.to_string(), expected);
+    let error = serde_yaml::to_value(&e).unwrap_err();
+    assert_eq!(error.to_string(), expected);
 
     let e = Outer::Inner(Inner::Struct { x: 0 });
     let error = serde_yaml::to_string(&e).unwrap_err();
     assert_eq!(error.to_string(), expected);
+    let error = serde_yaml::to_value(&e).unwrap_err();
+    assert_eq!(error.to_string(), expected);
 
     let e = Value::Tagged(Box::new(TaggedValue {
         tag: Tag::new("Outer"),
@@ -208,6 +736,87 @@ fn test_serialize_nested_enum() {
     }));
     let error = serde_yaml::to_string(&e).unwrap_err();
     assert_eq!(error.to_string(), expected);
+    let error = serde_yaml::to_value(&e).unwrap_err();
+    assert_eq!(error.to_string(), expected);
+}
+
+#[test]
+fn test_integer_out_of_range() {
+    let error = serde_yaml::to_value(u128::MAX).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "integer 340282366920938463463374607431768211455 does not fit in serde_yaml::Value",
+    );
+
+    let error = serde_yaml::to_value(i128::MIN).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "integer -170141183460469231731687303715884105728 does not fit in serde_yaml::Value",
+    );
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Count<T> {
+        count: T,
+    }
+
+    // Quoted as written, with the range of the type it did not fit in.
+    let yaml = "count: 99999999999999999999999999";
+    let expected = "count: integer `99999999999999999999999999` is out of range for u64 (0 to 18446744073709551615) at line 1 column 8";
+    test_error::<Count<u64>>(yaml, expected);
+    let expected = "count: integer `99999999999999999999999999` is out of range for serde_yaml::Value (-9223372036854775808 to 18446744073709551615) at line 1 column 8";
+    test_error::<Value>(yaml, expected);
+    let yaml = "count: 0x10000000000000000";
+    let expected = "count: integer `0x10000000000000000` is out of range for serde_yaml::Value (-9223372036854775808 to 18446744073709551615) at line 1 column 8";
+    test_error::<Value>(yaml, expected);
+    let yaml = "count: 0o2000000000000000000000";
+    let expected = "count: integer `0o2000000000000000000000` is out of range for serde_yaml::Value (-9223372036854775808 to 18446744073709551615) at line 1 column 8";
+    test_error::<Value>(yaml, expected);
+    let yaml = "count: +18446744073709551616";
+    let expected = "count: integer `+18446744073709551616` is out of range for serde_yaml::Value (-9223372036854775808 to 18446744073709551615) at line 1 column 8";
+    test_error::<Value>(yaml, expected);
+    let yaml = "count: -0x8000000000000001";
+    let expected = "count: integer `-0x8000000000000001` is out of range for serde_yaml::Value (-9223372036854775808 to 18446744073709551615) at line 1 column 8";
+    test_error::<Value>(yaml, expected);
+    let yaml = "count: !!int 0x10000000000000000";
+    let expected = "count: integer `0x10000000000000000` is out of range for serde_yaml::Value (-9223372036854775808 to 18446744073709551615) at line 1 column 8";
+    test_error::<Value>(yaml, expected);
+
+    let yaml = "count: 0x12C";
+    let expected = "count: integer `0x12C` is out of range for u8 (0 to 255) at line 1 column 8";
+    test_error::<Count<u8>>(yaml, expected);
+    let yaml = "count: -1";
+    let expected =
+        "count: integer `-1` is out of range for u32 (0 to 4294967295) at line 1 column 8";
+    test_error::<Count<u32>>(yaml, expected);
+    let yaml = "count: -0x8001";
+    let expected =
+        "count: integer `-0x8001` is out of range for i16 (-32768 to 32767) at line 1 column 8";
+    test_error::<Count<i16>>(yaml, expected);
+
+    // Too large for any integer type, rather than read as a float.
+    let yaml = "count: -340282366920938463463374607431768211456";
+    let expected = "count: integer `-340282366920938463463374607431768211456` is out of range for i128 (-170141183460469231731687303715884105728 to 170141183460469231731687303715884105727) at line 1 column 8";
+    test_error::<Count<i128>>(yaml, expected);
+    let expected = "count: integer `-340282366920938463463374607431768211456` is out of range for i128 or u128 (-170141183460469231731687303715884105728 to 340282366920938463463374607431768211455) at line 1 column 8";
+    test_error::<Value>(yaml, expected);
+    let expected = "count: invalid type: integer `-340282366920938463463374607431768211456`, expected a sequence at line 1 column 8";
+    test_error::<Count<Vec<u8>>>(yaml, expected);
+    let error = "-340282366920938463463374607431768211456"
+        .parse::<serde_yaml::Number>()
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "integer `-340282366920938463463374607431768211456` is out of range for serde_yaml::Number (-9223372036854775808 to 18446744073709551615)",
+    );
+
+    // Unless a float is what was asked for, or it is quoted.
+    let count: Count<f64> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(count.count, -3.402823669209385e38);
+    let string = "340282366920938463463374607431768211456".to_owned();
+    let yaml = serde_yaml::to_string(&string).unwrap();
+    assert_eq!(yaml, "'340282366920938463463374607431768211456'\n");
+    assert_eq!(serde_yaml::from_str::<Value>(&yaml).unwrap(), string);
 }
 
 #[test]
@@ -232,7 +841,7 @@ fn test_deserialize_nested_enum() {
         ---
         !Variant []
     "};
-    let expected = "unknown variant `Variant`, expected `Inner`";
+    let expected = "unknown variant `Variant`, expected `Inner` at line 2 column 1";
     test_error::<Outer>(yaml, expected);
 
     let yaml = indoc! {"
@@ -243,6 +852,115 @@ fn test_deserialize_nested_enum() {
     test_error::<Outer>(yaml, expected);
 }
 
+#[test]
+fn test_location() {
+    #[derive(Deserialize, Debug)]
+    pub struct Config {
+        #[allow(dead_code)]
+        pub name: String,
+        #[allow(dead_code)]
+        pub mode: Option<Mode>,
+        #[allow(dead_code)]
+        pub limit: Option<u8>,
+    }
+    #[derive(Deserialize, Debug)]
+    pub enum Mode {
+        Fast,
+        Slow(#[allow(dead_code)] u8),
+    }
+
+    let cases = [
+        ("name: x\n@", (8, 2, 1)),
+        ("name: []", (6, 1, 7)),
+        ("mode: Fast", (0, 1, 1)),
+        (
+            "name: x
+mode: Medium",
+            (14, 2, 7),
+        ),
+        (
+            "name: x
+mode: !Medium 1",
+            (14, 2, 7),
+        ),
+        (
+            "name: x
+mode: !Slow x",
+            (14, 2, 7),
+        ),
+        (
+            "name: x
+limit: !!null 1",
+            (15, 2, 8),
+        ),
+    ];
+    for (yaml, (index, line, column)) in cases {
+        let error = serde_yaml::from_str::<Config>(yaml).unwrap_err();
+        let location = error.location().unwrap();
+        assert_eq!(
+            (location.index(), location.line(), location.column()),
+            (index, line, column),
+            "{}",
+            error,
+        );
+    }
+}
+
+#[test]
+fn test_location_traits() {
+    let first = serde_yaml::from_str::<Value>("a: [1, }").unwrap_err();
+    let second = serde_yaml::from_str::<Value>("a: [1, 2\nb: 3\n").unwrap_err();
+    let first = first.location().unwrap();
+    let second = second.location().unwrap();
+    assert!(first < second);
+
+    let mut set = HashSet::new();
+    set.insert(first);
+    set.insert(second);
+    set.insert(first);
+    assert_eq!(2, set.len());
+
+    let expected = indoc! {"
+        index: 7
+        line: 1
+        column: 8
+    "};
+    assert_eq!(expected, serde_yaml::to_string(&first).unwrap());
+}
+
+#[test]
+fn test_context_location() {
+    let yaml = indoc! {"
+        a: [1, 2
+        b: 3
+    "};
+    let error = serde_yaml::from_str::<Value>(yaml).unwrap_err();
+    let expected = "did not find expected ',' or ']' at line 2 column 2, while parsing a flow sequence at line 1 column 4";
+    assert_eq!(expected, error.to_string());
+    assert_eq!(Some("while parsing a flow sequence"), error.context());
+    let location = error.location().unwrap();
+    assert_eq!(
+        (10, 2, 2),
+        (location.index(), location.line(), location.column())
+    );
+    let context = error.context_location().unwrap();
+    assert_eq!(
+        (3, 1, 4),
+        (context.index(), context.line(), context.column())
+    );
+
+    let error = serde_yaml::from_str::<Value>("[1, 2\n").unwrap_err();
+    let context = error.context_location().unwrap();
+    assert_eq!(
+        (0, 1, 1),
+        (context.index(), context.line(), context.column())
+    );
+
+    let error = serde_yaml::from_str::<Vec<u8>>("[1, x]").unwrap_err();
+    assert_eq!(None, error.context());
+    assert!(error.context_location().is_none());
+}
+
 #[test]
 fn test_variant_not_a_seq() {
     #[derive(Deserialize, Debug)]
@@ -270,10 +988,46 @@ fn test_struct_from_sequence() {
     let yaml = indoc! {"
         [0, 0]
     "};
-    let expected = "invalid type: sequence, expected struct Struct";
+    let expected = "expected a mapping at the document root, but the document is a sequence starting at line 1 column 1";
     test_error::<Struct>(yaml, expected);
 }
 
+#[test]
+fn test_root_mismatch() {
+    #[derive(Deserialize, Debug)]
+    pub struct Config {
+        #[allow(dead_code)]
+        pub name: String,
+        #[allow(dead_code)]
+        pub replicas: usize,
+    }
+
+    let yaml = indoc! {"
+        - name: web
+          replicas: 2
+    "};
+    let expected = "expected a mapping at the document root, but the document is a sequence starting at line 1 column 1 (did you mean to remove the `- ` before the first key?)";
+    test_error::<Config>(yaml, expected);
+
+    let yaml = indoc! {"
+        # services
+        name: web
+        replicas: 2
+    "};
+    let expected = "expected a sequence at the document root, but the document is a mapping starting at line 2 column 1 (did you mean to start each entry with `- `?)";
+    test_error::<Vec<Config>>(yaml, expected);
+
+    let yaml = "name:web\n";
+    let expected = "expected a mapping at the document root, but the document is a scalar starting at line 1 column 1 (did you mean to put a space after `:`?)";
+    test_error::<Config>(yaml, expected);
+    test_error::<Option<Config>>(yaml, expected);
+
+    // Below the root, the usual error is precise enough.
+    let yaml = "- [web, 2]\n";
+    let expected = ".[0]: invalid type: sequence, expected struct Config at line 1 column 3";
+    test_error::<Vec<Config>>(yaml, expected);
+}
+
 #[test]
 fn test_bad_bool() {
     let yaml = indoc! {"
@@ -314,13 +1068,32 @@ fn test_bad_null() {
     test_error::<()>(yaml, expected);
 }
 
+#[test]
+fn test_core_tag_mismatch() {
+    // Not a boolean in YAML 1.2, tagged or not.
+    let yaml = "enabled: !!bool yes\n";
+    let expected = "enabled: invalid value: string \"yes\", expected a boolean at line 1 column 10";
+    test_error::<BTreeMap<String, bool>>(yaml, expected);
+    test_error::<BTreeMap<String, Value>>(yaml, expected);
+
+    let yaml = "zip: !!str 007\n";
+    let expected = "zip: invalid type: string \"007\", expected u32 at line 1 column 6";
+    test_error::<BTreeMap<String, u32>>(yaml, expected);
+
+    let yaml = "port: !!int \"forty-two\"\n";
+    let expected =
+        "port: invalid value: string \"forty-two\", expected an integer at line 1 column 7";
+    test_error::<BTreeMap<String, u16>>(yaml, expected);
+}
+
 #[test]
 fn test_short_tuple() {
     let yaml = indoc! {"
         ---
         [0, 0]
     "};
-    let expected = "invalid length 2, expected a tuple of size 3 at line 2 column 1";
+    let expected =
+        "invalid length 2, expected a tuple of size 3 in sequence starting at line 2 column 1";
     test_error::<(u8, u8, u8)>(yaml, expected);
 }
 
@@ -330,10 +1103,47 @@ fn test_long_tuple() {
         ---
         [0, 0, 0]
     "};
-    let expected = "invalid length 3, expected sequence of 2 elements at line 2 column 1";
+    let expected =
+        "unexpected trailing elements, expected sequence of 2 elements at line 2 column 8";
     test_error::<(u8, u8)>(yaml, expected);
 }
 
+#[test]
+fn test_trailing_elements() {
+    #[derive(Deserialize, Debug)]
+    pub struct Pair(#[allow(dead_code)] u8, #[allow(dead_code)] u8);
+
+    #[derive(Deserialize, Debug)]
+    pub struct S {
+        #[allow(dead_code)]
+        pub pair: Pair,
+        #[allow(dead_code)]
+        pub array: [u8; 1],
+    }
+
+    let yaml = "[0, 0, 0, 0]";
+    let expected =
+        "unexpected trailing elements, expected sequence of 2 elements at line 1 column 8";
+    test_error::<Pair>(yaml, expected);
+
+    let yaml = indoc! {"
+        pair: [0, 0]
+        array:
+          - 0
+          - 0
+    "};
+    let expected =
+        "array: unexpected trailing elements, expected sequence of 1 element at line 4 column 5";
+    test_error::<S>(yaml, expected);
+
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let err = serde_yaml::from_value::<S>(value.clone()).unwrap_err();
+    let expected = "array: unexpected trailing elements, expected sequence of 1 element";
+    assert_eq!(expected, err.to_string());
+    let err = S::deserialize(&value).unwrap_err();
+    assert_eq!(expected, err.to_string());
+}
+
 #[test]
 fn test_invalid_scalar_type() {
     #[derive(Deserialize, Debug)]
@@ -464,6 +1274,56 @@ fn test_billion_laughs() {
     test_error::<BTreeMap<String, X>>(yaml, expected);
 }
 
+#[cfg(not(miri))]
+#[test]
+fn test_long_scalar_aliases() {
+    // Few aliases, but each repeats a megabyte.
+    let text = "x".repeat(1 << 20);
+    let mut yaml = format!("text: &text {}\naliases: [", text);
+    for _ in 0..5000 {
+        yaml.push_str("*text, ");
+    }
+    yaml.push_str("]\n");
+
+    let err = serde_yaml::from_str::<Value>(&yaml).unwrap_err();
+    assert_eq!(err.to_string(), "repetition limit exceeded");
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Numbers {
+        text: u32,
+        aliases: Vec<u32>,
+    }
+    let expected = format!(
+        "text: invalid type: string \"{}\"... (1048576 bytes), expected u32 at line 1 column 7",
+        "x".repeat(100),
+    );
+    test_error::<Numbers>(&yaml, &expected);
+}
+
+#[test]
+fn test_long_scalar_in_message() {
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    enum Enum {
+        Unit,
+        Other,
+    }
+    let variant = "é".repeat(60);
+    let yaml = format!("{}\n", variant);
+    let expected = format!(
+        "unknown variant `{}...` (120 bytes), expected `Unit` or `Other`",
+        "é".repeat(50),
+    );
+    test_error::<Enum>(&yaml, &expected);
+
+    let line = format!("{}: [}}", "k".repeat(1 << 20));
+    let err = serde_yaml::from_str::<Value>(&line).unwrap_err();
+    let rendered = err.display_with_source(&line).to_string();
+    assert!(rendered.len() < 400, "{}", rendered);
+    assert!(rendered.contains("| ...kkkk"), "{}", rendered);
+}
+
 #[test]
 fn test_duplicate_keys() {
     let yaml = indoc! {"
@@ -498,3 +1358,410 @@ fn test_duplicate_keys() {
     let expected = "duplicate entry in YAML map at line 2 column 1";
     test_error::<Value>(yaml, expected);
 }
+
+#[test]
+fn test_io_error() {
+    struct FailingWriter;
+
+    impl io::Write for FailingWriter {
+        fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::StorageFull, "disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let error = serde_yaml::to_writer(FailingWriter, &vec!["x"; 100]).unwrap_err();
+    assert_eq!(error.to_string(), "disk full");
+    assert_eq!(error.io_error().unwrap().kind(), io::ErrorKind::StorageFull);
+
+    let source = error.source().unwrap();
+    let io_error = source.downcast_ref::<io::Error>().unwrap();
+    assert_eq!(io_error.kind(), io::ErrorKind::StorageFull);
+
+    let error = anyhow::Error::new(error);
+    let kind = error
+        .chain()
+        .find_map(|cause| cause.downcast_ref::<io::Error>());
+    assert_eq!(kind.unwrap().kind(), io::ErrorKind::StorageFull);
+
+    let error = serde_yaml::from_str::<Value>("@").unwrap_err();
+    assert!(error.io_error().is_none());
+    assert!(error.source().is_none());
+}
+
+#[test]
+fn test_classify() {
+    #[derive(Deserialize, Debug)]
+    #[serde(deny_unknown_fields)]
+    pub struct S {
+        #[allow(dead_code)]
+        pub x: u8,
+    }
+
+    fn category<T: Debug>(result: Result<T, serde_yaml::Error>) -> Category {
+        result.unwrap_err().classify()
+    }
+
+    assert_eq!(category(serde_yaml::from_str::<S>("@")), Category::Syntax);
+    assert_eq!(
+        category(serde_yaml::from_str::<Value>("x: [1, }")),
+        Category::Syntax
+    );
+    assert_eq!(
+        category(serde_yaml::from_str::<S>("x: *a")),
+        Category::Syntax
+    );
+
+    assert_eq!(category(serde_yaml::from_str::<S>("x: 'a")), Category::Eof);
+    assert_eq!(
+        category(serde_yaml::from_str::<Value>("x: [1,\n")),
+        Category::Eof
+    );
+    assert_eq!(category(serde_yaml::from_str::<u8>("")), Category::Eof);
+
+    assert_eq!(category(serde_yaml::from_str::<S>("x: a")), Category::Data);
+    assert_eq!(category(serde_yaml::from_str::<S>("{}")), Category::Data);
+    assert_eq!(
+        category(serde_yaml::from_str::<S>("x: 1\n---\nx: 1")),
+        Category::Data
+    );
+    assert_eq!(
+        category(serde_yaml::from_str::<S>("x: 1\ny: 2")),
+        Category::Data
+    );
+    assert_eq!(
+        category(serde_yaml::from_value::<S>(Value::Null)),
+        Category::Data
+    );
+    assert_eq!(
+        category(serde_yaml::to_string(&Value::tagged(
+            "A",
+            Value::tagged("B", Value::Null)
+        ))),
+        Category::Data
+    );
+
+    #[cfg(not(miri))]
+    {
+        let yaml = "&a [0, *a]";
+        assert_eq!(
+            category(serde_yaml::from_str::<Value>(yaml)),
+            Category::Limit
+        );
+        let yaml = "[".repeat(1000);
+        assert_eq!(
+            category(serde_yaml::from_str::<Value>(&yaml)),
+            Category::Limit
+        );
+    }
+
+    struct FailingReader;
+
+    impl io::Read for FailingReader {
+        fn read(&mut self, _buf: &mut [u8]) -> io::Result<usize> {
+            Err(io::Error::new(io::ErrorKind::BrokenPipe, "broken pipe"))
+        }
+    }
+
+    let error = serde_yaml::from_reader::<_, Value>(FailingReader).unwrap_err();
+    assert!(error.is_io());
+    assert!(!error.is_syntax() && !error.is_data() && !error.is_eof() && !error.is_limit());
+}
+
+#[test]
+fn test_reader_error() {
+    // Yields part of a valid document, then fails.
+    struct FailingReader {
+        remaining: &'static [u8],
+    }
+
+    impl io::Read for FailingReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            if self.remaining.is_empty() {
+                return Err(io::Error::new(
+                    io::ErrorKind::ConnectionReset,
+                    "reset by peer",
+                ));
+            }
+            let n = buf.len().min(4).min(self.remaining.len());
+            buf[..n].copy_from_slice(&self.remaining[..n]);
+            self.remaining = &self.remaining[n..];
+            Ok(n)
+        }
+    }
+
+    let reader = FailingReader {
+        remaining: b"k: v\nlist: [1, 2",
+    };
+    let error = serde_yaml::from_reader::<_, Value>(reader).unwrap_err();
+    assert_eq!(error.to_string(), "reset by peer");
+    assert_eq!(
+        error.io_error().unwrap().kind(),
+        io::ErrorKind::ConnectionReset
+    );
+    let source = error.source().unwrap().downcast_ref::<io::Error>();
+    assert_eq!(source.unwrap().kind(), io::ErrorKind::ConnectionReset);
+
+    let reader = FailingReader {
+        remaining: b"k: v\n---\nk: w\n",
+    };
+    let mut documents = Deserializer::from_reader(reader);
+    let error = Value::deserialize(documents.next().unwrap()).unwrap_err();
+    assert_eq!(
+        error.io_error().unwrap().kind(),
+        io::ErrorKind::ConnectionReset
+    );
+}
+
+#[test]
+fn test_reader_interrupted() {
+    // Yields a few bytes at a time, each after first failing with `error`.
+    struct FlakyReader {
+        remaining: &'static [u8],
+        error: io::ErrorKind,
+        failed: bool,
+    }
+
+    impl io::Read for FlakyReader {
+        fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+            self.failed = !self.failed;
+            if self.failed {
+                return Err(io::Error::new(self.error, "not now"));
+            }
+            let n = buf.len().min(3).min(self.remaining.len());
+            buf[..n].copy_from_slice(&self.remaining[..n]);
+            self.remaining = &self.remaining[n..];
+            Ok(n)
+        }
+    }
+
+    let reader = |error| FlakyReader {
+        remaining: b"k: v\nlist: [1, 2]\n---\nk: w\n",
+        error,
+        failed: false,
+    };
+
+    // Retried the same as `Read::read_to_end` does.
+    let documents = Deserializer::from_reader(reader(io::ErrorKind::Interrupted));
+    let values: Vec<Value> = documents
+        .map(|de| Value::deserialize(de).unwrap())
+        .collect();
+    assert_eq!(values.len(), 2);
+    assert_eq!(values[0]["list"][1], 2);
+    assert_eq!(values[1]["k"], "w");
+    let de = Deserializer::from_reader_with_limit(reader(io::ErrorKind::Interrupted), 100);
+    assert_eq!(de.count(), 2);
+    let mut output = Vec::new();
+    let options = serde_yaml::EmitOptions::new();
+    serde_yaml::transcode(reader(io::ErrorKind::Interrupted), &mut output, &options).unwrap();
+    assert_eq!(output, b"k: v\nlist: [1, 2]\n---\nk: w\n");
+
+    // Not retried, since the reader must be blocking, and every document
+    // after that fails with the same error.
+    let documents = Deserializer::from_reader(reader(io::ErrorKind::WouldBlock));
+    for de in documents.take(3) {
+        let error = Value::deserialize(de).unwrap_err();
+        assert!(error.is_io());
+        assert_eq!(error.io_error().unwrap().kind(), io::ErrorKind::WouldBlock);
+    }
+}
+
+#[test]
+fn test_display_with_source() {
+    fn render(yaml: &str) -> String {
+        let error = serde_yaml::from_str::<Value>(yaml).unwrap_err();
+        error.display_with_source(yaml).to_string()
+    }
+
+    let expected = indoc! {"
+        found character that cannot start any token at line 2 column 1, while scanning for the next token
+          |
+        2 |     b: [}
+          | ^"};
+    assert_eq!(render("a:\n\tb: [}"), expected);
+
+    let expected = indoc! {"
+        did not find expected node content at line 1 column 5, while parsing a flow node
+          |
+        1 | é: [}
+          |     ^"};
+    assert_eq!(render("é: [}"), expected);
+
+    let expected = indoc! {"
+        did not find expected node content at line 1 column 6, while parsing a flow node
+          |
+        1 | 日本: [}
+          |        ^"};
+    assert_eq!(render("日本: [}"), expected);
+
+    let expected = indoc! {"
+        did not find expected node content at line 2 column 5, while parsing a flow node
+          |
+        2 | c: [}
+          |     ^"};
+    assert_eq!(render("a: b\r\nc: [}"), expected);
+
+    let expected = indoc! {"
+        flow sequence started at line 1 column 1 was never closed
+          |
+        1 | [1, 2
+          |      ^"};
+    assert_eq!(render("[1, 2"), expected);
+
+    let yaml = format!("key: [{}, }}, {}]", "1, ".repeat(40), "2, ".repeat(40));
+    let expected = indoc! {"
+        did not find expected node content at line 1 column 127, while parsing a flow node
+          |
+        1 | ..., 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, 1, , }, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, 2, ...
+          |                                                      ^"};
+    assert_eq!(render(&yaml), expected);
+
+    let expected = "deserializing from YAML containing more than one document is not supported";
+    assert_eq!(render("a: 1\n---\nb: 2"), expected);
+}
+
+#[test]
+fn test_seq_or_single() {
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Target {
+        host: String,
+        port: u16,
+    }
+
+    #[derive(Deserialize, Debug)]
+    #[allow(dead_code)]
+    struct Config {
+        #[serde(with = "serde_yaml::with::seq_or_single")]
+        targets: Vec<Target>,
+    }
+
+    let yaml = indoc! {"
+        targets:
+          host: example.com
+          port: http
+    "};
+    let expected = "targets.port: invalid type: string \"http\", expected u16 at line 3 column 9";
+    test_error::<Config>(yaml, expected);
+
+    let yaml = indoc! {"
+        targets:
+        - host: a.com
+          port: 80
+        - host: b.com
+          port: -1
+    "};
+    let expected =
+        "targets[1].port: integer `-1` is out of range for u16 (0 to 65535) at line 5 column 9";
+    test_error::<Config>(yaml, expected);
+
+    let yaml = indoc! {"
+        targets: example.com
+    "};
+    let expected =
+        "targets: invalid type: string \"example.com\", expected struct Target at line 1 column 10";
+    test_error::<Config>(yaml, expected);
+}
+
+#[test]
+fn test_error_size() {
+    assert_eq!(size_of::<serde_yaml::Error>(), size_of::<usize>());
+    assert_eq!(
+        size_of::<Result<(), serde_yaml::Error>>(),
+        size_of::<usize>()
+    );
+    assert_eq!(
+        size_of::<Result<Value, serde_yaml::Error>>(),
+        size_of::<Value>(),
+    );
+}
+
+#[test]
+fn test_send_sync() {
+    fn assert_send_sync<T: Send + Sync + 'static>() {}
+    assert_send_sync::<serde_yaml::Error>();
+
+    let error = serde_yaml::from_str::<Value>("@").unwrap_err();
+    let message = std::thread::spawn(move || error.to_string())
+        .join()
+        .unwrap();
+    assert_eq!(
+        message,
+        "found character that cannot start any token, while scanning for the next token",
+    );
+}
+
+#[test]
+fn test_validate() {
+    // The first error from deserializing every document into a `Value`.
+    fn deserialize(de: Deserializer) -> Result<(), String> {
+        for document in de {
+            Value::deserialize(document).map_err(|err| err.to_string())?;
+        }
+        Ok(())
+    }
+
+    let nested = |depth: usize| format!("{}{}", "[".repeat(depth), "]".repeat(depth));
+    let laughs = indoc! {"
+        a: &a [x, x, x, x, x, x, x, x, x]
+        b: &b [*a, *a, *a, *a, *a, *a, *a, *a, *a]
+        c: &c [*b, *b, *b, *b, *b, *b, *b, *b, *b]
+        d: &d [*c, *c, *c, *c, *c, *c, *c, *c, *c]
+        e: &e [*d, *d, *d, *d, *d, *d, *d, *d, *d]
+        f: [*e, *e, *e, *e, *e, *e, *e, *e, *e]
+    "};
+    let inputs = [
+        "".to_owned(),
+        "a: 1\n---\nb: [2, 3]\n".to_owned(),
+        "a: &x {b: 1}\nc: *x\nd: [*x, *x]\n".to_owned(),
+        "a: [1, 2\n".to_owned(),
+        "a: 1\n--- [b, *c]\n".to_owned(),
+        "a: &x [1, *x]\n".to_owned(),
+        laughs.to_owned(),
+        nested(128),
+        nested(129),
+        format!(
+            "a: &x {}\nb: {}\n",
+            nested(100),
+            nested(27).replacen(']', "*x]", 1)
+        ),
+        format!(
+            "a: &x {}\nb: {}\n",
+            nested(100),
+            nested(28).replacen(']', "*x]", 1)
+        ),
+    ];
+    for input in &inputs {
+        let validated = serde_yaml::validate(input).map_err(|err| err.to_string());
+        let expected = deserialize(Deserializer::from_str(input));
+        assert_eq!(validated, expected, "{}", input);
+        let validated = serde_yaml::validate_reader(input.as_bytes());
+        assert_eq!(validated.map_err(|err| err.to_string()), expected);
+    }
+    assert!(serde_yaml::validate(&inputs[9]).is_ok());
+    assert!(serde_yaml::validate(&inputs[10]).is_err());
+
+    // With the same options as deserializing.
+    let options = [
+        |de: Deserializer<'static>| de.max_documents(2),
+        |de: Deserializer<'static>| de.duplicate_anchors(DuplicateAnchors::Error),
+        |de: Deserializer<'static>| de.legacy_resolution(true),
+        |de: Deserializer<'static>| de.max_input_bytes(16),
+    ];
+    let input = "--- [&a 1, &a 2]\n--- =\n--- 3\n";
+    for option in options {
+        let validated = option(Deserializer::from_str(input)).validate();
+        let expected = deserialize(option(Deserializer::from_str(input)));
+        assert!(expected.is_err());
+        assert_eq!(validated.map_err(|err| err.to_string()), expected);
+    }
+
+    // A document yielded by iterating over a stream checks just itself.
+    let mut documents = Deserializer::from_str("--- 1\n--- *x\n");
+    documents.next().unwrap().validate().unwrap();
+    let err = documents.next().unwrap().validate().unwrap_err();
+    assert_eq!(err.to_string(), "unknown anchor at line 2 column 5");
+}
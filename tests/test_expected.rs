@@ -0,0 +1,138 @@
+#![cfg(feature = "expected")]
+
+use indoc::indoc;
+use serde::de::DeserializeOwned;
+use serde_derive::Deserialize;
+use serde_yaml::error::Expected;
+use serde_yaml::Value;
+use std::fmt::Debug;
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+#[serde(deny_unknown_fields)]
+struct Config {
+    name: String,
+    listen: Listen,
+    #[serde(default)]
+    format: Option<Format>,
+}
+
+#[derive(Deserialize, Debug)]
+#[allow(dead_code)]
+struct Listen {
+    host: String,
+    port: u16,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(rename_all = "lowercase")]
+enum Format {
+    Plain,
+    Json,
+    Logfmt,
+}
+
+fn expected<T>(yaml: &str) -> Option<Expected>
+where
+    T: DeserializeOwned + Debug,
+{
+    serde_yaml::from_str::<T>(yaml).unwrap_err().expected()
+}
+
+const CONFIG: Expected = Expected::Struct {
+    name: "Config",
+    fields: &["name", "listen", "format"],
+};
+
+const LISTEN: Expected = Expected::Struct {
+    name: "Listen",
+    fields: &["host", "port"],
+};
+
+const FORMAT: Expected = Expected::Enum {
+    name: "Format",
+    variants: &["plain", "json", "logfmt"],
+};
+
+#[test]
+fn test_expected() {
+    let yaml = indoc! {"
+        name: web
+        listen: {host: localhost, port: 8080}
+        format: xml
+    "};
+    assert_eq!(expected::<Config>(yaml), Some(FORMAT));
+
+    let yaml = indoc! {"
+        name: web
+        listen: {host: localhost, port: 80000}
+    "};
+    assert_eq!(expected::<Config>(yaml), Some(Expected::Primitive("u16")));
+
+    let yaml = indoc! {"
+        name: web
+        listen: {host: localhost, port: http}
+    "};
+    assert_eq!(expected::<Config>(yaml), Some(Expected::Primitive("u16")));
+
+    let yaml = indoc! {"
+        name: web
+        listen: {host: localhost}
+    "};
+    assert_eq!(expected::<Config>(yaml), Some(LISTEN));
+
+    let yaml = indoc! {"
+        name: web
+        listen: localhost:8080
+    "};
+    assert_eq!(expected::<Config>(yaml), Some(LISTEN));
+
+    let yaml = indoc! {"
+        name: web
+        listen: {host: localhost, port: 8080}
+        formats: json
+    "};
+    assert_eq!(expected::<Config>(yaml), Some(CONFIG));
+
+    let yaml = indoc! {"
+        name: [web]
+        listen: {host: localhost, port: 8080}
+    "};
+    assert_eq!(
+        expected::<Config>(yaml),
+        Some(Expected::Primitive("string"))
+    );
+
+    // Fields that deserialized fine before the failure are not blamed.
+    let yaml = indoc! {"
+        name: web
+        listen: {host: localhost, port: 8080}
+        format: json
+        extra: 1
+    "};
+    assert_eq!(expected::<Config>(yaml), Some(CONFIG));
+}
+
+#[test]
+fn test_expected_display() {
+    assert_eq!(FORMAT.to_string(), "one of `plain`, `json`, `logfmt`");
+    assert_eq!(
+        LISTEN.to_string(),
+        "struct Listen with fields `host`, `port`"
+    );
+    assert_eq!(Expected::Primitive("u16").to_string(), "u16");
+}
+
+#[test]
+fn test_expected_unknown() {
+    // Not about the type at all.
+    assert_eq!(expected::<Config>("@name: web"), None);
+    let yaml = "name: web\nlisten: {host: localhost, port: 80}\n---\n";
+    assert_eq!(expected::<Config>(yaml), None);
+
+    // A `Value` takes anything, and deserializing from one does not record
+    // what was expected.
+    let value: Value = serde_yaml::from_str("listen: {host: localhost}").unwrap();
+    let err = serde_yaml::from_value::<Config>(value).unwrap_err();
+    assert_eq!(err.expected(), None);
+}
@@ -0,0 +1,134 @@
+#![cfg(feature = "json")]
+#![allow(clippy::uninlined_format_args)]
+
+use indoc::indoc;
+use serde_yaml::{Mapping, Number, Sequence, Value};
+
+struct Rng(u64);
+
+impl Rng {
+    fn next(&mut self) -> u64 {
+        // xorshift64
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+fn arbitrary_json(rng: &mut Rng, depth: usize) -> serde_json::Value {
+    let kinds = if depth == 0 { 6 } else { 8 };
+    match rng.below(kinds) {
+        0 => serde_json::Value::Null,
+        1 => serde_json::Value::Bool(rng.below(2) == 0),
+        2 => serde_json::Value::from(rng.next()),
+        3 => serde_json::Value::from(rng.next() as i64),
+        4 => serde_json::Value::from(rng.next() as f64 / 7.0),
+        5 => serde_json::Value::String(format!("s{}", rng.below(1000))),
+        6 => {
+            let len = rng.below(4);
+            serde_json::Value::Array((0..len).map(|_| arbitrary_json(rng, depth - 1)).collect())
+        }
+        _ => {
+            let len = rng.below(4);
+            let mut object = serde_json::Map::new();
+            for _ in 0..len {
+                object.insert(
+                    format!("k{}", rng.below(1000)),
+                    arbitrary_json(rng, depth - 1),
+                );
+            }
+            serde_json::Value::Object(object)
+        }
+    }
+}
+
+#[test]
+fn test_json_roundtrip() {
+    let mut rng = Rng(0x9E37_79B9_7F4A_7C15);
+    for _ in 0..1000 {
+        let json = arbitrary_json(&mut rng, 4);
+        let yaml = Value::from(json.clone());
+        let back = serde_json::Value::try_from(yaml.clone()).unwrap();
+        assert_eq!(json, back);
+
+        let reparsed: Value = serde_yaml::from_str(&serde_yaml::to_string(&yaml).unwrap()).unwrap();
+        assert_eq!(yaml, reparsed);
+    }
+}
+
+#[test]
+fn test_json_number_kinds() {
+    let json = serde_json::json!([u64::MAX, i64::MIN, -1, 1.5]);
+    let yaml = Value::from(json);
+    assert_eq!(yaml[0], Value::Number(Number::from(u64::MAX)));
+    assert_eq!(yaml[1], Value::Number(Number::from(i64::MIN)));
+    assert_eq!(yaml[2], Value::Number(Number::from(-1)));
+    assert!(yaml[3].is_f64());
+
+    let back = serde_json::Value::try_from(yaml).unwrap();
+    assert!(back[0].is_u64());
+    assert!(back[1].is_i64());
+    assert!(back[3].is_f64());
+}
+
+#[test]
+fn test_json_errors() {
+    let cases = [
+        (
+            indoc! {"
+                a:
+                  - {1: x}
+            "},
+            "a[0]: mapping key is not a string and cannot be represented in JSON",
+        ),
+        (
+            "a: {b: !Tag x}",
+            "a.b: tagged value cannot be represented in JSON",
+        ),
+        (
+            "[.nan]",
+            ".[0]: infinite or NaN float cannot be represented in JSON",
+        ),
+        ("!Tag x", "tagged value cannot be represented in JSON"),
+    ];
+    for (yaml, expected) in cases {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        let error = serde_json::Value::try_from(value).unwrap_err();
+        assert_eq!(error.to_string(), expected);
+    }
+}
+
+#[test]
+fn test_mapping_sequence_through_json() {
+    let mapping: Mapping = serde_json::from_str(r#"{"b": 1, "a": [true, null]}"#).unwrap();
+    assert_eq!(
+        serde_yaml::to_string(&mapping).unwrap(),
+        "b: 1\na:\n- true\n- null\n"
+    );
+    assert_eq!(
+        serde_json::to_string(&mapping).unwrap(),
+        r#"{"b":1,"a":[true,null]}"#,
+    );
+
+    let sequence: Sequence = serde_json::from_str(r#"[1, "x", {"k": 2}]"#).unwrap();
+    assert_eq!(sequence[2]["k"], 2);
+    assert_eq!(
+        serde_json::to_string(&sequence).unwrap(),
+        r#"[1,"x",{"k":2}]"#
+    );
+
+    // JSON object keys are always strings, even if they look like numbers.
+    let mapping: Mapping = serde_json::from_str(r#"{"1": "x"}"#).unwrap();
+    assert_eq!(mapping.get("1").unwrap(), "x");
+    assert_eq!(mapping.get(1), None);
+
+    // YAML allows keys that JSON cannot represent.
+    let mapping: Mapping = serde_yaml::from_str("{[a]: x}").unwrap();
+    let error = serde_json::to_string(&mapping).unwrap_err();
+    assert_eq!(error.to_string(), "key must be a string");
+}
@@ -0,0 +1,514 @@
+use indoc::indoc;
+use serde_yaml::raw::{
+    Anchor, CollectionStyle, DocumentEnd, DocumentStart, Emitter, Event, Parser, Scalar,
+    ScalarStyle, Scanner, SequenceStart, Tag, Token,
+};
+use std::borrow::Cow;
+
+fn parse(yaml: &str) -> Vec<Event<'_>> {
+    Parser::new(yaml.as_bytes())
+        .unwrap()
+        .map(|result| result.unwrap().0)
+        .collect()
+}
+
+fn emit(events: Vec<Event>) -> String {
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    for event in events {
+        emitter.emit(event).unwrap();
+    }
+    String::from_utf8(emitter.into_inner().unwrap()).unwrap()
+}
+
+#[test]
+fn test_round_trip() {
+    let yaml = indoc! {r#"
+        plain: value
+        single: 'value'
+        double: "line\nbreak"
+        literal: |
+          first
+          second
+        folded: >
+          folded
+          text
+        base: &base
+          x: 1
+        alias: *base
+        tagged: !!str 1
+        custom: !point [1, 2]
+        ---
+        second
+        ...
+    "#};
+    let events = parse(yaml);
+    let output = emit(events.clone());
+    assert_eq!(parse(&output), events);
+}
+
+#[test]
+fn test_document_markers() {
+    let yaml = indoc! {"
+        implicit
+        --- explicit start
+        ...
+        %YAML 1.1
+        %TAG !e! tag:example.com,2000:
+        --- !e!thing with directives
+        ...
+        --- explicit start and end
+        ...
+    "};
+    let events = parse(yaml);
+    let document_starts: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::DocumentStart(document_start) => Some(document_start),
+            _ => None,
+        })
+        .collect();
+    let expected = [
+        DocumentStart {
+            implicit: true,
+            ..DocumentStart::default()
+        },
+        DocumentStart {
+            implicit: false,
+            ..DocumentStart::default()
+        },
+        DocumentStart {
+            implicit: false,
+            version: Some((1, 1)),
+            tag_directives: vec![("!e!".to_owned(), "tag:example.com,2000:".to_owned())],
+        },
+        DocumentStart {
+            implicit: false,
+            ..DocumentStart::default()
+        },
+    ];
+    assert_eq!(document_starts, expected.iter().collect::<Vec<_>>());
+
+    let document_ends: Vec<_> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::DocumentEnd(document_end) => Some(document_end.implicit),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(document_ends, [true, false, false, false]);
+
+    let output = emit(events.clone());
+    assert_eq!(output, yaml);
+    assert_eq!(parse(&output), events);
+}
+
+#[test]
+fn test_tag_shorthand() {
+    let yaml = indoc! {"
+        %TAG !e! tag:example.com,2024:
+        %TAG !x! !local-
+        ---
+        a: !e!widget 1
+        b: !x!thing [2]
+        c: !e!odd%21name 3
+        d: !!str 4
+        e: !plain 5
+        f: !<tag:other.org,2024:verbatim> 6
+    "};
+    let events = parse(yaml);
+    let directives = match &events[1] {
+        Event::DocumentStart(start) => start.tag_directives.clone(),
+        event => panic!("{:?}", event),
+    };
+    let tags: Vec<&Tag> = events
+        .iter()
+        .filter_map(|event| match event {
+            Event::Scalar(scalar) => scalar.tag.as_ref(),
+            Event::SequenceStart(sequence) => sequence.tag.as_ref(),
+            _ => None,
+        })
+        .collect();
+    let resolved: Vec<&[u8]> = tags.iter().map(|tag| tag.as_bytes()).collect();
+    assert_eq!(
+        resolved,
+        [
+            &b"tag:example.com,2024:widget"[..],
+            b"!local-thing",
+            b"tag:example.com,2024:odd!name",
+            b"tag:yaml.org,2002:str",
+            b"!plain",
+            b"tag:other.org,2024:verbatim",
+        ],
+    );
+    let shorthands: Vec<Option<String>> =
+        tags.iter().map(|tag| tag.shorthand(&directives)).collect();
+    let expected = [
+        Some("!e!widget"),
+        Some("!x!thing"),
+        Some("!e!odd%21name"),
+        Some("!!str"),
+        Some("!plain"),
+        None,
+    ];
+    assert_eq!(shorthands, expected.map(|tag| tag.map(str::to_owned)));
+    // Each reads back as the same tag under the same directives.
+    let header: String = directives
+        .iter()
+        .map(|(handle, prefix)| format!("%TAG {} {}\n", handle, prefix))
+        .collect();
+    for (tag, shorthand) in tags.iter().zip(&shorthands) {
+        if let Some(shorthand) = shorthand {
+            let yaml = format!("{}--- {} x\n", header, shorthand);
+            let reparsed = parse(&yaml);
+            let expected = tag.as_bytes();
+            assert!(reparsed.iter().any(|event| match event {
+                Event::Scalar(scalar) => scalar.tag.as_ref().map(Tag::as_bytes) == Some(expected),
+                _ => false,
+            }));
+        }
+    }
+
+    // The handle with the longest prefix wins, and directives replace the
+    // default handles.
+    let directives = [
+        ("!a!".to_owned(), "tag:example.com,".to_owned()),
+        ("!b!".to_owned(), "tag:example.com,2024:".to_owned()),
+        ("!!".to_owned(), "tag:example.com,2024:std/".to_owned()),
+    ];
+    let shorthand = |tag: &str| Tag::new(tag).shorthand(&directives);
+    assert_eq!(shorthand("tag:example.com,2024:x").as_deref(), Some("!b!x"));
+    assert_eq!(
+        shorthand("tag:example.com,2023:x").as_deref(),
+        Some("!a!2023:x")
+    );
+    assert_eq!(
+        shorthand("tag:example.com,2024:std/x").as_deref(),
+        Some("!!x")
+    );
+    assert_eq!(shorthand("tag:yaml.org,2002:str"), None);
+    assert_eq!(
+        shorthand("tag:example.com,2024:").as_deref(),
+        Some("!a!2024:")
+    );
+    assert_eq!(shorthand("tag:example.com,"), None);
+    assert_eq!(shorthand("!local").as_deref(), Some("!local"));
+}
+
+#[test]
+fn test_collection_styles() {
+    let yaml = indoc! {"
+        block:
+        - flow: [1, {a: b}]
+        - {nested: [c]}
+        flow: [[], {}]
+    "};
+    let styles: Vec<_> = parse(yaml)
+        .into_iter()
+        .filter_map(|event| match event {
+            Event::SequenceStart(sequence) => Some(('s', sequence.style)),
+            Event::MappingStart(mapping) => Some(('m', mapping.style)),
+            _ => None,
+        })
+        .collect();
+    let expected = [
+        ('m', CollectionStyle::Block),
+        ('s', CollectionStyle::Block),
+        ('m', CollectionStyle::Block),
+        ('s', CollectionStyle::Flow),
+        ('m', CollectionStyle::Flow),
+        ('m', CollectionStyle::Flow),
+        ('s', CollectionStyle::Flow),
+        ('s', CollectionStyle::Flow),
+        ('s', CollectionStyle::Flow),
+        ('m', CollectionStyle::Flow),
+    ];
+    assert_eq!(styles, expected);
+    assert_eq!(emit(parse(yaml)), yaml);
+}
+
+#[test]
+fn test_events() {
+    let yaml = "--- &a !t 'x'\n";
+    let expected = vec![
+        Event::StreamStart,
+        Event::DocumentStart(DocumentStart {
+            implicit: false,
+            ..DocumentStart::default()
+        }),
+        Event::Scalar(Scalar {
+            anchor: Some(Anchor::new("a")),
+            tag: Some(Tag::new("!t")),
+            value: Cow::Borrowed(b"x"),
+            style: ScalarStyle::SingleQuoted,
+        }),
+        Event::DocumentEnd(DocumentEnd { implicit: true }),
+        Event::StreamEnd,
+    ];
+    assert_eq!(parse(yaml), expected);
+    assert_eq!(emit(expected), yaml);
+}
+
+#[test]
+fn test_locations() {
+    let locations: Vec<_> = Parser::new("k: [v]\n".as_bytes())
+        .unwrap()
+        .map(|result| {
+            let location = result.unwrap().1;
+            (location.line(), location.column())
+        })
+        .collect();
+    let expected = [
+        (1, 1),
+        (1, 1),
+        (1, 1),
+        (1, 1),
+        (1, 4),
+        (1, 5),
+        (1, 6),
+        (2, 1),
+        (2, 1),
+        (2, 1),
+    ];
+    assert_eq!(locations, expected);
+}
+
+#[test]
+fn test_parse_error() {
+    let mut parser = Parser::new("k: [v\n".as_bytes()).unwrap();
+    let error = parser.find_map(Result::err).unwrap();
+    assert_eq!(
+        error.to_string(),
+        "flow sequence started at line 1 column 4 was never closed",
+    );
+    assert!(parser.next().is_none());
+}
+
+#[test]
+fn test_emit_error() {
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    let error = emitter.emit(Event::MappingEnd).unwrap_err();
+    assert_eq!(error.to_string(), "expected STREAM-START");
+}
+
+#[test]
+fn test_move_mid_stream() {
+    // Moves each value to a new stack frame and a new heap allocation between
+    // calls, so that anything depending on its address would break.
+    fn parse_one<'a>(mut parser: Box<Parser<'a>>, events: &mut Vec<Event<'a>>) -> Box<Parser<'a>> {
+        events.push(parser.next().unwrap().unwrap().0);
+        Box::new(*parser)
+    }
+
+    fn emit_one(mut emitter: Emitter<Vec<u8>>, event: Event) -> Emitter<Vec<u8>> {
+        emitter.emit(event).unwrap();
+        emitter
+    }
+
+    let yaml = "k: [a, b]\n";
+    let mut parser = Box::new(Parser::new(yaml.as_bytes().to_vec()).unwrap());
+    let mut events = Vec::new();
+    while events.last() != Some(&Event::StreamEnd) {
+        parser = parse_one(parser, &mut events);
+    }
+    assert!(parser.next().is_none());
+
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    for event in events {
+        emitter = emit_one(emitter, event);
+    }
+    let output = emitter.into_inner().unwrap();
+    assert_eq!(output, yaml.as_bytes());
+}
+
+#[test]
+fn test_into_inner_flushes() {
+    // libyaml holds on to output until the end of a document, or until it is
+    // flushed.
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    emitter.emit(Event::StreamStart).unwrap();
+    emitter
+        .emit(Event::DocumentStart(DocumentStart::default()))
+        .unwrap();
+    emitter
+        .emit(Event::Scalar(Scalar {
+            anchor: None,
+            tag: None,
+            value: Cow::Borrowed(b"x"),
+            style: ScalarStyle::Any,
+        }))
+        .unwrap();
+    let (output, result) = emitter.into_inner_parts();
+    result.unwrap();
+    assert_eq!(output, b"--- x");
+}
+
+#[test]
+fn test_drop_mid_stream() {
+    let mut parser = Parser::new("[a, b, c]\n".as_bytes()).unwrap();
+    parser.next().unwrap().unwrap();
+    parser.next().unwrap().unwrap();
+    drop(parser);
+
+    let mut scanner = Scanner::new(b"k: v\n".to_vec()).unwrap();
+    scanner.next().unwrap().unwrap();
+    drop(scanner);
+
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    emitter.emit(Event::StreamStart).unwrap();
+    emitter
+        .emit(Event::DocumentStart(DocumentStart::default()))
+        .unwrap();
+    emitter
+        .emit(Event::SequenceStart(SequenceStart {
+            anchor: None,
+            tag: None,
+            style: CollectionStyle::Any,
+        }))
+        .unwrap();
+    drop(emitter);
+}
+
+fn emit_error(event: Event) -> String {
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    emitter.emit(Event::StreamStart).unwrap();
+    emitter
+        .emit(Event::DocumentStart(DocumentStart::default()))
+        .unwrap();
+    emitter.emit(event).unwrap_err().to_string()
+}
+
+#[test]
+fn test_emit_invalid_anchor() {
+    let scalar = |anchor: &str| {
+        Event::Scalar(Scalar {
+            anchor: Some(Anchor::new(anchor)),
+            tag: None,
+            value: Cow::Borrowed(b"x"),
+            style: ScalarStyle::Any,
+        })
+    };
+    assert_eq!(
+        emit_error(scalar("a b")),
+        r#"invalid anchor "a b": ' ' is not allowed in an anchor"#,
+    );
+    assert_eq!(
+        emit_error(scalar("a,b")),
+        r#"invalid anchor "a,b": ',' is not allowed in an anchor"#,
+    );
+    assert_eq!(
+        emit_error(scalar("a\0b")),
+        r#"invalid anchor "a\0b": '\0' is not allowed in an anchor"#,
+    );
+    assert_eq!(
+        emit_error(scalar("")),
+        r#"invalid anchor "": anchors must not be empty"#,
+    );
+    assert_eq!(
+        emit_error(Event::Alias(Anchor::new("a]"))),
+        r#"invalid anchor "a]": ']' is not allowed in an anchor"#,
+    );
+}
+
+#[test]
+fn test_emit_invalid_tag() {
+    let event = Event::SequenceStart(SequenceStart {
+        anchor: None,
+        tag: Some(Tag::new("!a\0b")),
+        style: CollectionStyle::Any,
+    });
+    assert_eq!(
+        emit_error(event),
+        r#"invalid tag "!a\0b": '\0' is not allowed in a tag"#,
+    );
+
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    emitter.emit(Event::StreamStart).unwrap();
+    let event = Event::DocumentStart(DocumentStart {
+        implicit: false,
+        version: None,
+        tag_directives: vec![("!e!".to_owned(), "tag:\0".to_owned())],
+    });
+    assert_eq!(
+        emitter.emit(event).unwrap_err().to_string(),
+        r#"invalid tag prefix "tag:\0": '\0' is not allowed in a tag prefix"#,
+    );
+}
+
+#[test]
+fn test_scanner() {
+    let yaml = "%YAML 1.2\n---\nk: &a [x, 'y'] # c\n? *a\n: !t z\n";
+    let tokens: Vec<_> = Scanner::new(yaml.as_bytes())
+        .unwrap()
+        .map(|result| {
+            let (token, start, end) = result.unwrap();
+            (token, &yaml[start.index()..end.index()])
+        })
+        .collect();
+    let scalar = |value: &str, style| Token::Scalar {
+        value: value.as_bytes().to_vec(),
+        style,
+    };
+    let expected = vec![
+        (Token::StreamStart, ""),
+        (Token::VersionDirective { major: 1, minor: 2 }, "%YAML 1.2"),
+        (Token::DocumentStart, "---"),
+        (Token::BlockMappingStart, ""),
+        (Token::Key, ""),
+        (scalar("k", ScalarStyle::Plain), "k"),
+        (Token::Value, ":"),
+        (Token::Anchor(Anchor::new("a")), "&a"),
+        (Token::FlowSequenceStart, "["),
+        (scalar("x", ScalarStyle::Plain), "x"),
+        (Token::FlowEntry, ","),
+        (scalar("y", ScalarStyle::SingleQuoted), "'y'"),
+        (Token::FlowSequenceEnd, "]"),
+        (Token::Key, "?"),
+        (Token::Alias(Anchor::new("a")), "*a"),
+        (Token::Value, ":"),
+        (
+            Token::Tag {
+                handle: b"!".to_vec(),
+                suffix: b"t".to_vec(),
+            },
+            "!t",
+        ),
+        (scalar("z", ScalarStyle::Plain), "z"),
+        (Token::BlockEnd, ""),
+        (Token::StreamEnd, ""),
+    ];
+    assert_eq!(tokens, expected);
+}
+
+#[test]
+fn test_scanner_error() {
+    let mut scanner = Scanner::new("k: 'unterminated\n".as_bytes()).unwrap();
+    let error = scanner.find_map(Result::err).unwrap();
+    assert_eq!(
+        error.to_string(),
+        "single-quoted scalar started at line 1 column 4 was never closed",
+    );
+    assert!(scanner.next().is_none());
+}
+
+#[test]
+#[ignore = "allocates 2 GiB"]
+fn test_emit_scalar_too_long() {
+    let len = i32::MAX as usize + 1;
+    let mut emitter = Emitter::new(Vec::new()).unwrap();
+    emitter.emit(Event::StreamStart).unwrap();
+    emitter
+        .emit(Event::DocumentStart(DocumentStart::default()))
+        .unwrap();
+    let error = emitter
+        .emit(Event::Scalar(Scalar {
+            anchor: None,
+            tag: None,
+            value: Cow::Owned(vec![b'x'; len]),
+            style: ScalarStyle::Plain,
+        }))
+        .unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "scalar of 2147483648 bytes is longer than the 2147483647 that libyaml can emit",
+    );
+}
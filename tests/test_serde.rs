@@ -6,11 +6,15 @@
 )]
 
 use indoc::indoc;
-use serde::ser::SerializeMap;
+use serde::de::Deserialize as _;
+use serde::ser::{Serialize, SerializeMap};
 use serde_derive::{Deserialize, Serialize};
-use serde_yaml::{Mapping, Number, Value};
+use serde_yaml::{
+    Deserializer, DocumentValue, Mapping, Number, Sequence, SerializerBuilder, Value,
+};
 use std::collections::BTreeMap;
 use std::fmt::Debug;
+use std::io;
 use std::iter;
 
 fn test_serde<T>(thing: &T, yaml: &str)
@@ -20,6 +24,13 @@ where
     let serialized = serde_yaml::to_string(&thing).unwrap();
     assert_eq!(yaml, serialized);
 
+    let mut serializer = SerializerBuilder::new()
+        .verify_roundtrip(true)
+        .build(Vec::new())
+        .unwrap();
+    thing.serialize(&mut serializer).unwrap();
+    assert_eq!(yaml.as_bytes(), serializer.into_inner().unwrap());
+
     let value = serde_yaml::to_value(thing).unwrap();
     let serialized = serde_yaml::to_string(&value).unwrap();
     assert_eq!(yaml, serialized);
@@ -28,6 +39,8 @@ where
     assert_eq!(*thing, deserialized);
 
     let value: Value = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(value, serde_yaml::to_value(thing).unwrap());
+
     let deserialized = T::deserialize(&value).unwrap();
     assert_eq!(*thing, deserialized);
 
@@ -156,6 +169,95 @@ fn test_float32() {
     assert!(single_float.is_nan());
 }
 
+#[test]
+fn test_number_kinds() {
+    // The same kind of number, and for floats the same bits, as `number`.
+    fn same(number: &Number, other: &Number) -> bool {
+        match (number.as_u64(), number.as_i64(), number.as_f64()) {
+            _ if number.is_u64() => other.is_u64() && other.as_u64() == number.as_u64(),
+            (_, Some(i), _) => !other.is_u64() && other.as_i64() == Some(i),
+            (_, _, Some(f)) if f.is_nan() => other.is_f64() && other.is_nan(),
+            (_, _, f) => other.is_f64() && other.as_f64().map(f64::to_bits) == f.map(f64::to_bits),
+        }
+    }
+
+    let mut numbers: Vec<Number> = Vec::new();
+    for u in [
+        0,
+        1,
+        9,
+        10,
+        1 << 53,
+        (1 << 53) + 1,
+        i64::MAX as u64,
+        i64::MAX as u64 + 1,
+        u64::MAX - 1,
+        u64::MAX,
+    ] {
+        numbers.push(u.into());
+    }
+    for i in [-1, -(1 << 53) - 1, i64::MIN + 1, i64::MIN] {
+        numbers.push(i.into());
+    }
+    for f in [
+        0.0,
+        -0.0,
+        1.0,
+        -25.0,
+        0.1,
+        1e15,
+        1e16,
+        1e-7,
+        9.223372036854776e18,
+        1.8446744073709552e19,
+        f64::MAX,
+        f64::MIN,
+        f64::MIN_POSITIVE,
+        f64::EPSILON,
+        5e-324,
+        f64::INFINITY,
+        f64::NEG_INFINITY,
+        f64::NAN,
+    ] {
+        numbers.push(f.into());
+    }
+    // Bit patterns spread over every exponent, from a fixed xorshift sequence.
+    let mut bits = 0x9e37_79b9_7f4a_7c15u64;
+    for _ in 0..2000 {
+        bits ^= bits << 13;
+        bits ^= bits >> 7;
+        bits ^= bits << 17;
+        numbers.push(f64::from_bits(bits).into());
+        numbers.push(bits.into());
+        numbers.push((bits as i64 | i64::MIN).into());
+    }
+
+    for number in &numbers {
+        let value = Value::Number(number.clone());
+        let yaml = serde_yaml::to_string(&value).unwrap();
+        let back: Value = serde_yaml::from_str(&yaml).unwrap();
+        match &back {
+            Value::Number(back) => assert!(same(number, back), "{:?} as {:?}", number, yaml),
+            _ => panic!("{:?} as {:?} is {:?}", number, yaml, back),
+        }
+        let back: Number = serde_yaml::from_str(&yaml).unwrap();
+        assert!(same(number, &back), "{:?} as {:?}", number, yaml);
+        let back: Number = yaml.trim_end().parse().unwrap();
+        assert!(same(number, &back), "{:?} as {:?}", number, yaml);
+        match serde_yaml::to_value(number).unwrap() {
+            Value::Number(back) => assert!(same(number, &back), "{:?}", number),
+            other => panic!("{:?} is {:?}", number, other),
+        }
+    }
+
+    let yaml = "- 18446744073709551615\n- -9223372036854775808\n- 1.8446744073709552e19\n";
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(value[0].as_u64(), Some(u64::MAX));
+    assert_eq!(value[1].as_i64(), Some(i64::MIN));
+    assert!(value[2].is_f64());
+    assert_eq!(serde_yaml::to_string(&value).unwrap(), yaml);
+}
+
 #[test]
 fn test_char() {
     let ch = '.';
@@ -200,6 +302,36 @@ fn test_map() {
     test_serde(&thing, yaml);
 }
 
+#[test]
+fn test_map_non_string_keys() {
+    let mut thing = BTreeMap::new();
+    thing.insert(false, 0);
+    thing.insert(true, 1);
+    let yaml = indoc! {"
+        false: 0
+        true: 1
+    "};
+    test_serde(&thing, yaml);
+
+    let mut thing = BTreeMap::new();
+    thing.insert(None, 0);
+    thing.insert(Some(-1), 1);
+    let yaml = indoc! {"
+        null: 0
+        -1: 1
+    "};
+    test_serde(&thing, yaml);
+
+    let mut thing = BTreeMap::new();
+    thing.insert("true".to_owned(), 0);
+    thing.insert("null".to_owned(), 1);
+    let yaml = indoc! {"
+        'null': 1
+        'true': 0
+    "};
+    test_serde(&thing, yaml);
+}
+
 #[test]
 fn test_map_key_value() {
     struct Map;
@@ -428,6 +560,44 @@ fn test_newtype_struct() {
     test_serde(&thing, yaml);
 }
 
+#[test]
+fn test_newtype_struct_of_enum() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Shape {
+        Circle(f64),
+        Square { side: u8 },
+        Point,
+    }
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Wrapper(Shape);
+    let thing = vec![
+        Wrapper(Shape::Circle(1.5)),
+        Wrapper(Shape::Square { side: 2 }),
+        Wrapper(Shape::Point),
+    ];
+    let yaml = indoc! {"
+        - !Circle 1.5
+        - !Square
+          side: 2
+        - Point
+    "};
+    test_serde(&thing, yaml);
+
+    // Straight from the tagged value, too.
+    let value = serde_yaml::to_value(Shape::Circle(1.5)).unwrap();
+    let Value::Tagged(tagged) = value else {
+        panic!("expected a tagged value");
+    };
+    assert_eq!(
+        Wrapper::deserialize(&*tagged).unwrap(),
+        Wrapper(Shape::Circle(1.5)),
+    );
+    assert_eq!(
+        Wrapper::deserialize(*tagged).unwrap(),
+        Wrapper(Shape::Circle(1.5)),
+    );
+}
+
 #[test]
 fn test_newtype_variant() {
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -509,14 +679,17 @@ fn test_value() {
     }
     let thing = GenericInstructions {
         typ: "primary".to_string(),
-        config: Value::Sequence(vec![
-            Value::Null,
-            Value::Bool(true),
-            Value::Number(Number::from(65535)),
-            Value::Number(Number::from(0.54321)),
-            Value::String("s".into()),
-            Value::Mapping(Mapping::new()),
-        ]),
+        config: Value::Sequence(
+            vec![
+                Value::Null,
+                Value::Bool(true),
+                Value::Number(Number::from(65535)),
+                Value::Number(Number::from(0.54321)),
+                Value::String("s".into()),
+                Value::Mapping(Mapping::new()),
+            ]
+            .into(),
+        ),
     };
     let yaml = indoc! {"
         type: primary
@@ -559,6 +732,64 @@ fn test_mapping() {
     test_serde(&thing, yaml);
 }
 
+#[test]
+fn test_single_entry_mapping_with_complex_key() {
+    let mut key = Mapping::new();
+    key.insert(Value::from("b"), Value::from(1));
+    let mut thing = Mapping::new();
+    thing.insert(Value::Mapping(key), Value::Null);
+    let yaml = indoc! {"
+        ? b: 1
+        : null
+    "};
+    test_serde(&thing, yaml);
+
+    #[derive(Serialize, Deserialize, PartialEq, Eq, PartialOrd, Ord, Debug)]
+    enum Key {
+        Tuple(i32, i32),
+    }
+    let thing = BTreeMap::from([(Key::Tuple(1, 2), 0)]);
+    let yaml = indoc! {"
+        ? !Tuple
+        - 1
+        - 2
+        : 0
+    "};
+    test_serde(&thing, yaml);
+}
+
+#[test]
+fn test_bare_mapping_and_sequence() {
+    let mut mapping = Mapping::new();
+    mapping.insert(Value::from(1), Value::from("int"));
+    mapping.insert(Value::from(true), Value::from("bool"));
+    mapping.insert(
+        Value::Sequence(vec![Value::from("a")].into()),
+        Value::from("seq"),
+    );
+    let yaml = indoc! {"
+        1: int
+        true: bool
+        ? - a
+        : seq
+    "};
+    test_serde(&mapping, yaml);
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Data {
+        pub items: Sequence,
+    }
+    let thing = Data {
+        items: vec![Value::from(1), Value::from("two")].into(),
+    };
+    let yaml = indoc! {"
+        items:
+        - 1
+        - two
+    "};
+    test_serde(&thing, yaml);
+}
+
 #[test]
 fn test_long_string() {
     #[derive(Serialize, Deserialize, PartialEq, Debug)]
@@ -576,3 +807,704 @@ fn test_long_string() {
 
     test_serde(&thing, yaml);
 }
+
+#[test]
+fn test_buffer_capacity() {
+    struct CountingWriter {
+        bytes: Vec<u8>,
+        writes: usize,
+    }
+
+    impl io::Write for CountingWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.writes += 1;
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn serialize(builder: SerializerBuilder) -> CountingWriter {
+        let writer = CountingWriter {
+            bytes: Vec::new(),
+            writes: 0,
+        };
+        let mut serializer = builder.build(writer).unwrap();
+        for i in 0..1000 {
+            i.serialize(&mut serializer).unwrap();
+        }
+        serializer.into_inner().unwrap()
+    }
+
+    let unbuffered = serialize(SerializerBuilder::new());
+    let buffered = serialize(SerializerBuilder::new().buffer_capacity(64 * 1024));
+    assert_eq!(unbuffered.bytes, buffered.bytes);
+    assert!(unbuffered.writes >= 1000);
+    assert_eq!(buffered.writes, 1);
+}
+
+#[test]
+fn test_output_independent_of_writer() {
+    // Takes at most 7 bytes per call, so that no write lines up with the
+    // emitter's own buffer or with the lines of the output.
+    struct ChunkedWriter {
+        bytes: Vec<u8>,
+    }
+
+    impl io::Write for ChunkedWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            let n = buf.len().min(7);
+            self.bytes.extend_from_slice(&buf[..n]);
+            Ok(n)
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    fn serialize<W: io::Write>(builder: SerializerBuilder, writer: W, values: &[Value]) -> W {
+        let mut serializer = builder.build(writer).unwrap();
+        for value in values {
+            value.serialize(&mut serializer).unwrap();
+        }
+        serializer.into_inner().unwrap()
+    }
+
+    let words: String = iter::repeat(["word", " "]).flatten().take(9999).collect();
+    let corpus: Vec<Value> = vec![
+        Value::from("short"),
+        Value::from(words.as_str()),
+        Value::from("x".repeat(20000)),
+        Value::from(format!("{}\n{}\n", words, "y".repeat(5000))),
+        serde_yaml::from_str(indoc! {r#"
+            name: "\u00e9t\u00e9 \U0001F600"
+            script: "set -e\n\n  make\n"
+            steps: [a, {b: c}, [], {}]
+            numbers: [1, -2.5, .inf, null, true]
+        "#})
+        .unwrap(),
+        Value::Sequence(
+            (0..2000)
+                .map(|i| Value::from(format!("item {}", i)))
+                .collect(),
+        ),
+    ];
+    let corpus = [corpus.clone(), corpus.iter().rev().cloned().collect()].concat();
+
+    for capacity in [0, 7, 100, 64 * 1024] {
+        for indent in [0, 3] {
+            let builder = || {
+                SerializerBuilder::new()
+                    .buffer_capacity(capacity)
+                    .prefix_indent(indent)
+            };
+            let vec = serialize(builder(), Vec::new(), &corpus);
+            let chunked = serialize(builder(), ChunkedWriter { bytes: Vec::new() }, &corpus);
+            assert!(
+                vec == chunked.bytes,
+                "capacity {}, indent {}",
+                capacity,
+                indent
+            );
+        }
+    }
+
+    // And the same as a single document written to a `String`.
+    for value in &corpus {
+        let chunked = serialize(
+            SerializerBuilder::new(),
+            ChunkedWriter { bytes: Vec::new() },
+            std::slice::from_ref(value),
+        );
+        assert_eq!(
+            serde_yaml::to_string(value).unwrap().as_bytes(),
+            chunked.bytes
+        );
+    }
+}
+
+#[test]
+fn test_into_inner_parts() {
+    struct FlakyWriter {
+        bytes: Vec<u8>,
+        fail_flush: bool,
+    }
+
+    impl io::Write for FlakyWriter {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            self.bytes.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            if self.fail_flush {
+                Err(io::Error::new(io::ErrorKind::BrokenPipe, "flush failed"))
+            } else {
+                Ok(())
+            }
+        }
+    }
+
+    fn serializer(fail_flush: bool) -> serde_yaml::Serializer<FlakyWriter> {
+        let writer = FlakyWriter {
+            bytes: Vec::new(),
+            fail_flush,
+        };
+        let mut serializer = SerializerBuilder::new()
+            .buffer_capacity(1024)
+            .build(writer)
+            .unwrap();
+        vec!["a", "b"].serialize(&mut serializer).unwrap();
+        serializer
+    }
+
+    let (writer, result) = serializer(false).into_inner_parts();
+    result.unwrap();
+    assert_eq!(writer.bytes, b"- a\n- b\n");
+
+    let (writer, result) = serializer(true).into_inner_parts();
+    assert_eq!(result.unwrap_err().to_string(), "flush failed");
+    assert_eq!(writer.bytes, b"- a\n- b\n");
+
+    let error = serializer(true).into_inner().err().unwrap();
+    assert_eq!(error.to_string(), "flush failed");
+}
+
+#[test]
+fn test_bytes_written() {
+    let long = "x".repeat(20000);
+    let value = vec![long.as_str(), "short", ""];
+    let mut buffer = Vec::new();
+    let written = serde_yaml::to_writer_counted(&mut buffer, &value).unwrap();
+    assert_eq!(written, buffer.len() as u64);
+    assert!(buffer.ends_with(b"\n"));
+
+    for capacity in [0, 16, 64 * 1024] {
+        let mut serializer = SerializerBuilder::new()
+            .buffer_capacity(capacity)
+            .build(Vec::new())
+            .unwrap();
+        let mut offsets = vec![0];
+        for document in [&value[..], &value[1..], &value[2..]] {
+            document.serialize(&mut serializer).unwrap();
+            offsets.push(serializer.bytes_written());
+        }
+        let buffer = serializer.into_inner().unwrap();
+        assert_eq!(*offsets.last().unwrap(), buffer.len() as u64);
+        let documents: Vec<Value> = offsets
+            .windows(2)
+            .map(|range| {
+                let document = &buffer[range[0] as usize..range[1] as usize];
+                serde_yaml::from_slice(document).unwrap()
+            })
+            .collect();
+        assert_eq!(documents[1], serde_yaml::to_value(&value[1..]).unwrap());
+        assert_eq!(documents[2], serde_yaml::to_value(&value[2..]).unwrap());
+    }
+}
+
+#[test]
+fn test_prefix_indent() {
+    fn indented(value: &Value, indent: usize) -> String {
+        let mut serializer = SerializerBuilder::new()
+            .prefix_indent(indent)
+            .build(Vec::new())
+            .unwrap();
+        value.serialize(&mut serializer).unwrap();
+        String::from_utf8(serializer.into_inner().unwrap()).unwrap()
+    }
+
+    let value: Value = serde_yaml::from_str(indoc! {r#"
+        name: web
+        script: "set -e\n\n  make\n\n\nmake install\n"
+        lead: "  indented\nfirst line\n"
+        keep: "trailing\n\n"
+        steps:
+        - "one\ntwo"
+        - [a, {b: c}]
+        empty: {}
+    "#})
+    .unwrap();
+    let unindented = indoc! {"
+          name: web
+          script: |
+            set -e
+
+              make
+
+
+            make install
+          lead: |2
+              indented
+            first line
+          keep: |+
+            trailing
+
+          steps:
+          - |-
+            one
+            two
+          - - a
+            - b: c
+          empty: {}
+    "};
+    let expected: String = unindented
+        .lines()
+        .map(|line| match line {
+            "" => "\n".to_owned(),
+            line => format!("  {}\n", line),
+        })
+        .collect();
+    let output = indented(&value, 2);
+    assert_eq!(output, expected);
+    assert_eq!(serde_yaml::to_string(&value).unwrap(), unindented);
+
+    // Spliced below a key, and back out on its own.
+    let template = format!("spec:\n{}other: 1\n", output);
+    let parsed: Value = serde_yaml::from_str(&template).unwrap();
+    assert_eq!(parsed["spec"], value);
+    assert_eq!(parsed["other"], 1);
+    for indent in [1, 3, 10] {
+        let parsed: Value = serde_yaml::from_str(&indented(&value, indent)).unwrap();
+        assert_eq!(parsed, value);
+    }
+
+    // A block scalar that is the whole document, whose indentation indicator
+    // would not move with the prefix.
+    for string in ["  indented\nline\n", "\nafter a break\n", "two\nlines\n"] {
+        let value = Value::from(string);
+        let output = indented(&value, 4);
+        assert!(output.starts_with("    "), "{:?}", output);
+        let parsed: Value = serde_yaml::from_str(&output).unwrap();
+        assert_eq!(parsed, value, "{:?}", output);
+    }
+    assert_eq!(
+        indented(&Value::from("two\nlines\n"), 4),
+        "    |\n      two\n      lines\n"
+    );
+    assert_eq!(indented(&Value::from(" x\n"), 4), "    \" x\\n\"\n");
+}
+
+#[test]
+fn test_seq_or_single() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    enum Target {
+        Host(String),
+        Port(u16),
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Route {
+        name: String,
+        #[serde(with = "serde_yaml::with::seq_or_single")]
+        targets: Vec<Target>,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Config {
+        #[serde(with = "serde_yaml::with::seq_or_single")]
+        routes: Vec<Route>,
+    }
+
+    let thing = Config {
+        routes: vec![Route {
+            name: "a".to_owned(),
+            targets: vec![Target::Host("example.com".to_owned())],
+        }],
+    };
+    let yaml = indoc! {"
+        routes:
+          name: a
+          targets: !Host example.com
+    "};
+    test_serde(&thing, yaml);
+
+    let thing = Config {
+        routes: vec![
+            Route {
+                name: "a".to_owned(),
+                targets: vec![Target::Port(80), Target::Port(443)],
+            },
+            Route {
+                name: "b".to_owned(),
+                targets: Vec::new(),
+            },
+        ],
+    };
+    let yaml = indoc! {"
+        routes:
+        - name: a
+          targets:
+          - !Port 80
+          - !Port 443
+        - name: b
+          targets: []
+    "};
+    test_serde(&thing, yaml);
+
+    // Null and an empty value mean no elements.
+    let yaml = indoc! {"
+        routes:
+        - name: a
+          targets: null
+        - name: b
+          targets:
+    "};
+    let config: Config = serde_yaml::from_str(yaml).unwrap();
+    assert!(config.routes.iter().all(|route| route.targets.is_empty()));
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct AsSeq {
+        #[serde(with = "serde_yaml::with::seq_or_single_as_seq")]
+        hosts: Vec<String>,
+    }
+
+    let thing = AsSeq {
+        hosts: vec!["example.com".to_owned()],
+    };
+    let yaml = indoc! {"
+        hosts:
+        - example.com
+    "};
+    test_serde(&thing, yaml);
+    let deserialized: AsSeq = serde_yaml::from_str("hosts: example.com").unwrap();
+    assert_eq!(deserialized, thing);
+}
+
+#[test]
+fn test_double_option() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Patch {
+        #[serde(
+            default,
+            skip_serializing_if = "Option::is_none",
+            with = "serde_yaml::with::double_option"
+        )]
+        nickname: Option<Option<String>>,
+    }
+
+    let thing = Patch { nickname: None };
+    test_serde(&thing, "{}\n");
+
+    let thing = Patch {
+        nickname: Some(None),
+    };
+    test_serde(&thing, "nickname: null\n");
+
+    let thing = Patch {
+        nickname: Some(Some("Bob".to_owned())),
+    };
+    test_serde(&thing, "nickname: Bob\n");
+
+    // Every spelling of null clears the field, and a quoted null sets it.
+    for yaml in ["nickname: ~", "nickname:", "nickname: Null"] {
+        let patch: Patch = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(patch.nickname, Some(None));
+    }
+    let patch: Patch = serde_yaml::from_str("nickname: 'null'").unwrap();
+    assert_eq!(patch.nickname, Some(Some("null".to_owned())));
+
+    // Without the module, a null is indistinguishable from a missing field.
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Plain {
+        nickname: Option<Option<String>>,
+    }
+    let plain: Plain = serde_yaml::from_str("nickname: null").unwrap();
+    assert_eq!(plain.nickname, None);
+    let plain: Plain = serde_yaml::from_str("{}").unwrap();
+    assert_eq!(plain.nickname, None);
+    let yaml = serde_yaml::to_string(&Some(None::<String>)).unwrap();
+    assert_eq!(yaml, "null\n");
+}
+
+#[test]
+fn test_string_or_struct() {
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Port {
+        number: u16,
+        protocol: String,
+    }
+
+    impl std::str::FromStr for Port {
+        type Err = String;
+
+        fn from_str(s: &str) -> Result<Self, Self::Err> {
+            let (number, protocol) = s.split_once('/').unwrap_or((s, "tcp"));
+            Ok(Port {
+                number: number
+                    .parse()
+                    .map_err(|_| format!("invalid port {:?}", s))?,
+                protocol: protocol.to_owned(),
+            })
+        }
+    }
+
+    impl std::fmt::Display for Port {
+        fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+            write!(formatter, "{}/{}", self.number, self.protocol)
+        }
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Service {
+        #[serde(with = "serde_yaml::with::string_or_struct")]
+        port: Port,
+    }
+
+    #[derive(Serialize, Deserialize, PartialEq, Debug)]
+    struct Shorthand {
+        #[serde(with = "serde_yaml::with::string_or_struct_shorthand")]
+        port: Port,
+    }
+
+    let expected = Service {
+        port: Port {
+            number: 53,
+            protocol: "udp".to_owned(),
+        },
+    };
+    let yaml = indoc! {"
+        port:
+          number: 53
+          protocol: udp
+    "};
+    test_serde(&expected, yaml);
+    let deserialized: Service = serde_yaml::from_str("port: 53/udp").unwrap();
+    assert_eq!(deserialized, expected);
+
+    // A plain scalar that resolves to a number is still a shorthand.
+    let deserialized: Service = serde_yaml::from_str("port: 80").unwrap();
+    assert_eq!(deserialized.port.protocol, "tcp");
+
+    let error = serde_yaml::from_str::<Service>("port: http").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "port: invalid port \"http\" at line 1 column 7",
+    );
+    let error = serde_yaml::from_str::<Service>("port: [80]").unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "port: invalid type: sequence, expected a string or a map at line 1 column 7",
+    );
+
+    let thing = Shorthand {
+        port: Port {
+            number: 53,
+            protocol: "udp".to_owned(),
+        },
+    };
+    test_serde(&thing, "port: 53/udp\n");
+}
+
+#[test]
+fn test_verify_roundtrip() {
+    #[derive(Serialize)]
+    enum Enum {
+        #[serde(rename = "a,b")]
+        Variant(u8),
+    }
+
+    fn serialize<T: Serialize>(value: &T) -> serde_yaml::Result<Vec<u8>> {
+        let mut serializer = SerializerBuilder::new()
+            .verify_roundtrip(true)
+            .build(Vec::new())?;
+        value.serialize(&mut serializer)?;
+        serializer.into_inner()
+    }
+
+    let strings = ["---", "...", "- x", "true", "0x1", "~", "", " x", "#x"];
+    let output = serialize(&strings).unwrap();
+    let deserialized: Vec<String> = serde_yaml::from_slice(&output).unwrap();
+    assert_eq!(deserialized, strings);
+
+    // libyaml writes the `,` of this tag as is, after which the tag cannot be
+    // parsed.
+    assert_eq!(
+        serde_yaml::to_string(&Enum::Variant(1)).unwrap(),
+        "!a,b 1\n"
+    );
+    let error = serialize(&Enum::Variant(1)).unwrap_err();
+    assert_eq!(
+        error.to_string(),
+        "serialized YAML does not parse back the same: did not find expected whitespace or line break at line 1 column 3, while scanning a tag at line 1 column 1",
+    );
+    assert!(error.is_data());
+}
+
+#[test]
+fn test_erased_serde() {
+    #[derive(Serialize)]
+    struct Plugin {
+        name: &'static str,
+        enabled: bool,
+    }
+
+    let plugins: Vec<Box<dyn erased_serde::Serialize>> = vec![
+        Box::new(Plugin {
+            name: "x",
+            enabled: true,
+        }),
+        Box::new(vec![1, 2]),
+        Box::new("y"),
+    ];
+    let expected = indoc! {"
+        - name: x
+          enabled: true
+        - - 1
+          - 2
+        - y
+    "};
+    assert_eq!(serde_yaml::to_string(&plugins).unwrap(), expected);
+
+    let plugin: &dyn erased_serde::Serialize = &*plugins[0];
+    let expected = "name: x\nenabled: true\n";
+    assert_eq!(serde_yaml::to_string(plugin).unwrap(), expected);
+    assert_eq!(serde_yaml::to_vec(plugin).unwrap(), expected.as_bytes());
+    let value = serde_yaml::to_value(plugin).unwrap();
+    assert_eq!(value["enabled"], true);
+
+    let mut buffer = Vec::new();
+    let mut serializer = serde_yaml::Serializer::new(&mut buffer).unwrap();
+    let mut erased = <dyn erased_serde::Serializer>::erase(&mut serializer);
+    plugin.erased_serialize(&mut erased).unwrap();
+    drop(serializer);
+    assert_eq!(String::from_utf8(buffer).unwrap(), expected);
+}
+
+#[test]
+fn test_document_values() {
+    let streams = [
+        "a: 1\n",
+        "---\na: 1\n",
+        "a: 1\n...\n",
+        "%YAML 1.1\n---\na: 1\n...\n---\nb: 2\n",
+        "%TAG !e! tag:example.com,2000:\n---\n- 1\n--- 2\n...\n",
+        "--- first\n--- second\n--- third\n...\n",
+    ];
+    for input in streams {
+        let documents: Vec<DocumentValue> = Deserializer::from_str(input)
+            .document_values()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let mut output = Vec::new();
+        serde_yaml::to_writer_documents(&mut output, &documents).unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), input);
+    }
+
+    let input = "%TAG !e! tag:example.com,2000:\n--- !e!point {x: 1}\n";
+    let documents: Vec<DocumentValue> = Deserializer::from_str(input)
+        .document_values()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(documents.len(), 1);
+    let document = &documents[0];
+    assert!(document.explicit_start);
+    assert!(!document.explicit_end);
+    assert_eq!(document.version, None);
+    assert_eq!(
+        document.tag_directives,
+        [("!e!".to_owned(), "tag:example.com,2000:".to_owned())],
+    );
+    assert_eq!(
+        document.value,
+        serde_yaml::from_str::<Value>(input).unwrap()
+    );
+
+    // Local tags keep the handle they were written with.
+    let input = "%TAG !x! !local-\n--- !x!thing 1\n...\n%TAG !x! !local-\n--- !x!other 2\n";
+    for document in Deserializer::from_str(input) {
+        let directives = document.tag_directives().to_vec();
+        assert_eq!(directives, [("!x!".to_owned(), "!local-".to_owned())]);
+        let value = Value::deserialize(document).unwrap();
+        let tag = value.tag().unwrap();
+        assert!(tag.starts_with("local-"));
+        assert!(tag.shorthand(&directives).unwrap().starts_with("!x!"));
+    }
+    assert!(Deserializer::from_str(input).tag_directives().is_empty());
+
+    // Values without framing are written like a stream of `to_writer` calls.
+    let documents = [DocumentValue::new(Value::from(1)), Value::from(2).into()];
+    let mut output = Vec::new();
+    serde_yaml::to_writer_documents(&mut output, documents).unwrap();
+    assert_eq!(output, b"1\n--- 2\n");
+}
+
+#[test]
+fn test_reader_multi() {
+    // Every kind of document, including scalars that look like the lines the
+    // stream is cut at.
+    let kinds = [
+        Value::Null,
+        Value::String("---".to_owned()),
+        Value::String("...\n%YAML 1.2\n---\n".to_owned()),
+        Value::String("a long string\nover lines\n".to_owned()),
+        serde_yaml::from_str("{a: [1, 2.5, true], b: {c: ~}}").unwrap(),
+        serde_yaml::from_str("[[], {}, '', x]").unwrap(),
+        serde_yaml::from_str("!tagged {x: 1}").unwrap(),
+    ];
+    let documents: Vec<Value> = (0..10_000)
+        .map(|i| match &kinds[i % kinds.len()] {
+            Value::Null => Value::Number(i.into()),
+            kind => kind.clone(),
+        })
+        .collect();
+    let mut input = Vec::new();
+    serde_yaml::to_writer_multi(&mut input, &documents).unwrap();
+
+    // Piped from one into the other without collecting them.
+    let mut count = 0;
+    let mut output = Vec::new();
+    let piped = serde_yaml::from_reader_multi(&input[..]).map(|document: Result<Value, _>| {
+        count += 1;
+        document.unwrap()
+    });
+    serde_yaml::to_writer_multi(&mut output, piped).unwrap();
+    assert_eq!(count, documents.len());
+    assert_eq!(output, input);
+
+    let read: Vec<Value> = serde_yaml::from_reader_multi(&input[..])
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(read, documents);
+
+    // The same documents as reading the whole stream.
+    let streams = [
+        "a: 1\n",
+        "---\na: 1\n",
+        "a: 1\n...\n",
+        "\u{feff}--- a\n--- b\n",
+        "%YAML 1.1\n---\na: 1\n...\n---\nb: 2\n",
+        "%TAG !e! tag:example.com,2000:\n---\n- !e!x 1\n--- 2\n...\n",
+        "--- first\n--- second\n--- third\n...\n",
+        "# a\n---\n# b\n---\n...\n# c\n...\n--- |\n  x\n\n  y\n",
+        "plain scalar\n%YAML 1.2\nover lines\n--- \"quoted\n  over lines\"\n",
+        "a: 1\n%YAML 1.2\n---\nb: 2\n",
+        "a\n%YAML 1.2\n---\nb\n",
+        "a\n...\n%YAML 1.2\n---\nb\n",
+    ];
+    for input in streams {
+        let whole: Vec<Value> = Deserializer::from_str(input)
+            .map(Value::deserialize)
+            .collect::<Result<_, _>>()
+            .unwrap();
+        let multi: Vec<Value> = serde_yaml::from_reader_multi(input.as_bytes())
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(multi, whole, "{:?}", input);
+    }
+
+    // Nothing but comments is no documents, rather than one empty one.
+    for input in ["", "\n", "# nothing\n", "...\n"] {
+        assert_eq!(
+            serde_yaml::from_reader_multi::<_, Value>(input.as_bytes()).count(),
+            0
+        );
+    }
+    let mut output = Vec::new();
+    serde_yaml::to_writer_multi(&mut output, Vec::<Value>::new()).unwrap();
+    assert!(output.is_empty());
+}
@@ -0,0 +1,231 @@
+#![allow(clippy::uninlined_format_args)]
+
+use indoc::indoc;
+use serde::Deserialize as _;
+use serde_yaml::raw::{CollectionStyle, ScalarStyle};
+use serde_yaml::{Deserializer, EmitOptions, Value};
+
+const CORPUS: &[&str] = &[
+    indoc! {"
+        # A comment that is dropped.
+        name: web  # and another
+        replicas: 3
+        ratio: 0.5
+        enabled: true
+        nothing: ~
+        empty:
+        version: '3.10'
+        octal: '0755'
+        zip: 007
+        quoted: \"yes\"
+        multiline: |
+          first
+          second
+        folded: >
+          one
+          two
+        escaped: \"tab\\tand \\u00e9\"
+    "},
+    indoc! {"
+        defaults: &defaults
+          adapter: postgres
+          host: localhost
+        development:
+          <<: *defaults
+          database: dev
+        hosts: [&h1 a, b, *h1]
+        list: &list
+          - 1
+          - [2, 3]
+        again: *list
+    "},
+    indoc! {"
+        %YAML 1.2
+        %TAG !e! tag:example.com,2000:
+        ---
+        - !e!point {x: 1, y: 2}
+        - !!str 42
+        - !Custom [1, 2]
+        - ? [complex, key]
+          : value
+        ...
+        ---
+        second: document
+        --- plain scalar document
+    "},
+    indoc! {"
+        a: &x 1
+        b: *x
+        c: &x 2
+        d: *x
+        e: &y [&y 3, *y]
+        f: *y
+    "},
+    indoc! {"
+        flow: {a: , b: [c]}
+        ? 
+        : empty key
+    "},
+];
+
+fn values(yaml: &str) -> Vec<Value> {
+    Deserializer::from_str(yaml)
+        .map(|document| Value::deserialize(document).unwrap())
+        .collect()
+}
+
+fn transcode(yaml: &str, options: &EmitOptions) -> String {
+    let mut output = Vec::new();
+    serde_yaml::transcode(yaml.as_bytes(), &mut output, options).unwrap();
+    String::from_utf8(output).unwrap()
+}
+
+#[test]
+fn test_transcode_corpus() {
+    let options = [
+        EmitOptions::new(),
+        EmitOptions::new().expand_aliases(true),
+        EmitOptions::new().scalar_style(ScalarStyle::Any),
+        EmitOptions::new().scalar_style(ScalarStyle::Plain),
+        EmitOptions::new().scalar_style(ScalarStyle::SingleQuoted),
+        EmitOptions::new().scalar_style(ScalarStyle::DoubleQuoted),
+        EmitOptions::new().scalar_style(ScalarStyle::Literal),
+        EmitOptions::new().collection_style(CollectionStyle::Flow),
+        EmitOptions::new()
+            .collection_style(CollectionStyle::Block)
+            .scalar_style(ScalarStyle::Folded)
+            .expand_aliases(true),
+    ];
+    for yaml in CORPUS {
+        let expected = values(yaml);
+        for options in &options {
+            let output = transcode(yaml, options);
+            assert_eq!(values(&output), expected, "{:?}\n{}", options, output);
+            if format!("{:?}", options).contains("expand_aliases: true") {
+                assert!(!output.contains('*'), "{}", output);
+                assert!(!output.contains('&'), "{}", output);
+            }
+        }
+    }
+}
+
+#[test]
+fn test_transcode_output() {
+    let yaml = indoc! {"
+        # comment
+        a: &x 'one'   # trailing
+        b: *x
+        c: [\"two\", '3', 4]
+        d: {e: f}
+    "};
+
+    let output = transcode(yaml, &EmitOptions::new());
+    let expected = indoc! {"
+        a: &x 'one'
+        b: *x
+        c: [\"two\", '3', 4]
+        d: {e: f}
+    "};
+    assert_eq!(output, expected);
+
+    let options = EmitOptions::new()
+        .expand_aliases(true)
+        .scalar_style(ScalarStyle::Plain)
+        .collection_style(CollectionStyle::Block);
+    let output = transcode(yaml, &options);
+    let expected = indoc! {"
+        a: one
+        b: one
+        c:
+        - two
+        - '3'
+        - 4
+        d:
+          e: f
+    "};
+    assert_eq!(output, expected);
+
+    let options = EmitOptions::new().scalar_style(ScalarStyle::DoubleQuoted);
+    let output = transcode("[a, 1, '1', !!str b]\n", &options);
+    assert_eq!(output, "[\"a\", 1, \"1\", !!str b]\n");
+}
+
+#[test]
+fn test_transcode_expansion_limit() {
+    let yaml = indoc! {"
+        a: &a ~
+        b: &b [*a,*a,*a,*a,*a,*a,*a,*a,*a]
+        c: &c [*b,*b,*b,*b,*b,*b,*b,*b,*b]
+        d: &d [*c,*c,*c,*c,*c,*c,*c,*c,*c]
+        e: &e [*d,*d,*d,*d,*d,*d,*d,*d,*d]
+        f: &f [*e,*e,*e,*e,*e,*e,*e,*e,*e]
+        g: &g [*f,*f,*f,*f,*f,*f,*f,*f,*f]
+        h: &h [*g,*g,*g,*g,*g,*g,*g,*g,*g]
+        i: &i [*h,*h,*h,*h,*h,*h,*h,*h,*h]
+    "};
+    let options = EmitOptions::new().expand_aliases(true);
+    let mut output = Vec::new();
+    let err = serde_yaml::transcode(yaml.as_bytes(), &mut output, &options).unwrap_err();
+    assert_eq!(err.to_string(), "repetition limit exceeded");
+
+    // Without expansion, the aliases are copied as they are.
+    let output = transcode(yaml, &EmitOptions::new());
+    assert_eq!(output.matches('*').count(), 72);
+
+    let mut output = Vec::new();
+    let err = serde_yaml::transcode(&b"a: *missing\n"[..], &mut output, &options).unwrap_err();
+    assert_eq!(err.to_string(), "unknown anchor at line 1 column 4");
+}
+
+fn to_json(yaml: &str) -> Result<String, String> {
+    let mut json = Vec::new();
+    let mut serializer = serde_json::Serializer::new(&mut json);
+    match serde_transcode::transcode(Deserializer::from_str(yaml), &mut serializer) {
+        Ok(()) => Ok(String::from_utf8(json).unwrap()),
+        Err(err) => Err(err.to_string()),
+    }
+}
+
+#[test]
+fn test_serde_transcode() {
+    let yaml = indoc! {"
+        name: web
+        ports: [80, 443]
+        limits: &limits {cpu: 0.5, memory: 256Mi}
+        requests: *limits
+        empty:
+        1: one
+        true: yes
+    "};
+    let expected = concat!(
+        r#"{"name":"web","ports":[80,443],"limits":{"cpu":0.5,"memory":"256Mi"},"#,
+        r#""requests":{"cpu":0.5,"memory":"256Mi"},"empty":null,"1":"one","true":"yes"}"#,
+    );
+    assert_eq!(to_json(yaml).unwrap(), expected);
+
+    let cases = [
+        (
+            "a: 1\n? [x, y]\n: 2\n",
+            "mapping key at line 2 column 3 is a sequence and was rejected: key must be a string",
+        ),
+        (
+            "a:\n  ? {b: 1}\n  : 2\n",
+            "a: mapping key at line 2 column 5 is a mapping and was rejected: key must be a string",
+        ),
+        (
+            "a:\n  ~: 1\n",
+            "a: mapping key at line 2 column 3 is `~` and was rejected: key must be a string",
+        ),
+        (
+            "a:\n  b: [1, !point {x: 1}]\n",
+            "a.b[1]: tagged value `!point` at line 2 column 10 was rejected: invalid type: enum, expected any value",
+        ),
+        (
+            "a: &x [1, !t 2]\nb: *x\n",
+            "a[1]: tagged value `!t` at line 1 column 11 was rejected: invalid type: enum, expected any value",
+        ),
+    ];
+    for (yaml, expected) in cases {
+        assert_eq!(to_json(yaml).unwrap_err(), expected, "{}", yaml);
+    }
+}
@@ -0,0 +1,79 @@
+// Alone in its own test binary, since it measures the memory allocated by
+// the whole process.
+
+#![allow(clippy::uninlined_format_args)]
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::fmt::Write as _;
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+struct Counting;
+
+static ALLOCATED: AtomicUsize = AtomicUsize::new(0);
+static PEAK: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        let allocated = ALLOCATED.fetch_add(layout.size(), Ordering::Relaxed) + layout.size();
+        PEAK.fetch_max(allocated, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        ALLOCATED.fetch_sub(layout.size(), Ordering::Relaxed);
+        System.dealloc(ptr, layout);
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+// Counts what is written without keeping it.
+struct Sink(usize);
+
+impl Write for Sink {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0 += buf.len();
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn test_serde_transcode_bounded_memory() {
+    let mut yaml = String::new();
+    let mut i = 0;
+    while yaml.len() < 4 << 20 {
+        write!(
+            yaml,
+            "---\nid: {}\nname: \"service-{}\"\ntags: [web, prod, {}]\nlimits: &l {{cpu: 0.5, memory: 256Mi}}\nrequests: *l\n",
+            i, i, i,
+        )
+        .unwrap();
+        i += 1;
+    }
+
+    let baseline = ALLOCATED.load(Ordering::Relaxed);
+    PEAK.store(baseline, Ordering::Relaxed);
+    let mut sink = Sink(0);
+    for document in serde_yaml::Deserializer::from_str(&yaml) {
+        let mut serializer = serde_json::Serializer::new(&mut sink);
+        serde_transcode::transcode(document, &mut serializer).unwrap();
+        sink.write_all(b"\n").unwrap();
+    }
+    let peak = PEAK.load(Ordering::Relaxed) - baseline;
+
+    assert!(sink.0 > 4 << 20, "{}", sink.0);
+    // Each document is parsed, converted and dropped before the next, so
+    // memory stays far below the size of the input.
+    assert!(
+        peak < 1 << 20,
+        "peak {} bytes for {} of input",
+        peak,
+        yaml.len()
+    );
+}
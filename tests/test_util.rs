@@ -0,0 +1,84 @@
+#![cfg(feature = "test-util")]
+
+use indoc::indoc;
+use serde_yaml::assert_yaml_eq;
+use serde_yaml::test_util::diff;
+
+#[test]
+fn test_equal() {
+    let actual = indoc! {"
+        # comment
+        b: [1, 'two']
+        a: {x: !t ~}
+    "};
+    let expected = indoc! {"
+        a:
+          x: !t null
+        b:
+          - 1
+          - two
+    "};
+    assert_eq!(diff(actual, expected), None);
+    assert_yaml_eq!(actual, expected);
+    assert_yaml_eq!(actual.to_owned(), expected);
+}
+
+#[test]
+fn test_differences() {
+    let actual = indoc! {"
+        apiVersion: v1
+        spec:
+          replicas: 5
+          ports: [80, 443, 8080]
+          image: nginx
+          a/b: x
+          labels: !Set {}
+        extra: [1]
+    "};
+    let expected = indoc! {"
+        apiVersion: v1
+        spec:
+          replicas: 3
+          ports:
+            - 80
+            - '443'
+          image: nginx
+          a/b: y
+          labels: !Map {}
+          limits:
+            cpu: 1
+    "};
+    let expected_diff = indoc! {"
+        YAML values differ:
+          at /spec/replicas: expected 3, got 5 (actual line 3, expected line 3)
+          at /spec/ports/1: expected '443', got 443 (actual line 4, expected line 6)
+          at /spec/ports/2: unexpected 8080 (actual line 4, expected line 4)
+          at /spec/a~1b: expected y, got x (actual line 6, expected line 8)
+          at /spec/labels: expected !Map {}, got !Set {} (actual line 7, expected line 9)
+          at /spec/limits: missing, expected a mapping of 1 entry (actual line 2, expected line 10)
+          at /extra: unexpected a sequence of 1 element (actual line 8)"
+    };
+    assert_eq!(diff(actual, expected).unwrap(), expected_diff);
+
+    assert_eq!(
+        diff("[1, 2]", "{a: 1}").unwrap(),
+        "YAML values differ:\n  at the root: expected a mapping of 1 entry, got a sequence of 2 elements (actual line 1, expected line 1)",
+    );
+}
+
+#[test]
+fn test_parse_error() {
+    let expected_diff = indoc! {"
+        actual YAML failed to parse: did not find expected ',' or ']' at line 2 column 2, while parsing a flow sequence at line 1 column 4
+          |
+        2 | b: 2
+          |  ^"
+    };
+    assert_eq!(diff("a: [1\nb: 2\n", "a: [1]").unwrap(), expected_diff);
+}
+
+#[test]
+#[should_panic(expected = "at /a: expected 2, got 1")]
+fn test_assert_panics() {
+    assert_yaml_eq!("a: 1", "a: 2");
+}
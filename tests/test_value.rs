@@ -1,6 +1,7 @@
 #![allow(
     clippy::derive_partial_eq_without_eq,
     clippy::eq_op,
+    clippy::needless_borrows_for_generic_args,
     clippy::uninlined_format_args
 )]
 
@@ -8,7 +9,12 @@ use indoc::indoc;
 use serde::de::IntoDeserializer;
 use serde::Deserialize;
 use serde_derive::{Deserialize, Serialize};
-use serde_yaml::{Number, Value};
+use serde_yaml::value::{Tag, TaggedValue};
+use serde_yaml::{Mapping, Number, Sequence, Value};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::hash::BuildHasherDefault;
 
 #[test]
 fn test_nan() {
@@ -151,3 +157,1119 @@ fn test_tagged() {
     let serialized = serde_yaml::to_value(&value).unwrap();
     assert_eq!(value, serialized);
 }
+
+#[test]
+fn test_tag_ergonomics() {
+    let value = Value::tagged("!Ref", Value::from("x"));
+    assert_eq!(value, serde_yaml::from_str::<Value>("!Ref x").unwrap());
+    assert_eq!(value.tag().unwrap(), "Ref");
+    assert_eq!(value.tag().unwrap(), "!Ref");
+    assert_eq!(value.untag(), "x");
+    assert_eq!(value.clone().untag_owned(), "x");
+    assert_eq!(Value::from("x").untag(), "x");
+    assert_eq!(Value::from("x").tag(), None);
+
+    let tag: Tag = "Ref".parse().unwrap();
+    assert_eq!(tag, Tag::new("!Ref"));
+    assert_eq!(tag.to_string(), "!Ref");
+    assert!("".parse::<Tag>().is_err());
+    assert!(Tag::new("!aws/Ref").starts_with("aws/"));
+    assert!(Tag::new("aws/Ref").starts_with("!aws/"));
+    assert!(!Tag::new("!aws/Ref").starts_with("Ref"));
+
+    // Local tags are kept, whether written with the primary handle or
+    // verbatim. Global tags are resolved and never show up in a Value.
+    let cases = [
+        ("!Ref x", Some("Ref")),
+        ("!<!Ref> x", Some("Ref")),
+        ("!!str x", None),
+        ("!<tag:yaml.org,2002:str> x", None),
+        ("!<tag:example.com,2000:app/foo> x", None),
+    ];
+    for (yaml, expected) in cases {
+        let value: Value = serde_yaml::from_str(yaml).unwrap();
+        assert_eq!(
+            value.tag().map(Tag::to_string),
+            expected.map(|tag| format!("!{}", tag))
+        );
+        assert_eq!(value.untag(), "x");
+    }
+}
+
+#[test]
+fn test_flexible_keys() {
+    let yaml = indoc! {"
+        name: serde
+        42: answer
+        list: [a, b]
+    "};
+    let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+
+    let mapping = value.as_mapping().unwrap();
+    assert_eq!(mapping.get("name").unwrap(), "serde");
+    assert_eq!(mapping.get(String::from("name")).unwrap(), "serde");
+    assert_eq!(mapping.get(42).unwrap(), "answer");
+    assert_eq!(mapping.get(42u64).unwrap(), "answer");
+    assert!(mapping.contains_key(42));
+    assert!(!mapping.contains_key(-42));
+    assert!(mapping.get(43).is_none());
+
+    assert_eq!(value.get("name").unwrap(), "serde");
+    assert_eq!(value["list"].get(1).unwrap(), "b");
+    assert!(value["name"].get("name").is_none());
+
+    *value.get_mut("name").unwrap() = Value::from("yaml");
+    let mapping = value.as_mapping_mut().unwrap();
+    *mapping.get_mut(42).unwrap() = Value::from("question");
+    assert_eq!(mapping.remove(42).unwrap(), "question");
+    assert_eq!(mapping.remove("name").unwrap(), "yaml");
+    assert_eq!(mapping.len(), 1);
+}
+
+// A small xorshift generator, so that the same mappings are checked on
+// every run.
+struct Rng(u64);
+
+impl Rng {
+    fn below(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+
+    fn scalar(&mut self) -> Value {
+        let scalars = [
+            Value::Null,
+            Value::Bool(true),
+            Value::Bool(false),
+            Value::from(0),
+            Value::from(-1),
+            Value::from(u64::MAX),
+            Value::from(i64::MIN),
+            Value::from(1.5),
+            Value::from(1.0),
+            Value::from(-0.0),
+            Value::from(1e300),
+            Value::from(f64::NAN),
+            Value::from(f64::INFINITY),
+            Value::from(f64::NEG_INFINITY),
+            Value::from(""),
+            Value::from("true"),
+            Value::from("null"),
+            Value::from("~"),
+            Value::from("1.5"),
+            Value::from(".nan"),
+            Value::from("0x1"),
+            Value::from("key"),
+        ];
+        scalars[self.below(scalars.len())].clone()
+    }
+
+    fn value(&mut self, depth: usize) -> Value {
+        match if depth == 0 { 0 } else { self.below(5) } {
+            0 | 1 => self.scalar(),
+            2 => Value::Sequence((0..self.below(3)).map(|_| self.value(depth - 1)).collect()),
+            3 => Value::Mapping(self.mapping(depth - 1)),
+            _ => {
+                // A tag directly on a tagged value is not supported.
+                let mut value = self.value(depth - 1);
+                if let Value::Tagged(tagged) = value {
+                    value = tagged.value;
+                }
+                Value::Tagged(Box::new(TaggedValue {
+                    tag: Tag::new("t"),
+                    value,
+                }))
+            }
+        }
+    }
+
+    fn mapping(&mut self, depth: usize) -> Mapping {
+        let mut mapping = Mapping::new();
+        for _ in 0..self.below(6) {
+            mapping.insert(self.value(depth), self.value(depth));
+        }
+        mapping
+    }
+}
+
+#[test]
+fn test_key_types_roundtrip() {
+    let mut rng = Rng(0x2545_f491_4f6c_dd1d);
+    for _ in 0..1000 {
+        let mapping = Value::Mapping(rng.mapping(2));
+        let yaml = serde_yaml::to_string(&mapping).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(parsed, mapping, "{}", yaml);
+        assert_eq!(serde_yaml::to_value(&mapping).unwrap(), mapping);
+    }
+}
+
+#[test]
+fn test_ord() {
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    let values: Vec<Value> = (0..300).map(|_| rng.value(2)).collect();
+    for a in &values {
+        for b in &values {
+            assert_eq!(a.cmp(b) == Ordering::Equal, a == b, "{:?} {:?}", a, b);
+            assert_eq!(a.cmp(b), b.cmp(a).reverse(), "{:?} {:?}", a, b);
+            assert_eq!(a.partial_cmp(b), Some(a.cmp(b)));
+        }
+    }
+    let mut sorted = values.clone();
+    sorted.sort();
+    for pair in sorted.windows(2) {
+        for c in &values {
+            if pair[1] <= *c {
+                assert!(pair[0] <= *c, "{:?} {:?} {:?}", pair[0], pair[1], c);
+            }
+        }
+    }
+
+    // The same order as sorting keys.
+    let mut mapping = Mapping::new();
+    for value in &values {
+        mapping.insert(value.clone(), Value::Null);
+    }
+    mapping.sort_keys();
+    let set: BTreeSet<Value> = values.iter().cloned().collect();
+    assert!(mapping.keys().eq(&set));
+
+    // Mappings with the same entries in a different order are equal.
+    let a: Value = serde_yaml::from_str("{x: 1, y: 2}").unwrap();
+    let b: Value = serde_yaml::from_str("{y: 2, x: 1}").unwrap();
+    assert_eq!(a.cmp(&b), Ordering::Equal);
+
+    let ordered: Vec<Value> = serde_yaml::from_str(indoc! {"
+        - null
+        - false
+        - true
+        - -1
+        - 0
+        - 18446744073709551615
+        - -.inf
+        - 1.5
+        - .nan
+        - ''
+        - a
+        - []
+        - [a]
+        - {}
+        - !t a
+    "})
+    .unwrap();
+    let mut sorted = ordered.clone();
+    sorted.reverse();
+    sorted.sort();
+    assert_eq!(sorted, ordered);
+}
+
+#[test]
+fn test_collections_of_values() {
+    type Hashed<K, V> = HashMap<K, V, BuildHasherDefault<DefaultHasher>>;
+
+    let yaml = indoc! {"
+        ? [a, b]
+        : 1
+        ? {k: v}
+        : 2
+        null: 3
+        !t x: 4
+        1.5: 5
+    "};
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+
+    let btree: BTreeMap<Value, i32> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(btree, serde_yaml::from_value(value.clone()).unwrap());
+    assert_eq!(btree.len(), 5);
+    assert_eq!(btree.values().copied().collect::<Vec<_>>(), [3, 5, 1, 2, 4]);
+    let reparsed: BTreeMap<Value, i32> =
+        serde_yaml::from_str(&serde_yaml::to_string(&btree).unwrap()).unwrap();
+    assert_eq!(reparsed, btree);
+
+    let hashed: Hashed<Value, i32> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(hashed, serde_yaml::from_value(value.clone()).unwrap());
+    assert_eq!(hashed.len(), btree.len());
+    for (key, n) in &btree {
+        assert_eq!(hashed[key], *n);
+    }
+    let reparsed: Hashed<Value, i32> =
+        serde_yaml::from_str(&serde_yaml::to_string(&hashed).unwrap()).unwrap();
+    assert_eq!(reparsed, hashed);
+    assert_eq!(serde_yaml::to_value(&hashed).unwrap(), value);
+
+    let strings: Hashed<String, Vec<u8>> = serde_yaml::from_str("a: [1]\nb: []\n").unwrap();
+    let value = serde_yaml::to_value(&strings).unwrap();
+    assert_eq!(
+        serde_yaml::from_value::<Hashed<String, Vec<u8>>>(value).unwrap(),
+        strings
+    );
+
+    let yaml = "[[a, b], {k: v}, null, [a, b], !t x, 1.5, {k: v}]";
+    let value: Value = serde_yaml::from_str(yaml).unwrap();
+    let btree: BTreeSet<Value> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(btree, serde_yaml::from_value(value.clone()).unwrap());
+    assert_eq!(btree.len(), 5);
+    let hashed: HashSet<Value> = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(hashed, serde_yaml::from_value(value).unwrap());
+    assert!(hashed.iter().all(|value| btree.contains(value)));
+    for set in [
+        serde_yaml::to_string(&btree).unwrap(),
+        serde_yaml::to_string(&hashed).unwrap(),
+    ] {
+        let reparsed: BTreeSet<Value> = serde_yaml::from_str(&set).unwrap();
+        assert_eq!(reparsed, btree);
+    }
+}
+
+#[test]
+fn test_canonicalize() {
+    fn canonical(mut value: Value) -> Value {
+        value.canonicalize();
+        value
+    }
+
+    let value: Value = serde_yaml::from_str(indoc! {"
+        z: [-0.0, 1.0, +1, 0x1]
+        b: {y: 1, x: 2}
+        1: one
+        a: !Local {q: 1, p: 2}
+    "})
+    .unwrap();
+    let yaml = serde_yaml::to_string(&canonical(value)).unwrap();
+    let expected = indoc! {"
+        1: one
+        a: !Local
+          p: 2
+          q: 1
+        b:
+          x: 2
+          y: 1
+        z:
+        - 0.0
+        - 1.0
+        - 1
+        - 1
+    "};
+    assert_eq!(yaml, expected);
+
+    let tagged = |tag: &str, value: Value| {
+        Value::Tagged(Box::new(TaggedValue {
+            tag: Tag::new(tag),
+            value,
+        }))
+    };
+    let cases = [
+        (tagged("!!str", Value::from("x")), Value::from("x")),
+        (tagged("!!other", Value::from("x")), Value::from("x")),
+        (tagged("!!int", Value::from(1)), Value::from(1)),
+        (tagged("!!float", Value::from(1.5)), Value::from(1.5)),
+        (tagged("!!bool", Value::Bool(true)), Value::Bool(true)),
+        (tagged("!!null", Value::Null), Value::Null),
+        (
+            tagged("!!seq", Value::Sequence(Sequence::new())),
+            Value::Sequence(Sequence::new()),
+        ),
+        (
+            tagged("!!map", Value::Mapping(Mapping::new())),
+            Value::Mapping(Mapping::new()),
+        ),
+        (
+            tagged("!!int", Value::from("1")),
+            tagged("!!int", Value::from("1")),
+        ),
+        (
+            tagged("!!float", Value::from(1)),
+            tagged("!!float", Value::from(1)),
+        ),
+        (
+            tagged("str", Value::from("x")),
+            tagged("str", Value::from("x")),
+        ),
+    ];
+    for (value, expected) in cases {
+        assert_eq!(canonical(value.clone()), expected, "{:?}", value);
+    }
+
+    let mut rng = Rng(0x9e37_79b9_7f4a_7c15);
+    for _ in 0..1000 {
+        let value = rng.value(3);
+        let once = canonical(value.clone());
+        assert_eq!(canonical(once.clone()), once);
+
+        let yaml = serde_yaml::to_string(&value).unwrap();
+        let parsed: Value = serde_yaml::from_str(&yaml).unwrap();
+        assert_eq!(canonical(parsed.clone()), once, "{}", yaml);
+        assert_eq!(parsed.canonical_hash(), value.canonical_hash(), "{}", yaml);
+
+        if let Value::Mapping(mapping) = &value {
+            let reversed: Mapping = mapping.clone().into_iter().rev().collect();
+            let reversed = Value::Mapping(reversed);
+            assert_eq!(reversed.canonical_hash(), value.canonical_hash());
+        }
+    }
+
+    let a: Value = serde_yaml::from_str("{a: 1, b: [x]}").unwrap();
+    let b: Value = serde_yaml::from_str("{a: 1, b: x}").unwrap();
+    let c: Value = serde_yaml::from_str("{a: '1', b: [x]}").unwrap();
+    assert_ne!(a.canonical_hash(), b.canonical_hash());
+    assert_ne!(a.canonical_hash(), c.canonical_hash());
+}
+
+#[test]
+fn test_as_sequence_of() {
+    #[derive(Deserialize, PartialEq, Debug)]
+    struct Port {
+        port: u16,
+    }
+
+    let value: Value = serde_yaml::from_str(indoc! {"
+        hosts: [a.com, b.com]
+        mixed: [a, b, c, [x], d]
+        ports: [{port: 80}, {port: 443}, {port: 8080}, {port: http}, {port: 22}]
+        single: {port: 80}
+        empty:
+    "})
+    .unwrap();
+
+    let hosts: Vec<String> = value["hosts"].as_sequence_of(false).unwrap();
+    assert_eq!(hosts, ["a.com", "b.com"]);
+
+    let err = value["mixed"].as_sequence_of::<String>(false).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ".[3]: invalid type: sequence, expected a string",
+    );
+    let results: Vec<_> = value["mixed"].iter_as::<String>(false).collect();
+    assert_eq!(results.len(), 5);
+    assert_eq!(results[2].as_ref().unwrap(), "c");
+    assert!(results[3].is_err());
+    assert_eq!(results[4].as_ref().unwrap(), "d");
+
+    let err = value["ports"].as_sequence_of::<Port>(false).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        ".[3].port: invalid type: string \"http\", expected u16",
+    );
+    let ports: Vec<u16> = value["ports"]
+        .iter_as::<Port>(false)
+        .filter_map(Result::ok)
+        .map(|port| port.port)
+        .collect();
+    assert_eq!(ports, [80, 443, 8080, 22]);
+
+    let single: Vec<Port> = value["single"].as_sequence_of(true).unwrap();
+    assert_eq!(single, [Port { port: 80 }]);
+    let err = value["single"].as_sequence_of::<Port>(false).unwrap_err();
+    assert_eq!(err.to_string(), "invalid type: map, expected a sequence",);
+    let mut iter = value["single"].iter_as::<Port>(false);
+    assert_eq!(iter.len(), 1);
+    assert!(iter.next().unwrap().is_err());
+    assert!(iter.next().is_none());
+
+    let empty: Vec<Port> = value["empty"].as_sequence_of(true).unwrap();
+    assert_eq!(empty, []);
+    let err = value["hosts"][0].as_sequence_of::<u16>(true).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "invalid type: string \"a.com\", expected u16",
+    );
+}
+
+#[test]
+fn test_take_and_into() {
+    let mut value: Value = serde_yaml::from_str(indoc! {"
+        string: !Tag text
+        sequence: [1, 2]
+    "})
+    .unwrap();
+
+    let sequence = value["sequence"].take();
+    assert_eq!(value["sequence"], Value::Null);
+    assert_eq!(sequence.into_sequence().unwrap().len(), 2);
+
+    let string = value["string"].take();
+    assert!(string.is_tagged());
+    let string = string.into_mapping().unwrap_err();
+    assert!(string.is_tagged());
+    assert_eq!(string.into_string().unwrap(), "text");
+
+    assert!(value.as_mapping().is_some());
+    let mapping = value.into_mapping().unwrap();
+    assert_eq!(mapping.len(), 2);
+}
+
+#[test]
+fn test_merge_from() {
+    use serde_yaml::value::{MergeStrategy, NullMerge, SequenceMerge};
+
+    let base = indoc! {"
+        database:
+          host: localhost
+          port: 5432
+          options: [ssl]
+        replicas:
+          - {name: a, weight: 1}
+          - {name: b, weight: 1}
+        logging: !Syslog
+          facility: local0
+          level: info
+        cache: !Memory
+          size: 64
+    "};
+    let production = indoc! {"
+        database:
+          host: db.internal
+          options: [pool]
+          password: null
+        replicas:
+          - {weight: 3}
+        logging: !Syslog
+          level: warn
+        cache: !Redis
+          url: redis://cache
+    "};
+
+    let mut config: Value = serde_yaml::from_str(base).unwrap();
+    let overlay: Value = serde_yaml::from_str(production).unwrap();
+    config.merge_from(overlay.clone(), MergeStrategy::default());
+    let expected = indoc! {"
+        database:
+          host: db.internal
+          port: 5432
+          options:
+          - pool
+          password: null
+        replicas:
+        - weight: 3
+        logging: !Syslog
+          facility: local0
+          level: warn
+        cache: !Redis
+          url: redis://cache
+    "};
+    assert_eq!(serde_yaml::to_string(&config).unwrap(), expected);
+
+    let mut config: Value = serde_yaml::from_str(base).unwrap();
+    let strategy = MergeStrategy {
+        sequences: SequenceMerge::Append,
+        nulls: NullMerge::Delete,
+    };
+    config.merge_from(overlay.clone(), strategy);
+    assert_eq!(
+        config["database"]["options"],
+        serde_yaml::from_str::<Value>("[ssl, pool]").unwrap()
+    );
+    assert!(config["database"].get("password").is_none());
+    assert_eq!(config["replicas"].as_sequence().unwrap().len(), 3);
+
+    let mut config: Value = serde_yaml::from_str(base).unwrap();
+    let strategy = MergeStrategy {
+        sequences: SequenceMerge::MergeByIndex,
+        nulls: NullMerge::Set,
+    };
+    config.merge_from(overlay, strategy);
+    let expected = indoc! {"
+        - name: a
+          weight: 3
+        - name: b
+          weight: 1
+    "};
+    assert_eq!(
+        serde_yaml::to_string(&config["replicas"]).unwrap(),
+        expected
+    );
+}
+
+#[test]
+fn test_display_roundtrip() {
+    let yaml = indoc! {"
+        name: serde
+        list: [1, -2.5, true, null, '']
+        nested: {k: !Tag v, 'multi': \"a\\nb\"}
+        ? [complex, key]
+        : value
+    "};
+    let value: Value = yaml.parse().unwrap();
+    let display = format!("{}", value);
+    assert!(!display.ends_with('\n'));
+    assert_eq!(display.parse::<Value>().unwrap(), value);
+
+    assert_eq!(Value::from(1).to_string(), "1");
+    assert_eq!(Value::from("x").to_string(), "x");
+    assert!("[unclosed".parse::<Value>().is_err());
+
+    // A trailing newline that belongs to a string is kept.
+    let strings = ["a\n", "a\n\n", "a\nb\n"];
+    for string in strings {
+        let values = [
+            Value::from(string),
+            Value::from(vec![string]),
+            Value::from_iter([("k", string)]),
+        ];
+        for value in values {
+            let display = value.to_string();
+            assert_eq!(display.parse::<Value>().unwrap(), value, "{:?}", display);
+        }
+    }
+    assert_eq!(Value::from("a\nb").to_string(), "|-\n  a\n  b");
+}
+
+#[test]
+fn test_mapping_positional() {
+    let mut mapping: Mapping = serde_yaml::from_str("{a: 1, b: 2, c: 3}").unwrap();
+    assert_eq!(mapping.get_index_of("b"), Some(1));
+    assert_eq!(
+        mapping.get_index(2),
+        Some((&Value::from("c"), &Value::from(3)))
+    );
+    assert_eq!(mapping.get_index(3), None);
+
+    assert_eq!(
+        mapping.insert_before(1, Value::from("x"), Value::from(0)),
+        (1, None)
+    );
+    mapping.move_index(0, 3);
+    *mapping.get_index_mut(0).unwrap().1 = Value::from(9);
+    assert_eq!(
+        serde_yaml::to_string(&mapping).unwrap(),
+        "x: 9\nb: 2\nc: 3\na: 1\n"
+    );
+
+    mapping.swap_indices(0, 3);
+    assert_eq!(
+        mapping.shift_insert(0, Value::from("c"), Value::from(4)),
+        Some(Value::from(3))
+    );
+    assert_eq!(
+        serde_yaml::to_string(&mapping).unwrap(),
+        "c: 4\na: 1\nb: 2\nx: 9\n"
+    );
+
+    assert_eq!(
+        mapping.shift_remove_index(0),
+        Some((Value::from("c"), Value::from(4)))
+    );
+    assert_eq!(
+        mapping.swap_remove_index(0),
+        Some((Value::from("a"), Value::from(1)))
+    );
+    assert_eq!(serde_yaml::to_string(&mapping).unwrap(), "x: 9\nb: 2\n");
+}
+
+#[test]
+fn test_mapping_patch_positions() {
+    let yaml = indoc! {"
+        apiVersion: v1
+        metadata:
+          name: web
+        spec:
+          replicas: 1
+    "};
+    let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+    let mapping = value.as_mapping_mut().unwrap();
+
+    let after = mapping.get_index_of("apiVersion").unwrap() + 1;
+    assert_eq!(
+        mapping.shift_insert(after, "kind".into(), "Deployment".into()),
+        None
+    );
+    let metadata = mapping["metadata"].as_mapping_mut().unwrap();
+    metadata.shift_insert(0, "namespace".into(), "prod".into());
+    metadata.shift_insert(metadata.len(), "labels".into(), Mapping::new().into());
+    let spec = mapping.get_index_of("spec").unwrap();
+    mapping.move_index(spec, 1);
+    let expected = indoc! {"
+        apiVersion: v1
+        spec:
+          replicas: 1
+        kind: Deployment
+        metadata:
+          namespace: prod
+          name: web
+          labels: {}
+    "};
+    assert_eq!(serde_yaml::to_string(&value).unwrap(), expected);
+
+    // An existing key moves to the requested position, taking the new value.
+    let mapping = value.as_mapping_mut().unwrap();
+    assert_eq!(
+        mapping.shift_insert(0, "kind".into(), "Pod".into()),
+        Some("Deployment".into())
+    );
+    let spec = mapping["spec"].clone();
+    assert_eq!(
+        mapping.insert_before(4, "spec".into(), 2.into()),
+        (3, Some(spec))
+    );
+    let keys: Vec<&str> = mapping.keys().map(|key| key.as_str().unwrap()).collect();
+    assert_eq!(keys, ["kind", "apiVersion", "metadata", "spec"]);
+
+    // Order survives a round trip through YAML.
+    let reparsed: Value = serde_yaml::from_str(&serde_yaml::to_string(&value).unwrap()).unwrap();
+    let keys: Vec<&Value> = reparsed.as_mapping().unwrap().keys().collect();
+    assert_eq!(keys, value.as_mapping().unwrap().keys().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_mapping_remove_order() {
+    let mut mapping: Mapping = serde_yaml::from_str("{a: 1, b: 2, c: 3, 4: d, e: 5}").unwrap();
+    assert_eq!(mapping.remove("a"), Some(Value::from(1)));
+    assert_eq!(mapping.remove("a"), None);
+    assert_eq!(
+        mapping.remove_entry(4),
+        Some((Value::from(4), Value::from("d")))
+    );
+    assert_eq!(
+        serde_yaml::to_string(&mapping).unwrap(),
+        "b: 2\nc: 3\ne: 5\n"
+    );
+
+    if let serde_yaml::mapping::Entry::Occupied(entry) = mapping.entry(Value::from("b")) {
+        assert_eq!(entry.remove(), 2);
+    }
+    assert_eq!(serde_yaml::to_string(&mapping).unwrap(), "c: 3\ne: 5\n");
+
+    mapping.insert(Value::from("f"), Value::from(6));
+    assert_eq!(
+        mapping.pop_first(),
+        Some((Value::from("c"), Value::from(3)))
+    );
+    assert_eq!(mapping.pop_last(), Some((Value::from("f"), Value::from(6))));
+    assert_eq!(mapping.pop_last(), Some((Value::from("e"), Value::from(5))));
+    assert_eq!(mapping.pop_first(), None);
+    assert_eq!(mapping.pop_last(), None);
+}
+
+#[test]
+fn test_mapping_insert() {
+    let mut mapping = Mapping::new();
+    assert_eq!(mapping.insert("a".into(), 1.into()), None);
+    assert_eq!(mapping.put(Value::from("b"), "two"), None);
+    assert_eq!(mapping.put(true, Value::Null), None);
+    assert_eq!(mapping.put("a", 3), Some(Value::from(1)));
+    assert_eq!(
+        serde_yaml::to_string(&mapping).unwrap(),
+        "a: 3\nb: two\ntrue: null\n"
+    );
+
+    *mapping.try_insert("c".into(), 4.into()).unwrap() = Value::from(5);
+    assert_eq!(
+        mapping.try_insert("b".into(), 6.into()),
+        Err((Value::from("b"), Value::from(6)))
+    );
+    assert_eq!(
+        serde_yaml::to_string(&mapping).unwrap(),
+        "a: 3\nb: two\ntrue: null\nc: 5\n"
+    );
+
+    // The key given back is the caller's, not the equal one in the map.
+    let mut zeros = Mapping::new();
+    zeros.put(-0.0, "negative zero");
+    let (key, _value) = zeros.try_insert(0.0.into(), Value::Null).unwrap_err();
+    assert!(key.as_f64().unwrap().is_sign_positive());
+
+    let mut other: Mapping = serde_yaml::from_str("{d: 7, a: 8, e: 9}").unwrap();
+    mapping.append(&mut other);
+    assert!(other.is_empty());
+    assert_eq!(
+        serde_yaml::to_string(&mapping).unwrap(),
+        "a: 8\nb: two\ntrue: null\nc: 5\nd: 7\ne: 9\n"
+    );
+}
+
+#[test]
+fn test_mapping_key_identity() {
+    let mut mapping: Mapping = serde_yaml::from_str(indoc! {"
+        -0.0: negative zero
+        1: int
+        1.0: float
+        '1': string
+        .nan: nan
+        !t k: tagged
+        k: untagged
+    "})
+    .unwrap();
+    assert_eq!(mapping.len(), 7);
+
+    let (key, value) = mapping.get_key_value(1).unwrap();
+    assert!(key.is_u64());
+    assert_eq!(value, "int");
+    let (key, value) = mapping.get_key_value(Value::from(1.0)).unwrap();
+    assert!(key.is_f64());
+    assert_eq!(value, "float");
+    let (key, value) = mapping.get_key_value("1").unwrap();
+    assert!(key.is_string());
+    assert_eq!(value, "string");
+    let (key, value) = mapping.get_key_value(Value::from(f64::NAN)).unwrap();
+    assert!(key.as_f64().unwrap().is_nan());
+    assert_eq!(value, "nan");
+    let tagged: Value = serde_yaml::from_str("!t k").unwrap();
+    let (key, value) = mapping.get_key_value(&tagged).unwrap();
+    assert!(key.is_tagged());
+    assert_eq!(value, "tagged");
+    let (key, value) = mapping.get_key_value("k").unwrap();
+    assert!(!key.is_tagged());
+    assert_eq!(value, "untagged");
+    assert_eq!(mapping.get_key_value("missing"), None);
+
+    // Equal keys keep the one that was inserted first, and its position.
+    let (key, value) = mapping.get_key_value(Value::from(0.0)).unwrap();
+    assert!(key.as_f64().unwrap().is_sign_negative());
+    assert_eq!(value, "negative zero");
+    assert_eq!(
+        mapping.insert(0.0.into(), "zero".into()),
+        Some(Value::from("negative zero"))
+    );
+    assert_eq!(
+        mapping.insert(f64::NAN.into(), "nan again".into()),
+        Some(Value::from("nan"))
+    );
+    let (key, value) = mapping.get_index(0).unwrap();
+    assert!(key.as_f64().unwrap().is_sign_negative());
+    assert_eq!(value, "zero");
+    let entry = mapping.entry(Value::from(0.0));
+    assert!(entry.key().as_f64().unwrap().is_sign_negative());
+    assert_eq!(mapping.len(), 7);
+}
+
+#[test]
+fn test_sequence() {
+    let mut seq: Sequence = serde_yaml::from_str("[{name: a}, {name: b}, c]").unwrap();
+    assert_eq!(seq.len(), 3);
+    assert_eq!(seq.get_signed(-1).unwrap(), "c");
+    assert_eq!(seq.get_signed(-3).unwrap()["name"], "a");
+    assert!(seq.get_signed(3).is_none());
+    assert!(seq.get_signed(-4).is_none());
+    assert_eq!(seq.get(1usize).unwrap()["name"], "b");
+    assert_eq!(seq.get(1..).unwrap().len(), 2);
+    assert!(seq.get(3usize).is_none());
+
+    let names: Vec<&Value> = seq.iter_mapping_values("name").collect();
+    assert_eq!(names, [&Value::from("a"), &Value::from("b"), &Value::Null]);
+
+    *seq.get_signed_mut(-1).unwrap() = Value::from("d");
+    assert_eq!(seq.get_mut(2).unwrap(), "d");
+    seq.insert(0, Value::from(0));
+    seq.push(Value::from(1));
+    assert_eq!(seq.remove(0), 0);
+    assert_eq!(seq.pop(), Some(Value::from(1)));
+    assert_eq!(seq[2], "d");
+
+    let vec: Vec<Value> = seq.clone().into();
+    assert_eq!(Sequence::from(vec.clone()), seq);
+    assert_eq!(vec, seq);
+    assert_eq!(seq.iter().count(), 3);
+
+    let value = Value::Sequence(seq);
+    let yaml = serde_yaml::to_string(&value).unwrap();
+    assert_eq!(yaml, "- name: a\n- name: b\n- d\n");
+}
+
+#[test]
+fn test_collect() {
+    let value: Value = vec![Value::from(1), Value::from("two")]
+        .into_iter()
+        .collect();
+    assert_eq!(value, serde_yaml::from_str::<Value>("[1, two]").unwrap());
+
+    let value: Value = ["a", "b"].into_iter().collect();
+    assert_eq!(value, serde_yaml::from_str::<Value>("[a, b]").unwrap());
+
+    let value: Value = [("name", "web"), ("image", "nginx")].into_iter().collect();
+    assert_eq!(
+        value,
+        serde_yaml::from_str::<Value>("{name: web, image: nginx}").unwrap(),
+    );
+
+    let pairs = [(1, true), (2, false)];
+    let mapping: Mapping = pairs
+        .iter()
+        .map(|&(k, v)| (Value::from(k), Value::from(v)))
+        .collect();
+    assert_eq!(mapping[1], true);
+    assert_eq!(Value::from_iter(pairs), Value::Mapping(mapping));
+
+    // Pairs that are converted as they are collected still infer `Value`.
+    let mapping: Mapping = pairs.iter().map(|&(k, v)| (k.into(), v.into())).collect();
+    assert_eq!(mapping[2], false);
+
+    // Last wins, in the position the key first appeared.
+    let mapping = Mapping::from_pairs([("a", 1), ("b", 2), ("a", 3)]);
+    let yaml = serde_yaml::to_string(&mapping).unwrap();
+    assert_eq!(yaml, "a: 3\nb: 2\n");
+}
+
+#[test]
+fn test_walk() {
+    use serde_yaml::value::{PathSegment, Walk};
+
+    let yaml = indoc! {"
+        spec:
+          containers:
+            - image: nginx:1.0
+            - image: !Pinned redis:7
+        secret:
+          password: hunter2
+        1: one
+    "};
+    let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+
+    let mut visited = Vec::new();
+    value.walk(|path, value| {
+        if value.is_string() {
+            visited.push(format!("{}={}", path, value.as_str().unwrap()));
+        }
+    });
+    assert_eq!(
+        visited,
+        [
+            "spec.containers[0].image=nginx:1.0",
+            "spec.containers[1].image=redis:7",
+            "secret.password=hunter2",
+            "1=one",
+        ],
+    );
+
+    let mut count = 0;
+    value.walk_mut(|path, value| {
+        count += 1;
+        match path.segments() {
+            [PathSegment::Key(key)] if *key == "secret" => {
+                *value = Value::from("gone");
+                Walk::Continue
+            }
+            [PathSegment::Key(key), ..] if *key == "spec" => Walk::SkipChildren,
+            _ => Walk::Continue,
+        }
+    });
+    assert_eq!(count, 4);
+    assert_eq!(value["secret"], "gone");
+
+    let mut count = 0;
+    value.walk_mut(|path, _value| {
+        count += 1;
+        if path.depth() == 3 {
+            Walk::Stop
+        } else {
+            Walk::Continue
+        }
+    });
+    assert_eq!(count, 4);
+}
+
+#[test]
+fn test_pointer() {
+    let yaml = indoc! {"
+        spec:
+          replicas: 3
+          containers:
+            - name: web
+              ports: [80, 443]
+          labels: !Labels
+            a/b: slash
+        7: seven
+    "};
+    let mut value: Value = serde_yaml::from_str(yaml).unwrap();
+
+    assert_eq!(
+        value.get_as::<u16>("/spec/containers/0/ports/1").unwrap(),
+        443
+    );
+    assert_eq!(value.get_as::<&str>("/spec/labels/a~1b").unwrap(), "slash");
+    assert_eq!(value.get_as::<String>("/7").unwrap(), "seven");
+    assert_eq!(value.pointer("").unwrap(), &value);
+
+    let cases = [
+        (
+            "spec",
+            "invalid pointer \"spec\", expected it to be empty or to start with '/'",
+        ),
+        ("/spec/missing/name", "no value found at /spec/missing"),
+        (
+            "/spec/containers/web",
+            "no value found at /spec/containers/web",
+        ),
+        (
+            "/spec/replicas/count",
+            "/spec/replicas/count: parent is a number, not a mapping or sequence",
+        ),
+        (
+            "/spec/containers/1/name",
+            "/spec/containers/1: index is past the end of a sequence of length 1",
+        ),
+        (
+            "/spec/containers/0/name",
+            "/spec/containers/0/name: invalid type: string \"web\", expected u16",
+        ),
+    ];
+    for (pointer, expected) in cases {
+        let err = value.get_as::<u16>(pointer).unwrap_err();
+        assert_eq!(err.to_string(), expected, "{}", pointer);
+    }
+
+    value.set_from("/spec/replicas", &5).unwrap();
+    value.set_from("/spec/containers/0/ports/2", &8080).unwrap();
+    value
+        .set_from("/spec/containers/-/name", "sidecar")
+        .unwrap();
+    value
+        .set_from("/metadata/annotations/owner", "ops")
+        .unwrap();
+    value.set_from("/spec/labels/c", "tagged").unwrap();
+    assert_eq!(value.pointer("/spec/replicas").unwrap(), 5);
+    assert_eq!(value.pointer("/spec/containers/0/ports/2").unwrap(), 8080);
+    assert_eq!(value.pointer("/spec/containers/1/name").unwrap(), "sidecar");
+    assert_eq!(value.pointer("/metadata/annotations/owner").unwrap(), "ops");
+    assert!(value.pointer("/spec/labels").unwrap().is_tagged());
+    assert_eq!(value.pointer("/spec/labels/c").unwrap(), "tagged");
+
+    let err = value.set_from("/spec/containers/5/name", "x").unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "/spec/containers/5: index is past the end of a sequence of length 2"
+    );
+    let err = value.set_from("/spec/replicas/count", &1).unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "/spec/replicas/count: parent is a number, not a mapping or sequence"
+    );
+
+    *value.pointer_mut("/7").unwrap() = Value::from(7);
+    assert_eq!(value[7], 7);
+}
+
+#[test]
+fn test_mapping_iterators() {
+    let mut mapping: Mapping = serde_yaml::from_str("{c: 1, a: 2, b: 3}").unwrap();
+
+    let keys = mapping.keys();
+    assert_eq!(keys.size_hint(), (3, Some(3)));
+    assert_eq!(keys.collect::<Vec<_>>(), ["c", "a", "b"]);
+    assert_eq!(mapping.keys().rev().collect::<Vec<_>>(), ["b", "a", "c"]);
+    assert_eq!(mapping.values().len(), 3);
+    assert_eq!(mapping.values().collect::<Vec<_>>(), [1, 2, 3]);
+
+    for value in mapping.values_mut() {
+        *value = Value::from(value.as_u64().unwrap() * 10);
+    }
+    for (_key, value) in &mut mapping {
+        *value = Value::from(value.as_u64().unwrap() + 1);
+    }
+    let mut iter = mapping.iter();
+    assert_eq!(iter.len(), 3);
+    assert_eq!(
+        iter.next_back(),
+        Some((&Value::from("b"), &Value::from(31)))
+    );
+    assert_eq!(iter.len(), 2);
+    assert_eq!(iter.next(), Some((&Value::from("c"), &Value::from(11))));
+    assert_eq!(iter.next(), Some((&Value::from("a"), &Value::from(21))));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next(), None);
+
+    let mut other = Mapping::new();
+    other.insert(Value::from("z"), Value::from(0));
+    other.extend(&mapping);
+    assert_eq!(other.into_keys().collect::<Vec<_>>(), ["z", "c", "a", "b"]);
+    assert_eq!(
+        mapping.clone().into_values().rev().collect::<Vec<_>>(),
+        [31, 21, 11]
+    );
+    assert_eq!(mapping.into_iter().len(), 3);
+}
+
+#[test]
+fn test_to_value_bytes_and_wide_ints() {
+    struct Bytes(&'static [u8]);
+
+    impl serde::Serialize for Bytes {
+        fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+            serializer.serialize_bytes(self.0)
+        }
+    }
+
+    let value = serde_yaml::to_value(Bytes(b"\x00\xff")).unwrap();
+    assert_eq!(value, serde_yaml::from_str::<Value>("[0, 255]").unwrap());
+
+    let value = serde_yaml::to_value(u128::from(u64::MAX)).unwrap();
+    assert_eq!(value, Value::Number(Number::from(u64::MAX)));
+    let value = serde_yaml::to_value(i128::from(i64::MIN)).unwrap();
+    assert_eq!(value, Value::Number(Number::from(i64::MIN)));
+}
+
+#[test]
+fn test_styled_value() {
+    use serde_yaml::raw::ScalarStyle;
+    use serde_yaml::StyledValue;
+
+    let yaml = indoc! {r#"
+        name: web
+        'quoted key': "double"
+        version: '3.10'
+        octal: '0755'
+        flag: 'yes'
+        replicas: 3
+        ratio: 0.5
+        enabled: true
+        nothing: null
+        script: |
+          set -e
+          make
+        summary: >
+          one two
+        escaped: "tab\there"
+        tagged: !color 'ff8800'
+        list:
+        - 'a'
+        - b
+        - "c"
+        nested:
+          inner: 'x'
+    "#};
+    let value: StyledValue = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(serde_yaml::to_string(&value).unwrap(), yaml);
+    assert_eq!(
+        value.get("version").unwrap().style(),
+        Some(ScalarStyle::SingleQuoted)
+    );
+    assert_eq!(value.get("name").unwrap().style(), Some(ScalarStyle::Plain));
+    assert_eq!(value.get("replicas").unwrap().style(), None);
+
+    // The same as a plain `Value`, and back again without styles.
+    let plain: Value = serde_yaml::from_str(yaml).unwrap();
+    assert_eq!(Value::from(value.clone()), plain);
+    let unstyled = StyledValue::from(plain.clone());
+    assert_eq!(unstyled.get("version").unwrap().style(), None);
+    let from_value: StyledValue = serde_yaml::from_value(plain).unwrap();
+    assert_eq!(from_value, unstyled);
+
+    // Edited strings keep their style where they still can.
+    let mut value = value;
+    let edits = [
+        ("name", "true"),
+        ("version", "it's"),
+        ("script", "one line"),
+    ];
+    for (key, edit) in edits {
+        if let Some(StyledValue::String(string, _style)) = value.get_mut(key) {
+            *string = edit.to_owned();
+        }
+    }
+    let output = serde_yaml::to_string(&value).unwrap();
+    assert!(output.starts_with("name: 'true'\n"), "{}", output);
+    assert!(output.contains("version: 'it''s'\n"), "{}", output);
+    assert!(output.contains("script: |-\n  one line\n"), "{}", output);
+
+    // An alias reports the style of the scalar it refers to.
+    let value: StyledValue = serde_yaml::from_str("a: &x 'one'\nb: *x\n").unwrap();
+    assert_eq!(
+        value.get("b").unwrap().style(),
+        Some(ScalarStyle::SingleQuoted)
+    );
+    assert_eq!(
+        serde_yaml::to_string(&value).unwrap(),
+        "a: 'one'\nb: 'one'\n"
+    );
+}